@@ -2,7 +2,7 @@
 
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 /// Universal MCP to LSP Bridge
 ///
@@ -51,6 +51,74 @@ pub struct Args {
         env = "MCPLS_HTTP_PATH"
     )]
     pub http_path: String,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Subcommands. Without one, mcpls serves MCP.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Watch a workspace and stream diagnostics changes as JSON lines
+    ///
+    /// Runs the language servers without an MCP host, rescans the workspace
+    /// for changed files, and prints one line to stdout each time the
+    /// diagnostics of a file change.
+    Watch {
+        /// Workspace root to watch (default: the configured roots, or the
+        /// current directory)
+        #[arg(value_name = "PATH")]
+        path: Option<PathBuf>,
+
+        /// Milliseconds between two scans of the workspace
+        #[arg(long, value_name = "MS", default_value_t = 1000)]
+        interval_ms: u64,
+    },
+
+    /// Export the symbols of every workspace file as JSON or a SCIP index
+    ///
+    /// Runs the language servers without an MCP host and collects the
+    /// document symbols of every file they handle, for offline analysis.
+    ExportSymbols {
+        /// Workspace root to export (default: the configured roots, or the
+        /// current directory)
+        #[arg(value_name = "PATH")]
+        path: Option<PathBuf>,
+
+        /// Output format
+        #[arg(long, value_parser = ["json", "scip"], default_value = "json")]
+        format: String,
+
+        /// File to write the symbol map to (default: stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Only export files matching this gitignore-style glob (repeatable)
+        #[arg(long = "glob", value_name = "GLOB")]
+        globs: Vec<String>,
+
+        /// Stop after this many files
+        #[arg(long, value_name = "N")]
+        max_files: Option<usize>,
+    },
+
+    /// Write a diagnostics snapshot to attach to a bug report
+    ///
+    /// Starts the language servers without an MCP host and writes the
+    /// configuration with secrets redacted, server versions, capabilities,
+    /// logs, and the last LSP messages exchanged to a JSON file. The LSP
+    /// messages include document content; review the file before sharing it.
+    Snapshot {
+        /// Workspace root to start the servers for (default: the configured
+        /// roots, or the current directory)
+        #[arg(value_name = "PATH")]
+        path: Option<PathBuf>,
+
+        /// File to write the snapshot to (default:
+        /// .mcpls/snapshots/snapshot-<time>.json under the workspace root)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
 }
 
 #[cfg(test)]
@@ -181,6 +249,82 @@ mod tests {
         assert!(args.log_json);
     }
 
+    #[test]
+    fn test_no_subcommand_serves() {
+        let args = Args::parse_from(["mcpls"]);
+        assert!(args.command.is_none());
+    }
+
+    #[test]
+    fn test_watch_subcommand() {
+        let args = Args::parse_from(["mcpls", "--log-level", "warn", "watch", "/ws"]);
+        assert_eq!(args.log_level, "warn");
+        match args.command {
+            Some(Command::Watch { path, interval_ms }) => {
+                assert_eq!(path, Some(PathBuf::from("/ws")));
+                assert_eq!(interval_ms, 1000);
+            }
+            _ => panic!("expected watch subcommand"),
+        }
+
+        let args = Args::parse_from(["mcpls", "watch", "--interval-ms", "250"]);
+        match args.command {
+            Some(Command::Watch { path, interval_ms }) => {
+                assert!(path.is_none());
+                assert_eq!(interval_ms, 250);
+            }
+            _ => panic!("expected watch subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_export_symbols_subcommand() {
+        let args = Args::parse_from([
+            "mcpls",
+            "export-symbols",
+            "/ws",
+            "--format",
+            "scip",
+            "-o",
+            "index.scip",
+            "--glob",
+            "src/**",
+            "--glob",
+            "lib/**",
+        ]);
+        match args.command {
+            Some(Command::ExportSymbols {
+                path,
+                format,
+                output,
+                globs,
+                max_files,
+            }) => {
+                assert_eq!(path, Some(PathBuf::from("/ws")));
+                assert_eq!(format, "scip");
+                assert_eq!(output, Some(PathBuf::from("index.scip")));
+                assert_eq!(globs, ["src/**", "lib/**"]);
+                assert!(max_files.is_none());
+            }
+            _ => panic!("expected export-symbols subcommand"),
+        }
+
+        let result = Args::try_parse_from(["mcpls", "export-symbols", "--format", "lsif"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_snapshot_subcommand() {
+        let args = Args::parse_from(["mcpls", "snapshot", "/ws", "-o", "report.json"]);
+        match args.command {
+            Some(Command::Snapshot { path, output }) => {
+                assert_eq!(path, Some(PathBuf::from("/ws")));
+                assert_eq!(output, Some(PathBuf::from("report.json")));
+            }
+            _ => panic!("expected snapshot subcommand"),
+        }
+    }
+
     #[cfg(feature = "transport-http")]
     #[allow(clippy::unwrap_used)]
     mod http_transport_tests {
@@ -3,13 +3,16 @@
 //! This binary provides an MCP server that exposes LSP capabilities as tools,
 //! enabling AI agents to access semantic code intelligence.
 
+use std::io::Write as _;
+use std::time::Duration;
+
 use anyhow::{Context, Result};
 use clap::Parser;
 
 mod args;
 mod logging;
 
-use args::Args;
+use args::{Args, Command};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -33,6 +36,54 @@ async fn main() -> Result<()> {
         "configuration loaded"
     );
 
+    match args.command {
+        Some(Command::Watch { path, interval_ms }) => {
+            mcpls_core::watch(config, path, Duration::from_millis(interval_ms))
+                .await
+                .context("watch error")?;
+            return Ok(());
+        }
+        Some(Command::ExportSymbols {
+            path,
+            format,
+            output,
+            globs,
+            max_files,
+        }) => {
+            let format = if format == "scip" {
+                mcpls_core::SymbolMapFormat::Scip
+            } else {
+                mcpls_core::SymbolMapFormat::Json
+            };
+            let map = mcpls_core::export_symbols(
+                config,
+                path,
+                &globs,
+                max_files.unwrap_or(usize::MAX),
+                format,
+            )
+            .await
+            .context("export error")?;
+            match output {
+                Some(output) => std::fs::write(&output, map)
+                    .with_context(|| format!("failed to write {}", output.display()))?,
+                None => std::io::stdout()
+                    .write_all(&map)
+                    .context("failed to write symbol map")?,
+            }
+            return Ok(());
+        }
+        Some(Command::Snapshot { path, output }) => {
+            let result = mcpls_core::snapshot(config, path, output)
+                .await
+                .context("snapshot error")?;
+            writeln!(std::io::stdout(), "{}", result.output_path)
+                .context("failed to write snapshot path")?;
+            return Ok(());
+        }
+        None => {}
+    }
+
     // Select transport based on CLI flags.
     let transport = {
         #[cfg(feature = "transport-http")]
@@ -0,0 +1,218 @@
+//! Bracket matching without a language server.
+//!
+//! A light tokenizer finds the `()`, `[]`, and `{}` of a document, skipping
+//! those inside string and character literals and `//` and `/* */` comments,
+//! which covers the C family, Rust, Go, Java, and JavaScript well enough to
+//! find block boundaries. Positions are 1-based and count characters, like
+//! every other tool position.
+
+use serde::{Deserialize, Serialize};
+
+use super::translator::Position2D;
+
+/// A pair of matching brackets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BracketPair {
+    /// The two bracket characters, e.g. `{}`.
+    pub brackets: String,
+    /// Position of the opening bracket.
+    pub open: Position2D,
+    /// Position of the closing bracket.
+    pub close: Position2D,
+    /// Whether the requested position is inside the pair rather than on one
+    /// of its brackets.
+    pub enclosing: bool,
+}
+
+/// A bracket found by the tokenizer, at a 0-based line and character.
+#[derive(Debug, Clone, Copy)]
+struct Bracket {
+    ch: char,
+    line: usize,
+    character: usize,
+}
+
+impl Bracket {
+    fn position(self) -> Position2D {
+        Position2D {
+            line: one_based(self.line),
+            character: one_based(self.character),
+        }
+    }
+}
+
+const fn closing(open: char) -> Option<char> {
+    match open {
+        '(' => Some(')'),
+        '[' => Some(']'),
+        '{' => Some('}'),
+        _ => None,
+    }
+}
+
+/// Brackets of `content` outside of literals and comments, in order.
+fn code_brackets(content: &str) -> Vec<Bracket> {
+    let chars: Vec<char> = content.chars().collect();
+    let mut brackets = Vec::new();
+    let (mut line, mut character) = (0, 0);
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        let next = chars.get(i + 1).copied();
+        let len = match ch {
+            '/' if next == Some('/') => chars[i..].iter().take_while(|&&c| c != '\n').count(),
+            '/' if next == Some('*') => chars[i + 2..]
+                .windows(2)
+                .position(|pair| pair == ['*', '/'])
+                .map_or(chars.len() - i, |end| end + 4),
+            '"' | '`' => quoted_len(&chars[i..], ch),
+            // Lifetimes and apostrophes are not literals: only skip a quote
+            // that closes after one character or an escape.
+            '\'' if next == Some('\\') => quoted_len(&chars[i..], '\''),
+            '\'' if chars.get(i + 2) == Some(&'\'') => 3,
+            '(' | ')' | '[' | ']' | '{' | '}' => {
+                brackets.push(Bracket {
+                    ch,
+                    line,
+                    character,
+                });
+                1
+            }
+            _ => 1,
+        };
+        for &c in &chars[i..i + len] {
+            if c == '\n' {
+                line += 1;
+                character = 0;
+            } else {
+                character += 1;
+            }
+        }
+        i += len;
+    }
+    brackets
+}
+
+/// Length of the literal opening `chars` and ending at the next unescaped
+/// `quote`, or of the rest of the input if it never ends.
+fn quoted_len(chars: &[char], quote: char) -> usize {
+    let mut i = 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// The pair with a bracket at `line`:`character` (0-based, in characters),
+/// or else the innermost pair around it.
+///
+/// Returns `None` if the position is on an unmatched bracket or no pair
+/// encloses it.
+pub fn find_matching_bracket(content: &str, line: usize, character: usize) -> Option<BracketPair> {
+    let at = (line, character);
+    let mut open = Vec::new();
+    let mut enclosing = None;
+    for bracket in code_brackets(content) {
+        if closing(bracket.ch).is_some() {
+            open.push(bracket);
+            continue;
+        }
+        // Stray closing brackets are ignored.
+        let Some(&opening) = open.last() else {
+            continue;
+        };
+        if closing(opening.ch) != Some(bracket.ch) {
+            continue;
+        }
+        open.pop();
+        let start = (opening.line, opening.character);
+        let end = (bracket.line, bracket.character);
+        let pair = |enclosing| BracketPair {
+            brackets: format!("{}{}", opening.ch, bracket.ch),
+            open: opening.position(),
+            close: bracket.position(),
+            enclosing,
+        };
+        if at == start || at == end {
+            return Some(pair(false));
+        }
+        // Pairs close innermost first, so the first around the position wins.
+        if enclosing.is_none() && start < at && at < end {
+            enclosing = Some(pair(true));
+        }
+    }
+    let on_unmatched = open
+        .iter()
+        .any(|bracket| (bracket.line, bracket.character) == at);
+    if on_unmatched { None } else { enclosing }
+}
+
+fn one_based(index: usize) -> u32 {
+    u32::try_from(index + 1).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str =
+        "fn main() {\r\n    let s = \"}\"; // )\n    let c = '{';\n    /* ] */ foo(&'a x[0]);\n}\n";
+
+    #[test]
+    fn test_find_matching_bracket_on_bracket() {
+        let pair = find_matching_bracket(SOURCE, 0, 10).unwrap();
+        assert_eq!(pair.brackets, "{}");
+        assert_eq!(
+            pair.open,
+            Position2D {
+                line: 1,
+                character: 11
+            }
+        );
+        assert_eq!(
+            pair.close,
+            Position2D {
+                line: 5,
+                character: 1
+            }
+        );
+        assert!(!pair.enclosing);
+        assert_eq!(find_matching_bracket(SOURCE, 4, 0), Some(pair));
+
+        let pair = find_matching_bracket(SOURCE, 3, 21).unwrap();
+        assert_eq!(pair.brackets, "[]");
+        assert_eq!(
+            pair.close,
+            Position2D {
+                line: 4,
+                character: 24
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_matching_bracket_enclosing() {
+        // Inside the string literal, whose brace does not count.
+        let pair = find_matching_bracket(SOURCE, 1, 13).unwrap();
+        assert_eq!(pair.brackets, "{}");
+        assert!(pair.enclosing);
+
+        let pair = find_matching_bracket(SOURCE, 3, 16).unwrap();
+        assert_eq!(pair.brackets, "()");
+        assert_eq!(
+            pair.open,
+            Position2D {
+                line: 4,
+                character: 16
+            }
+        );
+
+        assert!(find_matching_bracket(SOURCE, 0, 0).is_none());
+        assert!(find_matching_bracket("foo(bar", 0, 3).is_none());
+    }
+}
@@ -0,0 +1,134 @@
+//! Edits held back until the agent confirms them.
+//!
+//! An edit-applying tool whose edit exceeds `workspace.auto_apply_limits`
+//! returns its preview with a confirmation token instead of writing it. The
+//! edit is kept here, together with the hashes of the content it was computed
+//! against, until the token is passed to `confirm_apply`. Each token can be
+//! used once, only by the session it was issued to, and only the most
+//! recently issued tokens are kept. Tokens carry a random part, so a client
+//! sharing the process cannot guess another's.
+
+use std::collections::{HashMap, VecDeque};
+use std::hash::{BuildHasher, RandomState};
+
+use super::edits::PendingFileEdit;
+use super::encoding::PositionEncoding;
+use super::overlays::SessionId;
+
+/// Number of unconfirmed edits kept before the oldest are forgotten.
+pub const MAX_PENDING_CONFIRMATIONS: usize = 16;
+
+/// An edit awaiting confirmation.
+#[derive(Debug, Clone)]
+pub struct PendingApply {
+    /// Tool that produced the edit, recorded in the undo journal.
+    pub tool: String,
+    /// Text edits per file, with the content hashes they were computed against.
+    pub files: Vec<PendingFileEdit>,
+    /// Encoding of the edit positions.
+    pub encoding: PositionEncoding,
+    /// Session the token was issued to, the only one that can confirm it.
+    pub session: SessionId,
+}
+
+/// Registry mapping confirmation tokens to the edits they were issued for.
+#[derive(Debug)]
+pub struct PendingConfirmations {
+    entries: HashMap<String, PendingApply>,
+    /// Tokens in the order they were issued.
+    order: VecDeque<String>,
+    next_id: u64,
+    /// Randomly keyed, so the suffixes of issued tokens cannot be predicted.
+    random: RandomState,
+}
+
+impl Default for PendingConfirmations {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            next_id: 1,
+            random: RandomState::new(),
+        }
+    }
+}
+
+impl PendingConfirmations {
+    /// Issue a token for `edit`, forgetting the oldest pending edit if the
+    /// registry is full.
+    pub fn insert(&mut self, edit: PendingApply) -> String {
+        let token = format!(
+            "confirm-{}-{:016x}",
+            self.next_id,
+            self.random.hash_one(self.next_id)
+        );
+        self.next_id += 1;
+        if self.order.len() == MAX_PENDING_CONFIRMATIONS
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.entries.remove(&oldest);
+        }
+        self.order.push_back(token.clone());
+        self.entries.insert(token.clone(), edit);
+        token
+    }
+
+    /// Remove and return the edit a token was issued to `session` for,
+    /// unless it has already been taken or forgotten. A token issued to
+    /// another session is left in place.
+    pub fn take(&mut self, token: &str, session: SessionId) -> Option<PendingApply> {
+        if self.entries.get(token)?.session != session {
+            return None;
+        }
+        let edit = self.entries.remove(token)?;
+        self.order.retain(|issued| issued != token);
+        Some(edit)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn edit(tool: &str) -> PendingApply {
+        PendingApply {
+            tool: tool.to_string(),
+            files: Vec::new(),
+            encoding: PositionEncoding::Utf16,
+            session: 1,
+        }
+    }
+
+    #[test]
+    fn test_tokens_are_single_use_and_bounded() {
+        let mut pending = PendingConfirmations::default();
+        let first = pending.insert(edit("rename_symbol"));
+        let second = pending.insert(edit("rust_ssr"));
+        assert_ne!(first, second);
+
+        assert_eq!(pending.take(&second, 1).unwrap().tool, "rust_ssr");
+        assert!(pending.take(&second, 1).is_none());
+
+        for _ in 0..MAX_PENDING_CONFIRMATIONS {
+            pending.insert(edit("rename_symbol"));
+        }
+        assert!(pending.take(&first, 1).is_none());
+        assert!(pending.take("confirm-1", 1).is_none());
+    }
+
+    #[test]
+    fn test_tokens_belong_to_issuing_session() {
+        let mut pending = PendingConfirmations::default();
+        let token = pending.insert(edit("rename_symbol"));
+        assert!(token.starts_with("confirm-1-"));
+        assert_ne!(
+            token,
+            PendingConfirmations::default().insert(edit("rename_symbol"))
+        );
+
+        // Another session can neither confirm nor use up the edit.
+        assert!(pending.take(&token, 2).is_none());
+        assert_eq!(pending.take(&token, 1).unwrap().tool, "rename_symbol");
+    }
+}
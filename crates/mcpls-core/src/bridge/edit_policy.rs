@@ -0,0 +1,242 @@
+//! Protection of gitignored, generated, and vendored files from edits.
+//!
+//! Workspace-wide edits such as renames can reach build artifacts, generated
+//! code, or vendored trees that should never be changed by hand. The
+//! [`EditGuard`] recognises such files so edit-applying tools can skip them
+//! or warn about them, as configured by `workspace.protected_edits`.
+
+use std::path::{Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::translator::marked_path_origin;
+use crate::config::ProtectedEditPolicy;
+
+/// Number of leading lines searched for a generated-file marker.
+const GENERATED_HEADER_LINES: usize = 5;
+
+/// Markers that tools put at the top of files they generate.
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT"];
+
+/// Why a file is protected from edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtectionReason {
+    /// Matched by a `.gitignore` file in the workspace.
+    Ignored,
+    /// Matched by `workspace.generated_patterns` or carries a generated-file
+    /// marker in its header.
+    Generated,
+    /// Inside a vendored dependency tree (`vendor`, `node_modules`, ...).
+    Vendored,
+}
+
+/// An edit target covered by the protected-edit policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtectedFile {
+    /// URI of the document.
+    pub uri: String,
+    /// Why the file is protected.
+    pub reason: ProtectionReason,
+    /// Whether the edit to this file was left unapplied.
+    pub skipped: bool,
+}
+
+/// Classifies edit targets according to the protected-edit policy.
+#[derive(Debug)]
+pub struct EditGuard {
+    policy: ProtectedEditPolicy,
+    generated: Gitignore,
+}
+
+impl Default for EditGuard {
+    fn default() -> Self {
+        Self {
+            policy: ProtectedEditPolicy::default(),
+            generated: Gitignore::empty(),
+        }
+    }
+}
+
+impl EditGuard {
+    /// Create a guard from the configured policy and generated-file patterns.
+    ///
+    /// Invalid patterns are logged and ignored.
+    #[must_use]
+    pub fn new(policy: ProtectedEditPolicy, generated_patterns: &[String]) -> Self {
+        let mut builder = GitignoreBuilder::new(".");
+        for pattern in generated_patterns {
+            if let Err(e) = builder.add_line(None, pattern) {
+                tracing::warn!("Ignoring invalid generated_patterns entry '{pattern}': {e}");
+            }
+        }
+        let generated = builder.build().unwrap_or_else(|e| {
+            tracing::warn!("Ignoring generated_patterns: {e}");
+            Gitignore::empty()
+        });
+        Self { policy, generated }
+    }
+
+    /// Whether protected files are left unapplied.
+    #[must_use]
+    pub fn skips(&self) -> bool {
+        self.policy == ProtectedEditPolicy::Skip
+    }
+
+    /// Why `path` is protected from edits, if it is.
+    ///
+    /// `workspace_roots` must be canonical. Path-based rules only apply to
+    /// files inside a root; the generated-file header is checked for every
+    /// file. Always `None` under [`ProtectedEditPolicy::Allow`].
+    pub async fn check(
+        &self,
+        path: &Path,
+        workspace_roots: &[PathBuf],
+    ) -> Option<ProtectionReason> {
+        if self.policy == ProtectedEditPolicy::Allow {
+            return None;
+        }
+        let path = tokio::fs::canonicalize(path)
+            .await
+            .unwrap_or_else(|_| path.to_path_buf());
+
+        if let Some(root) = workspace_roots.iter().find(|root| path.starts_with(root))
+            && let Ok(relative) = path.strip_prefix(root)
+        {
+            if marked_path_origin(relative).is_some() {
+                return Some(ProtectionReason::Vendored);
+            }
+            if self
+                .generated
+                .matched_path_or_any_parents(relative, false)
+                .is_ignore()
+            {
+                return Some(ProtectionReason::Generated);
+            }
+            if is_gitignored(root, relative) {
+                return Some(ProtectionReason::Ignored);
+            }
+        }
+
+        has_generated_header(&path)
+            .await
+            .then_some(ProtectionReason::Generated)
+    }
+}
+
+/// Whether `.gitignore` files between `root` and the file ignore it.
+///
+/// The deepest `.gitignore` with a matching rule decides, so a nested
+/// negation re-includes files ignored further up.
+fn is_gitignored(root: &Path, relative: &Path) -> bool {
+    let path = root.join(relative);
+    let Some(parent) = relative.parent() else {
+        return false;
+    };
+    for dir in parent.ancestors() {
+        let gitignore = root.join(dir).join(".gitignore");
+        if !gitignore.is_file() {
+            continue;
+        }
+        let (rules, error) = Gitignore::new(&gitignore);
+        if let Some(e) = error {
+            tracing::debug!("Partially parsed {}: {e}", gitignore.display());
+        }
+        let matched = rules.matched_path_or_any_parents(&path, false);
+        if matched.is_ignore() {
+            return true;
+        }
+        if matched.is_whitelist() {
+            return false;
+        }
+    }
+    false
+}
+
+/// Whether the first lines of a file carry a generated-file marker.
+async fn has_generated_header(path: &Path) -> bool {
+    let Ok(content) = tokio::fs::read_to_string(path).await else {
+        return false;
+    };
+    content
+        .lines()
+        .take(GENERATED_HEADER_LINES)
+        .any(|line| GENERATED_MARKERS.iter().any(|marker| line.contains(marker)))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn workspace() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src/proto")).unwrap();
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::create_dir_all(root.join("vendor/dep")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("src/.gitignore"), "!keep.log\n").unwrap();
+        for file in [
+            "src/lib.rs",
+            "src/keep.log",
+            "src/proto/api.pb.rs",
+            "target/debug/out.rs",
+            "vendor/dep/lib.rs",
+        ] {
+            fs::write(root.join(file), "fn main() {}\n").unwrap();
+        }
+        fs::write(
+            root.join("src/bindings.rs"),
+            "// @generated by bindgen\nfn main() {}\n",
+        )
+        .unwrap();
+        (dir, root)
+    }
+
+    #[tokio::test]
+    async fn test_check_classifies_protected_files() {
+        let (_dir, root) = workspace();
+        let roots = vec![root.clone()];
+        let guard = EditGuard::new(ProtectedEditPolicy::Skip, &["*.pb.rs".to_string()]);
+
+        let cases = [
+            ("src/lib.rs", None),
+            ("src/keep.log", None),
+            ("target/debug/out.rs", Some(ProtectionReason::Ignored)),
+            ("vendor/dep/lib.rs", Some(ProtectionReason::Vendored)),
+            ("src/proto/api.pb.rs", Some(ProtectionReason::Generated)),
+            ("src/bindings.rs", Some(ProtectionReason::Generated)),
+        ];
+        for (file, expected) in cases {
+            assert_eq!(
+                guard.check(&root.join(file), &roots).await,
+                expected,
+                "{file}"
+            );
+        }
+        assert!(guard.skips());
+    }
+
+    #[tokio::test]
+    async fn test_allow_policy_protects_nothing() {
+        let (_dir, root) = workspace();
+        let guard = EditGuard::new(ProtectedEditPolicy::Allow, &[]);
+
+        assert_eq!(
+            guard
+                .check(
+                    &root.join("target/debug/out.rs"),
+                    std::slice::from_ref(&root)
+                )
+                .await,
+            None
+        );
+        assert!(!guard.skips());
+    }
+}
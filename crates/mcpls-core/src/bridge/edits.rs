@@ -0,0 +1,400 @@
+//! Applying LSP text edits to files on disk.
+//!
+//! Edits computed by a language server are only valid against the exact
+//! content the server saw. Before anything is written, every target file is
+//! checked against the hash of that content so a concurrent edit by the MCP
+//! host (or anything else) is reported instead of being silently corrupted.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+
+use lsp_types::{Position, TextEdit};
+
+use super::encoding::{EncodingConverter, PositionEncoding};
+use super::state::LineEnding;
+use crate::error::{Error, Result};
+
+/// Text edits for one file, together with the hash of the content the
+/// server computed them against.
+#[derive(Debug, Clone)]
+pub struct PendingFileEdit {
+    /// Absolute path of the file to edit.
+    pub path: PathBuf,
+    /// [`content_hash`] of the content the edits were computed against.
+    pub expected_hash: u64,
+    /// Edits to apply, in LSP order.
+    pub edits: Vec<TextEdit>,
+    /// Line ending the inserted text is written with.
+    pub line_ending: LineEnding,
+}
+
+/// A checked file edit from [`prepare_file_edits`], ready to be written.
+#[derive(Debug, Clone)]
+pub struct PreparedFileEdit {
+    /// Absolute path of the file to edit.
+    pub path: PathBuf,
+    /// Content on disk before the edit.
+    pub original: String,
+    /// Content after the edit.
+    pub content: String,
+}
+
+/// Hash file content for conflict detection.
+#[must_use]
+pub fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Check edits for several files and compute their new contents.
+///
+/// Every file is read and checked against its expected hash, and every new
+/// content is computed, without writing anything, so a conflict or an invalid
+/// edit leaves the disk untouched. Pass the result to [`write_file_edits`].
+///
+/// # Errors
+///
+/// Returns [`Error::EditConflict`] listing every file whose content changed
+/// since the edits were computed, an [`Error::InvalidToolParams`] if an edit
+/// does not fit the file, or [`Error::FileIo`] if reading fails.
+pub async fn prepare_file_edits(
+    files: Vec<PendingFileEdit>,
+    encoding: PositionEncoding,
+) -> Result<Vec<PreparedFileEdit>> {
+    let mut current = Vec::with_capacity(files.len());
+    let mut conflicts = Vec::new();
+    for file in files {
+        let content = read_file(&file.path).await?;
+        if content_hash(&content) != file.expected_hash {
+            conflicts.push(file.path.clone());
+        }
+        current.push((file, content));
+    }
+    if !conflicts.is_empty() {
+        return Err(Error::EditConflict { paths: conflicts });
+    }
+
+    let mut prepared = Vec::with_capacity(current.len());
+    for (file, original) in current {
+        let content = apply_text_edits(&original, &file.edits, encoding, file.line_ending)
+            .map_err(|e| {
+                Error::InvalidToolParams(format!(
+                    "cannot apply edit to {}: {e}",
+                    file.path.display()
+                ))
+            })?;
+        prepared.push(PreparedFileEdit {
+            path: file.path,
+            original,
+            content,
+        });
+    }
+    Ok(prepared)
+}
+
+/// Write the new contents of prepared file edits to disk.
+///
+/// # Errors
+///
+/// Returns [`Error::FileIo`] if a file cannot be written.
+pub async fn write_file_edits(files: &[PreparedFileEdit]) -> Result<()> {
+    for file in files {
+        write_file(&file.path, &file.content).await?;
+    }
+    Ok(())
+}
+
+/// Write a file, mapping failures to [`Error::FileIo`].
+///
+/// # Errors
+///
+/// Returns [`Error::FileIo`] if the file cannot be written.
+pub async fn write_file(path: &std::path::Path, content: &str) -> Result<()> {
+    tokio::fs::write(path, content)
+        .await
+        .map_err(|e| Error::FileIo {
+            path: path.to_path_buf(),
+            source: e,
+        })
+}
+
+/// Read a file for editing.
+///
+/// # Errors
+///
+/// Returns [`Error::FileIo`] if the file cannot be read as UTF-8.
+pub async fn read_file(path: &std::path::Path) -> Result<String> {
+    tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| Error::FileIo {
+            path: path.to_path_buf(),
+            source: e,
+        })
+}
+
+/// Apply LSP text edits to `content`.
+///
+/// Edits are applied back to front; edits sharing a start position keep
+/// their array order in the result, as the LSP specification requires.
+/// Line breaks in the inserted text are rewritten to `line_ending`, since
+/// servers often send `\n` regardless of the document's line endings.
+///
+/// # Errors
+///
+/// Returns an error if a position lies outside the content or two edits
+/// overlap.
+pub fn apply_text_edits(
+    content: &str,
+    edits: &[TextEdit],
+    encoding: PositionEncoding,
+    line_ending: LineEnding,
+) -> std::result::Result<String, String> {
+    let converter = EncodingConverter::new(encoding);
+    let line_starts = line_starts(content);
+
+    let mut spans = edits
+        .iter()
+        .map(|edit| {
+            let start = byte_offset(content, &line_starts, edit.range.start, &converter)?;
+            let end = byte_offset(content, &line_starts, edit.range.end, &converter)?;
+            if end < start {
+                return Err(format!(
+                    "edit range ends before it starts at line {}",
+                    edit.range.start.line + 1
+                ));
+            }
+            Ok((start, end, line_ending.normalize(&edit.new_text)))
+        })
+        .collect::<std::result::Result<Vec<_>, String>>()?;
+    spans.sort_by_key(|&(start, end, _)| (start, end));
+
+    for pair in spans.windows(2) {
+        if pair[1].0 < pair[0].1 {
+            return Err("edits overlap".to_string());
+        }
+    }
+
+    let mut result = content.to_string();
+    for (start, end, new_text) in spans.iter().rev() {
+        result.replace_range(*start..*end, new_text);
+    }
+    Ok(result)
+}
+
+/// Byte span of `content` covered by an LSP range.
+///
+/// # Errors
+///
+/// Returns an error if a position lies outside the content or the range ends
+/// before it starts.
+pub fn byte_range(
+    content: &str,
+    range: lsp_types::Range,
+    encoding: PositionEncoding,
+) -> std::result::Result<std::ops::Range<usize>, String> {
+    let converter = EncodingConverter::new(encoding);
+    let line_starts = line_starts(content);
+    let start = byte_offset(content, &line_starts, range.start, &converter)?;
+    let end = byte_offset(content, &line_starts, range.end, &converter)?;
+    if end < start {
+        return Err(format!(
+            "range ends before it starts at line {}",
+            range.start.line + 1
+        ));
+    }
+    Ok(start..end)
+}
+
+/// Byte offsets at which each line of `content` starts.
+fn line_starts(content: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// Convert an LSP position to a byte offset into `content`.
+///
+/// A position one line past the end addresses the end of the content.
+fn byte_offset(
+    content: &str,
+    line_starts: &[usize],
+    position: Position,
+    converter: &EncodingConverter,
+) -> std::result::Result<usize, String> {
+    let line = position.line as usize;
+    let Some(&start) = line_starts.get(line) else {
+        if line == line_starts.len() && position.character == 0 {
+            return Ok(content.len());
+        }
+        return Err(format!("line {} is past the end of the file", line + 1));
+    };
+    let end = line_starts
+        .get(line + 1)
+        .map_or(content.len(), |&next| next - 1);
+    let line_text = content[start..end].trim_end_matches('\r');
+    converter
+        .character_to_byte_offset(line_text, position.character)
+        .map(|offset| start + offset)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use lsp_types::Range;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn edit(start: (u32, u32), end: (u32, u32), text: &str) -> TextEdit {
+        TextEdit {
+            range: Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1)),
+            new_text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_text_edits_replaces_back_to_front() {
+        let content = "fn old() {}\nfn main() { old(); }\n";
+        let edits = vec![edit((0, 3), (0, 6), "new"), edit((1, 12), (1, 15), "new")];
+        let result =
+            apply_text_edits(content, &edits, PositionEncoding::Utf16, LineEnding::Lf).unwrap();
+        assert_eq!(result, "fn new() {}\nfn main() { new(); }\n");
+    }
+
+    #[test]
+    fn test_apply_text_edits_keeps_order_of_inserts_at_same_position() {
+        let edits = vec![edit((0, 0), (0, 0), "a"), edit((0, 0), (0, 0), "b")];
+        let result = apply_text_edits("x", &edits, PositionEncoding::Utf8, LineEnding::Lf).unwrap();
+        assert_eq!(result, "abx");
+    }
+
+    #[test]
+    fn test_apply_text_edits_uses_position_encoding() {
+        // "é" is one UTF-16 unit but two UTF-8 bytes.
+        let content = "let é = 1;\n";
+        let utf16 = vec![edit((0, 4), (0, 5), "e")];
+        assert_eq!(
+            apply_text_edits(content, &utf16, PositionEncoding::Utf16, LineEnding::Lf).unwrap(),
+            "let e = 1;\n"
+        );
+        let utf8 = vec![edit((0, 4), (0, 6), "e")];
+        assert_eq!(
+            apply_text_edits(content, &utf8, PositionEncoding::Utf8, LineEnding::Lf).unwrap(),
+            "let e = 1;\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_text_edits_handles_crlf_and_end_of_file() {
+        let content = "a\r\nb";
+        let edits = vec![edit((0, 1), (0, 1), "!"), edit((2, 0), (2, 0), "\r\nc")];
+        let result =
+            apply_text_edits(content, &edits, PositionEncoding::Utf16, LineEnding::Crlf).unwrap();
+        assert_eq!(result, "a!\r\nb\r\nc");
+    }
+
+    #[test]
+    fn test_apply_text_edits_preserves_crlf_in_inserted_text() {
+        let content = "fn a() {}\r\nfn b() {}\r\n";
+        let edits = vec![edit((1, 0), (1, 0), "// b\n")];
+        let result =
+            apply_text_edits(content, &edits, PositionEncoding::Utf16, LineEnding::Crlf).unwrap();
+        assert_eq!(result, "fn a() {}\r\n// b\r\nfn b() {}\r\n");
+    }
+
+    #[test]
+    fn test_apply_text_edits_rejects_overlap_and_out_of_range() {
+        let overlapping = vec![edit((0, 0), (0, 3), "x"), edit((0, 2), (0, 4), "y")];
+        assert!(
+            apply_text_edits(
+                "abcdef",
+                &overlapping,
+                PositionEncoding::Utf8,
+                LineEnding::Lf
+            )
+            .is_err()
+        );
+
+        let past_end = vec![edit((5, 0), (5, 1), "x")];
+        assert!(
+            apply_text_edits("abc", &past_end, PositionEncoding::Utf8, LineEnding::Lf).is_err()
+        );
+    }
+
+    #[test]
+    fn test_byte_range_uses_position_encoding() {
+        let content = "let é = 1;\nfoo();\n";
+        let range = Range::new(Position::new(0, 4), Position::new(1, 3));
+        let span = byte_range(content, range, PositionEncoding::Utf16).unwrap();
+        assert_eq!(&content[span], "é = 1;\nfoo");
+        assert!(
+            byte_range(
+                content,
+                Range::new(Position::new(1, 0), Position::new(0, 0)),
+                PositionEncoding::Utf16
+            )
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prepare_and_write_file_edits_when_unchanged() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "fn old() {}\n").unwrap();
+
+        let prepared = prepare_file_edits(
+            vec![PendingFileEdit {
+                path: path.clone(),
+                expected_hash: content_hash("fn old() {}\n"),
+                edits: vec![edit((0, 3), (0, 6), "new")],
+                line_ending: LineEnding::Lf,
+            }],
+            PositionEncoding::Utf16,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(prepared.len(), 1);
+        assert_eq!(prepared[0].original, "fn old() {}\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn old() {}\n");
+
+        write_file_edits(&prepared).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn new() {}\n");
+    }
+
+    #[tokio::test]
+    async fn test_prepare_file_edits_reports_every_conflict() {
+        let dir = TempDir::new().unwrap();
+        let changed = dir.path().join("changed.rs");
+        let unchanged = dir.path().join("unchanged.rs");
+        std::fs::write(&changed, "fn old() { /* edited by host */ }\n").unwrap();
+        std::fs::write(&unchanged, "old();\n").unwrap();
+
+        let result = prepare_file_edits(
+            vec![
+                PendingFileEdit {
+                    path: unchanged.clone(),
+                    expected_hash: content_hash("old();\n"),
+                    edits: vec![edit((0, 0), (0, 3), "new")],
+                    line_ending: LineEnding::Lf,
+                },
+                PendingFileEdit {
+                    path: changed.clone(),
+                    expected_hash: content_hash("fn old() {}\n"),
+                    edits: vec![edit((0, 3), (0, 6), "new")],
+                    line_ending: LineEnding::Lf,
+                },
+            ],
+            PositionEncoding::Utf16,
+        )
+        .await;
+
+        match result {
+            Err(Error::EditConflict { paths }) => assert_eq!(paths, vec![changed]),
+            other => panic!("expected EditConflict, got {other:?}"),
+        }
+        assert_eq!(std::fs::read_to_string(&unchanged).unwrap(), "old();\n");
+    }
+}
@@ -3,7 +3,9 @@
 //! Handles conversion between MCP (1-based) and LSP (0-based) positions,
 //! as well as UTF-8/UTF-16/UTF-32 encoding conversions.
 
-use lsp_types::Position;
+use std::collections::HashMap;
+
+use lsp_types::{Location, Position, Range, Uri};
 
 /// Supported position encodings per LSP 3.17.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -68,7 +70,6 @@ pub struct EncodingConverter {
     encoding: PositionEncoding,
 }
 
-#[allow(dead_code)] // Will be used when LSP client integration is complete
 impl EncodingConverter {
     /// Create a new encoding converter with the specified encoding.
     #[must_use]
@@ -166,6 +167,145 @@ impl EncodingConverter {
     }
 }
 
+/// Line text of documents, for converting positions between a server's
+/// position encoding and the character columns mcpls exposes.
+///
+/// Agents count columns in characters (Unicode scalar values), while servers
+/// count in the negotiated encoding: UTF-16 counts `😀` as two units and UTF-8
+/// counts `é` as two bytes. Positions in documents that were not loaded are
+/// passed through unchanged; positions past the end of a line keep their
+/// distance from the line end.
+#[derive(Debug, Clone)]
+pub struct ColumnMap {
+    converter: EncodingConverter,
+    documents: HashMap<String, Vec<String>>,
+}
+
+impl ColumnMap {
+    /// Create an empty map for a server using `encoding`.
+    #[must_use]
+    pub fn new(encoding: PositionEncoding) -> Self {
+        Self {
+            converter: EncodingConverter::new(encoding),
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Whether conversion needs document text; UTF-32 offsets already count
+    /// characters.
+    #[must_use]
+    pub const fn needs_text(&self) -> bool {
+        !matches!(self.converter.encoding, PositionEncoding::Utf32)
+    }
+
+    /// Whether the text of `uri` is loaded.
+    #[must_use]
+    pub fn contains(&self, uri: &Uri) -> bool {
+        self.documents.contains_key(uri.as_str())
+    }
+
+    /// Load the text of `uri`.
+    pub fn insert(&mut self, uri: &Uri, content: &str) {
+        if self.needs_text() {
+            let lines = content.lines().map(str::to_string).collect();
+            self.documents.insert(uri.as_str().to_string(), lines);
+        }
+    }
+
+    fn line(&self, uri: &Uri, line: u32) -> Option<&str> {
+        self.documents
+            .get(uri.as_str())
+            .and_then(|lines| lines.get(line as usize))
+            .map(String::as_str)
+    }
+
+    /// Convert a position from server units to character columns.
+    #[must_use]
+    pub fn to_columns(&self, uri: &Uri, position: Position) -> Position {
+        let Some(text) = self.line(uri, position.line) else {
+            return position;
+        };
+        let character = self
+            .converter
+            .character_to_byte_offset(text, position.character)
+            .map_or_else(
+                |_| {
+                    saturating_u32(text.chars().count())
+                        .saturating_add(position.character.saturating_sub(self.units(text)))
+                },
+                // A UTF-8 offset inside a character counts that character.
+                |byte| saturating_u32(text.char_indices().take_while(|&(i, _)| i < byte).count()),
+            );
+        Position {
+            character,
+            ..position
+        }
+    }
+
+    /// Convert a position from character columns to server units.
+    #[must_use]
+    pub fn to_server(&self, uri: &Uri, position: Position) -> Position {
+        let Some(text) = self.line(uri, position.line) else {
+            return position;
+        };
+        let character = text
+            .char_indices()
+            .nth(position.character as usize)
+            .map_or_else(
+                || {
+                    let chars = saturating_u32(text.chars().count());
+                    self.units(text)
+                        .saturating_add(position.character.saturating_sub(chars))
+                },
+                |(byte, _)| {
+                    self.converter
+                        .byte_offset_to_character(text, byte)
+                        .unwrap_or(position.character)
+                },
+            );
+        Position {
+            character,
+            ..position
+        }
+    }
+
+    /// Convert a range from server units to character columns.
+    #[must_use]
+    pub fn range_to_columns(&self, uri: &Uri, range: Range) -> Range {
+        Range {
+            start: self.to_columns(uri, range.start),
+            end: self.to_columns(uri, range.end),
+        }
+    }
+
+    /// Convert a range from character columns to server units.
+    #[must_use]
+    pub fn range_to_server(&self, uri: &Uri, range: Range) -> Range {
+        Range {
+            start: self.to_server(uri, range.start),
+            end: self.to_server(uri, range.end),
+        }
+    }
+
+    /// Convert the ranges of `locations` to character columns in place.
+    pub fn locations_to_columns(&self, locations: &mut [Location]) {
+        for location in locations {
+            location.range = self.range_to_columns(&location.uri, location.range);
+        }
+    }
+
+    /// Length of a line in server units.
+    fn units(&self, text: &str) -> u32 {
+        self.converter
+            .byte_offset_to_character(text, text.len())
+            .unwrap_or(u32::MAX)
+    }
+}
+
+fn saturating_u32(n: usize) -> u32 {
+    u32::try_from(n).unwrap_or(u32::MAX)
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -305,4 +445,62 @@ mod tests {
         let end_offset = converter.byte_offset_to_character("test", 4).unwrap();
         assert_eq!(end_offset, 4);
     }
+
+    fn uri() -> Uri {
+        "file:///test.rs".parse().unwrap()
+    }
+
+    #[test]
+    fn test_column_map_converts_utf16_units_to_characters() {
+        let mut columns = ColumnMap::new(PositionEncoding::Utf16);
+        columns.insert(&uri(), "fn a() {}\nlet s = \"😀\"; x\n");
+
+        // `x` is at character 13 but at UTF-16 unit 14.
+        let server = Position::new(1, 14);
+        let user = Position::new(1, 13);
+        assert_eq!(columns.to_columns(&uri(), server), user);
+        assert_eq!(columns.to_server(&uri(), user), server);
+        // ASCII lines are unchanged.
+        assert_eq!(
+            columns.to_columns(&uri(), Position::new(0, 3)),
+            Position::new(0, 3)
+        );
+    }
+
+    #[test]
+    fn test_column_map_converts_utf8_bytes_to_characters() {
+        let mut columns = ColumnMap::new(PositionEncoding::Utf8);
+        columns.insert(&uri(), "let é = 1;\r\n");
+
+        let range = Range::new(Position::new(0, 4), Position::new(0, 6));
+        let expected = Range::new(Position::new(0, 4), Position::new(0, 5));
+        assert_eq!(columns.range_to_columns(&uri(), range), expected);
+        assert_eq!(columns.range_to_server(&uri(), expected), range);
+    }
+
+    #[test]
+    fn test_column_map_passes_through_unknown_positions() {
+        let mut columns = ColumnMap::new(PositionEncoding::Utf16);
+        columns.insert(&uri(), "😀\n");
+
+        // Past the end of the line, the overshoot is kept.
+        assert_eq!(
+            columns.to_columns(&uri(), Position::new(0, 5)),
+            Position::new(0, 4)
+        );
+        assert_eq!(
+            columns.to_server(&uri(), Position::new(0, 4)),
+            Position::new(0, 5)
+        );
+        // Lines and documents that are not loaded are unchanged.
+        assert_eq!(
+            columns.to_columns(&uri(), Position::new(3, 7)),
+            Position::new(3, 7)
+        );
+        let other: Uri = "file:///other.rs".parse().unwrap();
+        assert_eq!(
+            columns.to_columns(&other, Position::new(0, 2)),
+            Position::new(0, 2)
+        );
+    }
 }
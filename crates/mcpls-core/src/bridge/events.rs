@@ -0,0 +1,135 @@
+//! Events published by the bridge.
+//!
+//! The translator announces what happens to its servers, documents, and
+//! files on an [`EventBus`]. Embedders subscribe to it to follow the bridge
+//! without polling the translator, and so do `mcpls watch` and the MCP
+//! resource update notifications.
+
+use std::path::PathBuf;
+
+use lsp_types::Uri;
+use tokio::sync::broadcast;
+
+use crate::lsp::LspNotification;
+
+/// Events a subscriber can fall behind by before it misses some.
+const EVENT_CAPACITY: usize = 256;
+
+/// Something that happened in the bridge.
+#[derive(Debug, Clone)]
+pub enum BridgeEvent {
+    /// A server registered and is ready for requests.
+    ServerStarted {
+        /// Language ID of the server.
+        language_id: String,
+    },
+    /// A server exited without being asked to.
+    ServerCrashed {
+        /// Language ID of the server.
+        language_id: String,
+    },
+    /// `reset_bridge` restarted a server.
+    ServerRestarted {
+        /// Language ID of the server.
+        language_id: String,
+    },
+    /// A server published the diagnostics of a document. They are in the
+    /// notification cache by the time the event is received.
+    DiagnosticsUpdated {
+        /// Language ID of the server.
+        language_id: String,
+        /// Document the diagnostics are for.
+        uri: Uri,
+    },
+    /// A document was opened on its server.
+    DocumentOpened {
+        /// URI of the document.
+        uri: Uri,
+        /// Language ID of the document.
+        language_id: String,
+    },
+    /// A document was closed.
+    DocumentClosed {
+        /// URI of the document.
+        uri: Uri,
+    },
+    /// Edits were written to disk.
+    EditApplied {
+        /// Tool that wrote them, as recorded in the undo journal.
+        tool: String,
+        /// Files written.
+        files: Vec<PathBuf>,
+    },
+    /// A server sent a log message, a message to show, telemetry, progress,
+    /// or a notification mcpls does not handle.
+    Notification {
+        /// Language ID of the server.
+        language_id: String,
+        /// The notification.
+        notification: LspNotification,
+    },
+}
+
+/// Broadcast channel of [`BridgeEvent`]s. Clones publish to the same
+/// subscribers.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<BridgeEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(EVENT_CAPACITY).0,
+        }
+    }
+}
+
+impl EventBus {
+    /// Receive the events published from now on. A subscriber that falls
+    /// more than 256 events behind misses the oldest ones.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<BridgeEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Whether anyone is subscribed, for publishers that would have to copy
+    /// data to build an event.
+    #[must_use]
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Publish `event` to the current subscribers, if any.
+    pub fn publish(&self, event: BridgeEvent) {
+        // Without subscribers the event is simply dropped.
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_event_bus() {
+        let bus = EventBus::default();
+        assert!(!bus.has_subscribers());
+        bus.publish(BridgeEvent::ServerStarted {
+            language_id: "rust".to_string(),
+        });
+
+        let mut events = bus.clone().subscribe();
+        assert!(bus.has_subscribers());
+        bus.publish(BridgeEvent::ServerCrashed {
+            language_id: "go".to_string(),
+        });
+        // Events published before subscribing are not received.
+        assert!(matches!(
+            events.recv().await.unwrap(),
+            BridgeEvent::ServerCrashed { language_id } if language_id == "go"
+        ));
+        assert!(events.try_recv().is_err());
+    }
+}
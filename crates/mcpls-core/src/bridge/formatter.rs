@@ -0,0 +1,130 @@
+//! External formatters, run when a language server cannot format a file.
+//!
+//! rust-analyzer only formats through rustfmt, and a broken toolchain makes
+//! `textDocument/formatting` fail; other servers do not format at all. A
+//! language with a `[formatting.<language_id>.fallback]` formatter has
+//! `format_document` run it instead.
+
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::FormatterCommand;
+use crate::error::{Error, Result};
+
+/// Placeholder in formatter arguments for the path of the file.
+const FILE_PLACEHOLDER: &str = "{file}";
+
+/// Run `formatter` on `content`, the current text of the file at `path`, and
+/// return the formatted text.
+///
+/// The formatter runs in the file's directory, so that configuration files
+/// next to the file (e.g. `rustfmt.toml`) apply.
+///
+/// # Errors
+///
+/// Returns an error if the formatter cannot be started, fails, or runs past
+/// its timeout, or if its output is not UTF-8.
+pub async fn run_formatter(
+    formatter: &FormatterCommand,
+    path: &Path,
+    content: &str,
+) -> Result<String> {
+    let file = path.display().to_string();
+    let mut command = Command::new(&formatter.command);
+    command
+        .args(
+            formatter
+                .args
+                .iter()
+                .map(|arg| arg.replace(FILE_PLACEHOLDER, &file)),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = path.parent() {
+        command.current_dir(dir);
+    }
+    let mut child = command.spawn()?;
+
+    let mut stdin = child.stdin.take();
+    let input = async move {
+        if let Some(stdin) = stdin.as_mut() {
+            // A formatter that exits without reading its input fails below.
+            let _ = stdin.write_all(content.as_bytes()).await;
+        }
+        drop(stdin);
+    };
+    let run = async { tokio::join!(input, child.wait_with_output()).1 };
+    let output = tokio::time::timeout(Duration::from_secs(formatter.timeout_seconds), run)
+        .await
+        .map_err(|_| Error::Timeout(formatter.timeout_seconds))??;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Io(std::io::Error::other(format!(
+            "{} exited with {}: {}",
+            formatter.command,
+            output.status,
+            stderr.trim()
+        ))));
+    }
+    String::from_utf8(output.stdout).map_err(|_| {
+        Error::Io(std::io::Error::other(format!(
+            "{} printed output that is not UTF-8",
+            formatter.command
+        )))
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn formatter(command: &str, args: &[&str]) -> FormatterCommand {
+        FormatterCommand {
+            command: command.to_string(),
+            args: args.iter().map(ToString::to_string).collect(),
+            timeout_seconds: 5,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_formatter_pipes_content() {
+        let upper = formatter("tr", &["a-z", "A-Z"]);
+        let formatted = run_formatter(&upper, Path::new("/tmp/main.rs"), "fn main() {}\n")
+            .await
+            .unwrap();
+        assert_eq!(formatted, "FN MAIN() {}\n");
+    }
+
+    #[tokio::test]
+    async fn test_run_formatter_substitutes_file() {
+        let echo = formatter("sh", &["-c", "cat >/dev/null; echo \"$0\"", "{file}"]);
+        let formatted = run_formatter(&echo, Path::new("/tmp/lib.rs"), "")
+            .await
+            .unwrap();
+        assert_eq!(formatted, "/tmp/lib.rs\n");
+    }
+
+    #[tokio::test]
+    async fn test_run_formatter_reports_failure() {
+        let failing = formatter("sh", &["-c", "echo 'syntax error' >&2; exit 1"]);
+        let err = run_formatter(&failing, Path::new("/tmp/main.rs"), "fn")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("syntax error"));
+
+        let missing = formatter("mcpls-no-such-formatter", &[]);
+        assert!(
+            run_formatter(&missing, Path::new("/tmp/main.rs"), "")
+                .await
+                .is_err()
+        );
+    }
+}
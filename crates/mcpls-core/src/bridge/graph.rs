@@ -0,0 +1,145 @@
+//! Graphviz DOT rendering of call hierarchies and references.
+//!
+//! Hosts can render the graphs directly, and agents get a compact edge list:
+//! one line per caller, callee, or referencing file instead of a JSON object
+//! per location. Files are named by their path below the directory all files
+//! of the graph share, so same-named files in different directories stay
+//! apart while the names stay short.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf};
+
+use lsp_types::Uri;
+
+use super::uri::uri_to_path;
+
+/// Builder for a directed DOT graph.
+#[derive(Debug)]
+pub struct DotGraph {
+    out: String,
+}
+
+impl DotGraph {
+    /// Start a graph called `name`, laid out left to right.
+    pub fn new(name: &str) -> Self {
+        Self {
+            out: format!("digraph {name} {{\n  rankdir=LR;\n"),
+        }
+    }
+
+    /// Add a node drawn as a box, marking the subject of the graph.
+    pub fn subject(&mut self, id: &str) {
+        let _ = writeln!(self.out, "  {} [shape=box];", quote(id));
+    }
+
+    /// Add an edge from `from` to `to`, labelled `label` unless it is empty.
+    pub fn edge(&mut self, from: &str, to: &str, label: &str) {
+        let _ = write!(self.out, "  {} -> {}", quote(from), quote(to));
+        if !label.is_empty() {
+            let _ = write!(self.out, " [label={}]", quote(label));
+        }
+        self.out.push_str(";\n");
+    }
+
+    /// The finished graph.
+    pub fn finish(mut self) -> String {
+        self.out.push_str("}\n");
+        self.out
+    }
+}
+
+/// Label listing 1-based `lines`, e.g. `L3,L7`.
+pub fn lines_label(lines: impl IntoIterator<Item = u32>) -> String {
+    lines
+        .into_iter()
+        .map(|line| format!("L{line}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Short names of the files behind `uris`: their paths below the deepest
+/// directory containing all of them. URIs that are not file paths are left
+/// out.
+pub fn short_names<'a>(uris: impl IntoIterator<Item = &'a str>) -> HashMap<String, String> {
+    let paths: Vec<(String, PathBuf)> = uris
+        .into_iter()
+        .filter_map(|uri| {
+            let path = uri.parse::<Uri>().ok().and_then(|u| uri_to_path(&u))?;
+            Some((uri.to_string(), path))
+        })
+        .collect();
+    let base = common_dir(paths.iter().map(|(_, path)| path.as_path()));
+    paths
+        .into_iter()
+        .map(|(uri, path)| {
+            let short = base
+                .as_ref()
+                .and_then(|base| path.strip_prefix(base).ok())
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            (uri, short)
+        })
+        .collect()
+}
+
+/// Deepest directory that is an ancestor of every path.
+fn common_dir<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Option<PathBuf> {
+    let mut common: Option<Vec<Component<'a>>> = None;
+    for path in paths {
+        let parent: Vec<_> = path.parent()?.components().collect();
+        common = Some(match common {
+            None => parent,
+            Some(prefix) => prefix
+                .into_iter()
+                .zip(parent)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    common.map(|components| components.into_iter().collect())
+}
+
+/// `text` as a DOT string literal.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_graph() {
+        let mut graph = DotGraph::new("incoming_calls");
+        graph.subject("run (lib.rs:3)");
+        graph.edge("main (main.rs:1)", "run (lib.rs:3)", &lines_label([2, 5]));
+        graph.edge("say \"hi\"", "run (lib.rs:3)", "");
+        assert_eq!(
+            graph.finish(),
+            "digraph incoming_calls {\n  rankdir=LR;\n  \"run (lib.rs:3)\" [shape=box];\n  \
+             \"main (main.rs:1)\" -> \"run (lib.rs:3)\" [label=\"L2,L5\"];\n  \
+             \"say \\\"hi\\\"\" -> \"run (lib.rs:3)\";\n}\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_short_names() {
+        let names = short_names([
+            "file:///ws/src/api/mod.rs",
+            "file:///ws/src/db/mod.rs",
+            "file:///ws/src/main.rs",
+            "untitled:Untitled-1",
+        ]);
+        assert_eq!(names["file:///ws/src/api/mod.rs"], "api/mod.rs");
+        assert_eq!(names["file:///ws/src/main.rs"], "main.rs");
+        assert!(!names.contains_key("untitled:Untitled-1"));
+
+        let single = short_names(["file:///ws/src/main.rs"]);
+        assert_eq!(single["file:///ws/src/main.rs"], "main.rs");
+    }
+}
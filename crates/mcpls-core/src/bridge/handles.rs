@@ -0,0 +1,85 @@
+//! Short-lived handles for symbols returned by tools.
+//!
+//! Prepare and search tools tag each symbol they return with a small opaque
+//! ID. Follow-up tools accept the ID instead of the symbol itself, so agents
+//! do not have to copy large LSP structures back verbatim. Only the most
+//! recently issued handles are kept.
+
+use std::collections::{HashMap, VecDeque};
+
+/// Number of handles kept before the oldest are forgotten.
+pub const MAX_SYMBOL_HANDLES: usize = 1024;
+
+/// Registry mapping handle IDs to the symbols they were issued for.
+#[derive(Debug)]
+pub struct SymbolHandles<T> {
+    entries: HashMap<String, T>,
+    /// Handle IDs in the order they were issued.
+    order: VecDeque<String>,
+    next_id: u64,
+}
+
+impl<T> Default for SymbolHandles<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl<T> SymbolHandles<T> {
+    /// Issue a handle for `target`, forgetting the oldest handle if the
+    /// registry is full.
+    pub fn insert(&mut self, target: T) -> String {
+        let id = format!("h{}", self.next_id);
+        self.next_id += 1;
+        if self.order.len() == MAX_SYMBOL_HANDLES
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.entries.remove(&oldest);
+        }
+        self.order.push_back(id.clone());
+        self.entries.insert(id.clone(), target);
+        id
+    }
+
+    /// The symbol a handle was issued for, unless it has been forgotten.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&T> {
+        self.entries.get(id)
+    }
+
+    /// Forget every handle, e.g. after the servers that produced the symbols
+    /// restarted. IDs are not reused.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oldest_handles_are_forgotten() {
+        let mut handles = SymbolHandles::default();
+        let first = handles.insert(0);
+        let second = handles.insert(1);
+        assert_ne!(first, second);
+        assert_eq!(handles.get(&second), Some(&1));
+
+        for n in 2..=MAX_SYMBOL_HANDLES {
+            handles.insert(n);
+        }
+        assert!(handles.get(&first).is_none());
+        assert_eq!(handles.get(&second), Some(&1));
+
+        handles.clear();
+        assert!(handles.get(&second).is_none());
+        assert_ne!(handles.insert(0), second);
+    }
+}
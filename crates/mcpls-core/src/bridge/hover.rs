@@ -0,0 +1,226 @@
+//! Sectioning and length control for hover documentation.
+//!
+//! Hover text is often a signature followed by a long doc comment with
+//! examples. It is split into named sections so callers can ask for just the
+//! parts they need, and truncation drops whole trailing sections before it
+//! cuts into one, so the signature survives a tight length limit.
+
+/// Section holding the code blocks that precede any prose.
+pub const SIGNATURE_SECTION: &str = "signature";
+
+/// Section holding the prose before the first heading.
+pub const DESCRIPTION_SECTION: &str = "description";
+
+/// A named part of hover markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoverSection {
+    /// `signature`, `description`, or the lowercased text of the heading
+    /// that starts the section (e.g. `examples`, `panics`).
+    pub name: String,
+    /// Markdown of the section, including its heading.
+    pub text: String,
+}
+
+/// Split hover markdown into sections.
+///
+/// Code blocks before the first prose line form the signature; prose up to
+/// the first heading forms the description; every heading starts a section
+/// named after it. Thematic breaks (`---`) between sections are dropped.
+#[must_use]
+pub fn split_sections(markdown: &str) -> Vec<HoverSection> {
+    let mut sections: Vec<HoverSection> = Vec::new();
+    let mut name = SIGNATURE_SECTION.to_string();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut fence: Option<&str> = None;
+
+    let flush = |sections: &mut Vec<HoverSection>, name: &str, lines: &mut Vec<&str>| {
+        let text = lines.join("\n").trim().to_string();
+        lines.clear();
+        if !text.is_empty() {
+            sections.push(HoverSection {
+                name: name.to_string(),
+                text,
+            });
+        }
+    };
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            lines.push(line);
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            lines.push(line);
+            continue;
+        }
+        if is_thematic_break(trimmed) {
+            continue;
+        }
+        if let Some(heading) = heading_text(trimmed) {
+            flush(&mut sections, &name, &mut lines);
+            name = heading.to_lowercase();
+        } else if name == SIGNATURE_SECTION && !trimmed.is_empty() {
+            flush(&mut sections, &name, &mut lines);
+            name = DESCRIPTION_SECTION.to_string();
+        }
+        lines.push(line);
+    }
+    flush(&mut sections, &name, &mut lines);
+    sections
+}
+
+/// Text of an ATX heading line (`# Examples`), if the line is one.
+fn heading_text(line: &str) -> Option<&str> {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    ((1..=6).contains(&level) && text.starts_with(' '))
+        .then(|| text.trim().trim_end_matches('#').trim())
+}
+
+/// Whether a line is a thematic break such as `---` or `***`.
+fn is_thematic_break(line: &str) -> bool {
+    let line = line.trim_end();
+    line.len() >= 3
+        && line.chars().next().is_some_and(|first| {
+            matches!(first, '-' | '*' | '_') && line.chars().all(|c| c == first)
+        })
+}
+
+/// Hover text reduced to the requested sections and length.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShapedHover {
+    /// Markdown of the kept sections.
+    pub contents: String,
+    /// Names of sections left out, by the section filter or the length limit.
+    pub omitted_sections: Vec<String>,
+    /// Whether the last kept section was cut short.
+    pub truncated: bool,
+}
+
+/// Keep the sections named in `wanted` (all when empty), in document order,
+/// and fit them into `max_length` characters.
+///
+/// Whole sections are kept while they fit. The first section that does not
+/// fit is cut at a line boundary if nothing was kept before it, and dropped
+/// otherwise; every later section is dropped.
+#[must_use]
+pub fn shape_hover(markdown: &str, wanted: &[String], max_length: Option<usize>) -> ShapedHover {
+    let mut kept: Vec<String> = Vec::new();
+    let mut omitted = Vec::new();
+    let mut truncated = false;
+    let mut length = 0;
+
+    for section in split_sections(markdown) {
+        if !wanted.is_empty() && !wanted.iter().any(|w| w.eq_ignore_ascii_case(&section.name)) {
+            omitted.push(section.name);
+            continue;
+        }
+        let separator = if kept.is_empty() { 0 } else { 2 };
+        let size = section.text.chars().count();
+        match max_length {
+            Some(max) if length + separator + size > max => {
+                if kept.is_empty() && !truncated {
+                    kept.push(cut_at_line(&section.text, max));
+                    truncated = true;
+                } else {
+                    omitted.push(section.name);
+                }
+                length = max;
+            }
+            _ => {
+                length += separator + size;
+                kept.push(section.text);
+            }
+        }
+    }
+
+    ShapedHover {
+        contents: kept.join("\n\n"),
+        omitted_sections: omitted,
+        truncated,
+    }
+}
+
+/// The longest prefix of `text` of at most `max` characters ending at a line
+/// break, or the first `max` characters when the first line is longer.
+fn cut_at_line(text: &str, max: usize) -> String {
+    let prefix: String = text.chars().take(max).collect();
+    match prefix.rfind('\n') {
+        Some(end) if end > 0 => prefix[..end].trim_end().to_string(),
+        _ => prefix,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const RUST_HOVER: &str = "```rust\nmycrate::user\n```\n\n```rust\npub fn new(name: &str) -> User\n```\n\n---\n\nCreate a user.\n\nNames are trimmed.\n\n# Examples\n\n```rust\n# use mycrate::user::User;\nlet u = User::new(\"a\");\n```\n\n# Panics\n\nPanics if `name` is empty.";
+
+    #[test]
+    fn test_split_sections() {
+        let sections = split_sections(RUST_HOVER);
+        let names: Vec<_> = sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["signature", "description", "examples", "panics"]
+        );
+        assert!(
+            sections[0]
+                .text
+                .ends_with("pub fn new(name: &str) -> User\n```")
+        );
+        assert_eq!(sections[1].text, "Create a user.\n\nNames are trimmed.");
+        // A `#` line inside a code block is not a heading.
+        assert!(sections[2].text.contains("# use mycrate::user::User;"));
+    }
+
+    #[test]
+    fn test_shape_hover_selects_sections() {
+        let shaped = shape_hover(RUST_HOVER, &["Signature".to_string()], None);
+        assert!(shaped.contents.starts_with("```rust\nmycrate::user"));
+        assert!(!shaped.contents.contains("Create a user"));
+        assert_eq!(
+            shaped.omitted_sections,
+            vec!["description", "examples", "panics"]
+        );
+        assert!(!shaped.truncated);
+
+        let all = shape_hover(RUST_HOVER, &[], None);
+        assert!(all.omitted_sections.is_empty());
+        assert!(all.contents.contains("# Panics"));
+    }
+
+    #[test]
+    fn test_shape_hover_drops_whole_sections_before_cutting() {
+        let signature_len = split_sections(RUST_HOVER)[0].text.chars().count();
+        let shaped = shape_hover(RUST_HOVER, &[], Some(signature_len + 5));
+        assert_eq!(shaped.contents, split_sections(RUST_HOVER)[0].text);
+        assert_eq!(
+            shaped.omitted_sections,
+            vec!["description", "examples", "panics"]
+        );
+        assert!(!shaped.truncated);
+
+        let cut = shape_hover(RUST_HOVER, &[], Some(22));
+        assert_eq!(cut.contents, "```rust\nmycrate::user");
+        assert!(cut.truncated);
+        assert_eq!(
+            cut.omitted_sections,
+            vec!["description", "examples", "panics"]
+        );
+    }
+
+    #[test]
+    fn test_plain_text_hover_is_description() {
+        let sections = split_sections("fn main()");
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].name, "description");
+    }
+}
@@ -0,0 +1,354 @@
+//! LSIF index of the previous run, queried while a server initializes.
+//!
+//! Servers such as rust-analyzer take minutes to index a large workspace
+//! after a restart. A server configured with `index_export` has an external
+//! indexer write an LSIF dump when mcpls shuts down; on the next start the
+//! dump is loaded, and `get_definition` and `get_references` are answered
+//! from it until the live server has registered. The dump reflects the
+//! workspace as it was at shutdown, so results are marked as coming from the
+//! index.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use lsp_types::{Location, Position, Range, Uri};
+use serde::Deserialize;
+use tokio::process::Command;
+
+use super::create_state_dir;
+use super::uri::uri_to_path;
+use crate::config::IndexExport;
+use crate::error::{Error, Result};
+
+/// Directory, relative to the workspace root, holding the exported dumps.
+pub const INDEX_DIR: &str = ".mcpls/index";
+
+/// Path of the dump for `language_id` under `root`.
+#[must_use]
+pub fn index_path(root: &Path, language_id: &str) -> PathBuf {
+    root.join(INDEX_DIR).join(format!("{language_id}.lsif"))
+}
+
+/// Run the indexer of `export` in `root` and store its output as the dump
+/// for `language_id`.
+///
+/// The output goes to a temporary file that replaces the dump only once the
+/// indexer has succeeded, so a failed run keeps the previous dump.
+///
+/// # Errors
+///
+/// Returns an error if the indexer cannot be started, fails, or runs past its
+/// timeout, or if the dump cannot be written.
+pub async fn export_index(root: &Path, language_id: &str, export: &IndexExport) -> Result<()> {
+    let path = index_path(root, language_id);
+    create_state_dir(root, &root.join(INDEX_DIR)).await?;
+    let partial = path.with_extension("lsif.partial");
+    let output = std::fs::File::create(&partial).map_err(|e| Error::FileIo {
+        path: partial.clone(),
+        source: e,
+    })?;
+
+    let mut child = Command::new(&export.command)
+        .args(&export.args)
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(output))
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let status = tokio::time::timeout(Duration::from_secs(export.timeout_seconds), child.wait())
+        .await
+        .map_err(|_| Error::Timeout(export.timeout_seconds));
+    let status = match status {
+        Ok(status) => status?,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(e);
+        }
+    };
+    if !status.success() {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(Error::Io(std::io::Error::other(format!(
+            "{} exited with {status}",
+            export.command
+        ))));
+    }
+    tokio::fs::rename(&partial, &path)
+        .await
+        .map_err(|e| Error::FileIo { path, source: e })
+}
+
+/// Vertex or edge ID, a number or a string depending on the indexer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(untagged)]
+enum Id {
+    Number(u64),
+    Text(String),
+}
+
+/// One line of an LSIF dump. Only the properties the queries need are read.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Element {
+    id: Id,
+    label: String,
+    uri: Option<String>,
+    start: Option<Position>,
+    end: Option<Position>,
+    #[serde(rename = "outV")]
+    out_v: Option<Id>,
+    #[serde(rename = "inV")]
+    in_v: Option<Id>,
+    #[serde(rename = "inVs", default)]
+    in_vs: Vec<Id>,
+    document: Option<Id>,
+    property: Option<String>,
+}
+
+/// Ranges a definition or reference result lists.
+#[derive(Debug, Clone)]
+struct Item {
+    range: Id,
+    document: Option<Id>,
+    /// `definitions` or `references` for items of a reference result.
+    property: Option<String>,
+}
+
+/// Definitions and references of an LSIF dump.
+#[derive(Debug, Default)]
+pub struct LsifIndex {
+    documents: HashMap<Id, Uri>,
+    documents_by_path: HashMap<PathBuf, Id>,
+    ranges: HashMap<Id, Range>,
+    /// Ranges of each document.
+    contains: HashMap<Id, Vec<Id>>,
+    /// Document of each range.
+    range_documents: HashMap<Id, Id>,
+    /// Result set a range or result set is linked to.
+    next: HashMap<Id, Id>,
+    definition_results: HashMap<Id, Id>,
+    reference_results: HashMap<Id, Id>,
+    items: HashMap<Id, Vec<Item>>,
+}
+
+impl LsifIndex {
+    /// Read the dump at `path`, or `None` if there is none.
+    pub async fn load(path: &Path) -> Option<Self> {
+        let content = tokio::fs::read_to_string(path).await.ok()?;
+        tokio::task::spawn_blocking(move || Self::parse(&content))
+            .await
+            .ok()
+    }
+
+    /// Build the index from the lines of an LSIF dump. Lines that are not
+    /// LSIF elements are skipped.
+    #[must_use]
+    pub fn parse(dump: &str) -> Self {
+        let mut index = Self::default();
+        for line in dump.lines().filter(|line| !line.trim().is_empty()) {
+            if let Ok(element) = serde_json::from_str::<Element>(line) {
+                index.add(element);
+            }
+        }
+        index
+    }
+
+    fn add(&mut self, element: Element) {
+        match element.label.as_str() {
+            "document" => {
+                let Some(uri) = element.uri.and_then(|uri| uri.parse::<Uri>().ok()) else {
+                    return;
+                };
+                if let Some(path) = uri_to_path(&uri) {
+                    self.documents_by_path.insert(path, element.id.clone());
+                }
+                self.documents.insert(element.id, uri);
+            }
+            "range" => {
+                if let (Some(start), Some(end)) = (element.start, element.end) {
+                    self.ranges.insert(element.id, Range { start, end });
+                }
+            }
+            "contains" => {
+                let Some(document) = element.out_v else {
+                    return;
+                };
+                for range in &element.in_vs {
+                    self.range_documents.insert(range.clone(), document.clone());
+                }
+                self.contains
+                    .entry(document)
+                    .or_default()
+                    .extend(element.in_vs);
+            }
+            "next" | "textDocument/definition" | "textDocument/references" => {
+                let (Some(out_v), Some(in_v)) = (element.out_v, element.in_v) else {
+                    return;
+                };
+                let edges = match element.label.as_str() {
+                    "next" => &mut self.next,
+                    "textDocument/definition" => &mut self.definition_results,
+                    _ => &mut self.reference_results,
+                };
+                edges.insert(out_v, in_v);
+            }
+            "item" => {
+                let Some(result) = element.out_v else {
+                    return;
+                };
+                let items = self.items.entry(result).or_default();
+                items.extend(element.in_vs.into_iter().map(|range| Item {
+                    range,
+                    document: element.document.clone(),
+                    property: element.property.clone(),
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    /// Definitions of the symbol at `position` in the file at `path`.
+    #[must_use]
+    pub fn definitions(&self, path: &Path, position: Position) -> Vec<Location> {
+        self.result_at(path, position, &self.definition_results)
+            .map(|items| self.locations(items, |_| true))
+            .unwrap_or_default()
+    }
+
+    /// References to the symbol at `position` in the file at `path`,
+    /// including its definitions if `include_declaration` is set.
+    #[must_use]
+    pub fn references(
+        &self,
+        path: &Path,
+        position: Position,
+        include_declaration: bool,
+    ) -> Vec<Location> {
+        self.result_at(path, position, &self.reference_results)
+            .map(|items| {
+                self.locations(items, |item| {
+                    include_declaration || item.property.as_deref() != Some("definitions")
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    /// Items of the `results` entry for the innermost range at `position`,
+    /// following its chain of result sets.
+    fn result_at(
+        &self,
+        path: &Path,
+        position: Position,
+        results: &HashMap<Id, Id>,
+    ) -> Option<&[Item]> {
+        let document = self.documents_by_path.get(path)?;
+        let range = self
+            .contains
+            .get(document)?
+            .iter()
+            .filter_map(|id| Some((id, self.ranges.get(id)?)))
+            .filter(|(_, range)| range.start <= position && position <= range.end)
+            // The innermost of nested ranges starts last and ends first.
+            .max_by_key(|(_, range)| (range.start, Reverse(range.end)))?
+            .0;
+        let mut vertex = range;
+        loop {
+            if let Some(result) = results.get(vertex) {
+                return self.items.get(result).map(Vec::as_slice);
+            }
+            vertex = self.next.get(vertex)?;
+        }
+    }
+
+    fn locations(&self, items: &[Item], keep: impl Fn(&Item) -> bool) -> Vec<Location> {
+        items
+            .iter()
+            .filter(|item| keep(item))
+            .filter_map(|item| {
+                let document = item
+                    .document
+                    .as_ref()
+                    .or_else(|| self.range_documents.get(&item.range))?;
+                Some(Location {
+                    uri: self.documents.get(document)?.clone(),
+                    range: *self.ranges.get(&item.range)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// `fn add() {}` in lib.rs, called from main.rs line 2.
+    const DUMP: &str = r#"{"id":1,"type":"vertex","label":"metaData","version":"0.6.0","projectRoot":"file:///ws","positionEncoding":"utf-16"}
+{"id":2,"type":"vertex","label":"document","uri":"file:///ws/src/lib.rs","languageId":"rust"}
+{"id":3,"type":"vertex","label":"document","uri":"file:///ws/src/main.rs","languageId":"rust"}
+{"id":4,"type":"vertex","label":"resultSet"}
+{"id":5,"type":"vertex","label":"range","start":{"line":0,"character":3},"end":{"line":0,"character":6}}
+{"id":6,"type":"vertex","label":"range","start":{"line":1,"character":4},"end":{"line":1,"character":7}}
+{"id":7,"type":"edge","label":"next","outV":5,"inV":4}
+{"id":8,"type":"edge","label":"next","outV":6,"inV":4}
+{"id":9,"type":"vertex","label":"definitionResult"}
+{"id":10,"type":"edge","label":"textDocument/definition","outV":4,"inV":9}
+{"id":11,"type":"edge","label":"item","outV":9,"inVs":[5],"document":2}
+{"id":12,"type":"vertex","label":"referenceResult"}
+{"id":13,"type":"edge","label":"textDocument/references","outV":4,"inV":12}
+{"id":14,"type":"edge","label":"item","outV":12,"inVs":[5],"document":2,"property":"definitions"}
+{"id":15,"type":"edge","label":"item","outV":12,"inVs":[6],"document":3,"property":"references"}
+{"id":16,"type":"edge","label":"contains","outV":2,"inVs":[5]}
+{"id":17,"type":"edge","label":"contains","outV":3,"inVs":[6]}
+not json"#;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lsif_queries() {
+        let index = LsifIndex::parse(DUMP);
+        let main = Path::new("/ws/src/main.rs");
+
+        let definitions = index.definitions(main, Position::new(1, 5));
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0].uri.as_str(), "file:///ws/src/lib.rs");
+        assert_eq!(definitions[0].range.start, Position::new(0, 3));
+
+        let references = index.references(Path::new("/ws/src/lib.rs"), Position::new(0, 6), true);
+        assert_eq!(references.len(), 2);
+        let references = index.references(main, Position::new(1, 4), false);
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].uri.as_str(), "file:///ws/src/main.rs");
+
+        assert!(index.definitions(main, Position::new(0, 0)).is_empty());
+        assert!(
+            index
+                .definitions(Path::new("/ws/src/other.rs"), Position::new(1, 5))
+                .is_empty()
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_export_index_keeps_previous_dump_on_failure() {
+        let root = tempfile::tempdir().unwrap();
+        let export = |args: &[&str]| IndexExport {
+            command: "sh".to_string(),
+            args: args.iter().map(ToString::to_string).collect(),
+            timeout_seconds: 5,
+        };
+        export_index(root.path(), "rust", &export(&["-c", "echo dump"]))
+            .await
+            .unwrap();
+        let path = index_path(root.path(), "rust");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "dump\n");
+
+        let failing = export(&["-c", "echo partial; exit 1"]);
+        assert!(export_index(root.path(), "rust", &failing).await.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "dump\n");
+        assert!(!path.with_extension("lsif.partial").exists());
+    }
+}
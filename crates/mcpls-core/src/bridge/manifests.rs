@@ -0,0 +1,68 @@
+//! Build manifests whose changes servers are told about.
+//!
+//! Servers reload their project model when a manifest such as `Cargo.toml`
+//! changes, but most learn about it only through
+//! `workspace/didChangeWatchedFiles` from their client's file watcher, which
+//! mcpls does not have. Manifest edits mcpls writes itself, and those the
+//! agent reports, are forwarded to every server instead.
+
+use std::path::Path;
+
+use lsp_types::{DidChangeWatchedFilesParams, FileChangeType, FileEvent};
+
+use super::uri::path_to_uri;
+
+/// File names of the build manifests servers are told about.
+pub const MANIFEST_FILES: &[&str] = &["Cargo.toml", "go.mod", "package.json"];
+
+/// Whether `path` names a build manifest.
+#[must_use]
+pub fn is_manifest(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| MANIFEST_FILES.contains(&name))
+}
+
+/// The `workspace/didChangeWatchedFiles` notification for changes to the
+/// manifests among `changes`, or `None` if there are none.
+pub fn manifest_changes<'a>(
+    changes: impl IntoIterator<Item = (&'a Path, FileChangeType)>,
+) -> Option<DidChangeWatchedFilesParams> {
+    let events: Vec<_> = changes
+        .into_iter()
+        .filter(|(path, _)| is_manifest(path))
+        .map(|(path, typ)| FileEvent {
+            uri: path_to_uri(path),
+            typ,
+        })
+        .collect();
+    (!events.is_empty()).then_some(DidChangeWatchedFilesParams { changes: events })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_manifest_changes() {
+        let manifest = PathBuf::from("/ws/crates/core/Cargo.toml");
+        let source = PathBuf::from("/ws/crates/core/src/lib.rs");
+        assert!(is_manifest(&manifest));
+        assert!(!is_manifest(&source));
+        assert!(!is_manifest(Path::new("/ws/Cargo.toml.bak")));
+
+        let params = manifest_changes([
+            (source.as_path(), FileChangeType::CHANGED),
+            (manifest.as_path(), FileChangeType::DELETED),
+        ])
+        .unwrap();
+        assert_eq!(params.changes.len(), 1);
+        assert_eq!(params.changes[0].uri, path_to_uri(&manifest));
+        assert_eq!(params.changes[0].typ, FileChangeType::DELETED);
+
+        assert!(manifest_changes([(source.as_path(), FileChangeType::CHANGED)]).is_none());
+    }
+}
@@ -0,0 +1,215 @@
+//! Text that mcpls itself puts into tool results and tool errors.
+//!
+//! Language servers phrase their own hover text and errors; the messages here
+//! are the ones the bridge writes when there is nothing from the server to
+//! pass on, or when it rejects a request before asking the server. Each has a
+//! stable key, so hosts can replace the English default through the
+//! `[workspace.messages]` table of the configuration. Placeholders in braces,
+//! such as `{max}`, are filled in when the message is rendered.
+
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
+
+/// A message with a stable key and its English default text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Message {
+    /// Key used to override the message in the configuration.
+    pub key: &'static str,
+    /// Default text.
+    pub text: &'static str,
+}
+
+/// Hover result when the server has nothing for the position.
+pub const NO_HOVER: Message = Message {
+    key: "no_hover",
+    text: "No hover information available",
+};
+
+/// Position parameters below 1.
+pub const POSITION_TOO_SMALL: Message = Message {
+    key: "position_too_small",
+    text: "Line and character positions must be >= 1",
+};
+
+/// Position parameters above the accepted maximum.
+pub const POSITION_TOO_LARGE: Message = Message {
+    key: "position_too_large",
+    text: "Position values must be <= {max}",
+};
+
+/// Positions on a line past the end of the file, under
+/// `workspace.strict_positions`.
+pub const LINE_OUT_OF_FILE: Message = Message {
+    key: "line_out_of_file",
+    text: "Line {line} is past the end of the file, which has {lines} lines",
+};
+
+/// Positions past the end of their line, under `workspace.strict_positions`.
+pub const CHARACTER_OUT_OF_LINE: Message = Message {
+    key: "character_out_of_line",
+    text: "Character {character} is past the end of line {line}, which has {length} characters",
+};
+
+/// Ranges spanning too many lines.
+pub const RANGE_TOO_LARGE: Message = Message {
+    key: "range_too_large",
+    text: "Range size must be <= {max} lines",
+};
+
+/// Ranges whose start comes after their end.
+pub const RANGE_REVERSED: Message = Message {
+    key: "range_reversed",
+    text: "Start position must be before or equal to end position",
+};
+
+/// Relative tool paths that exist under more than one workspace root.
+pub const AMBIGUOUS_RELATIVE_PATH: Message = Message {
+    key: "ambiguous_relative_path",
+    text: "Relative path '{path}' exists under several workspace roots ({matches}); \
+           pass an absolute path",
+};
+
+/// Workspace symbol queries above the accepted length.
+pub const QUERY_TOO_LONG: Message = Message {
+    key: "query_too_long",
+    text: "Query too long: {length} chars (max {max})",
+};
+
+/// Symbol handles that were never issued or have been forgotten.
+pub const UNKNOWN_HANDLE: Message = Message {
+    key: "unknown_handle",
+    text: "Unknown or expired handle '{handle}': only the last {max} handles are kept, \
+           and restarting servers drops them all",
+};
+
+/// Positions without a call hierarchy item.
+pub const NO_CALL_HIERARCHY_ITEM: Message = Message {
+    key: "no_call_hierarchy_item",
+    text: "No call hierarchy item at {location}",
+};
+
+/// Positions without a type hierarchy item.
+pub const NO_TYPE_HIERARCHY_ITEM: Message = Message {
+    key: "no_type_hierarchy_item",
+    text: "No type hierarchy item at {location}",
+};
+
+/// Positions without an enclosing scope of the requested level.
+pub const NO_ENCLOSING_SCOPE: Message = Message {
+    key: "no_enclosing_scope",
+    text: "No enclosing {level} at {position}",
+};
+
+/// Confirmation tokens that were never issued, already used, or forgotten.
+pub const UNKNOWN_CONFIRMATION_TOKEN: Message = Message {
+    key: "unknown_confirmation_token",
+    text: "Unknown or expired confirmation token '{token}': each token can be used once, \
+           only by the session it was issued to, and only the last {max} are kept",
+};
+
+/// Positions neither on a matched bracket nor inside a bracket pair.
+pub const NO_BRACKET_PAIR: Message = Message {
+    key: "no_bracket_pair",
+    text: "No matching bracket at or around {position}",
+};
+
+/// Every message that can be overridden.
+pub const ALL: &[Message] = &[
+    NO_HOVER,
+    POSITION_TOO_SMALL,
+    POSITION_TOO_LARGE,
+    LINE_OUT_OF_FILE,
+    CHARACTER_OUT_OF_LINE,
+    RANGE_TOO_LARGE,
+    RANGE_REVERSED,
+    AMBIGUOUS_RELATIVE_PATH,
+    QUERY_TOO_LONG,
+    UNKNOWN_HANDLE,
+    UNKNOWN_CONFIRMATION_TOKEN,
+    NO_CALL_HIERARCHY_ITEM,
+    NO_TYPE_HIERARCHY_ITEM,
+    NO_ENCLOSING_SCOPE,
+    NO_BRACKET_PAIR,
+];
+
+/// Message texts in effect, with configured overrides applied.
+#[derive(Debug, Clone, Default)]
+pub struct Messages {
+    overrides: HashMap<&'static str, String>,
+}
+
+impl Messages {
+    /// Build the message set from configured overrides, keyed by message key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an override names an unknown message.
+    pub fn new(overrides: &HashMap<String, String>) -> Result<Self> {
+        let overrides = overrides
+            .iter()
+            .map(|(key, text)| {
+                ALL.iter()
+                    .find(|message| message.key == key)
+                    .map(|message| (message.key, text.clone()))
+                    .ok_or_else(|| {
+                        Error::InvalidConfig(format!(
+                            "unknown message '{key}' in [workspace.messages]; valid keys: {}",
+                            ALL.iter()
+                                .map(|message| message.key)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ))
+                    })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { overrides })
+    }
+
+    /// Text of `message`, with `args` substituted for its `{name}`
+    /// placeholders.
+    #[must_use]
+    pub fn render(&self, message: Message, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+        let mut text = self
+            .overrides
+            .get(message.key)
+            .map_or(message.text, String::as_str)
+            .to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{name}}}"), &value.to_string());
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_with_overrides() {
+        let defaults = Messages::default();
+        assert_eq!(
+            defaults.render(RANGE_TOO_LARGE, &[("max", &10_000)]),
+            "Range size must be <= 10000 lines"
+        );
+
+        let overrides = HashMap::from([(
+            "no_hover".to_string(),
+            "Keine Hover-Informationen verfügbar".to_string(),
+        )]);
+        let messages = Messages::new(&overrides).unwrap();
+        assert_eq!(
+            messages.render(NO_HOVER, &[]),
+            "Keine Hover-Informationen verfügbar"
+        );
+        assert_eq!(messages.render(RANGE_REVERSED, &[]), RANGE_REVERSED.text);
+
+        let unknown = HashMap::from([("no_such_message".to_string(), String::new())]);
+        assert!(matches!(
+            Messages::new(&unknown),
+            Err(Error::InvalidConfig(_))
+        ));
+    }
+}
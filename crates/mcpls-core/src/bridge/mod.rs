@@ -3,20 +3,92 @@
 //! This module handles the bidirectional conversion between
 //! MCP tool calls and LSP requests/responses.
 
+mod brackets;
+mod confirmations;
+mod edit_policy;
+mod edits;
 mod encoding;
+mod events;
+mod formatter;
+mod graph;
+mod handles;
+mod hover;
+mod index;
+mod manifests;
+pub mod messages;
 mod notifications;
+mod overlays;
+mod reanchor;
 pub mod resources;
+mod restore;
+mod router;
+mod search;
+mod server_status;
 mod state;
+mod symbol_map;
 mod translator;
+mod undo;
+mod uri;
 
-pub use encoding::{PositionEncoding, lsp_to_mcp_position, mcp_to_lsp_position};
+pub use brackets::BracketPair;
+pub use edit_policy::{ProtectedFile, ProtectionReason};
+pub use encoding::{ColumnMap, PositionEncoding, lsp_to_mcp_position, mcp_to_lsp_position};
+pub use events::{BridgeEvent, EventBus};
+pub use index::{INDEX_DIR, LsifIndex, export_index, index_path};
+pub use manifests::{MANIFEST_FILES, is_manifest};
 pub use notifications::{
     DiagnosticInfo, LogEntry, LogLevel, MessageType, NotificationCache, ServerMessage,
+    TelemetryEvent,
 };
+pub use overlays::{SessionId, next_session_id};
 pub use resources::ResourceSubscriptions;
-pub use state::{DocumentState, DocumentTracker, path_to_uri, uri_to_path};
+pub use restore::SavedDocument;
+pub use router::{ExtensionRouter, FALLBACK_LANGUAGE_ID, GlobRouter, LanguageRouter};
+pub use search::{SearchEngine, TextMatch, TextSearchResult};
+pub use server_status::LanguageServerStatus;
+pub use state::{DocumentState, DocumentTracker, LineEnding};
+pub use symbol_map::{
+    FileSymbols, SCIP_INDEX_FILE, ScipExportResult, SymbolMap, common_root, write_scip_index,
+};
 pub use translator::{
-    Completion, CompletionsResult, DefinitionResult, Diagnostic, DiagnosticSeverity,
-    DiagnosticsResult, DocumentChanges, DocumentSymbolsResult, FormatDocumentResult, HoverResult,
-    Location, Position2D, Range, ReferencesResult, RenameResult, Symbol, TextEdit, Translator,
+    CallHierarchyItemResult, CallHierarchyTarget, Completion, CompletionsResult,
+    ConfirmApplyResult, ContainingSymbol, DefinitionResult, Diagnostic, DiagnosticSeverity,
+    DiagnosticsBatch, DiagnosticsBatchResult, DiagnosticsResult, DocumentChanges,
+    DocumentDriftResult, DocumentSymbolsResult, EditSummary, EnclosingScope, FileDiagnostics,
+    FlycheckResult, HoverResult, InitializationInfo, InlineCompletion, InlineCompletionsResult,
+    LanguageInfo, LanguageInventory, Location, LocationOrigin, ManifestChangesResult, Position2D,
+    PrefetchResult, PrefetchStatus, PrefetchedFile, Range, ReferencesResult, RenameTarget,
+    RequestCanceller, ResetResult, ResourceOperation, SaveDocumentResult, ScanWorkspaceResult,
+    ServerSnapshot, ServerTelemetryResult, SupportedLanguage, SupportedLanguagesResult, Symbol,
+    TextEdit, Translator, TypeHierarchyItemResult, TypeHierarchyTarget, UndoResult,
+    UpdateDocumentResult, WorkspaceEditResult,
 };
+pub use uri::{CASE_INSENSITIVE_PATHS, path_to_uri, same_path, same_uri, uri_to_path};
+
+/// Directory, relative to the workspace root, holding the state mcpls keeps
+/// between runs.
+pub(crate) const STATE_DIR: &str = ".mcpls";
+
+/// Create `dir`, a directory under [`STATE_DIR`] of `root`, keeping the
+/// state directory out of version control without touching the project's
+/// own ignore files.
+///
+/// # Errors
+///
+/// Returns an error if the directory or the ignore file cannot be written.
+pub(crate) async fn create_state_dir(
+    root: &std::path::Path,
+    dir: &std::path::Path,
+) -> crate::error::Result<()> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| crate::error::Error::FileIo {
+            path: dir.to_path_buf(),
+            source: e,
+        })?;
+    let gitignore = root.join(STATE_DIR).join(".gitignore");
+    if !gitignore.exists() {
+        edits::write_file(&gitignore, "*\n").await?;
+    }
+    Ok(())
+}
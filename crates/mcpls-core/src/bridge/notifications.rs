@@ -1,11 +1,14 @@
 //! LSP notification storage and management.
 //!
-//! Stores diagnostics, log messages, and server messages received from LSP servers.
+//! Stores diagnostics, log messages, server messages, and telemetry events
+//! received from LSP servers. Diagnostics and log messages carry the
+//! `serverInfo` of the server that sent them, so findings stay attributable
+//! when several servers report on the same files.
 
 use std::collections::{HashMap, VecDeque};
 
 use chrono::{DateTime, Utc};
-use lsp_types::{Diagnostic as LspDiagnostic, Uri};
+use lsp_types::{Diagnostic as LspDiagnostic, ServerInfo, Uri};
 use serde::{Deserialize, Serialize};
 
 /// Maximum number of log entries to store.
@@ -29,6 +32,9 @@ fn uri_cache_key(uri: &str) -> std::borrow::Cow<'_, str> {
 /// Maximum number of server messages to store.
 const MAX_SERVER_MESSAGES: usize = 50;
 
+/// Maximum number of telemetry events to store.
+const MAX_TELEMETRY_EVENTS: usize = 100;
+
 /// Information about diagnostics for a document.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiagnosticInfo {
@@ -38,6 +44,9 @@ pub struct DiagnosticInfo {
     pub version: Option<i32>,
     /// List of diagnostics.
     pub diagnostics: Vec<LspDiagnostic>,
+    /// Name and version of the server that published the diagnostics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerInfo>,
 }
 
 /// A log entry from the LSP server.
@@ -49,6 +58,9 @@ pub struct LogEntry {
     pub message: String,
     /// Timestamp when the log was received.
     pub timestamp: DateTime<Utc>,
+    /// Name and version of the server that logged the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerInfo>,
 }
 
 /// Log severity level.
@@ -114,6 +126,17 @@ impl From<lsp_types::MessageType> for MessageType {
     }
 }
 
+/// A `telemetry/event` notification from an LSP server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryEvent {
+    /// Language ID of the server that sent the event.
+    pub language_id: String,
+    /// Event payload, as sent by the server.
+    pub data: serde_json::Value,
+    /// Timestamp when the event was received.
+    pub timestamp: DateTime<Utc>,
+}
+
 /// Cache for LSP server notifications.
 #[derive(Debug)]
 pub struct NotificationCache {
@@ -123,6 +146,8 @@ pub struct NotificationCache {
     logs: VecDeque<LogEntry>,
     /// Recent server messages (FIFO queue with max size).
     messages: VecDeque<ServerMessage>,
+    /// Recent telemetry events (FIFO queue with max size).
+    telemetry: VecDeque<TelemetryEvent>,
 }
 
 impl Default for NotificationCache {
@@ -139,10 +164,11 @@ impl NotificationCache {
             diagnostics: HashMap::with_capacity(32),
             logs: VecDeque::with_capacity(MAX_LOG_ENTRIES),
             messages: VecDeque::with_capacity(MAX_SERVER_MESSAGES),
+            telemetry: VecDeque::with_capacity(MAX_TELEMETRY_EVENTS),
         }
     }
 
-    /// Store diagnostics for a document.
+    /// Store diagnostics for a document, published by `server`.
     ///
     /// If diagnostics already exist for the URI, they are replaced.
     pub fn store_diagnostics(
@@ -150,24 +176,27 @@ impl NotificationCache {
         uri: &Uri,
         version: Option<i32>,
         diagnostics: Vec<LspDiagnostic>,
+        server: Option<ServerInfo>,
     ) {
         let info = DiagnosticInfo {
             uri: uri.clone(),
             version,
             diagnostics,
+            server,
         };
         self.diagnostics
             .insert(uri_cache_key(uri.as_str()).into_owned(), info);
     }
 
-    /// Store a log entry.
+    /// Store a log entry logged by `server`.
     ///
     /// Maintains a maximum of `MAX_LOG_ENTRIES` entries, removing oldest when full.
-    pub fn store_log(&mut self, level: LogLevel, message: String) {
+    pub fn store_log(&mut self, level: LogLevel, message: String, server: Option<ServerInfo>) {
         let entry = LogEntry {
             level,
             message,
             timestamp: Utc::now(),
+            server,
         };
 
         if self.logs.len() >= MAX_LOG_ENTRIES {
@@ -192,6 +221,22 @@ impl NotificationCache {
         self.messages.push_back(msg);
     }
 
+    /// Store a telemetry event from the server for `language_id`.
+    ///
+    /// Maintains a maximum of `MAX_TELEMETRY_EVENTS` entries, removing oldest when full.
+    pub fn store_telemetry(&mut self, language_id: &str, data: serde_json::Value) {
+        let event = TelemetryEvent {
+            language_id: language_id.to_string(),
+            data,
+            timestamp: Utc::now(),
+        };
+
+        if self.telemetry.len() >= MAX_TELEMETRY_EVENTS {
+            self.telemetry.pop_front();
+        }
+        self.telemetry.push_back(event);
+    }
+
     /// Get diagnostics for a document URI.
     #[inline]
     #[must_use]
@@ -213,6 +258,13 @@ impl NotificationCache {
         &self.messages
     }
 
+    /// Get all stored telemetry events, oldest first.
+    #[inline]
+    #[must_use]
+    pub const fn get_telemetry(&self) -> &VecDeque<TelemetryEvent> {
+        &self.telemetry
+    }
+
     /// Clear diagnostics for a specific document URI.
     ///
     /// Returns the cleared diagnostics if they existed.
@@ -235,6 +287,11 @@ impl NotificationCache {
         self.messages.clear();
     }
 
+    /// Clear all telemetry events.
+    pub fn clear_telemetry(&mut self) {
+        self.telemetry.clear();
+    }
+
     /// Get the number of documents with stored diagnostics.
     #[inline]
     #[must_use]
@@ -255,6 +312,13 @@ impl NotificationCache {
     pub fn messages_count(&self) -> usize {
         self.messages.len()
     }
+
+    /// Get the number of stored telemetry events.
+    #[inline]
+    #[must_use]
+    pub fn telemetry_count(&self) -> usize {
+        self.telemetry.len()
+    }
 }
 
 #[cfg(test)]
@@ -298,7 +362,7 @@ mod tests {
             data: None,
         };
 
-        cache.store_diagnostics(&uri, Some(1), vec![diagnostic]);
+        cache.store_diagnostics(&uri, Some(1), vec![diagnostic], None);
 
         let stored = cache.get_diagnostics(uri.as_str()).unwrap();
         assert_eq!(stored.uri, uri);
@@ -312,10 +376,10 @@ mod tests {
         let mut cache = NotificationCache::new();
         let uri: Uri = "file:///test.rs".parse().unwrap();
 
-        cache.store_diagnostics(&uri, Some(1), vec![]);
+        cache.store_diagnostics(&uri, Some(1), vec![], None);
         assert_eq!(cache.diagnostics_count(), 1);
 
-        cache.store_diagnostics(&uri, Some(2), vec![]);
+        cache.store_diagnostics(&uri, Some(2), vec![], None);
         assert_eq!(cache.diagnostics_count(), 1);
 
         let stored = cache.get_diagnostics(uri.as_str()).unwrap();
@@ -327,7 +391,7 @@ mod tests {
         let mut cache = NotificationCache::new();
         let uri: Uri = "file:///test.rs".parse().unwrap();
 
-        cache.store_diagnostics(&uri, Some(1), vec![]);
+        cache.store_diagnostics(&uri, Some(1), vec![], None);
         assert_eq!(cache.diagnostics_count(), 1);
 
         let cleared = cache.clear_diagnostics(uri.as_str());
@@ -341,8 +405,8 @@ mod tests {
         let uri1: Uri = "file:///test1.rs".parse().unwrap();
         let uri2: Uri = "file:///test2.rs".parse().unwrap();
 
-        cache.store_diagnostics(&uri1, Some(1), vec![]);
-        cache.store_diagnostics(&uri2, Some(1), vec![]);
+        cache.store_diagnostics(&uri1, Some(1), vec![], None);
+        cache.store_diagnostics(&uri2, Some(1), vec![], None);
         assert_eq!(cache.diagnostics_count(), 2);
 
         cache.clear_all_diagnostics();
@@ -353,15 +417,25 @@ mod tests {
     fn test_store_and_get_logs() {
         let mut cache = NotificationCache::new();
 
-        cache.store_log(LogLevel::Error, "error message".to_string());
-        cache.store_log(LogLevel::Info, "info message".to_string());
+        let server = ServerInfo {
+            name: "gopls".to_string(),
+            version: Some("v0.16.2".to_string()),
+        };
+        cache.store_log(
+            LogLevel::Error,
+            "error message".to_string(),
+            Some(server.clone()),
+        );
+        cache.store_log(LogLevel::Info, "info message".to_string(), None);
 
         let logs = cache.get_logs();
         assert_eq!(logs.len(), 2);
         assert_eq!(logs[0].level, LogLevel::Error);
         assert_eq!(logs[0].message, "error message");
+        assert_eq!(logs[0].server, Some(server));
         assert_eq!(logs[1].level, LogLevel::Info);
         assert_eq!(logs[1].message, "info message");
+        assert!(logs[1].server.is_none());
     }
 
     #[test]
@@ -370,7 +444,7 @@ mod tests {
 
         // Add more than MAX_LOG_ENTRIES
         for i in 0..MAX_LOG_ENTRIES + 10 {
-            cache.store_log(LogLevel::Info, format!("message {i}"));
+            cache.store_log(LogLevel::Info, format!("message {i}"), None);
         }
 
         assert_eq!(cache.logs_count(), MAX_LOG_ENTRIES);
@@ -387,7 +461,7 @@ mod tests {
     #[test]
     fn test_clear_logs() {
         let mut cache = NotificationCache::new();
-        cache.store_log(LogLevel::Info, "test".to_string());
+        cache.store_log(LogLevel::Info, "test".to_string(), None);
         assert_eq!(cache.logs_count(), 1);
 
         cache.clear_logs();
@@ -443,10 +517,10 @@ mod tests {
     fn test_log_levels() {
         let mut cache = NotificationCache::new();
 
-        cache.store_log(LogLevel::Error, "error".to_string());
-        cache.store_log(LogLevel::Warning, "warning".to_string());
-        cache.store_log(LogLevel::Info, "info".to_string());
-        cache.store_log(LogLevel::Debug, "debug".to_string());
+        cache.store_log(LogLevel::Error, "error".to_string(), None);
+        cache.store_log(LogLevel::Warning, "warning".to_string(), None);
+        cache.store_log(LogLevel::Info, "info".to_string(), None);
+        cache.store_log(LogLevel::Debug, "debug".to_string(), None);
 
         let logs = cache.get_logs();
         assert_eq!(logs[0].level, LogLevel::Error);
@@ -475,9 +549,9 @@ mod tests {
     fn test_timestamp_ordering() {
         let mut cache = NotificationCache::new();
 
-        cache.store_log(LogLevel::Info, "first".to_string());
+        cache.store_log(LogLevel::Info, "first".to_string(), None);
         std::thread::sleep(std::time::Duration::from_millis(10));
-        cache.store_log(LogLevel::Info, "second".to_string());
+        cache.store_log(LogLevel::Info, "second".to_string(), None);
 
         let logs = cache.get_logs();
         assert!(logs[0].timestamp < logs[1].timestamp);
@@ -509,7 +583,7 @@ mod tests {
             data: None,
         };
 
-        cache.store_diagnostics(&uri, Some(1), vec![diagnostic]);
+        cache.store_diagnostics(&uri, Some(1), vec![diagnostic], None);
         assert_eq!(
             cache
                 .get_diagnostics(uri.as_str())
@@ -519,7 +593,7 @@ mod tests {
             1
         );
 
-        cache.store_diagnostics(&uri, Some(2), vec![]);
+        cache.store_diagnostics(&uri, Some(2), vec![], None);
         let stored = cache.get_diagnostics(uri.as_str()).unwrap();
         assert_eq!(stored.diagnostics.len(), 0);
         assert_eq!(stored.version, Some(2));
@@ -553,7 +627,7 @@ mod tests {
             })
             .collect();
 
-        cache.store_diagnostics(&uri, Some(1), diagnostics);
+        cache.store_diagnostics(&uri, Some(1), diagnostics, None);
 
         let stored = cache.get_diagnostics(uri.as_str()).unwrap();
         assert_eq!(stored.diagnostics.len(), 100);
@@ -564,15 +638,36 @@ mod tests {
         let mut cache = NotificationCache::new();
 
         for i in 0..MAX_LOG_ENTRIES {
-            cache.store_log(LogLevel::Info, format!("message {i}"));
+            cache.store_log(LogLevel::Info, format!("message {i}"), None);
         }
         assert_eq!(cache.logs_count(), MAX_LOG_ENTRIES);
 
-        cache.store_log(LogLevel::Info, "overflow".to_string());
+        cache.store_log(LogLevel::Info, "overflow".to_string(), None);
         assert_eq!(cache.logs_count(), MAX_LOG_ENTRIES);
         assert_eq!(cache.get_logs().front().unwrap().message, "message 1");
     }
 
+    #[test]
+    fn test_telemetry_exact_capacity_boundary() {
+        let mut cache = NotificationCache::new();
+
+        for i in 0..MAX_TELEMETRY_EVENTS {
+            cache.store_telemetry("java", serde_json::json!({ "seq": i }));
+        }
+        assert_eq!(cache.telemetry_count(), MAX_TELEMETRY_EVENTS);
+
+        cache.store_telemetry("typescript", serde_json::json!("overflow"));
+        assert_eq!(cache.telemetry_count(), MAX_TELEMETRY_EVENTS);
+        assert_eq!(cache.get_telemetry().front().unwrap().data["seq"], 1);
+        assert_eq!(
+            cache.get_telemetry().back().unwrap().language_id,
+            "typescript"
+        );
+
+        cache.clear_telemetry();
+        assert_eq!(cache.telemetry_count(), 0);
+    }
+
     #[test]
     fn test_messages_exact_capacity_boundary() {
         let mut cache = NotificationCache::new();
@@ -599,7 +694,7 @@ mod tests {
         let mut cache = NotificationCache::new();
         let uri: Uri = "file:///test.rs".parse().unwrap();
 
-        cache.store_diagnostics(&uri, None, vec![]);
+        cache.store_diagnostics(&uri, None, vec![], None);
         let stored = cache.get_diagnostics(uri.as_str()).unwrap();
         assert_eq!(stored.version, None);
     }
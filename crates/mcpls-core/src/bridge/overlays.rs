@@ -0,0 +1,193 @@
+//! Unsaved document contents pushed by MCP clients, kept per session.
+//!
+//! Several MCP clients can share one mcpls process (e.g. over HTTP) and with
+//! it one set of language servers. Every client session keeps its own
+//! overlays; before a session's tool call runs, the documents held by the
+//! servers are switched to that session's view, so one agent's in-flight
+//! edits never show up in another agent's results.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Identifies an MCP client session.
+pub type SessionId = u64;
+
+/// Next ID handed out by [`next_session_id`]. Zero is never handed out; it
+/// is the session of a translator used without any client session.
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Allocate an ID for a new MCP client session.
+pub fn next_session_id() -> SessionId {
+    NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Content a server must be sent so a document matches a session's view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverlaySync {
+    /// The session's unsaved content.
+    Overlay(String),
+    /// The content on disk.
+    Disk,
+}
+
+/// Overlays of all sessions, and which of them the servers currently hold.
+#[derive(Debug, Default)]
+pub struct DocumentOverlays {
+    /// Unsaved contents by session and path.
+    sessions: HashMap<SessionId, HashMap<PathBuf, String>>,
+    /// Session whose overlay a server holds, by path. Documents not listed
+    /// hold their content on disk.
+    applied: HashMap<PathBuf, SessionId>,
+}
+
+impl DocumentOverlays {
+    /// Set a session's unsaved content for a document.
+    pub fn set(&mut self, session: SessionId, path: PathBuf, content: String) {
+        self.sessions
+            .entry(session)
+            .or_default()
+            .insert(path, content);
+    }
+
+    /// Drop a session's overlay for a document. Returns whether it had one.
+    pub fn remove(&mut self, session: SessionId, path: &Path) -> bool {
+        let Some(overlays) = self.sessions.get_mut(&session) else {
+            return false;
+        };
+        let removed = overlays.remove(path).is_some();
+        if overlays.is_empty() {
+            self.sessions.remove(&session);
+        }
+        removed
+    }
+
+    /// Drop every overlay of a session that has ended.
+    ///
+    /// Documents still holding one of them are switched back to disk by the
+    /// next [`Self::plan`].
+    pub fn end_session(&mut self, session: SessionId) {
+        self.sessions.remove(&session);
+    }
+
+    /// A session's unsaved content for a document.
+    #[must_use]
+    pub fn get(&self, session: SessionId, path: &Path) -> Option<&str> {
+        self.sessions
+            .get(&session)
+            .and_then(|overlays| overlays.get(path))
+            .map(String::as_str)
+    }
+
+    /// Number of documents a session has unsaved content for.
+    #[must_use]
+    pub fn count(&self, session: SessionId) -> usize {
+        self.sessions.get(&session).map_or(0, HashMap::len)
+    }
+
+    /// Record which session's overlay a server now holds for a document,
+    /// `None` meaning the content on disk.
+    pub fn mark_applied(&mut self, path: &Path, session: Option<SessionId>) {
+        match session {
+            Some(session) => {
+                self.applied.insert(path.to_path_buf(), session);
+            }
+            None => {
+                self.applied.remove(path);
+            }
+        }
+    }
+
+    /// Forget which overlays the servers hold, after all documents were
+    /// closed.
+    pub fn clear_applied(&mut self) {
+        self.applied.clear();
+    }
+
+    /// Documents whose server-side content differs from what `session`
+    /// should see, with the content to send.
+    #[must_use]
+    pub fn plan(&self, session: SessionId) -> Vec<(PathBuf, OverlaySync)> {
+        let own = self.sessions.get(&session);
+        let mut plan: Vec<_> = self
+            .applied
+            .iter()
+            .filter_map(|(path, &holder)| {
+                let wanted = own.and_then(|own| own.get(path));
+                match wanted {
+                    Some(_) if holder == session => None,
+                    Some(content) => Some((path.clone(), OverlaySync::Overlay(content.clone()))),
+                    None => Some((path.clone(), OverlaySync::Disk)),
+                }
+            })
+            .collect();
+        if let Some(own) = own {
+            plan.extend(
+                own.iter()
+                    .filter(|&(path, _)| !self.applied.contains_key(path))
+                    .map(|(path, content)| (path.clone(), OverlaySync::Overlay(content.clone()))),
+            );
+        }
+        plan.sort_by(|a, b| a.0.cmp(&b.0));
+        plan
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_switches_documents_between_sessions() {
+        let lib = PathBuf::from("/ws/lib.rs");
+        let main = PathBuf::from("/ws/main.rs");
+        let mut overlays = DocumentOverlays::default();
+        overlays.set(1, lib.clone(), "fn a() {}".to_string());
+        overlays.set(2, lib.clone(), "fn b() {}".to_string());
+        overlays.set(2, main.clone(), "fn main() {}".to_string());
+
+        assert_eq!(
+            overlays.plan(1),
+            vec![(lib.clone(), OverlaySync::Overlay("fn a() {}".to_string()))]
+        );
+        overlays.mark_applied(&lib, Some(1));
+        assert!(overlays.plan(1).is_empty());
+
+        assert_eq!(
+            overlays.plan(2),
+            vec![
+                (lib.clone(), OverlaySync::Overlay("fn b() {}".to_string())),
+                (
+                    main.clone(),
+                    OverlaySync::Overlay("fn main() {}".to_string())
+                ),
+            ]
+        );
+        overlays.mark_applied(&lib, Some(2));
+        overlays.mark_applied(&main, Some(2));
+
+        assert_eq!(
+            overlays.plan(3),
+            vec![(lib, OverlaySync::Disk), (main, OverlaySync::Disk)]
+        );
+    }
+
+    #[test]
+    fn test_ended_session_reverts_to_disk() {
+        let lib = PathBuf::from("/ws/lib.rs");
+        let mut overlays = DocumentOverlays::default();
+        overlays.set(1, lib.clone(), "unsaved".to_string());
+        overlays.mark_applied(&lib, Some(1));
+        assert_eq!(overlays.count(1), 1);
+        assert_eq!(overlays.get(1, &lib), Some("unsaved"));
+
+        overlays.end_session(1);
+        assert_eq!(overlays.count(1), 0);
+        assert_eq!(overlays.plan(1), vec![(lib.clone(), OverlaySync::Disk)]);
+
+        overlays.mark_applied(&lib, None);
+        assert!(overlays.plan(1).is_empty());
+        assert!(!overlays.remove(1, &lib));
+    }
+}
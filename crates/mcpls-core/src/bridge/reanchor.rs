@@ -0,0 +1,138 @@
+//! Re-anchoring of positions computed against an older document version.
+//!
+//! Agents often query a position they read before an edit: after
+//! `update_document` inserts two lines, the identifier that was on line 10 is
+//! on line 12, and line 10 holds something else. The document tracker keeps
+//! the last few versions of each document, so when the token at a position
+//! differs from the token that was there in the version the agent last saw,
+//! the position is carried through a line diff of the two versions to where
+//! that token is now. Positions are 1-based and count characters.
+
+use similar::{DiffOp, TextDiff};
+
+/// Whether `c` can be part of an identifier.
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The identifier at 1-based `character` of `line`: its text and the
+/// 0-based offset of `character` within it.
+fn token_at(line: &str, character: u32) -> Option<(String, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let index = (character as usize).checked_sub(1)?;
+    if !chars.get(index).copied().is_some_and(is_ident) {
+        return None;
+    }
+    let start = chars[..index]
+        .iter()
+        .rposition(|c| !is_ident(*c))
+        .map_or(0, |i| i + 1);
+    let end = chars[index..]
+        .iter()
+        .position(|c| !is_ident(*c))
+        .map_or(chars.len(), |i| index + i);
+    Some((chars[start..end].iter().collect(), index - start))
+}
+
+/// 0-based character starts of the whole-word matches of `token` in `line`.
+fn token_starts(line: &str, token: &str) -> Vec<usize> {
+    let chars: Vec<char> = line.chars().collect();
+    let token: Vec<char> = token.chars().collect();
+    (0..chars.len().saturating_sub(token.len() - 1))
+        .filter(|&start| {
+            chars[start..].starts_with(&token)
+                && (start == 0 || !is_ident(chars[start - 1]))
+                && chars.get(start + token.len()).is_none_or(|c| !is_ident(*c))
+        })
+        .collect()
+}
+
+/// Where the position `line`:`character`, computed against `old`, is in
+/// `new`, or `None` when it needs no adjustment or cannot be carried over.
+///
+/// A position needs adjustment when the identifier under it in `old` is no
+/// longer under it in `new`. The line is mapped through the diff of the two
+/// versions; on an unchanged line the identifier is expected at the same
+/// character, on an edited line at its whole-word match closest to it.
+pub fn reanchor(old: &str, new: &str, line: u32, character: u32) -> Option<(u32, u32)> {
+    let old_index = (line as usize).checked_sub(1)?;
+    let (token, offset) = token_at(old.lines().nth(old_index)?, character)?;
+    if new
+        .lines()
+        .nth(old_index)
+        .and_then(|line| token_at(line, character))
+        .is_some_and(|current| current == (token.clone(), offset))
+    {
+        return None;
+    }
+
+    let diff = TextDiff::from_lines(old, new);
+    let candidates = diff.ops().iter().find_map(|op| match *op {
+        DiffOp::Equal {
+            old_index: from,
+            new_index: to,
+            len,
+        } if (from..from + len).contains(&old_index) => {
+            let mapped = to + (old_index - from);
+            Some(mapped..mapped + 1)
+        }
+        DiffOp::Replace {
+            old_index: from,
+            old_len,
+            new_index: to,
+            new_len,
+        } if (from..from + old_len).contains(&old_index) => Some(to..to + new_len),
+        _ => None,
+    })?;
+
+    let new_lines: Vec<&str> = new.lines().collect();
+    let wanted = (character as usize - 1).saturating_sub(offset);
+    candidates
+        .filter_map(|index| Some((index, *new_lines.get(index)?)))
+        .flat_map(|(index, text)| {
+            token_starts(text, &token)
+                .into_iter()
+                .map(move |start| (index, start))
+        })
+        .min_by_key(|&(index, start)| (index.abs_diff(old_index), start.abs_diff(wanted)))
+        .map(|(index, start)| {
+            (
+                u32::try_from(index + 1).unwrap_or(u32::MAX),
+                u32::try_from(start + offset + 1).unwrap_or(u32::MAX),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_at() {
+        assert_eq!(
+            token_at("let total = 1;", 6),
+            Some(("total".to_string(), 1))
+        );
+        assert_eq!(token_at("let total = 1;", 4), None);
+        assert_eq!(token_at("let", 9), None);
+    }
+
+    #[test]
+    fn test_reanchor_shifted_lines() {
+        let old = "fn main() {\n    run();\n}\n";
+        let new = "use std::io;\n\nfn main() {\n    run();\n}\n";
+        assert_eq!(reanchor(old, new, 2, 6), Some((4, 6)));
+        // Unchanged positions are left alone.
+        assert_eq!(reanchor(old, old, 2, 6), None);
+    }
+
+    #[test]
+    fn test_reanchor_edited_line() {
+        let old = "fn main() {\n    run(1);\n}\n";
+        let new = "fn main() {\n    self.run(1, 2);\n}\n";
+        assert_eq!(reanchor(old, new, 2, 7), Some((2, 12)));
+        // A token that is gone cannot be re-anchored.
+        let new = "fn main() {\n    start(1);\n}\n";
+        assert_eq!(reanchor(old, new, 2, 7), None);
+    }
+}
@@ -0,0 +1,77 @@
+//! Open-document set carried over between runs.
+//!
+//! On graceful shutdown the documents the language servers had open are
+//! listed in `.mcpls/open-documents.json`; the next run reopens them once its
+//! servers are ready, so they start warm on the files the agent was working
+//! on instead of cold.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::edits::write_file;
+use super::{STATE_DIR, create_state_dir};
+use crate::error::Result;
+
+/// File, relative to the workspace root, listing the open documents.
+pub const OPEN_DOCUMENTS_FILE: &str = ".mcpls/open-documents.json";
+
+/// A document that was open when mcpls last shut down.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedDocument {
+    /// Absolute path of the document.
+    pub path: PathBuf,
+    /// Version the document had.
+    pub version: i32,
+}
+
+/// Write the open-document list under `root`.
+///
+/// # Errors
+///
+/// Returns an error if the list cannot be written.
+pub async fn save_open_documents(root: &Path, documents: &[SavedDocument]) -> Result<()> {
+    let file = root.join(OPEN_DOCUMENTS_FILE);
+    create_state_dir(root, &root.join(STATE_DIR)).await?;
+    write_file(&file, &serde_json::to_string_pretty(documents)?).await
+}
+
+/// Read the open-document list saved under `root`.
+///
+/// A missing or unreadable list yields no documents.
+pub async fn load_open_documents(root: &Path) -> Vec<SavedDocument> {
+    let file = root.join(OPEN_DOCUMENTS_FILE);
+    let Ok(content) = tokio::fs::read_to_string(&file).await else {
+        return Vec::new();
+    };
+    serde_json::from_str(&content).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring invalid {}: {e}", file.display());
+        Vec::new()
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_save_and_load_open_documents() {
+        let root = TempDir::new().unwrap();
+        assert!(load_open_documents(root.path()).await.is_empty());
+
+        let documents = vec![SavedDocument {
+            path: root.path().join("src/lib.rs"),
+            version: 7,
+        }];
+        save_open_documents(root.path(), &documents).await.unwrap();
+
+        assert_eq!(load_open_documents(root.path()).await, documents);
+        assert!(root.path().join(".mcpls/.gitignore").exists());
+
+        std::fs::write(root.path().join(OPEN_DOCUMENTS_FILE), "not json").unwrap();
+        assert!(load_open_documents(root.path()).await.is_empty());
+    }
+}
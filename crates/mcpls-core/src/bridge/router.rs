@@ -0,0 +1,537 @@
+//! Routing of files to language servers.
+//!
+//! Which server handles a file is decided in two steps: the file's language
+//! is detected from its path, then the language is mapped to the server that
+//! serves it, falling back to the catch-all server for languages without one
+//! of their own. The [`LanguageRouter`] trait holds both steps so the
+//! translator and the document tracker route the same way, and so routing
+//! schemes beyond file extensions can be plugged in.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::config::{CATCH_ALL_LANGUAGE_ID, ServerConfig};
+
+/// Language ID of files no language is detected for.
+pub const FALLBACK_LANGUAGE_ID: &str = "plaintext";
+
+/// Decides which language server handles a file.
+pub trait LanguageRouter: fmt::Debug + Send + Sync {
+    /// Language ID of the file at `path`, [`FALLBACK_LANGUAGE_ID`] if none
+    /// applies.
+    fn language_of(&self, path: &Path) -> String;
+
+    /// File extensions with the language ID they map to, for listing the
+    /// supported languages. Routers that do not route by extension list
+    /// none.
+    fn extensions(&self) -> Vec<(&str, &str)> {
+        Vec::new()
+    }
+
+    /// Language ID of the server handling files of `language_id`, given
+    /// whether a language has a server, running, starting or failed.
+    ///
+    /// Languages without a server of their own are handled by the catch-all
+    /// server if one is configured.
+    fn serving_language<'a>(
+        &self,
+        language_id: &'a str,
+        has_server: &dyn Fn(&str) -> bool,
+    ) -> &'a str {
+        if !has_server(language_id) && has_server(CATCH_ALL_LANGUAGE_ID) {
+            CATCH_ALL_LANGUAGE_ID
+        } else {
+            language_id
+        }
+    }
+}
+
+/// Routes files by extension, using an extension table such as the built-in
+/// mappings with `workspace.language_extensions`.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionRouter {
+    extension_map: HashMap<String, String>,
+}
+
+impl ExtensionRouter {
+    /// Create a router from file extension to language ID mappings.
+    #[must_use]
+    pub const fn new(extension_map: HashMap<String, String>) -> Self {
+        Self { extension_map }
+    }
+}
+
+impl LanguageRouter for ExtensionRouter {
+    fn language_of(&self, path: &Path) -> String {
+        detect_language(path, &self.extension_map)
+    }
+
+    fn extensions(&self) -> Vec<(&str, &str)> {
+        self.extension_map
+            .iter()
+            .map(|(extension, language_id)| (extension.as_str(), language_id.as_str()))
+            .collect()
+    }
+}
+
+/// Routes files by the `file_patterns` globs of the configured servers,
+/// falling back to extensions for files no pattern matches.
+///
+/// When several patterns match, the one of the server configured last wins.
+/// Patterns that are not anchored (`/...` or `**/...`) match below any
+/// directory, so `*.h` and `src/*.rs` behave like `**/*.h` and `**/src/*.rs`.
+#[derive(Debug, Clone)]
+pub struct GlobRouter {
+    patterns: GlobSet,
+    /// Language ID of each pattern in `patterns`, by index.
+    languages: Vec<String>,
+    fallback: ExtensionRouter,
+    /// Extensions listed as supported: the fallback table with those of
+    /// simple patterns such as `**/*.rs`.
+    listed: HashMap<String, String>,
+}
+
+impl GlobRouter {
+    /// Create a router from `(pattern, language ID)` pairs, falling back to
+    /// `extension_map`. Invalid patterns are logged and skipped.
+    #[must_use]
+    pub fn new(
+        extension_map: HashMap<String, String>,
+        file_patterns: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        let mut languages = Vec::new();
+        let mut listed = extension_map.clone();
+        for (pattern, language_id) in file_patterns {
+            let anchored = if pattern.starts_with('/') || pattern.starts_with("**") {
+                pattern.clone()
+            } else {
+                format!("**/{pattern}")
+            };
+            match GlobBuilder::new(&anchored).literal_separator(true).build() {
+                Ok(glob) => {
+                    builder.add(glob);
+                    if let Some(extension) = extension_from_pattern(&pattern) {
+                        listed.insert(extension, language_id.clone());
+                    }
+                    languages.push(language_id);
+                }
+                Err(e) => tracing::warn!("Ignoring file pattern '{pattern}' of {language_id}: {e}"),
+            }
+        }
+        let patterns = builder.build().unwrap_or_else(|e| {
+            tracing::warn!("Ignoring file patterns: {e}");
+            languages.clear();
+            GlobSet::empty()
+        });
+        Self {
+            patterns,
+            languages,
+            fallback: ExtensionRouter::new(extension_map),
+            listed,
+        }
+    }
+
+    /// Create a router from the server `file_patterns` of `config`, falling
+    /// back to its workspace extension table.
+    #[must_use]
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self::new(
+            config.workspace.build_extension_map(),
+            config.lsp_servers.iter().flat_map(|server| {
+                server
+                    .file_patterns
+                    .iter()
+                    .map(|pattern| (pattern.clone(), server.language_id.clone()))
+            }),
+        )
+    }
+}
+
+impl LanguageRouter for GlobRouter {
+    fn language_of(&self, path: &Path) -> String {
+        self.patterns.matches(path).into_iter().max().map_or_else(
+            || self.fallback.language_of(path),
+            |index| self.languages[index].clone(),
+        )
+    }
+
+    fn extensions(&self) -> Vec<(&str, &str)> {
+        self.listed
+            .iter()
+            .map(|(extension, language_id)| (extension.as_str(), language_id.as_str()))
+            .collect()
+    }
+}
+
+/// Extract a file extension from a glob-like file pattern, for listing it.
+///
+/// Supports common patterns such as `**/*.rs` and `*.h`.
+/// Returns `None` for patterns without a simple trailing extension.
+fn extension_from_pattern(pattern: &str) -> Option<String> {
+    let basename = pattern.rsplit('/').next().unwrap_or(pattern);
+    if basename.starts_with('.') {
+        return None;
+    }
+
+    let (_, ext) = basename.rsplit_once('.')?;
+    if ext.is_empty() {
+        return None;
+    }
+
+    // Keep this conservative: only accept plain extension-like tokens.
+    if ext
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Some(ext.to_string())
+    } else {
+        None
+    }
+}
+
+/// Detect the language ID from a file path.
+///
+/// Consults the extension map to determine the language ID for a file.
+/// If the extension is not found in the map, returns "plaintext".
+#[must_use]
+pub fn detect_language(path: &Path, extension_map: &HashMap<String, String>) -> String {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    extension_map
+        .get(extension)
+        .cloned()
+        .unwrap_or_else(|| FALLBACK_LANGUAGE_ID.to_string())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        let mut map = HashMap::new();
+        map.insert("rs".to_string(), "rust".to_string());
+        map.insert("py".to_string(), "python".to_string());
+        map.insert("ts".to_string(), "typescript".to_string());
+
+        assert_eq!(detect_language(Path::new("main.rs"), &map), "rust");
+        assert_eq!(detect_language(Path::new("script.py"), &map), "python");
+        assert_eq!(detect_language(Path::new("app.ts"), &map), "typescript");
+        assert_eq!(detect_language(Path::new("unknown.xyz"), &map), "plaintext");
+    }
+
+    #[test]
+    #[allow(clippy::too_many_lines)]
+    fn test_detect_language_all_extensions() {
+        let mut map = HashMap::new();
+        map.insert("rs".to_string(), "rust".to_string());
+        map.insert("py".to_string(), "python".to_string());
+        map.insert("pyw".to_string(), "python".to_string());
+        map.insert("pyi".to_string(), "python".to_string());
+        map.insert("js".to_string(), "javascript".to_string());
+        map.insert("mjs".to_string(), "javascript".to_string());
+        map.insert("cjs".to_string(), "javascript".to_string());
+        map.insert("ts".to_string(), "typescript".to_string());
+        map.insert("mts".to_string(), "typescript".to_string());
+        map.insert("cts".to_string(), "typescript".to_string());
+        map.insert("tsx".to_string(), "typescriptreact".to_string());
+        map.insert("jsx".to_string(), "javascriptreact".to_string());
+        map.insert("go".to_string(), "go".to_string());
+        map.insert("c".to_string(), "c".to_string());
+        map.insert("h".to_string(), "c".to_string());
+        map.insert("cpp".to_string(), "cpp".to_string());
+        map.insert("cc".to_string(), "cpp".to_string());
+        map.insert("cxx".to_string(), "cpp".to_string());
+        map.insert("hpp".to_string(), "cpp".to_string());
+        map.insert("hh".to_string(), "cpp".to_string());
+        map.insert("hxx".to_string(), "cpp".to_string());
+        map.insert("java".to_string(), "java".to_string());
+        map.insert("rb".to_string(), "ruby".to_string());
+        map.insert("php".to_string(), "php".to_string());
+        map.insert("swift".to_string(), "swift".to_string());
+        map.insert("kt".to_string(), "kotlin".to_string());
+        map.insert("kts".to_string(), "kotlin".to_string());
+        map.insert("scala".to_string(), "scala".to_string());
+        map.insert("sc".to_string(), "scala".to_string());
+        map.insert("zig".to_string(), "zig".to_string());
+        map.insert("lua".to_string(), "lua".to_string());
+        map.insert("sh".to_string(), "shellscript".to_string());
+        map.insert("bash".to_string(), "shellscript".to_string());
+        map.insert("zsh".to_string(), "shellscript".to_string());
+        map.insert("json".to_string(), "json".to_string());
+        map.insert("toml".to_string(), "toml".to_string());
+        map.insert("yaml".to_string(), "yaml".to_string());
+        map.insert("yml".to_string(), "yaml".to_string());
+        map.insert("xml".to_string(), "xml".to_string());
+        map.insert("html".to_string(), "html".to_string());
+        map.insert("htm".to_string(), "html".to_string());
+        map.insert("css".to_string(), "css".to_string());
+        map.insert("scss".to_string(), "scss".to_string());
+        map.insert("less".to_string(), "less".to_string());
+        map.insert("md".to_string(), "markdown".to_string());
+        map.insert("markdown".to_string(), "markdown".to_string());
+
+        assert_eq!(detect_language(Path::new("main.rs"), &map), "rust");
+        assert_eq!(detect_language(Path::new("script.py"), &map), "python");
+        assert_eq!(detect_language(Path::new("script.pyw"), &map), "python");
+        assert_eq!(detect_language(Path::new("script.pyi"), &map), "python");
+        assert_eq!(detect_language(Path::new("app.js"), &map), "javascript");
+        assert_eq!(detect_language(Path::new("app.mjs"), &map), "javascript");
+        assert_eq!(detect_language(Path::new("app.cjs"), &map), "javascript");
+        assert_eq!(detect_language(Path::new("app.ts"), &map), "typescript");
+        assert_eq!(detect_language(Path::new("app.mts"), &map), "typescript");
+        assert_eq!(detect_language(Path::new("app.cts"), &map), "typescript");
+        assert_eq!(
+            detect_language(Path::new("component.tsx"), &map),
+            "typescriptreact"
+        );
+        assert_eq!(
+            detect_language(Path::new("component.jsx"), &map),
+            "javascriptreact"
+        );
+        assert_eq!(detect_language(Path::new("main.go"), &map), "go");
+        assert_eq!(detect_language(Path::new("main.c"), &map), "c");
+        assert_eq!(detect_language(Path::new("header.h"), &map), "c");
+        assert_eq!(detect_language(Path::new("main.cpp"), &map), "cpp");
+        assert_eq!(detect_language(Path::new("main.cc"), &map), "cpp");
+        assert_eq!(detect_language(Path::new("main.cxx"), &map), "cpp");
+        assert_eq!(detect_language(Path::new("header.hpp"), &map), "cpp");
+        assert_eq!(detect_language(Path::new("header.hh"), &map), "cpp");
+        assert_eq!(detect_language(Path::new("header.hxx"), &map), "cpp");
+        assert_eq!(detect_language(Path::new("Main.java"), &map), "java");
+        assert_eq!(detect_language(Path::new("script.rb"), &map), "ruby");
+        assert_eq!(detect_language(Path::new("index.php"), &map), "php");
+        assert_eq!(detect_language(Path::new("App.swift"), &map), "swift");
+        assert_eq!(detect_language(Path::new("Main.kt"), &map), "kotlin");
+        assert_eq!(detect_language(Path::new("script.kts"), &map), "kotlin");
+        assert_eq!(detect_language(Path::new("Main.scala"), &map), "scala");
+        assert_eq!(detect_language(Path::new("script.sc"), &map), "scala");
+        assert_eq!(detect_language(Path::new("main.zig"), &map), "zig");
+        assert_eq!(detect_language(Path::new("script.lua"), &map), "lua");
+        assert_eq!(detect_language(Path::new("script.sh"), &map), "shellscript");
+        assert_eq!(
+            detect_language(Path::new("script.bash"), &map),
+            "shellscript"
+        );
+        assert_eq!(
+            detect_language(Path::new("script.zsh"), &map),
+            "shellscript"
+        );
+        assert_eq!(detect_language(Path::new("data.json"), &map), "json");
+        assert_eq!(detect_language(Path::new("config.toml"), &map), "toml");
+        assert_eq!(detect_language(Path::new("config.yaml"), &map), "yaml");
+        assert_eq!(detect_language(Path::new("config.yml"), &map), "yaml");
+        assert_eq!(detect_language(Path::new("data.xml"), &map), "xml");
+        assert_eq!(detect_language(Path::new("index.html"), &map), "html");
+        assert_eq!(detect_language(Path::new("index.htm"), &map), "html");
+        assert_eq!(detect_language(Path::new("styles.css"), &map), "css");
+        assert_eq!(detect_language(Path::new("styles.scss"), &map), "scss");
+        assert_eq!(detect_language(Path::new("styles.less"), &map), "less");
+        assert_eq!(detect_language(Path::new("README.md"), &map), "markdown");
+        assert_eq!(
+            detect_language(Path::new("README.markdown"), &map),
+            "markdown"
+        );
+        assert_eq!(detect_language(Path::new("unknown.xyz"), &map), "plaintext");
+        assert_eq!(
+            detect_language(Path::new("no_extension"), &map),
+            "plaintext"
+        );
+    }
+
+    #[test]
+    fn test_detect_language_with_custom_extension() {
+        let mut map = HashMap::new();
+        map.insert("nu".to_string(), "nushell".to_string());
+
+        assert_eq!(detect_language(Path::new("script.nu"), &map), "nushell");
+
+        let empty_map = HashMap::new();
+        assert_eq!(
+            detect_language(Path::new("script.nu"), &empty_map),
+            "plaintext"
+        );
+    }
+
+    #[test]
+    fn test_detect_language_custom_overrides_default() {
+        let mut custom_map = HashMap::new();
+        custom_map.insert("rs".to_string(), "custom-rust".to_string());
+
+        assert_eq!(
+            detect_language(Path::new("main.rs"), &custom_map),
+            "custom-rust"
+        );
+
+        let mut default_map = HashMap::new();
+        default_map.insert("rs".to_string(), "rust".to_string());
+
+        assert_eq!(detect_language(Path::new("main.rs"), &default_map), "rust");
+    }
+
+    #[test]
+    fn test_detect_language_fallback_to_plaintext() {
+        let mut map = HashMap::new();
+        map.insert("nu".to_string(), "nushell".to_string());
+
+        // .rs not in custom map, should return plaintext
+        assert_eq!(detect_language(Path::new("main.rs"), &map), "plaintext");
+    }
+
+    #[test]
+    fn test_detect_language_empty_map() {
+        let map = HashMap::new();
+        assert_eq!(detect_language(Path::new("main.rs"), &map), "plaintext");
+    }
+
+    #[test]
+    fn test_multiple_extensions_same_language() {
+        let mut map = HashMap::new();
+        map.insert("cpp".to_string(), "c++".to_string());
+        map.insert("cc".to_string(), "c++".to_string());
+        map.insert("cxx".to_string(), "c++".to_string());
+
+        assert_eq!(detect_language(Path::new("main.cpp"), &map), "c++");
+        assert_eq!(detect_language(Path::new("main.cc"), &map), "c++");
+        assert_eq!(detect_language(Path::new("main.cxx"), &map), "c++");
+    }
+
+    #[test]
+    fn test_case_sensitive_extensions() {
+        let mut map = HashMap::new();
+        map.insert("NU".to_string(), "nushell".to_string());
+
+        // Lowercase .nu should not match uppercase "NU" in map
+        assert_eq!(detect_language(Path::new("script.nu"), &map), "plaintext");
+    }
+
+    #[test]
+    fn test_extension_router() {
+        let router = ExtensionRouter::new(HashMap::from([
+            ("rs".to_string(), "rust".to_string()),
+            ("md".to_string(), "markdown".to_string()),
+        ]));
+        assert_eq!(router.language_of(Path::new("/ws/lib.rs")), "rust");
+        assert_eq!(
+            router.language_of(Path::new("/ws/notes.txt")),
+            FALLBACK_LANGUAGE_ID
+        );
+
+        let mut extensions = router.extensions();
+        extensions.sort_unstable();
+        assert_eq!(extensions, [("md", "markdown"), ("rs", "rust")]);
+    }
+
+    fn patterns(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(pattern, language_id)| ((*pattern).to_string(), (*language_id).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_glob_router_matches_file_patterns() {
+        let router = GlobRouter::new(
+            HashMap::from([
+                ("h".to_string(), "c".to_string()),
+                ("rs".to_string(), "rust".to_string()),
+            ]),
+            patterns(&[
+                ("**/*.{h,hpp}", "cpp"),
+                ("build/*.rs", "generated"),
+                ("/opt/tools/**/*.py", "python"),
+            ]),
+        );
+        assert_eq!(router.language_of(Path::new("/ws/include/a.h")), "cpp");
+        assert_eq!(router.language_of(Path::new("/ws/include/a.hpp")), "cpp");
+        // Unanchored patterns match below any directory, one level only.
+        assert_eq!(
+            router.language_of(Path::new("/ws/build/out.rs")),
+            "generated"
+        );
+        assert_eq!(router.language_of(Path::new("/ws/build/x/out.rs")), "rust");
+        assert_eq!(router.language_of(Path::new("/opt/tools/a/b.py")), "python");
+        assert_eq!(
+            router.language_of(Path::new("/ws/tools/b.py")),
+            FALLBACK_LANGUAGE_ID
+        );
+    }
+
+    #[test]
+    fn test_glob_router_prefers_last_configured_pattern() {
+        let router = GlobRouter::new(
+            HashMap::new(),
+            patterns(&[("**/*.ts", "typescript"), ("**/web/**/*.ts", "deno")]),
+        );
+        assert_eq!(router.language_of(Path::new("/ws/src/a.ts")), "typescript");
+        assert_eq!(router.language_of(Path::new("/ws/web/src/a.ts")), "deno");
+    }
+
+    #[test]
+    fn test_glob_router_skips_invalid_patterns() {
+        let router = GlobRouter::new(
+            HashMap::new(),
+            patterns(&[("**/*.{rs", "broken"), ("**/*.rs", "rust")]),
+        );
+        assert_eq!(router.language_of(Path::new("/ws/lib.rs")), "rust");
+    }
+
+    #[test]
+    fn test_glob_router_lists_simple_pattern_extensions() {
+        let router = GlobRouter::new(
+            HashMap::from([("h".to_string(), "c".to_string())]),
+            patterns(&[("**/*.c", "cpp"), ("**/*.h", "cpp"), ("**/*", "cpp")]),
+        );
+        let mut extensions = router.extensions();
+        extensions.sort_unstable();
+        assert_eq!(extensions, [("c", "cpp"), ("h", "cpp")]);
+    }
+
+    #[test]
+    fn test_glob_router_from_config() {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [[lsp_servers]]
+            language_id = "cpp"
+            command = "clangd"
+            file_patterns = ["**/*.c", "**/*.h"]
+            "#,
+        )
+        .unwrap();
+        let router = GlobRouter::from_config(&config);
+        assert_eq!(router.language_of(Path::new("/ws/a.h")), "cpp");
+        // Extensions without a pattern keep their workspace mapping.
+        assert_eq!(router.language_of(Path::new("/ws/a.py")), "python");
+    }
+
+    #[test]
+    fn test_extension_from_pattern() {
+        assert_eq!(extension_from_pattern("**/*.rs"), Some("rs".to_string()));
+        assert_eq!(extension_from_pattern(""), None);
+        assert_eq!(extension_from_pattern("**/*"), None);
+        assert_eq!(extension_from_pattern(".gitignore"), None);
+        assert_eq!(extension_from_pattern("**/*.{h,hpp}"), None);
+        assert_eq!(extension_from_pattern("foo.tar.gz"), Some("gz".to_string()));
+    }
+
+    #[test]
+    fn test_serving_language_falls_back_to_catch_all() {
+        let router = ExtensionRouter::default();
+        let only_rust = |language_id: &str| language_id == "rust";
+        assert_eq!(router.serving_language("rust", &only_rust), "rust");
+        assert_eq!(router.serving_language("markdown", &only_rust), "markdown");
+
+        let with_catch_all =
+            |language_id: &str| language_id == "rust" || language_id == CATCH_ALL_LANGUAGE_ID;
+        assert_eq!(router.serving_language("rust", &with_catch_all), "rust");
+        assert_eq!(
+            router.serving_language("markdown", &with_catch_all),
+            CATCH_ALL_LANGUAGE_ID
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Text search across the workspace.
+//!
+//! A search runs on a language server when one offers a search extension
+//! covering every file in scope (rust-analyzer's structural search, SSR), and
+//! on a plain substring scan of the files otherwise. Both engines see the same
+//! files: those under the workspace roots that match the search globs and are
+//! not gitignored.
+
+use lsp_types::{TextDocumentIdentifier, Uri, WorkspaceEdit};
+use serde::{Deserialize, Serialize};
+
+use super::translator::{Position2D, Range};
+
+/// Method of rust-analyzer's structural search and replace extension.
+pub const SSR_METHOD: &str = "experimental/ssr";
+
+/// Which engine answered a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchEngine {
+    /// A language server search extension.
+    Lsp,
+    /// The built-in substring scan.
+    Text,
+}
+
+impl SearchEngine {
+    /// Parse the `engine` tool parameter; `auto` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the rejected value if it is not a valid engine.
+    pub fn parse(engine: &str) -> std::result::Result<Option<Self>, String> {
+        match engine.to_lowercase().as_str() {
+            "auto" => Ok(None),
+            "lsp" => Ok(Some(Self::Lsp)),
+            "text" => Ok(Some(Self::Text)),
+            _ => Err(engine.to_string()),
+        }
+    }
+}
+
+/// One occurrence of the query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextMatch {
+    /// Path of the file.
+    pub file_path: String,
+    /// Range of the occurrence.
+    pub range: Range,
+    /// Text of the line the occurrence starts on, without its line break.
+    pub line_text: String,
+}
+
+/// Result of a workspace text search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextSearchResult {
+    /// Engine that answered the search.
+    pub engine: SearchEngine,
+    /// Matches, ordered by file path and position.
+    pub matches: Vec<TextMatch>,
+    /// Whether more matches were found than `max_results`.
+    pub truncated: bool,
+}
+
+/// Parameters of rust-analyzer's `experimental/ssr` request.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SsrParams {
+    /// Rule of the form `pattern ==>> replacement`.
+    pub query: String,
+    /// Whether to only check that the rule parses.
+    pub parse_only: bool,
+    /// Document paths in the rule are resolved against.
+    pub text_document: TextDocumentIdentifier,
+    /// Position paths in the rule are resolved at.
+    pub position: lsp_types::Position,
+    /// Ranges to restrict the search to; empty for the whole workspace.
+    pub selections: Vec<lsp_types::Range>,
+}
+
+impl SsrParams {
+    /// A rule replacing each match of `pattern` with `replacement`, resolved
+    /// in the context of `text_document`.
+    pub fn rule(pattern: &str, replacement: &str, text_document: TextDocumentIdentifier) -> Self {
+        Self {
+            query: format!("{pattern} ==>> {replacement}"),
+            parse_only: false,
+            text_document,
+            position: lsp_types::Position::default(),
+            selections: Vec::new(),
+        }
+    }
+
+    /// A rule that replaces each match of `pattern` with itself, so the edit
+    /// it produces marks every match.
+    pub fn search(pattern: &str, text_document: TextDocumentIdentifier) -> Self {
+        Self::rule(pattern, pattern, text_document)
+    }
+}
+
+/// Ranges of the edits in an SSR response, in server units.
+pub fn ssr_matches(edit: WorkspaceEdit) -> Vec<(Uri, lsp_types::Range)> {
+    let mut matches = Vec::new();
+    for (uri, edits) in edit.changes.into_iter().flatten() {
+        matches.extend(edits.into_iter().map(|edit| (uri.clone(), edit.range)));
+    }
+    if let Some(lsp_types::DocumentChanges::Edits(documents)) = edit.document_changes {
+        for document in documents {
+            let uri = document.text_document.uri;
+            matches.extend(document.edits.into_iter().map(|edit| {
+                let range = match edit {
+                    lsp_types::OneOf::Left(edit) => edit.range,
+                    lsp_types::OneOf::Right(edit) => edit.text_edit.range,
+                };
+                (uri.clone(), range)
+            }));
+        }
+    }
+    matches
+}
+
+/// Occurrences of `query` in `content`, with 1-based positions in characters.
+pub fn find_in_text(
+    file_path: &str,
+    content: &str,
+    query: &str,
+    case_sensitive: bool,
+) -> Vec<TextMatch> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let query = if case_sensitive {
+        query.to_string()
+    } else {
+        query.to_lowercase()
+    };
+
+    let mut matches = Vec::new();
+    for (line_index, line) in content.lines().enumerate() {
+        let haystack = if case_sensitive {
+            line.to_string()
+        } else {
+            line.to_lowercase()
+        };
+        // Columns count characters of `haystack`; lowercasing keeps the
+        // character count of all but a few exotic letters.
+        for (offset, _) in haystack.match_indices(&query) {
+            let start = haystack[..offset].chars().count();
+            let length = query.chars().count();
+            let line_number = u32::try_from(line_index + 1).unwrap_or(u32::MAX);
+            matches.push(TextMatch {
+                file_path: file_path.to_string(),
+                range: Range {
+                    start: Position2D {
+                        line: line_number,
+                        character: column(start),
+                    },
+                    end: Position2D {
+                        line: line_number,
+                        character: column(start + length),
+                    },
+                },
+                line_text: line.to_string(),
+            });
+        }
+    }
+    matches
+}
+
+/// 1-based column of a 0-based character offset.
+fn column(offset: usize) -> u32 {
+    u32::try_from(offset + 1).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_in_text() {
+        let content = "let näme = 1;\r\n// TODO: rename näme\nlet x = NÄME;\n";
+
+        let matches = find_in_text("/ws/a.rs", content, "näme", true);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].range.start.line, 1);
+        assert_eq!(matches[0].range.start.character, 5);
+        assert_eq!(matches[0].range.end.character, 9);
+        assert_eq!(matches[0].line_text, "let näme = 1;");
+        assert_eq!(matches[1].range.start.line, 2);
+        assert_eq!(matches[1].range.start.character, 17);
+
+        let matches = find_in_text("/ws/a.rs", content, "näme", false);
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[2].range.start.character, 9);
+
+        assert!(find_in_text("/ws/a.rs", content, "", true).is_empty());
+    }
+
+    #[test]
+    fn test_ssr_matches() {
+        let uri: Uri = "file:///ws/src/lib.rs".parse().unwrap();
+        let range = lsp_types::Range::new(
+            lsp_types::Position::new(3, 4),
+            lsp_types::Position::new(3, 12),
+        );
+        let edit = WorkspaceEdit {
+            document_changes: Some(lsp_types::DocumentChanges::Edits(vec![
+                lsp_types::TextDocumentEdit {
+                    text_document: lsp_types::OptionalVersionedTextDocumentIdentifier {
+                        uri: uri.clone(),
+                        version: None,
+                    },
+                    edits: vec![lsp_types::OneOf::Left(lsp_types::TextEdit::new(
+                        range,
+                        "foo(x)".to_string(),
+                    ))],
+                },
+            ])),
+            ..WorkspaceEdit::default()
+        };
+
+        assert_eq!(ssr_matches(edit), vec![(uri, range)]);
+        assert_eq!(
+            SsrParams::search(
+                "foo($a)",
+                TextDocumentIdentifier::new("file:///ws/src/lib.rs".parse().unwrap())
+            )
+            .query,
+            "foo($a) ==>> foo($a)"
+        );
+    }
+}
@@ -0,0 +1,22 @@
+//! Status of the language server for a language, as reported by the
+//! workspace scan, the supported languages listing, and snapshots.
+//!
+//! This is the bridge's view of a language: whether a server handles it at
+//! all. The protocol state of a running server is
+//! [`crate::lsp::ServerState`].
+
+use serde::{Deserialize, Serialize};
+
+/// Status of the language server for a language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LanguageServerStatus {
+    /// A server is running.
+    Running,
+    /// A server is configured and still initializing.
+    Initializing,
+    /// The configured server failed to start.
+    Failed,
+    /// No server is configured or applicable.
+    None,
+}
@@ -2,14 +2,51 @@
 //!
 //! Tracks open documents and their versions for LSP synchronization.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use futures::StreamExt;
 use lsp_types::{DidOpenTextDocumentParams, TextDocumentItem, Uri};
-use url::Url;
+use tracing::debug;
 
+use super::events::{BridgeEvent, EventBus};
+use super::router::{ExtensionRouter, FALLBACK_LANGUAGE_ID, LanguageRouter};
+use super::uri::path_to_uri;
+use crate::config::CompanionFiles;
 use crate::error::{Error, Result};
-use crate::lsp::LspClient;
+use crate::lsp::{LspClient, metrics};
+
+/// Line ending style of a document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, also assumed for documents without any line break.
+    #[default]
+    Lf,
+    /// `\r\n`.
+    Crlf,
+}
+
+impl LineEnding {
+    /// Detect the line ending of `content` from its first line break.
+    #[must_use]
+    pub fn detect(content: &str) -> Self {
+        match content.find('\n') {
+            Some(i) if content[..i].ends_with('\r') => Self::Crlf,
+            _ => Self::Lf,
+        }
+    }
+
+    /// Rewrite every line break in `text` to this line ending.
+    #[must_use]
+    pub fn normalize(self, text: &str) -> String {
+        let lf = text.replace("\r\n", "\n");
+        match self {
+            Self::Lf => lf,
+            Self::Crlf => lf.replace('\n', "\r\n"),
+        }
+    }
+}
 
 /// State of a single document.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,8 +59,20 @@ pub struct DocumentState {
     pub version: i32,
     /// Document content.
     pub content: String,
+    /// Line ending of the content, preserved when edits are written back.
+    pub line_ending: LineEnding,
 }
 
+/// Number of earlier versions of each document kept for re-anchoring
+/// positions.
+const HISTORY_LIMIT: usize = 8;
+
+/// Maximum number of files [`DocumentTracker::prefetch`] reads at once.
+const PREFETCH_CONCURRENCY: usize = 8;
+
+/// Maximum number of companion files opened along with one document.
+const MAX_COMPANION_FILES: usize = 16;
+
 /// Resource limits for document tracking.
 #[derive(Debug, Clone, Copy)]
 pub struct ResourceLimits {
@@ -47,23 +96,53 @@ impl Default for ResourceLimits {
 pub struct DocumentTracker {
     /// Open documents by file path.
     documents: HashMap<PathBuf, DocumentState>,
+    /// Earlier versions and content of open documents, oldest first.
+    history: HashMap<PathBuf, VecDeque<(i32, String)>>,
+    /// Version of each document when a tool last used it.
+    used_versions: HashMap<PathBuf, i32>,
     /// Resource limits for tracking.
     limits: ResourceLimits,
-    /// Custom file extension to language ID mappings.
-    extension_map: HashMap<String, String>,
+    /// Router detecting the language of documents.
+    router: Arc<dyn LanguageRouter>,
+    /// Companion file strategy by language ID.
+    companion_files: HashMap<String, CompanionFiles>,
+    /// Bus announcing documents opened on and closed.
+    events: EventBus,
 }
 
 impl DocumentTracker {
     /// Create a new document tracker with custom limits and extension mappings.
     #[must_use]
     pub fn new(limits: ResourceLimits, extension_map: HashMap<String, String>) -> Self {
+        Self::with_router(limits, Arc::new(ExtensionRouter::new(extension_map)))
+    }
+
+    /// Create a new document tracker with custom limits, detecting the
+    /// language of documents with `router`.
+    #[must_use]
+    pub fn with_router(limits: ResourceLimits, router: Arc<dyn LanguageRouter>) -> Self {
         Self {
             documents: HashMap::new(),
+            history: HashMap::new(),
+            used_versions: HashMap::new(),
             limits,
-            extension_map,
+            router,
+            companion_files: HashMap::new(),
+            events: EventBus::default(),
         }
     }
 
+    /// Announce opened and closed documents on `events`.
+    pub fn set_events(&mut self, events: EventBus) {
+        self.events = events;
+    }
+
+    /// Set which sibling files are opened along with documents of
+    /// `language_id`.
+    pub fn set_companion_files(&mut self, language_id: String, strategy: CompanionFiles) {
+        self.companion_files.insert(language_id, strategy);
+    }
+
     /// Check if a document is currently open.
     #[must_use]
     pub fn is_open(&self, path: &Path) -> bool {
@@ -76,6 +155,12 @@ impl DocumentTracker {
         self.documents.get(path)
     }
 
+    /// Get the resource limits.
+    #[must_use]
+    pub const fn limits(&self) -> ResourceLimits {
+        self.limits
+    }
+
     /// Get the number of open documents.
     #[must_use]
     pub fn len(&self) -> usize {
@@ -116,12 +201,13 @@ impl DocumentTracker {
         }
 
         let uri = path_to_uri(&path);
-        let language_id = detect_language(&path, &self.extension_map);
+        let language_id = self.router.language_of(&path);
 
         let state = DocumentState {
             uri: uri.clone(),
             language_id,
             version: 1,
+            line_ending: LineEnding::detect(&content),
             content,
         };
 
@@ -134,8 +220,16 @@ impl DocumentTracker {
     /// Returns `None` if the document is not open.
     pub fn update(&mut self, path: &Path, content: String) -> Option<i32> {
         if let Some(state) = self.documents.get_mut(path) {
+            let history = self.history.entry(path.to_path_buf()).or_default();
+            if history.len() == HISTORY_LIMIT {
+                history.pop_front();
+            }
+            history.push_back((
+                state.version,
+                std::mem::replace(&mut state.content, content),
+            ));
             state.version += 1;
-            state.content = content;
+            state.line_ending = LineEnding::detect(&state.content);
             Some(state.version)
         } else {
             None
@@ -146,12 +240,48 @@ impl DocumentTracker {
     ///
     /// Returns the document state if it was open.
     pub fn close(&mut self, path: &Path) -> Option<DocumentState> {
-        self.documents.remove(path)
+        let state = self.documents.remove(path)?;
+        self.history.remove(path);
+        self.used_versions.remove(path);
+        self.events.publish(BridgeEvent::DocumentClosed {
+            uri: state.uri.clone(),
+        });
+        Some(state)
     }
 
     /// Close all documents.
     pub fn close_all(&mut self) -> Vec<DocumentState> {
-        self.documents.drain().map(|(_, state)| state).collect()
+        let closed: Vec<DocumentState> = self.documents.drain().map(|(_, state)| state).collect();
+        self.history.clear();
+        self.used_versions.clear();
+        for state in &closed {
+            self.events.publish(BridgeEvent::DocumentClosed {
+                uri: state.uri.clone(),
+            });
+        }
+        closed
+    }
+
+    /// Content of version `version` of an open document, if it is the
+    /// current version or one of the last few.
+    #[must_use]
+    pub fn content_at(&self, path: &Path, version: i32) -> Option<&str> {
+        let state = self.documents.get(path)?;
+        if state.version == version {
+            return Some(&state.content);
+        }
+        self.history
+            .get(path)?
+            .iter()
+            .find(|(old, _)| *old == version)
+            .map(|(_, content)| content.as_str())
+    }
+
+    /// Version of a document when a tool last used it through
+    /// [`Self::ensure_open`], which positions given since refer to.
+    #[must_use]
+    pub fn used_version(&self, path: &Path) -> Option<i32> {
+        self.used_versions.get(path).copied()
     }
 
     /// Iterate over the filesystem paths of all currently open documents.
@@ -172,22 +302,124 @@ impl DocumentTracker {
     /// - The `didOpen` notification fails to send
     /// - Resource limits are exceeded
     pub async fn ensure_open(&mut self, path: &Path, lsp_client: &LspClient) -> Result<Uri> {
+        self.ensure_open_at(path, lsp_client, 1).await
+    }
+
+    /// Same as [`Self::ensure_open`], but a document opened by the call starts
+    /// at `version` instead of 1.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Self::ensure_open`].
+    pub async fn ensure_open_at(
+        &mut self,
+        path: &Path,
+        lsp_client: &LspClient,
+        version: i32,
+    ) -> Result<Uri> {
         if let Some(state) = self.documents.get(path) {
+            metrics::record_document(&state.language_id, state.version);
+            self.used_versions.insert(path.to_path_buf(), state.version);
             return Ok(state.uri.clone());
         }
 
-        let content = tokio::fs::read_to_string(path)
+        let uri = self.open_from_disk(path, lsp_client, version).await?;
+        metrics::record_document(&self.router.language_of(path), version);
+        self.used_versions.insert(path.to_path_buf(), version);
+        self.open_companions(path, lsp_client).await;
+        Ok(uri)
+    }
+
+    /// Open the companion files of a newly opened document. Companions that
+    /// cannot be opened are skipped, and only half of the document limit is
+    /// spent on them so documents tools ask for still fit.
+    async fn open_companions(&mut self, path: &Path, lsp_client: &LspClient) {
+        let language_id = self.router.language_of(path);
+        let strategy = self
+            .companion_files
+            .get(&language_id)
+            .copied()
+            .unwrap_or_default();
+        for companion in companion_paths(path, strategy, self.router.as_ref()) {
+            if self.limits.max_documents > 0
+                && self.documents.len() >= self.limits.max_documents / 2
+            {
+                break;
+            }
+            if self.documents.contains_key(&companion) {
+                continue;
+            }
+            if let Err(e) = self.open_from_disk(&companion, lsp_client, 1).await {
+                debug!("Skipping companion file {}: {e}", companion.display());
+            }
+        }
+    }
+
+    /// Open every document of `files` that is not open yet, reading them
+    /// from disk concurrently.
+    ///
+    /// Returns one outcome per file, in order: `true` if the call opened it,
+    /// `false` if it was already open. Files are tracked in order, so once
+    /// the document limit is reached the remaining files fail with
+    /// [`Error::DocumentLimitExceeded`].
+    pub async fn prefetch(&mut self, files: Vec<(PathBuf, LspClient)>) -> Vec<Result<bool>> {
+        let reads: Vec<_> = futures::stream::iter(files)
+            .map(|(path, lsp_client)| {
+                let open = self.documents.contains_key(&path);
+                async move {
+                    let content = if open {
+                        None
+                    } else {
+                        Some(read_document(&path).await)
+                    };
+                    (path, lsp_client, content)
+                }
+            })
+            .buffered(PREFETCH_CONCURRENCY)
+            .collect()
+            .await;
+
+        let mut outcomes = Vec::with_capacity(reads.len());
+        for (path, lsp_client, content) in reads {
+            let outcome = match content {
+                None => Ok(false),
+                Some(Err(e)) => Err(e),
+                Some(Ok(content)) => self
+                    .open_with_content(&path, content, &lsp_client, 1)
+                    .await
+                    .map(|_| true),
+            };
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+
+    /// Read a document from disk, track it, and send `didOpen`.
+    async fn open_from_disk(
+        &mut self,
+        path: &Path,
+        lsp_client: &LspClient,
+        version: i32,
+    ) -> Result<Uri> {
+        let content = read_document(path).await?;
+        self.open_with_content(path, content, lsp_client, version)
             .await
-            .map_err(|e| Error::FileIo {
-                path: path.to_path_buf(),
-                source: e,
-            })?;
+    }
 
+    /// Track a document read from disk and send `didOpen`.
+    async fn open_with_content(
+        &mut self,
+        path: &Path,
+        content: String,
+        lsp_client: &LspClient,
+        version: i32,
+    ) -> Result<Uri> {
         let uri = self.open(path.to_path_buf(), content.clone())?;
         let state = self
             .documents
-            .get(path)
+            .get_mut(path)
             .ok_or_else(|| Error::DocumentNotFound(path.to_path_buf()))?;
+        state.version = version;
 
         let params = DidOpenTextDocumentParams {
             text_document: TextDocumentItem {
@@ -198,63 +430,62 @@ impl DocumentTracker {
             },
         };
 
+        let language_id = params.text_document.language_id.clone();
         lsp_client.notify("textDocument/didOpen", params).await?;
+        self.events.publish(BridgeEvent::DocumentOpened {
+            uri: uri.clone(),
+            language_id,
+        });
 
         Ok(uri)
     }
 }
 
-/// Convert a file path to a URI.
-///
-/// # Panics
-///
-/// Panics if the path cannot be represented as a `file://` URI. This should
-/// not occur for valid absolute paths.
-#[must_use]
-pub fn path_to_uri(path: &Path) -> Uri {
-    let uri_string = if cfg!(windows) {
-        let path_str = path.to_string_lossy();
-        // canonicalize() on Windows adds a \\?\ extended-path prefix.
-        // Strip it before building the URI — file:////?\C:/ is not valid.
-        let stripped = path_str.strip_prefix(r"\\?\").unwrap_or(&path_str);
-        format!("file:///{}", stripped.replace('\\', "/"))
-    } else {
-        format!("file://{}", path.display())
-    };
-    #[allow(clippy::expect_used)]
-    uri_string.parse().expect("failed to create URI from path")
+async fn read_document(path: &Path) -> Result<String> {
+    tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| Error::FileIo {
+            path: path.to_path_buf(),
+            source: e,
+        })
 }
 
-/// Convert an LSP `file://` URI to an absolute filesystem path.
-///
-/// Returns `None` if the URI is not a valid `file://` URI, uses a non-file
-/// scheme, or contains percent-encoding that cannot map to a valid path.
+/// Sibling files of `path` to open along with it under `strategy`, in
+/// name order and at most [`MAX_COMPANION_FILES`].
 #[must_use]
-pub fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
-    let url = Url::parse(uri.as_str()).ok()?;
-    if url.scheme() != "file" {
-        return None;
-    }
-    // Reject authority-bearing file URIs (e.g. `file://server/share`) to
-    // avoid UNC path confusion on Windows.
-    if !url.host_str().unwrap_or("").is_empty() {
-        return None;
-    }
-    url.to_file_path().ok()
-}
-
-/// Detect the language ID from a file path.
-///
-/// Consults the extension map to determine the language ID for a file.
-/// If the extension is not found in the map, returns "plaintext".
-#[must_use]
-pub fn detect_language(path: &Path, extension_map: &HashMap<String, String>) -> String {
-    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-
-    extension_map
-        .get(extension)
-        .cloned()
-        .unwrap_or_else(|| "plaintext".to_string())
+pub fn companion_paths(
+    path: &Path,
+    strategy: CompanionFiles,
+    router: &dyn LanguageRouter,
+) -> Vec<PathBuf> {
+    if strategy == CompanionFiles::None {
+        return Vec::new();
+    }
+    let (Some(dir), Some(stem)) = (path.parent(), path.file_stem()) else {
+        return Vec::new();
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let language_id = router.language_of(path);
+    let mut companions: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|candidate| candidate != path && candidate.is_file())
+        .filter(|candidate| {
+            let candidate_language = router.language_of(candidate);
+            match strategy {
+                CompanionFiles::None => false,
+                CompanionFiles::SameStem => {
+                    candidate.file_stem() == Some(stem)
+                        && candidate_language != FALLBACK_LANGUAGE_ID
+                }
+                CompanionFiles::Directory => candidate_language == language_id,
+            }
+        })
+        .collect();
+    companions.sort();
+    companions.truncate(MAX_COMPANION_FILES);
+    companions
 }
 
 #[cfg(test)]
@@ -263,16 +494,36 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_detect_language() {
+    fn test_companion_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "widget.cpp",
+            "widget.h",
+            "widget.txt",
+            "gadget.cpp",
+            "gadget.h",
+        ] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
         let mut map = HashMap::new();
-        map.insert("rs".to_string(), "rust".to_string());
-        map.insert("py".to_string(), "python".to_string());
-        map.insert("ts".to_string(), "typescript".to_string());
+        map.insert("cpp".to_string(), "cpp".to_string());
+        map.insert("h".to_string(), "cpp".to_string());
+        let router = ExtensionRouter::new(map);
+        let source = dir.path().join("widget.cpp");
 
-        assert_eq!(detect_language(Path::new("main.rs"), &map), "rust");
-        assert_eq!(detect_language(Path::new("script.py"), &map), "python");
-        assert_eq!(detect_language(Path::new("app.ts"), &map), "typescript");
-        assert_eq!(detect_language(Path::new("unknown.xyz"), &map), "plaintext");
+        assert!(companion_paths(&source, CompanionFiles::None, &router).is_empty());
+        assert_eq!(
+            companion_paths(&source, CompanionFiles::SameStem, &router),
+            vec![dir.path().join("widget.h")]
+        );
+        assert_eq!(
+            companion_paths(&source, CompanionFiles::Directory, &router),
+            vec![
+                dir.path().join("gadget.cpp"),
+                dir.path().join("gadget.h"),
+                dir.path().join("widget.h"),
+            ]
+        );
     }
 
     #[test]
@@ -395,6 +646,30 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_line_ending_detect_and_normalize() {
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\nb\r\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("no break"), LineEnding::Lf);
+
+        assert_eq!(LineEnding::Crlf.normalize("a\nb\r\nc"), "a\r\nb\r\nc");
+        assert_eq!(LineEnding::Lf.normalize("a\r\nb\nc"), "a\nb\nc");
+    }
+
+    #[test]
+    fn test_tracker_records_line_ending() {
+        let mut tracker = DocumentTracker::new(ResourceLimits::default(), HashMap::new());
+        let path = PathBuf::from("/test/file.rs");
+
+        tracker
+            .open(path.clone(), "a\r\nb\r\n".to_string())
+            .unwrap();
+        assert_eq!(tracker.get(&path).unwrap().line_ending, LineEnding::Crlf);
+
+        tracker.update(&path, "a\nb\n".to_string());
+        assert_eq!(tracker.get(&path).unwrap().line_ending, LineEnding::Lf);
+    }
+
     #[test]
     fn test_document_state_clone() {
         let state = DocumentState {
@@ -402,6 +677,7 @@ mod tests {
             language_id: "rust".to_string(),
             version: 5,
             content: "fn main() {}".to_string(),
+            line_ending: LineEnding::Lf,
         };
 
         #[allow(clippy::redundant_clone)]
@@ -412,6 +688,25 @@ mod tests {
         assert_eq!(cloned.content, state.content);
     }
 
+    #[test]
+    fn test_content_at_keeps_recent_versions() {
+        let mut tracker = DocumentTracker::new(ResourceLimits::default(), HashMap::new());
+        let path = PathBuf::from("/test/history.rs");
+        tracker.open(path.clone(), "v1".to_string()).unwrap();
+        for n in 2..=HISTORY_LIMIT + 2 {
+            tracker.update(&path, format!("v{n}"));
+        }
+        let current = tracker.get(&path).unwrap().version;
+        assert_eq!(tracker.content_at(&path, current), Some("v10"));
+        assert_eq!(tracker.content_at(&path, current - 1), Some("v9"));
+        // Only the last HISTORY_LIMIT earlier versions are kept.
+        assert_eq!(tracker.content_at(&path, 2), Some("v2"));
+        assert_eq!(tracker.content_at(&path, 1), None);
+
+        tracker.close(&path);
+        assert_eq!(tracker.content_at(&path, current), None);
+    }
+
     #[test]
     fn test_update_nonexistent_document() {
         let map = HashMap::new();
@@ -462,6 +757,32 @@ mod tests {
         assert!(tracker.is_empty());
     }
 
+    #[test]
+    fn test_close_publishes_events() {
+        let mut tracker = DocumentTracker::new(ResourceLimits::default(), HashMap::new());
+        let events = EventBus::default();
+        let mut rx = events.subscribe();
+        tracker.set_events(events);
+        let path = PathBuf::from("/test/file1.rs");
+        tracker.open(path.clone(), "content1".to_string()).unwrap();
+        tracker
+            .open(PathBuf::from("/test/file2.rs"), "content2".to_string())
+            .unwrap();
+
+        tracker.close(&path);
+        assert!(tracker.close(&path).is_none());
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            BridgeEvent::DocumentClosed { uri } if uri == path_to_uri(&path)
+        ));
+        tracker.close_all();
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            BridgeEvent::DocumentClosed { .. }
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
     #[test]
     fn test_get_nonexistent_document() {
         let map = HashMap::new();
@@ -496,146 +817,6 @@ mod tests {
         assert_eq!(tracker.get(&path).unwrap().version, 4);
     }
 
-    #[test]
-    #[allow(clippy::too_many_lines)]
-    fn test_detect_language_all_extensions() {
-        let mut map = HashMap::new();
-        map.insert("rs".to_string(), "rust".to_string());
-        map.insert("py".to_string(), "python".to_string());
-        map.insert("pyw".to_string(), "python".to_string());
-        map.insert("pyi".to_string(), "python".to_string());
-        map.insert("js".to_string(), "javascript".to_string());
-        map.insert("mjs".to_string(), "javascript".to_string());
-        map.insert("cjs".to_string(), "javascript".to_string());
-        map.insert("ts".to_string(), "typescript".to_string());
-        map.insert("mts".to_string(), "typescript".to_string());
-        map.insert("cts".to_string(), "typescript".to_string());
-        map.insert("tsx".to_string(), "typescriptreact".to_string());
-        map.insert("jsx".to_string(), "javascriptreact".to_string());
-        map.insert("go".to_string(), "go".to_string());
-        map.insert("c".to_string(), "c".to_string());
-        map.insert("h".to_string(), "c".to_string());
-        map.insert("cpp".to_string(), "cpp".to_string());
-        map.insert("cc".to_string(), "cpp".to_string());
-        map.insert("cxx".to_string(), "cpp".to_string());
-        map.insert("hpp".to_string(), "cpp".to_string());
-        map.insert("hh".to_string(), "cpp".to_string());
-        map.insert("hxx".to_string(), "cpp".to_string());
-        map.insert("java".to_string(), "java".to_string());
-        map.insert("rb".to_string(), "ruby".to_string());
-        map.insert("php".to_string(), "php".to_string());
-        map.insert("swift".to_string(), "swift".to_string());
-        map.insert("kt".to_string(), "kotlin".to_string());
-        map.insert("kts".to_string(), "kotlin".to_string());
-        map.insert("scala".to_string(), "scala".to_string());
-        map.insert("sc".to_string(), "scala".to_string());
-        map.insert("zig".to_string(), "zig".to_string());
-        map.insert("lua".to_string(), "lua".to_string());
-        map.insert("sh".to_string(), "shellscript".to_string());
-        map.insert("bash".to_string(), "shellscript".to_string());
-        map.insert("zsh".to_string(), "shellscript".to_string());
-        map.insert("json".to_string(), "json".to_string());
-        map.insert("toml".to_string(), "toml".to_string());
-        map.insert("yaml".to_string(), "yaml".to_string());
-        map.insert("yml".to_string(), "yaml".to_string());
-        map.insert("xml".to_string(), "xml".to_string());
-        map.insert("html".to_string(), "html".to_string());
-        map.insert("htm".to_string(), "html".to_string());
-        map.insert("css".to_string(), "css".to_string());
-        map.insert("scss".to_string(), "scss".to_string());
-        map.insert("less".to_string(), "less".to_string());
-        map.insert("md".to_string(), "markdown".to_string());
-        map.insert("markdown".to_string(), "markdown".to_string());
-
-        assert_eq!(detect_language(Path::new("main.rs"), &map), "rust");
-        assert_eq!(detect_language(Path::new("script.py"), &map), "python");
-        assert_eq!(detect_language(Path::new("script.pyw"), &map), "python");
-        assert_eq!(detect_language(Path::new("script.pyi"), &map), "python");
-        assert_eq!(detect_language(Path::new("app.js"), &map), "javascript");
-        assert_eq!(detect_language(Path::new("app.mjs"), &map), "javascript");
-        assert_eq!(detect_language(Path::new("app.cjs"), &map), "javascript");
-        assert_eq!(detect_language(Path::new("app.ts"), &map), "typescript");
-        assert_eq!(detect_language(Path::new("app.mts"), &map), "typescript");
-        assert_eq!(detect_language(Path::new("app.cts"), &map), "typescript");
-        assert_eq!(
-            detect_language(Path::new("component.tsx"), &map),
-            "typescriptreact"
-        );
-        assert_eq!(
-            detect_language(Path::new("component.jsx"), &map),
-            "javascriptreact"
-        );
-        assert_eq!(detect_language(Path::new("main.go"), &map), "go");
-        assert_eq!(detect_language(Path::new("main.c"), &map), "c");
-        assert_eq!(detect_language(Path::new("header.h"), &map), "c");
-        assert_eq!(detect_language(Path::new("main.cpp"), &map), "cpp");
-        assert_eq!(detect_language(Path::new("main.cc"), &map), "cpp");
-        assert_eq!(detect_language(Path::new("main.cxx"), &map), "cpp");
-        assert_eq!(detect_language(Path::new("header.hpp"), &map), "cpp");
-        assert_eq!(detect_language(Path::new("header.hh"), &map), "cpp");
-        assert_eq!(detect_language(Path::new("header.hxx"), &map), "cpp");
-        assert_eq!(detect_language(Path::new("Main.java"), &map), "java");
-        assert_eq!(detect_language(Path::new("script.rb"), &map), "ruby");
-        assert_eq!(detect_language(Path::new("index.php"), &map), "php");
-        assert_eq!(detect_language(Path::new("App.swift"), &map), "swift");
-        assert_eq!(detect_language(Path::new("Main.kt"), &map), "kotlin");
-        assert_eq!(detect_language(Path::new("script.kts"), &map), "kotlin");
-        assert_eq!(detect_language(Path::new("Main.scala"), &map), "scala");
-        assert_eq!(detect_language(Path::new("script.sc"), &map), "scala");
-        assert_eq!(detect_language(Path::new("main.zig"), &map), "zig");
-        assert_eq!(detect_language(Path::new("script.lua"), &map), "lua");
-        assert_eq!(detect_language(Path::new("script.sh"), &map), "shellscript");
-        assert_eq!(
-            detect_language(Path::new("script.bash"), &map),
-            "shellscript"
-        );
-        assert_eq!(
-            detect_language(Path::new("script.zsh"), &map),
-            "shellscript"
-        );
-        assert_eq!(detect_language(Path::new("data.json"), &map), "json");
-        assert_eq!(detect_language(Path::new("config.toml"), &map), "toml");
-        assert_eq!(detect_language(Path::new("config.yaml"), &map), "yaml");
-        assert_eq!(detect_language(Path::new("config.yml"), &map), "yaml");
-        assert_eq!(detect_language(Path::new("data.xml"), &map), "xml");
-        assert_eq!(detect_language(Path::new("index.html"), &map), "html");
-        assert_eq!(detect_language(Path::new("index.htm"), &map), "html");
-        assert_eq!(detect_language(Path::new("styles.css"), &map), "css");
-        assert_eq!(detect_language(Path::new("styles.scss"), &map), "scss");
-        assert_eq!(detect_language(Path::new("styles.less"), &map), "less");
-        assert_eq!(detect_language(Path::new("README.md"), &map), "markdown");
-        assert_eq!(
-            detect_language(Path::new("README.markdown"), &map),
-            "markdown"
-        );
-        assert_eq!(detect_language(Path::new("unknown.xyz"), &map), "plaintext");
-        assert_eq!(
-            detect_language(Path::new("no_extension"), &map),
-            "plaintext"
-        );
-    }
-
-    #[test]
-    fn test_path_to_uri_unix() {
-        #[cfg(not(windows))]
-        {
-            let path = Path::new("/home/user/project/main.rs");
-            let uri = path_to_uri(path);
-            assert!(
-                uri.as_str()
-                    .starts_with("file:///home/user/project/main.rs")
-            );
-        }
-    }
-
-    #[test]
-    fn test_path_to_uri_with_special_chars() {
-        let path = Path::new("/home/user/project-test/main.rs");
-        let uri = path_to_uri(path);
-        assert!(uri.as_str().starts_with("file://"));
-        assert!(uri.as_str().contains("project-test"));
-    }
-
     #[test]
     fn test_document_tracker_concurrent_operations() {
         let mut map = HashMap::new();
@@ -735,51 +916,6 @@ mod tests {
         assert!(matches!(result, Err(Error::FileSizeLimitExceeded { .. })));
     }
 
-    #[test]
-    fn test_detect_language_with_custom_extension() {
-        let mut map = HashMap::new();
-        map.insert("nu".to_string(), "nushell".to_string());
-
-        assert_eq!(detect_language(Path::new("script.nu"), &map), "nushell");
-
-        let empty_map = HashMap::new();
-        assert_eq!(
-            detect_language(Path::new("script.nu"), &empty_map),
-            "plaintext"
-        );
-    }
-
-    #[test]
-    fn test_detect_language_custom_overrides_default() {
-        let mut custom_map = HashMap::new();
-        custom_map.insert("rs".to_string(), "custom-rust".to_string());
-
-        assert_eq!(
-            detect_language(Path::new("main.rs"), &custom_map),
-            "custom-rust"
-        );
-
-        let mut default_map = HashMap::new();
-        default_map.insert("rs".to_string(), "rust".to_string());
-
-        assert_eq!(detect_language(Path::new("main.rs"), &default_map), "rust");
-    }
-
-    #[test]
-    fn test_detect_language_fallback_to_plaintext() {
-        let mut map = HashMap::new();
-        map.insert("nu".to_string(), "nushell".to_string());
-
-        // .rs not in custom map, should return plaintext
-        assert_eq!(detect_language(Path::new("main.rs"), &map), "plaintext");
-    }
-
-    #[test]
-    fn test_detect_language_empty_map() {
-        let map = HashMap::new();
-        assert_eq!(detect_language(Path::new("main.rs"), &map), "plaintext");
-    }
-
     #[test]
     fn test_document_tracker_with_extensions() {
         let mut map = HashMap::new();
@@ -811,64 +947,6 @@ mod tests {
         assert_eq!(state.language_id, "rust");
     }
 
-    #[test]
-    fn test_multiple_extensions_same_language() {
-        let mut map = HashMap::new();
-        map.insert("cpp".to_string(), "c++".to_string());
-        map.insert("cc".to_string(), "c++".to_string());
-        map.insert("cxx".to_string(), "c++".to_string());
-
-        assert_eq!(detect_language(Path::new("main.cpp"), &map), "c++");
-        assert_eq!(detect_language(Path::new("main.cc"), &map), "c++");
-        assert_eq!(detect_language(Path::new("main.cxx"), &map), "c++");
-    }
-
-    #[test]
-    fn test_case_sensitive_extensions() {
-        let mut map = HashMap::new();
-        map.insert("NU".to_string(), "nushell".to_string());
-
-        // Lowercase .nu should not match uppercase "NU" in map
-        assert_eq!(detect_language(Path::new("script.nu"), &map), "plaintext");
-    }
-
-    // ------------------------------------------------------------------
-    // uri_to_path
-    // ------------------------------------------------------------------
-
-    #[cfg(unix)]
-    #[test]
-    fn test_uri_to_path_file_scheme() {
-        let uri: Uri = "file:///home/user/main.rs".parse().unwrap();
-        let path = uri_to_path(&uri).unwrap();
-        assert_eq!(path, PathBuf::from("/home/user/main.rs"));
-    }
-
-    #[test]
-    fn test_uri_to_path_non_file_scheme_returns_none() {
-        let uri: Uri = "https://example.com/file.rs".parse().unwrap();
-        assert!(uri_to_path(&uri).is_none());
-    }
-
-    #[test]
-    fn test_uri_to_path_lsp_diagnostics_scheme_returns_none() {
-        // Custom scheme must not be decoded by uri_to_path.
-        let uri: Uri = "lsp-diagnostics:///home/user/main.rs".parse().unwrap();
-        assert!(uri_to_path(&uri).is_none());
-    }
-
-    #[test]
-    fn test_uri_to_path_with_authority_returns_none() {
-        // Authority-bearing file URIs must be rejected (UNC path defence).
-        // lsp_types::Uri may or may not accept this string; either way
-        // uri_to_path should return None.
-        let result = "file://server/share/path.rs"
-            .parse::<Uri>()
-            .ok()
-            .and_then(|u| uri_to_path(&u));
-        assert!(result.is_none());
-    }
-
     // ------------------------------------------------------------------
     // open_paths
     // ------------------------------------------------------------------
@@ -0,0 +1,446 @@
+//! Whole-workspace symbol maps for offline analysis.
+//!
+//! A symbol map holds the document symbols of every workspace file a server
+//! handles. It is exported either as JSON, in the shape `get_document_symbols`
+//! returns per file, or as a [SCIP](https://github.com/sourcegraph/scip) index
+//! that code intelligence pipelines read directly.
+//!
+//! SCIP symbols are derived from the outline alone: a symbol is named by the
+//! path of its file followed by the names of its enclosing symbols, e.g.
+//! ``mcpls . . . src/`lib.rs`/Parser#parse().``. The index holds one definition
+//! occurrence per symbol and no references.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::edits::write_file;
+use super::translator::{Range, Symbol};
+use super::uri::path_to_uri;
+use crate::error::{Error, Result};
+
+/// File, relative to the workspace root, the `export_symbols` tool writes
+/// SCIP indexes to.
+pub const SCIP_INDEX_FILE: &str = ".mcpls/index/symbols.scip";
+
+/// Document symbols of one file of a symbol map.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSymbols {
+    /// Path of the file.
+    pub file_path: String,
+    /// Language ID of the file.
+    pub language_id: String,
+    /// Symbols of the file (empty if `error` is set).
+    #[serde(default)]
+    pub symbols: Vec<Symbol>,
+    /// Why the symbols of this file could not be collected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Document symbols of the workspace files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolMap {
+    /// Directory the files are mapped under: the workspace root, or the
+    /// directory containing every root.
+    pub root: String,
+    /// Per-file symbols, sorted by path.
+    pub files: Vec<FileSymbols>,
+    /// Whether more files had a server than `max_files`.
+    pub truncated: bool,
+}
+
+impl SymbolMap {
+    /// Total number of symbols in the map, nested ones included.
+    #[must_use]
+    pub fn symbol_count(&self) -> usize {
+        fn count(symbols: &[Symbol]) -> usize {
+            symbols
+                .iter()
+                .map(|symbol| 1 + symbol.children.as_deref().map_or(0, count))
+                .sum()
+        }
+        self.files.iter().map(|file| count(&file.symbols)).sum()
+    }
+
+    /// Encode the map as a SCIP index.
+    ///
+    /// Files outside [`Self::root`] and files without symbols are left out.
+    #[must_use]
+    pub fn to_scip(&self) -> Vec<u8> {
+        let root = Path::new(&self.root);
+        let mut tool_info = Message::default();
+        tool_info.string(1, "mcpls");
+        tool_info.string(2, env!("CARGO_PKG_VERSION"));
+        let mut metadata = Message::default();
+        metadata.message(2, &tool_info);
+        metadata.string(3, path_to_uri(root).as_str());
+        metadata.uint(4, TEXT_ENCODING_UTF8);
+
+        let mut index = Message::default();
+        index.message(1, &metadata);
+        for file in &self.files {
+            let Ok(relative) = Path::new(&file.file_path).strip_prefix(root) else {
+                continue;
+            };
+            if file.symbols.is_empty() {
+                continue;
+            }
+            let mut prefix = format!("{SCHEME} ");
+            for component in relative {
+                prefix.push_str(&escape(&component.to_string_lossy()));
+                prefix.push('/');
+            }
+            let mut document = Message::default();
+            document.string(1, &relative.to_string_lossy().replace('\\', "/"));
+            document.string(4, &file.language_id);
+            document.uint(6, POSITION_ENCODING_UTF32);
+            for symbol in &file.symbols {
+                encode_symbol(&mut document, &prefix, "", symbol);
+            }
+            index.message(2, &document);
+        }
+        index.0
+    }
+}
+
+/// Result of an `export_symbols` call that wrote a SCIP index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScipExportResult {
+    /// Path of the written index.
+    pub output_path: String,
+    /// Number of files mapped.
+    pub files: usize,
+    /// Number of symbols mapped, nested ones included.
+    pub symbols: usize,
+    /// Whether more files had a server than `max_files`.
+    pub truncated: bool,
+}
+
+/// Write `map` as a SCIP index to [`SCIP_INDEX_FILE`] under its root.
+///
+/// # Errors
+///
+/// Returns [`Error::FileIo`] if the index cannot be written.
+pub async fn write_scip_index(map: &SymbolMap) -> Result<ScipExportResult> {
+    let file = Path::new(&map.root).join(SCIP_INDEX_FILE);
+    let dir = file.parent().unwrap_or(&file).to_path_buf();
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| Error::FileIo {
+            path: dir.clone(),
+            source: e,
+        })?;
+    if let Some(state_dir) = dir.parent() {
+        let gitignore = state_dir.join(".gitignore");
+        if !gitignore.exists() {
+            write_file(&gitignore, "*\n").await?;
+        }
+    }
+    tokio::fs::write(&file, map.to_scip())
+        .await
+        .map_err(|e| Error::FileIo {
+            path: file.clone(),
+            source: e,
+        })?;
+    Ok(ScipExportResult {
+        output_path: file.display().to_string(),
+        files: map.files.len(),
+        symbols: map.symbol_count(),
+        truncated: map.truncated,
+    })
+}
+
+/// Scheme and empty package of the SCIP symbols mcpls derives.
+const SCHEME: &str = "mcpls . . .";
+
+/// SCIP `TextEncoding.UTF8`.
+const TEXT_ENCODING_UTF8: u64 = 1;
+
+/// SCIP `PositionEncoding.UTF32CodeUnitOffsetFromLineStart`: mcpls columns
+/// count characters.
+const POSITION_ENCODING_UTF32: u64 = 3;
+
+/// SCIP `SymbolRole.Definition`.
+const ROLE_DEFINITION: u64 = 1;
+
+/// Add the definition occurrence and information of `symbol` and its
+/// children to `document`. `parent` is the SCIP symbol of the enclosing
+/// symbol, or the file prefix for top-level symbols; `enclosing` is empty for
+/// top-level symbols.
+fn encode_symbol(document: &mut Message, parent: &str, enclosing: &str, symbol: &Symbol) {
+    let name = format!("{parent}{}", descriptor(&symbol.kind, &symbol.name));
+
+    let mut occurrence = Message::default();
+    occurrence.packed(1, &scip_range(&symbol.selection_range));
+    occurrence.string(2, &name);
+    occurrence.uint(3, ROLE_DEFINITION);
+    occurrence.packed(7, &scip_range(&symbol.range));
+    document.message(2, &occurrence);
+
+    let mut information = Message::default();
+    information.string(1, &name);
+    if let Some(kind) = scip_kind(&symbol.kind) {
+        information.uint(5, kind);
+    }
+    information.string(6, &symbol.name);
+    information.string(8, enclosing);
+    document.message(3, &information);
+
+    for child in symbol.children.iter().flatten() {
+        encode_symbol(document, &name, &name, child);
+    }
+}
+
+/// SCIP descriptor of a symbol of LSP kind `kind` named `name`.
+fn descriptor(kind: &str, name: &str) -> String {
+    let name = escape(name);
+    match kind {
+        "File" | "Module" | "Namespace" | "Package" => format!("{name}/"),
+        "Class" | "Enum" | "Interface" | "Struct" => format!("{name}#"),
+        "Constructor" | "Function" | "Method" | "Operator" => format!("{name}()."),
+        "TypeParameter" => format!("[{name}]"),
+        _ => format!("{name}."),
+    }
+}
+
+/// `name` as a SCIP identifier: backquoted unless it consists of letters,
+/// digits, and `_+-$` only.
+fn escape(name: &str) -> String {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_+-$".contains(c))
+    {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+/// SCIP `SymbolInformation.Kind` of an LSP symbol kind.
+fn scip_kind(kind: &str) -> Option<u64> {
+    Some(match kind {
+        "Array" => 1,
+        "Boolean" => 6,
+        "Class" => 7,
+        "Constant" => 8,
+        "Constructor" => 9,
+        "Enum" => 11,
+        "EnumMember" => 12,
+        "Event" => 13,
+        "Field" => 15,
+        "File" => 16,
+        "Function" => 17,
+        "Interface" => 21,
+        "Key" => 22,
+        "Method" => 26,
+        "Module" => 29,
+        "Namespace" => 30,
+        "Null" => 31,
+        "Number" => 32,
+        "Object" => 33,
+        "Operator" => 34,
+        "Package" => 35,
+        "Property" => 41,
+        "Struct" => 49,
+        "TypeParameter" => 58,
+        "Variable" => 60,
+        _ => return None,
+    })
+}
+
+/// A 1-based mcpls range as a 0-based SCIP range: `[line, character,
+/// end_character]` on one line, `[line, character, end_line, end_character]`
+/// otherwise.
+fn scip_range(range: &Range) -> Vec<u64> {
+    let (start, end) = (&range.start, &range.end);
+    let mut encoded = vec![
+        u64::from(start.line.saturating_sub(1)),
+        u64::from(start.character.saturating_sub(1)),
+    ];
+    if end.line != start.line {
+        encoded.push(u64::from(end.line.saturating_sub(1)));
+    }
+    encoded.push(u64::from(end.character.saturating_sub(1)));
+    encoded
+}
+
+/// Directory containing every path of `roots`, or `None` when there are no
+/// roots.
+#[must_use]
+pub fn common_root(roots: &[PathBuf]) -> Option<PathBuf> {
+    let (first, rest) = roots.split_first()?;
+    let mut common = first.clone();
+    for root in rest {
+        while !root.starts_with(&common) {
+            if !common.pop() {
+                break;
+            }
+        }
+    }
+    Some(common)
+}
+
+/// Protobuf message under construction. Only the wire types SCIP needs are
+/// supported, and default values are left out as proto3 does.
+#[derive(Debug, Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            #[allow(clippy::cast_possible_truncation)]
+            self.0.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        self.0.push(value as u8);
+    }
+
+    fn tag(&mut self, field: u64, wire_type: u64) {
+        self.varint(field << 3 | wire_type);
+    }
+
+    fn bytes(&mut self, field: u64, bytes: &[u8]) {
+        self.tag(field, 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn uint(&mut self, field: u64, value: u64) {
+        if value != 0 {
+            self.tag(field, 0);
+            self.varint(value);
+        }
+    }
+
+    fn string(&mut self, field: u64, value: &str) {
+        if !value.is_empty() {
+            self.bytes(field, value.as_bytes());
+        }
+    }
+
+    fn message(&mut self, field: u64, message: &Self) {
+        self.bytes(field, &message.0);
+    }
+
+    fn packed(&mut self, field: u64, values: &[u64]) {
+        let mut packed = Self::default();
+        for value in values {
+            packed.varint(*value);
+        }
+        self.bytes(field, &packed.0);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::bridge::translator::Position2D;
+
+    fn range(line: u32, start: u32, end_line: u32, end: u32) -> Range {
+        Range {
+            start: Position2D {
+                line,
+                character: start,
+            },
+            end: Position2D {
+                line: end_line,
+                character: end,
+            },
+        }
+    }
+
+    fn symbol(name: &str, kind: &str, children: Option<Vec<Symbol>>) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            range: range(1, 1, 3, 2),
+            selection_range: range(1, 8, 1, 14),
+            deprecated: false,
+            tags: Vec::new(),
+            children,
+        }
+    }
+
+    #[test]
+    fn test_descriptors() {
+        assert_eq!(descriptor("Struct", "Parser"), "Parser#");
+        assert_eq!(descriptor("Method", "parse"), "parse().");
+        assert_eq!(descriptor("Module", "lib.rs"), "`lib.rs`/");
+        assert_eq!(descriptor("TypeParameter", "T"), "[T]");
+        assert_eq!(descriptor("Field", "a`b"), "`a``b`.");
+        assert_eq!(descriptor("Constant", ""), "``.");
+    }
+
+    #[test]
+    fn test_scip_range() {
+        assert_eq!(scip_range(&range(1, 8, 1, 14)), [0, 7, 13]);
+        assert_eq!(scip_range(&range(1, 1, 3, 2)), [0, 0, 2, 1]);
+    }
+
+    #[test]
+    fn test_common_root() {
+        assert_eq!(common_root(&[]), None);
+        assert_eq!(
+            common_root(&[PathBuf::from("/ws/a")]),
+            Some(PathBuf::from("/ws/a"))
+        );
+        assert_eq!(
+            common_root(&[PathBuf::from("/ws/a/x"), PathBuf::from("/ws/b")]),
+            Some(PathBuf::from("/ws"))
+        );
+    }
+
+    #[test]
+    fn test_message_encoding() {
+        let mut message = Message::default();
+        message.uint(1, 300);
+        message.uint(2, 0);
+        message.string(3, "");
+        message.string(4, "hi");
+        message.packed(5, &[0, 7, 13]);
+        assert_eq!(
+            message.0,
+            [0x08, 0xAC, 0x02, 0x22, 2, b'h', b'i', 0x2A, 3, 0, 7, 13]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_to_scip() {
+        let map = SymbolMap {
+            root: "/ws".to_string(),
+            files: vec![
+                FileSymbols {
+                    file_path: "/ws/src/lib.rs".to_string(),
+                    language_id: "rust".to_string(),
+                    symbols: vec![symbol(
+                        "Parser",
+                        "Struct",
+                        Some(vec![symbol("parse", "Method", None)]),
+                    )],
+                    error: None,
+                },
+                FileSymbols {
+                    file_path: "/other/main.rs".to_string(),
+                    language_id: "rust".to_string(),
+                    symbols: vec![symbol("main", "Function", None)],
+                    error: None,
+                },
+            ],
+            truncated: false,
+        };
+        assert_eq!(map.symbol_count(), 3);
+
+        let scip = map.to_scip();
+        let contains = |needle: &[u8]| scip.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"file:///ws"));
+        assert!(contains(b"src/lib.rs"));
+        assert!(contains(b"mcpls . . . src/`lib.rs`/Parser#"));
+        assert!(contains(b"mcpls . . . src/`lib.rs`/Parser#parse()."));
+        assert!(!contains(b"main.rs"));
+    }
+}
@@ -0,0 +1,354 @@
+//! Session journal of edits applied to disk, for undo.
+//!
+//! Before an edit is written, the pre-image of every target file is saved
+//! under `.mcpls/undo/<session>/<sequence>/` together with a manifest. The
+//! journal is a stack: [`UndoJournal::undo_last`] restores the most recent
+//! entry, provided the files still hold what mcpls wrote.
+//!
+//! Only the last [`MAX_UNDO_ENTRIES`] entries of a session are kept, and the
+//! journals of earlier sessions, which can no longer be undone, are deleted
+//! once stale.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::create_state_dir;
+use super::edits::{PreparedFileEdit, content_hash, read_file, write_file};
+use crate::error::{Error, Result};
+
+/// Directory, relative to the journal root, holding undo entries.
+pub const UNDO_DIR: &str = ".mcpls/undo";
+
+/// Number of entries kept per session; older edits can no longer be undone.
+pub const MAX_UNDO_ENTRIES: usize = 50;
+
+/// Time since its last entry after which another session's journal is
+/// deleted. The journal lives in memory, so other sessions' entries cannot
+/// be undone; the delay only spares mcpls processes still running on the
+/// same workspace.
+const STALE_SESSION_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Manifest stored next to the pre-images of one journal entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Manifest {
+    /// Tool that applied the edit.
+    tool: String,
+    /// Edited files, in the order their pre-images were saved.
+    files: Vec<JournaledFile>,
+}
+
+/// One file of a journal entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournaledFile {
+    /// Absolute path of the edited file.
+    path: PathBuf,
+    /// Name of the pre-image file inside the entry directory.
+    backup: String,
+    /// [`content_hash`] of the content mcpls wrote.
+    written_hash: u64,
+}
+
+/// A recorded edit.
+#[derive(Debug, Clone)]
+struct JournalEntry {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+/// Files restored by [`UndoJournal::undo_last`].
+#[derive(Debug, Clone)]
+pub struct UndoneEdit {
+    /// Tool that applied the undone edit.
+    pub tool: String,
+    /// Restored files with the content written back.
+    pub files: Vec<(PathBuf, String)>,
+}
+
+/// Stack of edits applied during this session.
+#[derive(Debug)]
+pub struct UndoJournal {
+    session: String,
+    next_sequence: u64,
+    entries: Vec<JournalEntry>,
+    /// Whether stale sessions were pruned, which happens on the first
+    /// record.
+    pruned: bool,
+}
+
+impl Default for UndoJournal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UndoJournal {
+    /// Create an empty journal with a session ID unique to this process.
+    #[must_use]
+    pub fn new() -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            session: format!("{started}-{}", std::process::id()),
+            next_sequence: 1,
+            entries: Vec::new(),
+            pruned: false,
+        }
+    }
+
+    /// Number of edits that can still be undone.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Save the pre-images of `files` under `root` before they are written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry directory, a pre-image, or the manifest
+    /// cannot be written.
+    pub async fn record(
+        &mut self,
+        root: &Path,
+        tool: &str,
+        files: &[PreparedFileEdit],
+    ) -> Result<()> {
+        let undo_root = root.join(UNDO_DIR);
+        let dir = undo_root
+            .join(&self.session)
+            .join(format!("{:04}", self.next_sequence));
+        create_state_dir(root, &dir).await?;
+
+        let mut journaled = Vec::with_capacity(files.len());
+        for (index, file) in files.iter().enumerate() {
+            let backup = format!("{index}.orig");
+            write_file(&dir.join(&backup), &file.original).await?;
+            journaled.push(JournaledFile {
+                path: file.path.clone(),
+                backup,
+                written_hash: content_hash(&file.content),
+            });
+        }
+        let manifest = Manifest {
+            tool: tool.to_string(),
+            files: journaled,
+        };
+        write_file(
+            &dir.join("manifest.json"),
+            &serde_json::to_string_pretty(&manifest)?,
+        )
+        .await?;
+
+        self.next_sequence += 1;
+        self.entries.push(JournalEntry { dir, manifest });
+        if self.entries.len() > MAX_UNDO_ENTRIES {
+            let oldest = self.entries.remove(0);
+            remove_entry_dir(&oldest.dir).await;
+        }
+        if !self.pruned {
+            self.pruned = true;
+            prune_stale_sessions(&undo_root, &self.session, STALE_SESSION_AGE).await;
+        }
+        Ok(())
+    }
+
+    /// Drop the most recent entry and its pre-images.
+    async fn discard_last(&mut self) {
+        if let Some(entry) = self.entries.pop() {
+            remove_entry_dir(&entry.dir).await;
+        }
+    }
+
+    /// Restore the files of the most recent entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NothingToUndo`] if the journal is empty, or
+    /// [`Error::EditConflict`] if a file was changed after mcpls wrote it;
+    /// in that case nothing is restored and the entry is kept.
+    pub async fn undo_last(&mut self) -> Result<UndoneEdit> {
+        let entry = self.entries.last().ok_or(Error::NothingToUndo)?;
+
+        let mut conflicts = Vec::new();
+        let mut restored = Vec::with_capacity(entry.manifest.files.len());
+        for file in &entry.manifest.files {
+            let current = read_file(&file.path).await?;
+            if content_hash(&current) != file.written_hash {
+                conflicts.push(file.path.clone());
+            }
+            let original = read_file(&entry.dir.join(&file.backup)).await?;
+            restored.push((file.path.clone(), original));
+        }
+        if !conflicts.is_empty() {
+            return Err(Error::EditConflict { paths: conflicts });
+        }
+
+        for (path, original) in &restored {
+            write_file(path, original).await?;
+        }
+        let tool = entry.manifest.tool.clone();
+        self.discard_last().await;
+
+        Ok(UndoneEdit {
+            tool,
+            files: restored,
+        })
+    }
+}
+
+/// Delete the pre-images of an entry.
+async fn remove_entry_dir(dir: &Path) {
+    if let Err(e) = tokio::fs::remove_dir_all(dir).await {
+        tracing::debug!("Failed to remove undo entry {}: {e}", dir.display());
+    }
+}
+
+/// Delete the journals in `undo_root` of sessions other than `current` that
+/// have not changed for `max_age`.
+async fn prune_stale_sessions(undo_root: &Path, current: &str, max_age: Duration) {
+    let Ok(mut sessions) = tokio::fs::read_dir(undo_root).await else {
+        return;
+    };
+    while let Ok(Some(session)) = sessions.next_entry().await {
+        if session.file_name() == current {
+            continue;
+        }
+        let stale = session
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age >= max_age);
+        if stale {
+            remove_entry_dir(&session.path()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn prepared(path: &Path, original: &str, content: &str) -> PreparedFileEdit {
+        PreparedFileEdit {
+            path: path.to_path_buf(),
+            original: original.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_undo_last_restores_pre_images() {
+        let root = TempDir::new().unwrap();
+        let file = root.path().join("lib.rs");
+        let mut journal = UndoJournal::new();
+
+        let first = [prepared(&file, "v1", "v2")];
+        journal
+            .record(root.path(), "rename_symbol", &first)
+            .await
+            .unwrap();
+        std::fs::write(&file, "v2").unwrap();
+        let second = [prepared(&file, "v2", "v3")];
+        journal
+            .record(root.path(), "rename_symbol", &second)
+            .await
+            .unwrap();
+        std::fs::write(&file, "v3").unwrap();
+        assert_eq!(journal.len(), 2);
+        assert!(root.path().join(".mcpls/.gitignore").exists());
+
+        let undone = journal.undo_last().await.unwrap();
+        assert_eq!(undone.tool, "rename_symbol");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "v2");
+
+        journal.undo_last().await.unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "v1");
+        assert_eq!(journal.len(), 0);
+        assert!(matches!(
+            journal.undo_last().await,
+            Err(Error::NothingToUndo)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_record_keeps_latest_entries() {
+        let root = TempDir::new().unwrap();
+        let file = root.path().join("lib.rs");
+        let mut journal = UndoJournal::new();
+
+        for version in 0..=MAX_UNDO_ENTRIES {
+            let edit = [prepared(&file, &format!("v{version}"), "next")];
+            journal
+                .record(root.path(), "rust_ssr", &edit)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(journal.len(), MAX_UNDO_ENTRIES);
+        let session = root.path().join(UNDO_DIR).join(&journal.session);
+        assert_eq!(
+            std::fs::read_dir(&session).unwrap().count(),
+            MAX_UNDO_ENTRIES
+        );
+        assert!(!session.join("0001").exists());
+    }
+
+    // Directories cannot be opened as files to set their times on Windows.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_record_prunes_stale_sessions() {
+        let root = TempDir::new().unwrap();
+        let undo_root = root.path().join(UNDO_DIR);
+        let stale = undo_root.join("1-100");
+        let recent = undo_root.join("2-200");
+        std::fs::create_dir_all(stale.join("0001")).unwrap();
+        std::fs::create_dir_all(recent.join("0001")).unwrap();
+        let long_ago = SystemTime::now() - STALE_SESSION_AGE * 2;
+        std::fs::File::open(&stale)
+            .unwrap()
+            .set_modified(long_ago)
+            .unwrap();
+
+        let mut journal = UndoJournal::new();
+        let edit = [prepared(&root.path().join("lib.rs"), "v1", "v2")];
+        journal
+            .record(root.path(), "rust_ssr", &edit)
+            .await
+            .unwrap();
+
+        assert!(!stale.exists());
+        assert!(recent.exists());
+        assert!(undo_root.join(&journal.session).exists());
+    }
+
+    #[tokio::test]
+    async fn test_undo_last_refuses_when_file_changed_after_write() {
+        let root = TempDir::new().unwrap();
+        let file = root.path().join("lib.rs");
+        let mut journal = UndoJournal::new();
+
+        journal
+            .record(root.path(), "rename_symbol", &[prepared(&file, "v1", "v2")])
+            .await
+            .unwrap();
+        std::fs::write(&file, "v2 plus host edits").unwrap();
+
+        assert!(matches!(
+            journal.undo_last().await,
+            Err(Error::EditConflict { .. })
+        ));
+        assert_eq!(journal.len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "v2 plus host edits"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Conversion between filesystem paths and `file://` URIs.
+//!
+//! Paths go out to servers and come back from them as URIs, so both
+//! directions must agree: a path converted to a URI and back is the same
+//! path. Outgoing URIs are percent-encoded, so paths with spaces, `#`, `%`,
+//! or non-ASCII characters stay valid, and Windows paths are written as
+//! `file:///C:/...` with an upper-case drive letter and without the `\\?\`
+//! prefix `canonicalize()` adds. Incoming URIs are accepted in any of the
+//! spellings servers use, including lower-case and percent-encoded drive
+//! letters (`file:///c%3A/...`).
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use lsp_types::Uri;
+use url::Url;
+
+/// Whether paths differing only in letter case name the same file.
+///
+/// True on Windows and macOS, whose default filesystems ignore case.
+pub const CASE_INSENSITIVE_PATHS: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Convert a file path to a URI.
+///
+/// Path components that are not valid UTF-8 are replaced lossily.
+///
+/// # Panics
+///
+/// Panics if the encoded path cannot be parsed as a URI, which percent
+/// encoding rules out.
+#[must_use]
+pub fn path_to_uri(path: &Path) -> Uri {
+    let path = path.to_string_lossy();
+    let path = if cfg!(windows) {
+        // canonicalize() on Windows adds a \\?\ extended-path prefix;
+        // file:////?\C:/ is not valid.
+        let stripped = path.strip_prefix(r"\\?\").unwrap_or(&path);
+        format!("/{}", normalize_drive_letter(&stripped.replace('\\', "/")))
+    } else {
+        path.into_owned()
+    };
+    #[allow(clippy::expect_used)]
+    format!("file://{}", percent_encode_path(&path))
+        .parse()
+        .expect("percent-encoded path is a valid URI")
+}
+
+/// Convert an LSP `file://` URI to an absolute filesystem path.
+///
+/// Returns `None` if the URI is not a valid `file://` URI, uses a non-file
+/// scheme, or contains percent-encoding that cannot map to a valid path.
+#[must_use]
+pub fn uri_to_path(uri: &Uri) -> Option<PathBuf> {
+    let url = Url::parse(&decode_drive_colon(uri.as_str())).ok()?;
+    if url.scheme() != "file" {
+        return None;
+    }
+    // Reject authority-bearing file URIs (e.g. `file://server/share`) to
+    // avoid UNC path confusion on Windows.
+    if !url.host_str().unwrap_or("").is_empty() {
+        return None;
+    }
+    let path = url.to_file_path().ok()?;
+    if cfg!(windows) {
+        Some(PathBuf::from(normalize_drive_letter(
+            &path.to_string_lossy(),
+        )))
+    } else {
+        Some(path)
+    }
+}
+
+/// Whether two URIs name the same file.
+///
+/// `file://` URIs are compared as paths, so differences in percent encoding
+/// and drive letter spelling do not matter, nor does letter case where
+/// [`CASE_INSENSITIVE_PATHS`] holds. Other URIs are compared as strings.
+#[must_use]
+pub fn same_uri(a: &Uri, b: &Uri) -> bool {
+    if a == b {
+        return true;
+    }
+    match (uri_to_path(a), uri_to_path(b)) {
+        (Some(a), Some(b)) => same_path(&a, &b),
+        _ => false,
+    }
+}
+
+/// Whether two absolute paths name the same file, ignoring letter case where
+/// [`CASE_INSENSITIVE_PATHS`] holds. Symbolic links are not resolved.
+#[must_use]
+pub fn same_path(a: &Path, b: &Path) -> bool {
+    if CASE_INSENSITIVE_PATHS {
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+/// Percent-encode every byte of `path` that may not appear literally in a
+/// URI path. `/` separates segments and `:` is kept for drive letters.
+fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:!$&'()*+,;=@".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            let _ = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// Upper-case the drive letter of a Windows path such as `c:/src`.
+fn normalize_drive_letter(path: &str) -> String {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => {
+            format!("{}{}", drive.to_ascii_uppercase(), &path[1..])
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Decode a percent-encoded colon after the drive letter of a `file://` URI
+/// (`file:///c%3A/src`), which URL parsers do not recognize as a drive.
+fn decode_drive_colon(uri: &str) -> String {
+    let Some(path) = uri.strip_prefix("file:///") else {
+        return uri.to_string();
+    };
+    let bytes = path.as_bytes();
+    if bytes.len() >= 4 && bytes[0].is_ascii_alphabetic() && path[1..4].eq_ignore_ascii_case("%3a")
+    {
+        format!("file:///{}:{}", &path[..1], &path[4..])
+    } else {
+        uri.to_string()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_to_uri_unix() {
+        let uri = path_to_uri(Path::new("/home/user/project/main.rs"));
+        assert_eq!(uri.as_str(), "file:///home/user/project/main.rs");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_path_to_uri_windows() {
+        let uri = path_to_uri(Path::new(r"\\?\c:\Users\dev\main.rs"));
+        assert_eq!(uri.as_str(), "file:///C:/Users/dev/main.rs");
+        assert_eq!(
+            uri_to_path(&uri).unwrap(),
+            PathBuf::from(r"C:\Users\dev\main.rs")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_to_uri_percent_encodes() {
+        let path = Path::new("/home/user/my project/#1/naïve 100%.rs");
+        let uri = path_to_uri(path);
+        assert_eq!(
+            uri.as_str(),
+            "file:///home/user/my%20project/%231/na%C3%AFve%20100%25.rs"
+        );
+        assert_eq!(uri_to_path(&uri).unwrap(), path);
+    }
+
+    #[test]
+    fn test_path_to_uri_with_special_chars() {
+        let uri = path_to_uri(Path::new("/home/user/project-test/main.rs"));
+        assert!(uri.as_str().starts_with("file://"));
+        assert!(uri.as_str().contains("project-test"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_uri_to_path_file_scheme() {
+        let uri: Uri = "file:///home/user/main.rs".parse().unwrap();
+        let path = uri_to_path(&uri).unwrap();
+        assert_eq!(path, PathBuf::from("/home/user/main.rs"));
+    }
+
+    #[test]
+    fn test_uri_to_path_non_file_scheme_returns_none() {
+        let uri: Uri = "https://example.com/file.rs".parse().unwrap();
+        assert!(uri_to_path(&uri).is_none());
+    }
+
+    #[test]
+    fn test_uri_to_path_lsp_diagnostics_scheme_returns_none() {
+        // Custom scheme must not be decoded by uri_to_path.
+        let uri: Uri = "lsp-diagnostics:///home/user/main.rs".parse().unwrap();
+        assert!(uri_to_path(&uri).is_none());
+    }
+
+    #[test]
+    fn test_uri_to_path_with_authority_returns_none() {
+        // Authority-bearing file URIs must be rejected (UNC path defence).
+        // lsp_types::Uri may or may not accept this string; either way
+        // uri_to_path should return None.
+        let result = "file://server/share/path.rs"
+            .parse::<Uri>()
+            .ok()
+            .and_then(|u| uri_to_path(&u));
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_drive_letter_spellings() {
+        assert_eq!(normalize_drive_letter("c:/src/lib.rs"), "C:/src/lib.rs");
+        assert_eq!(normalize_drive_letter("/src/c:/lib.rs"), "/src/c:/lib.rs");
+        assert_eq!(
+            decode_drive_colon("file:///c%3a/src/lib.rs"),
+            "file:///c:/src/lib.rs"
+        );
+        assert_eq!(
+            decode_drive_colon("file:///src/lib.rs"),
+            "file:///src/lib.rs"
+        );
+    }
+
+    #[test]
+    fn test_same_uri() {
+        let plain: Uri = "file:///ws/my%20lib.rs".parse().unwrap();
+        let encoded: Uri = "file:///ws/my%20lib%2Ers".parse().unwrap();
+        let upper: Uri = "file:///ws/My%20Lib.rs".parse().unwrap();
+        let other: Uri = "file:///ws/other.rs".parse().unwrap();
+        assert!(same_uri(&plain, &encoded));
+        assert_eq!(same_uri(&plain, &upper), CASE_INSENSITIVE_PATHS);
+        assert!(!same_uri(&plain, &other));
+
+        let untitled: Uri = "untitled:Untitled-1".parse().unwrap();
+        assert!(same_uri(&untitled, &untitled.clone()));
+        assert!(!same_uri(&untitled, &plain));
+    }
+}
@@ -8,8 +8,12 @@ mod server;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use lsp_types::FormattingProperty;
 use serde::{Deserialize, Serialize};
-pub use server::{DEFAULT_HEURISTICS_MAX_DEPTH, LspServerConfig, ServerHeuristics};
+pub use server::{
+    CATCH_ALL_LANGUAGE_ID, CompanionFiles, DEFAULT_HEURISTICS_MAX_DEPTH, DEFAULT_MAX_MESSAGE_SIZE,
+    IndexExport, LspServerConfig, ServerHeuristics, StderrMode, TraceMode,
+};
 
 use crate::error::{Error, Result};
 
@@ -33,6 +37,10 @@ pub struct ServerConfig {
     #[serde(default)]
     pub workspace: WorkspaceConfig,
 
+    /// Default formatting options, keyed by language ID.
+    #[serde(default)]
+    pub formatting: HashMap<String, FormattingDefaults>,
+
     /// LSP server configurations.
     #[serde(default)]
     pub lsp_servers: Vec<LspServerConfig>,
@@ -41,6 +49,7 @@ pub struct ServerConfig {
 /// Workspace-level configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct WorkspaceConfig {
     /// Root directories for the workspace.
     #[serde(default)]
@@ -61,6 +70,260 @@ pub struct WorkspaceConfig {
     /// Default: 10
     #[serde(default = "default_heuristics_max_depth")]
     pub heuristics_max_depth: usize,
+
+    /// Let read-only tools open files in dependency and standard library
+    /// source trees outside the workspace roots (e.g. `~/.cargo/registry`,
+    /// `node_modules`). Edit-producing tools stay restricted to the workspace.
+    /// Default: false
+    #[serde(default)]
+    pub allow_dependency_reads: bool,
+
+    /// What navigation tools do with returned locations whose files mcpls
+    /// tools would refuse (outside the workspace roots and not an allowed
+    /// dependency read). Valid values: "keep", "annotate", "filter"
+    /// Default: "keep"
+    #[serde(default)]
+    pub external_locations: ExternalLocationPolicy,
+
+    /// What edit-applying tools do with gitignored, generated, or vendored
+    /// files. Valid values: "skip", "warn", "allow"
+    /// Default: "skip"
+    #[serde(default)]
+    pub protected_edits: ProtectedEditPolicy,
+
+    /// Extra gitignore-style patterns, relative to a workspace root, marking
+    /// files as generated (e.g. `"*.pb.go"`, `"src/generated/"`).
+    #[serde(default)]
+    pub generated_patterns: Vec<String>,
+
+    /// Size above which edit-applying tools return a preview and a
+    /// confirmation token instead of writing the edit.
+    #[serde(default)]
+    pub auto_apply_limits: AutoApplyLimits,
+
+    /// Reject tool positions past the end of their line or of the file,
+    /// checked against the document's current content, instead of passing
+    /// them on to the server.
+    #[serde(default)]
+    pub strict_positions: bool,
+
+    /// Attach request metrics (LSP round-trip time, server language,
+    /// document version, cache hit) to tool results as `_meta`.
+    /// Default: false
+    #[serde(default)]
+    pub response_metadata: bool,
+
+    /// Replacement text for bridge-generated messages, keyed by message key
+    /// (e.g. `no_hover`). Unset messages keep their English default.
+    #[serde(default)]
+    pub messages: HashMap<String, String>,
+
+    /// Maximum tool calls per minute and MCP session, keyed by tool class
+    /// (see [`TOOL_CLASSES`]). Classes without a limit, or with a limit of 0,
+    /// are not limited.
+    #[serde(default)]
+    pub rate_limits: HashMap<String, u32>,
+
+    /// Seconds each language server request of a tool may take, keyed by
+    /// tool name. Tools not listed keep their default (see
+    /// [`TOOL_TIMEOUT_DEFAULTS`]); a tool call can still pass its own
+    /// `timeout_seconds`.
+    #[serde(default)]
+    pub tool_timeouts: HashMap<String, u64>,
+
+    /// Seconds the documents opened by HTTP clients stay open after the
+    /// last session closes, so a client that reconnects finds the servers
+    /// warm. In-flight requests are cancelled right away either way.
+    /// Default: 0
+    #[serde(default)]
+    pub disconnect_grace_seconds: u64,
+
+    /// Add the roots the MCP client reports (`roots/list`) to the workspace
+    /// roots and pass them on to the language servers as workspace folders.
+    /// Default: true
+    #[serde(default = "default_client_roots")]
+    pub client_roots: bool,
+}
+
+/// Classes of tools that share a `workspace.rate_limits` budget.
+pub const TOOL_CLASSES: &[&str] = &["navigation", "diagnostics", "search", "edit", "server"];
+
+/// Seconds a tool's language server requests may take when neither
+/// `workspace.tool_timeouts` nor [`TOOL_TIMEOUT_DEFAULTS`] sets it.
+pub const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 30;
+
+/// Tools whose language server requests get a different default timeout,
+/// in seconds: completions are only useful when fast, structural search and
+/// replace walks the whole workspace.
+pub const TOOL_TIMEOUT_DEFAULTS: &[(&str, u64)] = &[
+    ("get_completions", 10),
+    ("get_inline_completions", 10),
+    ("rust_ssr", 60),
+    ("search_workspace_text", 60),
+];
+
+/// Tools that send requests to language servers and so take a request
+/// timeout. The others read state mcpls keeps, work on files directly, or
+/// only send notifications.
+pub const SERVER_REQUEST_TOOLS: &[&str] = &[
+    "get_hover",
+    "get_definition",
+    "get_declaration",
+    "get_references",
+    "go_to_implementation",
+    "go_to_type_definition",
+    "get_diagnostics",
+    "get_diagnostics_batch",
+    "prepare_rename",
+    "rename_symbol",
+    "rename_symbols_batch",
+    "save_document",
+    "get_completions",
+    "get_inline_completions",
+    "get_signature_help",
+    "get_document_symbols",
+    "get_enclosing_scope",
+    "format_document",
+    "format_range",
+    "workspace_symbol_search",
+    "export_symbols",
+    "search_workspace_text",
+    "rust_ssr",
+    "get_code_actions",
+    "prepare_call_hierarchy",
+    "get_incoming_calls",
+    "get_outgoing_calls",
+    "prepare_type_hierarchy",
+    "get_supertypes",
+    "get_subtypes",
+    "get_inlay_hints",
+    "get_semantic_tokens",
+    "get_folding_ranges",
+    "get_selection_ranges",
+    "get_code_lenses",
+];
+
+/// Largest timeout, in seconds, configured or passed with a tool call.
+pub const MAX_TOOL_TIMEOUT_SECS: u64 = 600;
+
+/// Handling of edits that target gitignored, generated, or vendored files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtectedEditPolicy {
+    /// Leave protected files untouched and report them.
+    #[default]
+    Skip,
+    /// Edit protected files, but report them.
+    Warn,
+    /// Edit protected files like any other file.
+    Allow,
+}
+
+/// Default options `format_document` sends for one language.
+///
+/// Options the tool call does not set are taken from here; unset indentation
+/// options fall back to 4 spaces, and unset whitespace options are left to
+/// the server.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormattingDefaults {
+    /// Size of a tab in spaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab_size: Option<u32>,
+    /// Whether to indent with spaces instead of tabs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insert_spaces: Option<bool>,
+    /// Whether to trim trailing whitespace on a line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trim_trailing_whitespace: Option<bool>,
+    /// Whether to insert a newline character at the end of the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insert_final_newline: Option<bool>,
+    /// Further options, sent as the extra properties of LSP
+    /// `FormattingOptions`. Values are booleans, integers, or strings.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub properties: HashMap<String, FormattingProperty>,
+    /// Formatter `format_document` runs when the server does not support
+    /// formatting or fails to format a file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<FormatterCommand>,
+}
+
+/// An external formatter, such as `rustfmt`, `black`, or `prettier`.
+///
+/// The file's content is piped to its stdin, and the formatted content read
+/// from its stdout. `{file}` in the arguments is replaced with the file's
+/// path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatterCommand {
+    /// Formatter command.
+    pub command: String,
+    /// Arguments to pass to the formatter.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Time in seconds the formatter may take before it is killed.
+    #[serde(default = "default_formatter_timeout")]
+    pub timeout_seconds: u64,
+}
+
+const fn default_formatter_timeout() -> u64 {
+    30
+}
+
+/// Largest workspace edit that edit-applying tools write without
+/// confirmation. A limit of 0 disables that check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AutoApplyLimits {
+    /// Maximum number of files touched.
+    /// Default: 50
+    #[serde(default = "default_auto_apply_max_files")]
+    pub max_files: usize,
+    /// Maximum number of text edits across all files.
+    /// Default: 500
+    #[serde(default = "default_auto_apply_max_edits")]
+    pub max_edits: usize,
+}
+
+impl AutoApplyLimits {
+    /// Whether an edit touching `files` files with `edits` text edits in
+    /// total exceeds the limits.
+    #[must_use]
+    pub const fn exceeded_by(&self, files: usize, edits: usize) -> bool {
+        (self.max_files > 0 && files > self.max_files)
+            || (self.max_edits > 0 && edits > self.max_edits)
+    }
+}
+
+impl Default for AutoApplyLimits {
+    fn default() -> Self {
+        Self {
+            max_files: default_auto_apply_max_files(),
+            max_edits: default_auto_apply_max_edits(),
+        }
+    }
+}
+
+const fn default_auto_apply_max_files() -> usize {
+    50
+}
+
+const fn default_auto_apply_max_edits() -> usize {
+    500
+}
+
+/// Handling of returned locations that cannot be passed back to mcpls tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalLocationPolicy {
+    /// Return them like any other location.
+    #[default]
+    Keep,
+    /// Return them marked with `outside_workspace: true`.
+    Annotate,
+    /// Leave them out.
+    Filter,
 }
 
 impl Default for WorkspaceConfig {
@@ -70,10 +333,26 @@ impl Default for WorkspaceConfig {
             position_encodings: default_position_encodings(),
             language_extensions: default_language_extensions(),
             heuristics_max_depth: default_heuristics_max_depth(),
+            allow_dependency_reads: false,
+            external_locations: ExternalLocationPolicy::default(),
+            protected_edits: ProtectedEditPolicy::default(),
+            generated_patterns: Vec::new(),
+            auto_apply_limits: AutoApplyLimits::default(),
+            strict_positions: false,
+            response_metadata: false,
+            messages: HashMap::new(),
+            rate_limits: HashMap::new(),
+            tool_timeouts: HashMap::new(),
+            disconnect_grace_seconds: 0,
+            client_roots: true,
         }
     }
 }
 
+const fn default_client_roots() -> bool {
+    true
+}
+
 const fn default_heuristics_max_depth() -> usize {
     DEFAULT_HEURISTICS_MAX_DEPTH
 }
@@ -116,32 +395,6 @@ impl WorkspaceConfig {
     }
 }
 
-/// Extract a file extension from a glob-like file pattern.
-///
-/// Supports common patterns such as `**/*.rs` and `*.h`.
-/// Returns `None` for patterns without a simple trailing extension.
-fn extract_extension_from_pattern(pattern: &str) -> Option<String> {
-    let basename = pattern.rsplit('/').next().unwrap_or(pattern);
-    if basename.starts_with('.') {
-        return None;
-    }
-
-    let (_, ext) = basename.rsplit_once('.')?;
-    if ext.is_empty() {
-        return None;
-    }
-
-    // Keep this conservative: only accept plain extension-like tokens.
-    if ext
-        .chars()
-        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
-    {
-        Some(ext.to_string())
-    } else {
-        None
-    }
-}
-
 fn default_position_encodings() -> Vec<String> {
     vec!["utf-8".to_string(), "utf-16".to_string()]
 }
@@ -284,23 +537,17 @@ fn default_language_extensions() -> Vec<LanguageExtensionMapping> {
 }
 
 impl ServerConfig {
-    /// Build the effective extension map used for language detection.
-    ///
-    /// Starts with workspace mappings and overlays mappings inferred from
-    /// configured LSP server `file_patterns`.
+    /// The configuration as JSON, with the values of server environment
+    /// variables, which often hold tokens, replaced by `<redacted>`.
     #[must_use]
-    pub fn build_effective_extension_map(&self) -> HashMap<String, String> {
-        let mut map = self.workspace.build_extension_map();
-
-        for server in &self.lsp_servers {
-            for pattern in &server.file_patterns {
-                if let Some(ext) = extract_extension_from_pattern(pattern) {
-                    map.insert(ext, server.language_id.clone());
-                }
+    pub fn sanitized(&self) -> serde_json::Value {
+        let mut config = self.clone();
+        for server in &mut config.lsp_servers {
+            for value in server.env.values_mut() {
+                "<redacted>".clone_into(value);
             }
         }
-
-        map
+        serde_json::to_value(config).unwrap_or_default()
     }
 
     /// Load configuration from the default path.
@@ -403,6 +650,27 @@ impl ServerConfig {
                 )));
             }
         }
+        if let Some(class) = self
+            .workspace
+            .rate_limits
+            .keys()
+            .find(|class| !TOOL_CLASSES.contains(&class.as_str()))
+        {
+            return Err(Error::InvalidConfig(format!(
+                "unknown tool class '{class}' in workspace.rate_limits; valid classes: {}",
+                TOOL_CLASSES.join(", ")
+            )));
+        }
+        if let Some((tool, seconds)) = self
+            .workspace
+            .tool_timeouts
+            .iter()
+            .find(|(_, seconds)| !(1..=MAX_TOOL_TIMEOUT_SECS).contains(*seconds))
+        {
+            return Err(Error::InvalidConfig(format!(
+                "workspace.tool_timeouts.{tool} is {seconds}; timeouts must be between 1 and {MAX_TOOL_TIMEOUT_SECS} seconds"
+            )));
+        }
         Ok(())
     }
 }
@@ -411,6 +679,7 @@ impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             workspace: WorkspaceConfig::default(),
+            formatting: HashMap::new(),
             lsp_servers: vec![
                 LspServerConfig::rust_analyzer(),
                 LspServerConfig::pyright(),
@@ -691,6 +960,18 @@ mod tests {
                 },
             ],
             heuristics_max_depth: DEFAULT_HEURISTICS_MAX_DEPTH,
+            allow_dependency_reads: false,
+            external_locations: ExternalLocationPolicy::default(),
+            protected_edits: ProtectedEditPolicy::default(),
+            generated_patterns: Vec::new(),
+            auto_apply_limits: AutoApplyLimits::default(),
+            strict_positions: false,
+            response_metadata: false,
+            messages: HashMap::new(),
+            rate_limits: HashMap::new(),
+            tool_timeouts: HashMap::new(),
+            disconnect_grace_seconds: 0,
+            client_roots: true,
         };
 
         let map = workspace.build_extension_map();
@@ -702,68 +983,21 @@ mod tests {
     }
 
     #[test]
-    fn test_extract_extension_from_pattern_empty_string() {
-        assert_eq!(extract_extension_from_pattern(""), None);
-    }
-
-    #[test]
-    fn test_extract_extension_from_pattern_without_dot() {
-        assert_eq!(extract_extension_from_pattern("**/*"), None);
-    }
-
-    #[test]
-    fn test_extract_extension_from_pattern_dotfile() {
-        assert_eq!(extract_extension_from_pattern(".gitignore"), None);
-    }
-
-    #[test]
-    fn test_extract_extension_from_pattern_multi_dot_extension() {
-        assert_eq!(
-            extract_extension_from_pattern("foo.tar.gz"),
-            Some("gz".to_string())
-        );
-    }
-
-    #[test]
-    fn test_build_effective_extension_map_overrides_with_file_patterns() {
-        let config = ServerConfig {
-            workspace: WorkspaceConfig::default(),
-            lsp_servers: vec![LspServerConfig {
-                language_id: "cpp".to_string(),
-                command: "clangd".to_string(),
-                args: vec![],
-                env: HashMap::new(),
-                file_patterns: vec!["**/*.c".to_string(), "**/*.h".to_string()],
-                initialization_options: None,
-                timeout_seconds: 30,
-                heuristics: None,
-            }],
-        };
-
-        let map = config.build_effective_extension_map();
-        assert_eq!(map.get("c"), Some(&"cpp".to_string()));
-        assert_eq!(map.get("h"), Some(&"cpp".to_string()));
-    }
-
-    #[test]
-    fn test_build_effective_extension_map_ignores_complex_patterns_without_extension() {
-        let config = ServerConfig {
-            workspace: WorkspaceConfig::default(),
-            lsp_servers: vec![LspServerConfig {
-                language_id: "cpp".to_string(),
-                command: "clangd".to_string(),
-                args: vec![],
-                env: HashMap::new(),
-                file_patterns: vec!["**/*".to_string(), "**/*.{h,hpp}".to_string()],
-                initialization_options: None,
-                timeout_seconds: 30,
-                heuristics: None,
-            }],
-        };
-
-        let map = config.build_effective_extension_map();
-        // Default C/C++ mappings remain unchanged when patterns cannot be parsed.
-        assert_eq!(map.get("h"), Some(&"c".to_string()));
+    fn test_sanitized_redacts_server_env() {
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [[lsp_servers]]
+            language_id = "rust"
+            command = "rust-analyzer"
+            env = { RA_TOKEN = "secret" }
+            "#,
+        )
+        .unwrap();
+        let sanitized = config.sanitized();
+        let server = &sanitized["lsp_servers"][0];
+        assert_eq!(server["command"], "rust-analyzer");
+        assert_eq!(server["env"]["RA_TOKEN"], "<redacted>");
+        assert!(!sanitized.to_string().contains("secret"));
     }
 
     #[test]
@@ -782,6 +1016,18 @@ mod tests {
                 },
             ],
             heuristics_max_depth: DEFAULT_HEURISTICS_MAX_DEPTH,
+            allow_dependency_reads: false,
+            external_locations: ExternalLocationPolicy::default(),
+            protected_edits: ProtectedEditPolicy::default(),
+            generated_patterns: Vec::new(),
+            auto_apply_limits: AutoApplyLimits::default(),
+            strict_positions: false,
+            response_metadata: false,
+            messages: HashMap::new(),
+            rate_limits: HashMap::new(),
+            tool_timeouts: HashMap::new(),
+            disconnect_grace_seconds: 0,
+            client_roots: true,
         };
 
         assert_eq!(
@@ -894,6 +1140,163 @@ mod tests {
         assert_eq!(config.heuristics_max_depth, 10);
     }
 
+    #[test]
+    fn test_protected_edits_from_config() {
+        let toml = r#"
+            [workspace]
+            protected_edits = "warn"
+            generated_patterns = ["*.pb.go", "src/generated/"]
+        "#;
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.workspace.protected_edits, ProtectedEditPolicy::Warn);
+        assert_eq!(config.workspace.generated_patterns.len(), 2);
+        assert_eq!(
+            WorkspaceConfig::default().protected_edits,
+            ProtectedEditPolicy::Skip
+        );
+    }
+
+    #[test]
+    fn test_auto_apply_limits_from_config() {
+        let toml = r"
+            [workspace.auto_apply_limits]
+            max_files = 10
+            max_edits = 0
+        ";
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        let limits = config.workspace.auto_apply_limits;
+        assert_eq!(limits.max_files, 10);
+        assert!(limits.exceeded_by(11, 1));
+        assert!(!limits.exceeded_by(10, 100_000));
+
+        let defaults = AutoApplyLimits::default();
+        assert!(!defaults.exceeded_by(50, 500));
+        assert!(defaults.exceeded_by(1, 501));
+    }
+
+    #[test]
+    fn test_rate_limits_from_config() {
+        let toml = r"
+            [workspace.rate_limits]
+            navigation = 120
+            diagnostics = 30
+        ";
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.workspace.rate_limits["navigation"], 120);
+        assert!(config.validate().is_ok());
+
+        let toml = r"
+            [workspace.rate_limits]
+            references = 10
+        ";
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("unknown tool class 'references'"));
+    }
+
+    #[test]
+    fn test_tool_timeouts_from_config() {
+        let toml = r"
+            [workspace.tool_timeouts]
+            get_references = 120
+        ";
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.workspace.tool_timeouts["get_references"], 120);
+        assert!(config.validate().is_ok());
+
+        let toml = r"
+            [workspace.tool_timeouts]
+            get_hover = 0
+        ";
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("workspace.tool_timeouts.get_hover is 0")
+        );
+    }
+
+    #[test]
+    fn test_formatting_from_config() {
+        let toml = r#"
+            [formatting.go]
+            insert_spaces = false
+            trim_trailing_whitespace = true
+
+            [formatting.go.properties]
+            gofumpt = true
+            line_length = 100
+            style = "google"
+        "#;
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        let go = &config.formatting["go"];
+        assert_eq!(go.insert_spaces, Some(false));
+        assert_eq!(go.tab_size, None);
+        assert_eq!(go.properties["gofumpt"], FormattingProperty::Bool(true));
+        assert_eq!(
+            go.properties["line_length"],
+            FormattingProperty::Number(100)
+        );
+        assert_eq!(
+            go.properties["style"],
+            FormattingProperty::String("google".to_string())
+        );
+
+        let unknown = "[formatting.go]\nindent = 2\n";
+        assert!(toml::from_str::<ServerConfig>(unknown).is_err());
+    }
+
+    #[test]
+    fn test_formatting_fallback_from_config() {
+        let toml = r#"
+            [formatting.python.fallback]
+            command = "black"
+            args = ["-q", "-"]
+        "#;
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        let fallback = config.formatting["python"].fallback.as_ref().unwrap();
+        assert_eq!(fallback.command, "black");
+        assert_eq!(fallback.args, ["-q", "-"]);
+        assert_eq!(fallback.timeout_seconds, 30);
+        assert_eq!(config.formatting["python"].tab_size, None);
+    }
+
+    #[test]
+    fn test_index_export_from_config() {
+        let toml = r#"
+            [[lsp_servers]]
+            language_id = "rust"
+            command = "rust-analyzer"
+
+            [lsp_servers.index_export]
+            command = "rust-analyzer"
+            args = ["lsif", "."]
+        "#;
+        let config: ServerConfig = toml::from_str(toml).unwrap();
+        let export = config.lsp_servers[0].index_export.as_ref().unwrap();
+        assert_eq!(export.command, "rust-analyzer");
+        assert_eq!(export.args, ["lsif", "."]);
+        assert_eq!(export.timeout_seconds, 300);
+        assert!(LspServerConfig::rust_analyzer().index_export.is_none());
+    }
+
+    #[test]
+    fn test_allow_dependency_reads_from_config() {
+        let tmp_dir = TempDir::new().unwrap();
+        let config_path = tmp_dir.path().join("deps.toml");
+
+        let toml_content = r"
+            [workspace]
+            allow_dependency_reads = true
+        ";
+
+        fs::write(&config_path, toml_content).unwrap();
+
+        let config = ServerConfig::load_from(&config_path).unwrap();
+        assert!(config.workspace.allow_dependency_reads);
+        assert!(!WorkspaceConfig::default().allow_dependency_reads);
+    }
+
     #[test]
     fn test_heuristics_max_depth_from_config() {
         let tmp_dir = TempDir::new().unwrap();
@@ -928,4 +1331,23 @@ mod tests {
             DEFAULT_HEURISTICS_MAX_DEPTH
         );
     }
+
+    #[test]
+    fn test_client_roots_default_and_opt_out() {
+        assert!(WorkspaceConfig::default().client_roots);
+
+        let tmp_dir = TempDir::new().unwrap();
+        let config_path = tmp_dir.path().join("no_client_roots.toml");
+        fs::write(
+            &config_path,
+            r"
+            [workspace]
+            client_roots = false
+        ",
+        )
+        .unwrap();
+
+        let config = ServerConfig::load_from(&config_path).unwrap();
+        assert!(!config.workspace.client_roots);
+    }
 }
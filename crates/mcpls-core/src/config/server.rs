@@ -1,7 +1,7 @@
 //! LSP server configuration types.
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use ignore::WalkBuilder;
 use serde::{Deserialize, Serialize};
@@ -9,6 +9,13 @@ use serde::{Deserialize, Serialize};
 /// Default max depth for recursive marker search.
 pub const DEFAULT_HEURISTICS_MAX_DEPTH: usize = 10;
 
+/// Default maximum size of a single message from a server (10 MB).
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Language ID of the catch-all server, which handles files of every
+/// language that has no server of its own.
+pub const CATCH_ALL_LANGUAGE_ID: &str = "*";
+
 /// Directories excluded from recursive marker search.
 /// These are well-known directories that should never contain project markers.
 const EXCLUDED_DIRECTORIES: &[&str] = &[
@@ -142,11 +149,91 @@ impl ServerHeuristics {
     }
 }
 
+/// How an LSP server's stderr output is handled.
+///
+/// In TOML this is written as `stderr = "discard"`, `"log"`, `"inherit"`, or
+/// `stderr = { file = "/path/to/server.log" }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StderrMode {
+    /// Drop stderr output.
+    #[default]
+    Discard,
+    /// Record each stderr line in the server log buffer (`get_server_logs`).
+    Log,
+    /// Append stderr output to the given file.
+    File(PathBuf),
+    /// Forward stderr output to mcpls's own stderr.
+    Inherit,
+}
+
+/// How much of its protocol traffic a server reports through `$/logTrace`.
+///
+/// In TOML this is written as `trace = "off"`, `"messages"`, or `"verbose"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceMode {
+    /// No tracing.
+    #[default]
+    Off,
+    /// Trace messages only.
+    Messages,
+    /// Trace messages with their details.
+    Verbose,
+}
+
+/// Which sibling files are opened alongside a document the server analyzes
+/// better with its neighbors, such as a C/C++ header and its source file.
+///
+/// In TOML this is written as `companion_files = "none"`, `"same_stem"`, or
+/// `"directory"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompanionFiles {
+    /// Open only the documents tools ask for.
+    #[default]
+    None,
+    /// Also open siblings with the same file stem, e.g. `widget.h` for
+    /// `widget.cpp`.
+    SameStem,
+    /// Also open siblings of the same language, up to a small limit.
+    Directory,
+}
+
+/// External indexer run when mcpls shuts down, whose LSIF dump answers
+/// definition and reference queries while the server initializes on the next
+/// start.
+///
+/// The command runs in the first workspace root and must write an LSIF dump
+/// (JSON lines) to stdout, e.g. `rust-analyzer lsif .`. The dump is stored
+/// in `.mcpls/index/<language_id>.lsif`; a failed or timed-out export keeps
+/// the previous dump.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IndexExport {
+    /// Indexer command.
+    pub command: String,
+
+    /// Arguments to pass to the indexer.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Time in seconds the indexer may take before it is killed.
+    #[serde(default = "default_index_timeout")]
+    pub timeout_seconds: u64,
+}
+
+const fn default_index_timeout() -> u64 {
+    300
+}
+
 /// Configuration for a single LSP server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct LspServerConfig {
-    /// Language identifier (e.g., "rust", "python", "typescript").
+    /// Language identifier (e.g., "rust", "python", "typescript"), or
+    /// [`CATCH_ALL_LANGUAGE_ID`] for a server handling the files of every
+    /// language without a server of its own.
     pub language_id: String,
 
     /// Command to start the LSP server.
@@ -160,6 +247,17 @@ pub struct LspServerConfig {
     #[serde(default)]
     pub env: HashMap<String, String>,
 
+    /// Variables of the mcpls environment the server inherits, as names or
+    /// `PREFIX*` patterns. When set, every other variable is withheld; when
+    /// unset, the whole environment is inherited.
+    #[serde(default)]
+    pub env_allow: Option<Vec<String>>,
+
+    /// Variables of the mcpls environment withheld from the server, as names
+    /// or `PREFIX*` patterns. Applied after `env_allow`.
+    #[serde(default)]
+    pub env_deny: Vec<String>,
+
     /// File patterns this server handles (glob patterns).
     #[serde(default)]
     pub file_patterns: Vec<String>,
@@ -172,6 +270,71 @@ pub struct LspServerConfig {
     #[serde(default = "default_timeout")]
     pub timeout_seconds: u64,
 
+    /// Timeout in seconds for spawning and initializing the server.
+    ///
+    /// Falls back to `timeout_seconds` when not specified. Servers that
+    /// load large projects on startup may need a much higher value.
+    #[serde(default)]
+    pub init_timeout_seconds: Option<u64>,
+
+    /// Seconds after the server starts during which request timeouts and
+    /// `ContentModified` errors are reported as the server still indexing,
+    /// with a time to retry, instead of as failures. Past that time, the
+    /// same holds while the server reports work in progress. Off when not
+    /// specified.
+    #[serde(default)]
+    pub startup_grace_seconds: Option<u64>,
+
+    /// Maximum size in bytes of a single message from the server.
+    ///
+    /// Larger messages are skipped. Servers that send very large payloads,
+    /// such as clangd semantic tokens or diagnostics for huge files, may
+    /// need a higher value.
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+
+    /// How the server's stderr output is handled.
+    ///
+    /// The first KB is always kept for startup failure diagnostics.
+    #[serde(default)]
+    pub stderr: StderrMode,
+
+    /// Protocol tracing requested from the server with `$/setTrace`. Traces
+    /// the server sends back are recorded in the server log buffer
+    /// (`get_server_logs`).
+    #[serde(default)]
+    pub trace: TraceMode,
+
+    /// Advertise the proposed `textDocument/inlineCompletion` capability
+    /// (LSP 3.18) to this server, enabling `get_inline_completions` for it.
+    ///
+    /// Experimental: the request may still change before it is finalized,
+    /// and few servers implement it.
+    #[serde(default)]
+    pub inline_completions: bool,
+
+    /// Sibling files to open when a document is first opened, so the server
+    /// has their context on the first query.
+    #[serde(default)]
+    pub companion_files: CompanionFiles,
+
+    /// Path prefixes outside the workspace roots whose files of this
+    /// server's language read-only tools may open, such as generated code in
+    /// a build directory. Relative paths are resolved against each workspace
+    /// root.
+    #[serde(default)]
+    pub visible_paths: Vec<PathBuf>,
+
+    /// Like `visible_paths`, but edit-producing tools may write there too.
+    #[serde(default)]
+    pub writable_paths: Vec<PathBuf>,
+
+    /// Indexer exporting an LSIF dump on shutdown, used to answer
+    /// `get_definition` and `get_references` from the last run's index while
+    /// the server initializes.
+    #[serde(default)]
+    pub index_export: Option<IndexExport>,
+
     /// Heuristics for determining if this server should be spawned.
     /// If not specified, the server will always attempt to spawn.
     #[serde(default)]
@@ -182,6 +345,10 @@ const fn default_timeout() -> u64 {
     30
 }
 
+const fn default_max_message_size() -> usize {
+    DEFAULT_MAX_MESSAGE_SIZE
+}
+
 impl LspServerConfig {
     /// Check if this server should be spawned for the given workspace.
     ///
@@ -198,6 +365,43 @@ impl LspServerConfig {
             .is_none_or(|h| h.is_applicable_recursive(workspace_root, max_depth))
     }
 
+    /// Whether the server gets a filtered copy of the mcpls environment
+    /// instead of inheriting all of it.
+    #[must_use]
+    pub const fn isolates_env(&self) -> bool {
+        self.env_allow.is_some() || !self.env_deny.is_empty()
+    }
+
+    /// Whether the mcpls environment variable `name` is passed on to the
+    /// server. Variables set in `env` are passed regardless.
+    #[must_use]
+    pub fn inherits_env(&self, name: &str) -> bool {
+        let matches = |pattern: &String| {
+            pattern
+                .strip_suffix('*')
+                .map_or(pattern == name, |prefix| name.starts_with(prefix))
+        };
+        self.env_allow
+            .as_ref()
+            .is_none_or(|allow| allow.iter().any(matches))
+            && !self.env_deny.iter().any(matches)
+    }
+
+    /// Effective initialization timeout in seconds.
+    ///
+    /// Returns `init_timeout_seconds` if set, otherwise `timeout_seconds`.
+    #[must_use]
+    pub fn init_timeout(&self) -> u64 {
+        self.init_timeout_seconds.unwrap_or(self.timeout_seconds)
+    }
+
+    /// Startup grace period, if `startup_grace_seconds` is set.
+    #[must_use]
+    pub fn startup_grace(&self) -> Option<std::time::Duration> {
+        self.startup_grace_seconds
+            .map(std::time::Duration::from_secs)
+    }
+
     /// Create a default configuration for rust-analyzer.
     #[must_use]
     pub fn rust_analyzer() -> Self {
@@ -206,9 +410,21 @@ impl LspServerConfig {
             command: "rust-analyzer".to_string(),
             args: vec![],
             env: HashMap::new(),
+            env_allow: None,
+            env_deny: Vec::new(),
             file_patterns: vec!["**/*.rs".to_string()],
             initialization_options: None,
             timeout_seconds: default_timeout(),
+            init_timeout_seconds: None,
+            startup_grace_seconds: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stderr: StderrMode::Discard,
+            trace: TraceMode::Off,
+            inline_completions: false,
+            companion_files: CompanionFiles::None,
+            visible_paths: Vec::new(),
+            writable_paths: Vec::new(),
+            index_export: None,
             heuristics: Some(ServerHeuristics::with_markers([
                 "Cargo.toml",
                 "rust-toolchain.toml",
@@ -224,9 +440,21 @@ impl LspServerConfig {
             command: "pyright-langserver".to_string(),
             args: vec!["--stdio".to_string()],
             env: HashMap::new(),
+            env_allow: None,
+            env_deny: Vec::new(),
             file_patterns: vec!["**/*.py".to_string()],
             initialization_options: None,
             timeout_seconds: default_timeout(),
+            init_timeout_seconds: None,
+            startup_grace_seconds: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stderr: StderrMode::Discard,
+            trace: TraceMode::Off,
+            inline_completions: false,
+            companion_files: CompanionFiles::None,
+            visible_paths: Vec::new(),
+            writable_paths: Vec::new(),
+            index_export: None,
             heuristics: Some(ServerHeuristics::with_markers([
                 "pyproject.toml",
                 "setup.py",
@@ -244,9 +472,21 @@ impl LspServerConfig {
             command: "typescript-language-server".to_string(),
             args: vec!["--stdio".to_string()],
             env: HashMap::new(),
+            env_allow: None,
+            env_deny: Vec::new(),
             file_patterns: vec!["**/*.ts".to_string(), "**/*.tsx".to_string()],
             initialization_options: None,
             timeout_seconds: default_timeout(),
+            init_timeout_seconds: None,
+            startup_grace_seconds: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stderr: StderrMode::Discard,
+            trace: TraceMode::Off,
+            inline_completions: false,
+            companion_files: CompanionFiles::None,
+            visible_paths: Vec::new(),
+            writable_paths: Vec::new(),
+            index_export: None,
             heuristics: Some(ServerHeuristics::with_markers([
                 "package.json",
                 "tsconfig.json",
@@ -263,9 +503,21 @@ impl LspServerConfig {
             command: "gopls".to_string(),
             args: vec!["serve".to_string()],
             env: HashMap::new(),
+            env_allow: None,
+            env_deny: Vec::new(),
             file_patterns: vec!["**/*.go".to_string()],
             initialization_options: None,
             timeout_seconds: default_timeout(),
+            init_timeout_seconds: None,
+            startup_grace_seconds: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stderr: StderrMode::Discard,
+            trace: TraceMode::Off,
+            inline_completions: false,
+            companion_files: CompanionFiles::None,
+            visible_paths: Vec::new(),
+            writable_paths: Vec::new(),
+            index_export: None,
             heuristics: Some(ServerHeuristics::with_markers(["go.mod", "go.sum"])),
         }
     }
@@ -278,6 +530,8 @@ impl LspServerConfig {
             command: "clangd".to_string(),
             args: vec![],
             env: HashMap::new(),
+            env_allow: None,
+            env_deny: Vec::new(),
             file_patterns: vec![
                 "**/*.c".to_string(),
                 "**/*.cpp".to_string(),
@@ -286,6 +540,16 @@ impl LspServerConfig {
             ],
             initialization_options: None,
             timeout_seconds: default_timeout(),
+            init_timeout_seconds: None,
+            startup_grace_seconds: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stderr: StderrMode::Discard,
+            trace: TraceMode::Off,
+            inline_completions: false,
+            companion_files: CompanionFiles::None,
+            visible_paths: Vec::new(),
+            writable_paths: Vec::new(),
+            index_export: None,
             heuristics: Some(ServerHeuristics::with_markers([
                 "CMakeLists.txt",
                 "compile_commands.json",
@@ -303,9 +567,21 @@ impl LspServerConfig {
             command: "zls".to_string(),
             args: vec![],
             env: HashMap::new(),
+            env_allow: None,
+            env_deny: Vec::new(),
             file_patterns: vec!["**/*.zig".to_string()],
             initialization_options: None,
             timeout_seconds: default_timeout(),
+            init_timeout_seconds: None,
+            startup_grace_seconds: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stderr: StderrMode::Discard,
+            trace: TraceMode::Off,
+            inline_completions: false,
+            companion_files: CompanionFiles::None,
+            visible_paths: Vec::new(),
+            writable_paths: Vec::new(),
+            index_export: None,
             heuristics: Some(ServerHeuristics::with_markers([
                 "build.zig",
                 "build.zig.zon",
@@ -365,6 +641,93 @@ mod tests {
         assert_eq!(default_timeout(), 30);
     }
 
+    #[test]
+    fn test_init_timeout_falls_back_to_request_timeout() {
+        let mut config = LspServerConfig::rust_analyzer();
+        assert!(config.init_timeout_seconds.is_none());
+        assert_eq!(config.init_timeout(), 30);
+
+        config.init_timeout_seconds = Some(300);
+        assert_eq!(config.init_timeout(), 300);
+    }
+
+    #[test]
+    fn test_init_timeout_from_toml() {
+        let config: LspServerConfig = toml::from_str(
+            r#"
+            language_id = "csharp"
+            command = "omnisharp"
+            init_timeout_seconds = 600
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.timeout_seconds, 30);
+        assert_eq!(config.init_timeout(), 600);
+    }
+
+    #[test]
+    fn test_startup_grace_from_toml() {
+        let config: LspServerConfig = toml::from_str(
+            r#"
+            language_id = "java"
+            command = "jdtls"
+            startup_grace_seconds = 120
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.startup_grace(),
+            Some(std::time::Duration::from_secs(120))
+        );
+        assert!(LspServerConfig::rust_analyzer().startup_grace().is_none());
+    }
+
+    #[test]
+    fn test_stderr_mode_defaults_to_discard() {
+        let config: LspServerConfig = toml::from_str(
+            r#"
+            language_id = "rust"
+            command = "rust-analyzer"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.stderr, StderrMode::Discard);
+        assert_eq!(LspServerConfig::rust_analyzer().stderr, StderrMode::Discard);
+    }
+
+    #[test]
+    fn test_stderr_mode_from_toml() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            stderr: StderrMode,
+        }
+
+        let parse = |value: &str| toml::from_str::<Wrapper>(&format!("stderr = {value}"));
+
+        assert_eq!(parse(r#""log""#).unwrap().stderr, StderrMode::Log);
+        assert_eq!(parse(r#""inherit""#).unwrap().stderr, StderrMode::Inherit);
+        assert_eq!(
+            parse(r#"{ file = "/tmp/tsserver.log" }"#).unwrap().stderr,
+            StderrMode::File(PathBuf::from("/tmp/tsserver.log"))
+        );
+        assert!(parse(r#""verbose""#).is_err());
+    }
+
+    #[test]
+    fn test_trace_mode_from_toml() {
+        let config: LspServerConfig = toml::from_str(
+            r#"
+            language_id = "typescript"
+            command = "typescript-language-server"
+            trace = "verbose"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.trace, TraceMode::Verbose);
+        assert_eq!(LspServerConfig::rust_analyzer().trace, TraceMode::Off);
+    }
+
     #[test]
     fn test_custom_config() {
         let mut env = HashMap::new();
@@ -375,9 +738,21 @@ mod tests {
             command: "custom-lsp".to_string(),
             args: vec!["--flag".to_string()],
             env: env.clone(),
+            env_allow: None,
+            env_deny: Vec::new(),
             file_patterns: vec!["**/*.custom".to_string()],
             initialization_options: Some(serde_json::json!({"key": "value"})),
             timeout_seconds: 60,
+            init_timeout_seconds: None,
+            startup_grace_seconds: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stderr: StderrMode::Discard,
+            trace: TraceMode::Off,
+            inline_completions: false,
+            companion_files: CompanionFiles::None,
+            visible_paths: Vec::new(),
+            writable_paths: Vec::new(),
+            index_export: None,
             heuristics: None,
         };
 
@@ -427,6 +802,38 @@ mod tests {
         assert!(config.file_patterns.contains(&"**/*.tsx".to_string()));
     }
 
+    #[test]
+    fn test_env_isolation() {
+        let mut config = LspServerConfig::rust_analyzer();
+        assert!(!config.isolates_env());
+        assert!(config.inherits_env("AWS_SECRET_ACCESS_KEY"));
+
+        config.env_deny = vec!["AWS_*".to_string(), "GITHUB_TOKEN".to_string()];
+        assert!(config.isolates_env());
+        assert!(!config.inherits_env("AWS_SECRET_ACCESS_KEY"));
+        assert!(!config.inherits_env("GITHUB_TOKEN"));
+        assert!(config.inherits_env("GITHUB_TOKEN_FILE"));
+        assert!(config.inherits_env("PATH"));
+
+        config.env_allow = Some(vec!["PATH".to_string(), "LC_*".to_string()]);
+        config.env_deny = vec!["LC_SECRET".to_string()];
+        assert!(config.inherits_env("PATH"));
+        assert!(config.inherits_env("LC_ALL"));
+        assert!(!config.inherits_env("LC_SECRET"));
+        assert!(!config.inherits_env("HOME"));
+
+        let parsed: LspServerConfig = toml::from_str(
+            r#"
+            language_id = "rust"
+            command = "rust-analyzer"
+            env_allow = ["PATH", "HOME", "CARGO_*"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(parsed.env_allow.unwrap().len(), 3);
+        assert!(parsed.env_deny.is_empty());
+    }
+
     #[test]
     fn test_initialization_options_none_by_default() {
         let configs = vec![
@@ -481,9 +888,21 @@ mod tests {
             command: "test-lsp".to_string(),
             args: vec![],
             env: HashMap::new(),
+            env_allow: None,
+            env_deny: Vec::new(),
             file_patterns: vec![],
             initialization_options: None,
             timeout_seconds: 30,
+            init_timeout_seconds: None,
+            startup_grace_seconds: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            stderr: StderrMode::Discard,
+            trace: TraceMode::Off,
+            inline_completions: false,
+            companion_files: CompanionFiles::None,
+            visible_paths: Vec::new(),
+            writable_paths: Vec::new(),
+            index_export: None,
             heuristics: None,
         };
 
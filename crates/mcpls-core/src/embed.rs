@@ -0,0 +1,155 @@
+//! Embedding the bridge in other programs.
+//!
+//! [`Bridge`] runs the language servers of a workspace without an MCP host,
+//! for Rust programs that want LSP-backed analysis without speaking MCP. The
+//! translator's `handle_*` methods answer the same questions as the MCP
+//! tools, server notifications are cached as they are by `mcpls serve`, and
+//! the translator's [`EventBus`] announces them along with server, document,
+//! and edit events. `mcpls watch` and `mcpls export-symbols` are built on it.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, MutexGuard, broadcast, mpsc};
+use tokio::task::JoinSet;
+use tracing::info;
+
+use crate::bridge::{BridgeEvent, EventBus, Translator};
+use crate::config::ServerConfig;
+use crate::error::Result;
+use crate::lsp::LspNotification;
+
+/// Language servers of a workspace, running without an MCP host.
+///
+/// ```rust,ignore
+/// let bridge = Bridge::start(&ServerConfig::load()?, Some(root)).await?;
+/// let mut events = bridge.subscribe();
+/// let hover = bridge.translator().await.handle_hover(path, 10, 5).await?;
+/// bridge.shutdown().await;
+/// ```
+#[derive(Debug)]
+pub struct Bridge {
+    translator: Arc<Mutex<Translator>>,
+    events: EventBus,
+    pumps: JoinSet<()>,
+}
+
+impl Bridge {
+    /// Start the servers of `config` for `root`, or the configured workspace
+    /// roots when it is `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `root` cannot be resolved, the configuration is
+    /// invalid, no server applies to the workspace, or every server fails to
+    /// start.
+    pub async fn start(config: &ServerConfig, root: Option<PathBuf>) -> Result<Self> {
+        let (translator, receivers) = crate::start_headless(config, root).await?;
+        Ok(Self::from_parts(translator, receivers))
+    }
+
+    /// A bridge serving `translator`, draining the notifications of its
+    /// servers from `receivers`.
+    fn from_parts(translator: Translator, receivers: crate::NotificationReceivers) -> Self {
+        let events = translator.events().clone();
+        let translator = Arc::new(Mutex::new(translator));
+        let mut pumps = JoinSet::new();
+        for (language_id, rx) in receivers {
+            pumps.spawn(pump(language_id, rx, Arc::clone(&translator)));
+        }
+        Self {
+            translator,
+            events,
+            pumps,
+        }
+    }
+
+    /// Lock the translator to call its handlers.
+    ///
+    /// Notifications are cached under the same lock, so events wait while
+    /// the guard is held.
+    pub async fn translator(&self) -> MutexGuard<'_, Translator> {
+        self.translator.lock().await
+    }
+
+    /// Receive the events of the bridge from now on; notifications are
+    /// announced after they are cached. A subscriber that falls more than
+    /// 256 events behind misses the oldest ones.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<BridgeEvent> {
+        self.events.subscribe()
+    }
+
+    /// Stop caching notifications, cancel requests still running, and close
+    /// every open document. The servers exit when the bridge is dropped.
+    pub async fn shutdown(mut self) {
+        self.pumps.shutdown().await;
+        let mut translator = self.translator.lock().await;
+        match translator.request_canceller().cancel_all().await {
+            0 => {}
+            count => info!("Cancelled {count} in-flight LSP request(s)"),
+        }
+        translator.close_all_documents().await;
+        drop(translator);
+    }
+}
+
+/// Cache the notifications of the `language_id` server, which announces
+/// them on the translator's event bus.
+async fn pump(
+    language_id: String,
+    mut rx: mpsc::Receiver<LspNotification>,
+    translator: Arc<Mutex<Translator>>,
+) {
+    while let Some(notification) = rx.recv().await {
+        crate::cache_notification(&mut *translator.lock().await, &language_id, notification);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lsp_types::{Diagnostic, PublishDiagnosticsParams, Uri};
+
+    use super::*;
+
+    fn diagnostics(uri: &Uri) -> LspNotification {
+        LspNotification::PublishDiagnostics(PublishDiagnosticsParams {
+            uri: uri.clone(),
+            diagnostics: vec![Diagnostic {
+                message: "unused variable".to_string(),
+                ..Diagnostic::default()
+            }],
+            version: Some(1),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_bridge_caches_and_announces_notifications() {
+        let (tx, rx) = mpsc::channel(8);
+        let bridge =
+            Bridge::from_parts(Translator::new(), HashMap::from([("rust".to_string(), rx)]));
+        let mut events = bridge.subscribe();
+        let uri: Uri = "file:///ws/src/lib.rs".parse().unwrap();
+
+        tx.send(diagnostics(&uri)).await.unwrap();
+        let event = events.recv().await.unwrap();
+        assert!(matches!(
+            event,
+            BridgeEvent::DiagnosticsUpdated { ref language_id, uri: ref updated }
+                if language_id == "rust" && *updated == uri
+        ));
+        let translator = bridge.translator().await;
+        let cached = translator
+            .notification_cache()
+            .get_diagnostics(uri.as_str())
+            .unwrap();
+        assert_eq!(cached.diagnostics.len(), 1);
+        drop(translator);
+
+        bridge.shutdown().await;
+        assert!(tx.send(diagnostics(&uri)).await.is_err());
+    }
+}
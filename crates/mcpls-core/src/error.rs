@@ -60,9 +60,17 @@ pub enum Error {
     #[error("document not found: {0}")]
     DocumentNotFound(PathBuf),
 
-    /// No LSP server configured for the given language.
-    #[error("no LSP server configured for language: {0}")]
-    NoServerForLanguage(String),
+    /// No LSP server available for the given language.
+    ///
+    /// `spawn_failure` is set when a server was configured for the language
+    /// but failed to spawn or initialize, so callers can see why.
+    #[error("{}", describe_missing_server(language_id, spawn_failure.as_ref()))]
+    NoServerForLanguage {
+        /// Language ID that was requested.
+        language_id: String,
+        /// Why the configured server is unavailable, if it failed to start.
+        spawn_failure: Option<ServerSpawnFailure>,
+    },
 
     /// LSP server for the language is configured but still initializing.
     #[error(
@@ -70,6 +78,21 @@ pub enum Error {
     )]
     ServerInitializing(String),
 
+    /// LSP server timed out or gave up on a request while still indexing the
+    /// workspace, within its `startup_grace_seconds`.
+    #[error(
+        "LSP server for language '{language_id}' is still indexing{}; retry in {retry_after_secs} seconds",
+        progress.as_ref().map(|progress| format!(" ({progress})")).unwrap_or_default()
+    )]
+    ServerIndexing {
+        /// Language ID of the server.
+        language_id: String,
+        /// Seconds to wait before retrying.
+        retry_after_secs: u64,
+        /// Work the server reports in progress, if any.
+        progress: Option<String>,
+    },
+
     /// No LSP server is currently configured.
     #[error("no LSP server configured")]
     NoServerConfigured,
@@ -110,6 +133,10 @@ pub enum Error {
     #[error("request timed out after {0} seconds")]
     Timeout(u64),
 
+    /// Request was cancelled by a bridge reset.
+    #[error("request cancelled by a bridge reset")]
+    RequestCancelled,
+
     /// Server shutdown requested.
     #[error("server shutdown requested")]
     Shutdown,
@@ -140,6 +167,52 @@ pub enum Error {
     #[error("LSP server process terminated unexpectedly")]
     ServerTerminated,
 
+    /// LSP server process exited before it finished initializing.
+    #[error(
+        "LSP server '{command}' exited{}{}",
+        describe_exit(*exit_code),
+        describe_output(stderr, stdout)
+    )]
+    ServerExited {
+        /// Command that was spawned.
+        command: String,
+        /// Process exit code, if the process was not killed by a signal.
+        exit_code: Option<i32>,
+        /// Leading portion of the process's stderr output.
+        stderr: String,
+        /// Leading portion of what the process printed to stdout outside of
+        /// LSP messages.
+        stdout: String,
+    },
+
+    /// LSP server process kept running but never answered `initialize`.
+    #[error(
+        "LSP server '{command}' did not respond to initialize (timed out after {timeout_secs}s){}",
+        describe_output(stderr, stdout)
+    )]
+    InitializeTimeout {
+        /// Command that was spawned.
+        command: String,
+        /// Init timeout that elapsed, in seconds.
+        timeout_secs: u64,
+        /// Leading portion of the process's stderr output.
+        stderr: String,
+        /// Leading portion of what the process printed to stdout outside of
+        /// LSP messages.
+        stdout: String,
+    },
+
+    /// LSP server answered `initialize` with an error response.
+    #[error("LSP server '{command}' responded to initialize with an error: {code} - {message}")]
+    InitializeRejected {
+        /// Command that was spawned.
+        command: String,
+        /// JSON-RPC error code.
+        code: i32,
+        /// Error message from the server.
+        message: String,
+    },
+
     /// Invalid tool parameters provided.
     #[error("invalid tool parameters: {0}")]
     InvalidToolParams(String),
@@ -158,6 +231,48 @@ pub enum Error {
     #[error("path outside workspace: {0}")]
     PathOutsideWorkspace(PathBuf),
 
+    /// Files changed on disk after an edit was computed; the edit was not
+    /// applied. Open documents among them are resent to their servers, so
+    /// recomputing the edit against the current content is safe.
+    #[error(
+        "files changed since the edit was computed, nothing was written (retry the request): {}",
+        describe_paths(paths)
+    )]
+    EditConflict {
+        /// Files whose content no longer matches what the server saw.
+        paths: Vec<PathBuf>,
+    },
+
+    /// Documents hold unsaved content in the active session, which an edit
+    /// computed against them cannot be written over; the edit was not
+    /// applied.
+    #[error(
+        "documents have unsaved content, nothing was written (save it with save_document or clear it with update_document first): {}",
+        describe_paths(paths)
+    )]
+    UnsavedOverlay {
+        /// Documents whose overlay differs from the file on disk.
+        paths: Vec<PathBuf>,
+    },
+
+    /// A session made more calls of a class of tools in the last minute than
+    /// `workspace.rate_limits` allows.
+    #[error(
+        "rate limited: at most {limit} {class} tool calls per minute, retry after {retry_after_ms} ms"
+    )]
+    RateLimited {
+        /// Tool class whose limit was reached.
+        class: String,
+        /// Calls per minute allowed for the class.
+        limit: u32,
+        /// Time until the next call of the class is accepted.
+        retry_after_ms: u64,
+    },
+
+    /// No edit applied during this session is left to undo.
+    #[error("no applied edit to undo in this session")]
+    NothingToUndo,
+
     /// Document limit exceeded.
     #[error("document limit exceeded: {current}/{max}")]
     DocumentLimitExceeded {
@@ -201,6 +316,50 @@ pub enum Error {
     NoServersAvailable(String),
 }
 
+fn describe_missing_server(
+    language_id: &str,
+    spawn_failure: Option<&ServerSpawnFailure>,
+) -> String {
+    spawn_failure.map_or_else(
+        || format!("no LSP server configured for language: {language_id}"),
+        |failure| {
+            format!(
+                "LSP server for language '{language_id}' failed to start ({}): {}",
+                failure.command, failure.message
+            )
+        },
+    )
+}
+
+fn describe_exit(exit_code: Option<i32>) -> String {
+    exit_code.map_or_else(
+        || " (terminated by signal)".to_string(),
+        |code| format!(" with code {code}"),
+    )
+}
+
+fn describe_output(stderr: &str, stdout: &str) -> String {
+    let mut description = String::new();
+    for (name, output) in [("stderr", stderr), ("stdout", stdout)] {
+        let output = output.trim();
+        if !output.is_empty() {
+            description.push_str("; ");
+            description.push_str(name);
+            description.push_str(": ");
+            description.push_str(output);
+        }
+    }
+    description
+}
+
+fn describe_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// A specialized Result type for mcpls-core operations.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -241,13 +400,92 @@ mod tests {
 
     #[test]
     fn test_error_display_no_server_for_language() {
-        let err = Error::NoServerForLanguage("rust".to_string());
+        let err = Error::NoServerForLanguage {
+            language_id: "rust".to_string(),
+            spawn_failure: None,
+        };
         assert_eq!(
             err.to_string(),
             "no LSP server configured for language: rust"
         );
     }
 
+    #[test]
+    fn test_error_display_no_server_for_language_with_spawn_failure() {
+        let err = Error::NoServerForLanguage {
+            language_id: "rust".to_string(),
+            spawn_failure: Some(ServerSpawnFailure {
+                language_id: "rust".to_string(),
+                command: "rust-analyzer".to_string(),
+                message: "No such file or directory".to_string(),
+            }),
+        };
+        assert_eq!(
+            err.to_string(),
+            "LSP server for language 'rust' failed to start (rust-analyzer): No such file or directory"
+        );
+    }
+
+    #[test]
+    fn test_error_display_server_exited() {
+        let err = Error::ServerExited {
+            command: "pyright-langserver".to_string(),
+            exit_code: Some(1),
+            stderr: "Error: Cannot find module 'pyright'\n".to_string(),
+            stdout: String::new(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "LSP server 'pyright-langserver' exited with code 1; stderr: Error: Cannot find module 'pyright'"
+        );
+
+        let err = Error::ServerExited {
+            command: "gopls".to_string(),
+            exit_code: None,
+            stderr: String::new(),
+            stdout: String::new(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "LSP server 'gopls' exited (terminated by signal)"
+        );
+
+        let err = Error::ServerExited {
+            command: "clangd".to_string(),
+            exit_code: Some(2),
+            stderr: String::new(),
+            stdout: "Usage: clangd [options]\n".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "LSP server 'clangd' exited with code 2; stdout: Usage: clangd [options]"
+        );
+    }
+
+    #[test]
+    fn test_error_display_initialize_failures() {
+        let err = Error::InitializeTimeout {
+            command: "omnisharp".to_string(),
+            timeout_secs: 60,
+            stderr: "loading solution\n".to_string(),
+            stdout: "OmniSharp v1.39\n".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "LSP server 'omnisharp' did not respond to initialize (timed out after 60s); stderr: loading solution; stdout: OmniSharp v1.39"
+        );
+
+        let err = Error::InitializeRejected {
+            command: "pylsp".to_string(),
+            code: -32602,
+            message: "rootUri is required".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "LSP server 'pylsp' responded to initialize with an error: -32602 - rootUri is required"
+        );
+    }
+
     #[test]
     fn test_error_display_timeout() {
         let err = Error::Timeout(30);
@@ -441,4 +679,24 @@ mod tests {
         let err = Error::NoServersAvailable(custom_msg.to_string());
         assert_eq!(err.to_string(), custom_msg);
     }
+
+    #[test]
+    fn test_error_display_edit_conflict() {
+        let err = Error::EditConflict {
+            paths: vec![PathBuf::from("/ws/a.rs"), PathBuf::from("/ws/b.rs")],
+        };
+        let message = err.to_string();
+        assert!(message.contains("retry"));
+        assert!(message.ends_with("/ws/a.rs, /ws/b.rs"));
+    }
+
+    #[test]
+    fn test_error_display_unsaved_overlay() {
+        let err = Error::UnsavedOverlay {
+            paths: vec![PathBuf::from("/ws/a.rs")],
+        };
+        let message = err.to_string();
+        assert!(message.contains("save_document"));
+        assert!(message.ends_with("/ws/a.rs"));
+    }
 }
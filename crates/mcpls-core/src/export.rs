@@ -0,0 +1,69 @@
+//! Headless symbol map export.
+//!
+//! `mcpls export-symbols` runs the bridge without an MCP host: it spawns the
+//! language servers for a workspace, collects the document symbols of every
+//! file they handle, and writes the symbol map as JSON or as a SCIP index for
+//! offline analysis pipelines.
+
+use std::path::PathBuf;
+
+use tracing::{info, warn};
+
+use crate::config::ServerConfig;
+use crate::embed::Bridge;
+use crate::error::Result;
+
+/// Encoding of an exported symbol map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymbolMapFormat {
+    /// The symbol map as JSON, in the shape of the `export_symbols` tool.
+    #[default]
+    Json,
+    /// A SCIP index (protobuf).
+    Scip,
+}
+
+/// Export the symbols of the files under `root`, or the configured workspace
+/// roots when it is `None`, matching `globs` (all files when empty).
+///
+/// At most `max_files` files are exported. Files whose symbols cannot be
+/// collected are logged and, in JSON, listed with the error.
+///
+/// # Errors
+///
+/// Returns an error if `root` cannot be resolved, the configuration is
+/// invalid, no server applies to the workspace, every server fails to start,
+/// or a glob is invalid.
+pub async fn export_symbols(
+    config: ServerConfig,
+    root: Option<PathBuf>,
+    globs: &[String],
+    max_files: usize,
+    format: SymbolMapFormat,
+) -> Result<Vec<u8>> {
+    let bridge = Bridge::start(&config, root).await?;
+    let map = bridge
+        .translator()
+        .await
+        .handle_export_symbols(globs, max_files)
+        .await;
+    bridge.shutdown().await;
+    let map = map?;
+    for file in &map.files {
+        if let Some(error) = &file.error {
+            warn!("No symbols for {}: {error}", file.file_path);
+        }
+    }
+    if map.truncated {
+        warn!("Stopped after {max_files} file(s)");
+    }
+    info!(
+        "Exported {} symbol(s) from {} file(s)",
+        map.symbol_count(),
+        map.files.len()
+    );
+    Ok(match format {
+        SymbolMapFormat::Json => serde_json::to_vec(&map)?,
+        SymbolMapFormat::Scip => map.to_scip(),
+    })
+}
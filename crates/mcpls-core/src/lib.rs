@@ -13,6 +13,7 @@
 //! - [`mcp`] - MCP tool definitions and handlers
 //! - [`bridge`] - Translation layer between MCP and LSP protocols
 //! - [`config`] - Configuration types and loading
+//! - [`embed`] - Running the bridge as a library, without MCP
 //! - [`mod@error`] - Error types for the library
 //!
 //! ## Example
@@ -35,53 +36,58 @@
 
 pub mod bridge;
 pub mod config;
+pub mod embed;
 pub mod error;
+pub mod export;
 pub mod lsp;
 pub mod mcp;
+pub mod snapshot;
 pub mod transport;
+pub mod watch;
 
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use bridge::messages::Messages;
 use bridge::resources::make_uri;
-use bridge::{ResourceSubscriptions, Translator};
+pub use bridge::{BridgeEvent, EventBus};
+use bridge::{GlobRouter, LsifIndex, ResourceSubscriptions, Translator, export_index, index_path};
+use config::IndexExport;
 pub use config::ServerConfig;
+pub use embed::Bridge;
 pub use error::Error;
+pub use export::{SymbolMapFormat, export_symbols};
+use futures::StreamExt;
 use lsp::{LspNotification, LspServer, ServerInitConfig};
 use rmcp::model::ResourceUpdatedNotificationParam;
+pub use snapshot::snapshot;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::{Mutex, OnceCell};
 use tokio::task::JoinSet;
 use tracing::{error, info, warn};
 #[cfg(feature = "transport-http")]
 pub use transport::HttpConfig;
 pub use transport::Transport;
-#[cfg(feature = "transport-http")]
-use transport::run_http;
 use transport::run_stdio;
+#[cfg(feature = "transport-http")]
+use transport::{IdleShutdown, run_http};
+pub use watch::watch;
 
-/// Background task that drains LSP notifications, writes them to the cache,
-/// and forwards `resources/updated` to the MCP peer when subscribed.
-///
-/// The task operates in two phases without explicit state:
-/// - **Phase A** (before peer is set): caches every notification, skips peer notify.
-/// - **Phase B** (after peer is set): additionally fires `notify_resource_updated`
-///   for subscribed `PublishDiagnostics` URIs.
+/// Background task that drains LSP notifications and writes them to the
+/// cache, which announces them on the translator's event bus.
 ///
 /// The task exits when:
 /// - The LSP notification channel closes (`rx.recv()` returns `None`).
 /// - The cancellation watch fires (or the sender is dropped).
-/// - `notify_resource_updated` returns an error (peer disconnect / transport closed).
 ///
 /// # Note on lock contention (TODO critic-S4)
 /// All cache writes acquire `Arc<Mutex<Translator>>`, which is the same lock used
 /// by every MCP tool call. Splitting `NotificationCache` into its own `Arc<RwLock>`
 /// would eliminate this contention. Tracked as a P2 follow-up.
 pub(crate) async fn diagnostics_pump(
-    _lang: String,
+    lang: String,
     mut rx: tokio::sync::mpsc::Receiver<LspNotification>,
     translator: Arc<Mutex<Translator>>,
-    subs: Arc<ResourceSubscriptions>,
-    peer_cell: Arc<OnceCell<rmcp::Peer<rmcp::RoleServer>>>,
     mut cancel_rx: tokio::sync::watch::Receiver<bool>,
 ) {
     loop {
@@ -95,76 +101,166 @@ pub(crate) async fn diagnostics_pump(
             }
             msg = rx.recv() => {
                 let Some(notif) = msg else { break };
-                match notif {
-                    LspNotification::PublishDiagnostics(p) => {
-                        // Always cache unconditionally.
-                        {
-                            let mut t = translator.lock().await;
-                            t.notification_cache_mut()
-                                .store_diagnostics(&p.uri, p.version, p.diagnostics);
-                        }
-
-                        // Fast path: skip URI construction when nothing is subscribed.
-                        if subs.is_empty().await {
-                            continue;
-                        }
-
-                        // Notify only when peer is ready and URI is subscribed.
-                        let Some(peer) = peer_cell.get() else { continue };
-                        let Some(path) = bridge::uri_to_path(&p.uri) else { continue };
-                        let Ok(mcp_uri) = make_uri(&path) else { continue };
-
-                        // TODO(critic-S3): on subscribe, replay cached diagnostics once
-                        // so clients that subscribe after the first PublishDiagnostics
-                        // do not have to wait for the next LSP push.
-                        if !subs.contains(&mcp_uri).await {
-                            continue;
-                        }
-
-                        if peer
-                            .notify_resource_updated(ResourceUpdatedNotificationParam::new(
-                                mcp_uri,
-                            ))
-                            .await
-                            .is_err()
-                        {
-                            // Peer disconnected; stop the pump.
-                            break;
-                        }
-                    }
-                    LspNotification::LogMessage(m) => {
-                        let mut t = translator.lock().await;
-                        t.notification_cache_mut()
-                            .store_log(m.typ.into(), m.message);
-                    }
-                    LspNotification::ShowMessage(m) => {
-                        let mut t = translator.lock().await;
-                        t.notification_cache_mut()
-                            .store_message(m.typ.into(), m.message);
-                    }
-                    LspNotification::Progress { .. } | LspNotification::Other { .. } => {}
+                cache_notification(&mut *translator.lock().await, &lang, notif);
+            }
+        }
+    }
+}
+
+/// Background task that forwards `resources/updated` to the MCP peer for
+/// subscribed documents whose diagnostics are updated on the event bus.
+///
+/// Updates before the peer is set are skipped; their diagnostics are cached
+/// all the same. The task exits when the bus closes, the cancellation watch
+/// fires (or the sender is dropped), or `notify_resource_updated` returns an
+/// error (peer disconnect / transport closed).
+pub(crate) async fn resource_updates(
+    mut events: tokio::sync::broadcast::Receiver<BridgeEvent>,
+    subs: Arc<ResourceSubscriptions>,
+    peer_cell: Arc<OnceCell<rmcp::Peer<rmcp::RoleServer>>>,
+    mut cancel_rx: tokio::sync::watch::Receiver<bool>,
+) {
+    loop {
+        let uri = tokio::select! {
+            result = cancel_rx.changed() => {
+                if result.is_err() || *cancel_rx.borrow() {
+                    break;
                 }
+                continue;
             }
+            event = events.recv() => match event {
+                Ok(BridgeEvent::DiagnosticsUpdated { uri, .. }) => uri,
+                Ok(_) => continue,
+                Err(RecvError::Lagged(count)) => {
+                    warn!("Missed {count} bridge event(s); some resource updates were not sent");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        // Fast path: skip URI construction when nothing is subscribed.
+        if subs.is_empty().await {
+            continue;
+        }
+
+        // Notify only when peer is ready and URI is subscribed.
+        let Some(peer) = peer_cell.get() else {
+            continue;
+        };
+        let Some(path) = bridge::uri_to_path(&uri) else {
+            continue;
+        };
+        let Ok(mcp_uri) = make_uri(&path) else {
+            continue;
+        };
+
+        // TODO(critic-S3): on subscribe, replay cached diagnostics once
+        // so clients that subscribe after the first PublishDiagnostics
+        // do not have to wait for the next LSP push.
+        if !subs.contains(&mcp_uri).await {
+            continue;
+        }
+
+        if peer
+            .notify_resource_updated(ResourceUpdatedNotificationParam::new(mcp_uri))
+            .await
+            .is_err()
+        {
+            // Peer disconnected; stop forwarding.
+            break;
         }
     }
 }
 
+/// Store a notification of the `lang` server in the translator's
+/// notification cache and announce it on the event bus. Progress is
+/// recorded on the server's client; unknown notifications are only
+/// announced.
+pub(crate) fn cache_notification(
+    translator: &mut Translator,
+    lang: &str,
+    notification: LspNotification,
+) {
+    // Diagnostics are announced without their content, which is cached.
+    let announced = (translator.events().has_subscribers()
+        && !matches!(notification, LspNotification::PublishDiagnostics(_)))
+    .then(|| notification.clone());
+    match notification {
+        LspNotification::PublishDiagnostics(p) => {
+            let server = translator.server_info(lang).cloned();
+            translator.notification_cache_mut().store_diagnostics(
+                &p.uri,
+                p.version,
+                p.diagnostics,
+                server,
+            );
+            translator
+                .events()
+                .publish(BridgeEvent::DiagnosticsUpdated {
+                    language_id: lang.to_string(),
+                    uri: p.uri,
+                });
+        }
+        LspNotification::LogMessage(m) => {
+            let server = translator.server_info(lang).cloned();
+            translator
+                .notification_cache_mut()
+                .store_log(m.typ.into(), m.message, server);
+        }
+        LspNotification::ShowMessage(m) => {
+            translator
+                .notification_cache_mut()
+                .store_message(m.typ.into(), m.message);
+        }
+        LspNotification::Telemetry(data) => {
+            translator
+                .notification_cache_mut()
+                .store_telemetry(lang, data);
+        }
+        LspNotification::Progress { token, value } => {
+            translator.record_progress(lang, &token, &value);
+        }
+        LspNotification::Other { .. } => {}
+    }
+    if let Some(notification) = announced {
+        translator.events().publish(BridgeEvent::Notification {
+            language_id: lang.to_string(),
+            notification,
+        });
+    }
+}
+
 /// Register initialized LSP servers with the translator and extract notification receivers.
 ///
-/// Takes ownership of the `ServerInitResult`, extracts `notification_rx` from each server
-/// before registration, and returns a map of language-id to receiver for the pump tasks.
-fn register_servers(
-    mut result: lsp::ServerInitResult,
+/// Takes ownership of the `ServerInitResult` and returns a map of language-id to
+/// notification receiver for the pump tasks. Servers spawned from one of `configs`
+/// are registered as restartable, so their receiver stays valid when `reset_bridge`
+/// restarts them. Spawn failures are recorded so tool calls for those languages
+/// report why.
+pub(crate) fn register_servers(
+    result: lsp::ServerInitResult,
+    configs: &[ServerInitConfig],
     translator: &mut bridge::Translator,
-) -> std::collections::HashMap<String, tokio::sync::mpsc::Receiver<lsp::LspNotification>> {
+) -> NotificationReceivers {
     let mut receivers = std::collections::HashMap::new();
-    for (lang, server) in &mut result.servers {
-        receivers.insert(lang.clone(), server.take_notification_rx());
+    for (language_id, mut server) in result.servers {
+        let config = configs
+            .iter()
+            .find(|c| c.server_config.language_id == language_id);
+        let rx = if let Some(config) = config {
+            translator.register_restartable_server(config.clone(), server)
+        } else {
+            let rx = server.take_notification_rx();
+            let client = server.client().clone();
+            translator.register_client(language_id.clone(), client);
+            translator.register_server(language_id.clone(), server);
+            rx
+        };
+        receivers.insert(language_id, rx);
     }
-    for (language_id, server) in result.servers {
-        let client = server.client().clone();
-        translator.register_client(language_id.clone(), client);
-        translator.register_server(language_id.clone(), server);
+    for failure in result.failures {
+        translator.record_spawn_failure(failure);
     }
     receivers
 }
@@ -179,7 +275,7 @@ fn register_servers(
 /// A vector of workspace root paths. If config roots are provided, they are
 /// returned as-is. Otherwise, returns the canonicalized current directory,
 /// falling back to relative "." if canonicalization fails.
-fn resolve_workspace_roots(config_roots: &[PathBuf]) -> Vec<PathBuf> {
+pub(crate) fn resolve_workspace_roots(config_roots: &[PathBuf]) -> Vec<PathBuf> {
     if config_roots.is_empty() {
         match std::env::current_dir() {
             Ok(cwd) => {
@@ -275,36 +371,8 @@ pub async fn serve_with(config: ServerConfig, transport: Transport) -> Result<()
     info!("Starting MCPLS server...");
 
     let workspace_roots = resolve_workspace_roots(&config.workspace.roots);
-    let extension_map = config.build_effective_extension_map();
-    let max_depth = Some(config.workspace.heuristics_max_depth);
-
-    let mut translator = Translator::new().with_extensions(extension_map);
-    translator.set_workspace_roots(workspace_roots.clone());
-
-    let applicable_configs: Vec<ServerInitConfig> = config
-        .lsp_servers
-        .iter()
-        .filter_map(|lsp_config| {
-            let should_spawn = workspace_roots
-                .iter()
-                .any(|root| lsp_config.should_spawn(root, max_depth));
-
-            if !should_spawn {
-                info!(
-                    "Skipping LSP server '{}' ({}): no project markers found",
-                    lsp_config.language_id, lsp_config.command
-                );
-                return None;
-            }
-
-            Some(ServerInitConfig {
-                server_config: lsp_config.clone(),
-                workspace_roots: workspace_roots.clone(),
-                initialization_options: lsp_config.initialization_options.clone(),
-                notification_tx: None,
-            })
-        })
-        .collect();
+    let mut translator = build_translator(&config, &workspace_roots)?;
+    let applicable_configs = applicable_configs(&config, &workspace_roots);
 
     info!(
         "Attempting to spawn {} applicable LSP server(s)...",
@@ -319,6 +387,9 @@ pub async fn serve_with(config: ServerConfig, transport: Transport) -> Result<()
         .map(|c| c.server_config.language_id.clone())
         .collect();
     translator.set_expected_languages(expected_languages);
+    let index_exports = index_exports(&applicable_configs);
+    let canceller = translator.request_canceller();
+    let events = translator.events().subscribe();
 
     // Shared state, built BEFORE LSP initialization so the MCP server can answer
     // `initialize` immediately. LSP servers (which can take minutes to initialize
@@ -333,6 +404,13 @@ pub async fn serve_with(config: ServerConfig, transport: Transport) -> Result<()
 
     // Cancellation for pump tasks: send `true` to request shutdown.
     let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(resource_updates(
+        events,
+        Arc::clone(&subscriptions),
+        Arc::clone(&peer_cell),
+        cancel_rx.clone(),
+    ));
+    load_saved_indexes(&workspace_roots, &index_exports, Arc::clone(&translator));
 
     if applicable_configs.is_empty() {
         warn!("No applicable LSP servers configured — starting in protocol-only mode");
@@ -344,8 +422,6 @@ pub async fn serve_with(config: ServerConfig, transport: Transport) -> Result<()
         spawn_lsp_servers_background(
             applicable_configs,
             Arc::clone(&translator),
-            Arc::clone(&subscriptions),
-            Arc::clone(&peer_cell),
             cancel_rx.clone(),
         );
     }
@@ -360,16 +436,226 @@ pub async fn serve_with(config: ServerConfig, transport: Transport) -> Result<()
             run_stdio(mcp_server, &peer_cell).await
         }
         #[cfg(feature = "transport-http")]
-        Transport::Http(cfg) => run_http(mcp_server, cfg).await,
+        Transport::Http(cfg) => {
+            let idle = IdleShutdown {
+                translator: Arc::clone(&translator),
+                canceller: canceller.clone(),
+                grace: std::time::Duration::from_secs(config.workspace.disconnect_grace_seconds),
+            };
+            run_http(mcp_server, cfg, idle).await
+        }
     };
 
     // Signal background pump tasks to exit.
     let _ = cancel_tx.send(true);
 
+    // Nobody reads the answers of requests still running for the closed
+    // connection; cancel them so their tool calls release the translator.
+    match canceller.cancel_all().await {
+        0 => {}
+        count => info!("Cancelled {count} in-flight LSP request(s)"),
+    }
+    let mut translator = translator.lock().await;
+    if result.is_ok() {
+        match translator.save_open_documents().await {
+            Ok(0) => {}
+            Ok(count) => info!("Saved {count} open document(s) for the next start"),
+            Err(e) => warn!("Failed to save open documents: {e}"),
+        }
+    }
+    translator.close_all_documents().await;
+    drop(translator);
+    if result.is_ok() {
+        export_indexes(&workspace_roots, &index_exports).await;
+    }
+
     info!("MCPLS server shutting down");
     result
 }
 
+/// Create the translator for `config`, serving `workspace_roots`.
+pub(crate) fn build_translator(
+    config: &ServerConfig,
+    workspace_roots: &[PathBuf],
+) -> Result<Translator, Error> {
+    let mut translator = Translator::new().with_router(Arc::new(GlobRouter::from_config(config)));
+    translator.set_workspace_roots(workspace_roots.to_vec());
+    apply_tool_settings(&mut translator, config)?;
+    for lsp_config in &config.lsp_servers {
+        translator.set_companion_files(lsp_config.language_id.clone(), lsp_config.companion_files);
+        translator.set_extra_paths(
+            lsp_config.language_id.clone(),
+            lsp_config.visible_paths.clone(),
+            lsp_config.writable_paths.clone(),
+        );
+    }
+    Ok(translator)
+}
+
+/// Notification receivers of the started servers, by language ID.
+pub(crate) type NotificationReceivers =
+    std::collections::HashMap<String, tokio::sync::mpsc::Receiver<LspNotification>>;
+
+/// Start the servers of `config` for `root`, or the configured workspace
+/// roots when it is `None`, without an MCP host.
+///
+/// # Errors
+///
+/// Returns an error if `root` cannot be resolved, the configuration is
+/// invalid, no server applies to the workspace, or every server fails to
+/// start.
+pub(crate) async fn start_headless(
+    config: &ServerConfig,
+    root: Option<PathBuf>,
+) -> Result<(Translator, NotificationReceivers), Error> {
+    let workspace_roots = match root {
+        Some(root) => vec![root.canonicalize().map_err(|source| Error::FileIo {
+            path: root.clone(),
+            source,
+        })?],
+        None => resolve_workspace_roots(&config.workspace.roots),
+    };
+    let mut translator = build_translator(config, &workspace_roots)?;
+    let configs = applicable_configs(config, &workspace_roots);
+    if configs.is_empty() {
+        return Err(Error::NoServerConfigured);
+    }
+
+    let result = LspServer::spawn_batch(&configs).await;
+    if result.all_failed() {
+        return Err(Error::AllServersFailedToInit {
+            count: result.failure_count(),
+            failures: result.failures,
+        });
+    }
+    for failure in &result.failures {
+        warn!("Server initialization failed: {failure}");
+    }
+    let receivers = register_servers(result, &configs, &mut translator);
+    Ok((translator, receivers))
+}
+
+/// Servers of `config` to spawn for `workspace_roots`: those whose project
+/// markers are found under one of the roots.
+pub(crate) fn applicable_configs(
+    config: &ServerConfig,
+    workspace_roots: &[PathBuf],
+) -> Vec<ServerInitConfig> {
+    let max_depth = Some(config.workspace.heuristics_max_depth);
+    config
+        .lsp_servers
+        .iter()
+        .filter_map(|lsp_config| {
+            let should_spawn = workspace_roots
+                .iter()
+                .any(|root| lsp_config.should_spawn(root, max_depth));
+
+            if !should_spawn {
+                info!(
+                    "Skipping LSP server '{}' ({}): no project markers found",
+                    lsp_config.language_id, lsp_config.command
+                );
+                return None;
+            }
+
+            Some(ServerInitConfig {
+                server_config: lsp_config.clone(),
+                workspace_roots: workspace_roots.to_vec(),
+                initialization_options: lsp_config.initialization_options.clone(),
+                notification_tx: None,
+            })
+        })
+        .collect()
+}
+
+/// Apply the workspace settings that shape tool behaviour, and the default
+/// formatting options, to `translator`.
+fn apply_tool_settings(translator: &mut Translator, config: &ServerConfig) -> Result<(), Error> {
+    translator.set_allow_dependency_reads(config.workspace.allow_dependency_reads);
+    translator.set_client_roots_enabled(config.workspace.client_roots);
+    translator.set_external_locations(config.workspace.external_locations);
+    translator.set_response_metadata(config.workspace.response_metadata);
+    translator.set_rate_limits(config.workspace.rate_limits.clone());
+    let tools = mcp::McplsServer::tool_names();
+    if let Some(tool) = config
+        .workspace
+        .tool_timeouts
+        .keys()
+        .find(|tool| !tools.contains(tool))
+    {
+        return Err(Error::InvalidConfig(format!(
+            "unknown tool '{tool}' in workspace.tool_timeouts"
+        )));
+    }
+    translator.set_tool_timeouts(config.workspace.tool_timeouts.clone());
+    translator.set_messages(Messages::new(&config.workspace.messages)?);
+    translator.set_protected_edits(
+        config.workspace.protected_edits,
+        &config.workspace.generated_patterns,
+    );
+    translator.set_auto_apply_limits(config.workspace.auto_apply_limits);
+    translator.set_strict_positions(config.workspace.strict_positions);
+    translator.set_formatting(config.formatting.clone());
+    translator.set_sanitized_config(config.sanitized());
+    Ok(())
+}
+
+/// Languages of `configs` with an index exporter, and their exporters.
+fn index_exports(configs: &[ServerInitConfig]) -> Vec<(String, IndexExport)> {
+    configs
+        .iter()
+        .filter_map(|c| {
+            let export = c.server_config.index_export.clone()?;
+            Some((c.server_config.language_id.clone(), export))
+        })
+        .collect()
+}
+
+/// Load the indexes the previous run exported for `exports` under the first
+/// workspace root in a background task, handing each to the translator for
+/// use until its server registers.
+fn load_saved_indexes(
+    workspace_roots: &[PathBuf],
+    exports: &[(String, IndexExport)],
+    translator: Arc<Mutex<Translator>>,
+) {
+    let Some(root) = workspace_roots.first() else {
+        return;
+    };
+    let paths: Vec<(String, PathBuf)> = exports
+        .iter()
+        .map(|(language_id, _)| (language_id.clone(), index_path(root, language_id)))
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        for (language_id, path) in paths {
+            if let Some(index) = LsifIndex::load(&path).await {
+                info!(
+                    "Loaded saved index for {language_id} from {}",
+                    path.display()
+                );
+                translator.lock().await.set_saved_index(language_id, index);
+            }
+        }
+    });
+}
+
+/// Run the index exporters configured for `exports` in the first workspace
+/// root, one after another.
+async fn export_indexes(workspace_roots: &[PathBuf], exports: &[(String, IndexExport)]) {
+    let Some(root) = workspace_roots.first() else {
+        return;
+    };
+    for (language_id, export) in exports {
+        info!("Exporting index for {language_id} with {}", export.command);
+        if let Err(e) = export_index(root, language_id, export).await {
+            warn!("Failed to export index for {language_id}: {e}");
+        }
+    }
+}
+
 /// Spawn the applicable LSP servers in a background task and register them into
 /// the shared `translator` once ready.
 ///
@@ -383,62 +669,67 @@ pub async fn serve_with(config: ServerConfig, transport: Transport) -> Result<()
 fn spawn_lsp_servers_background(
     applicable_configs: Vec<ServerInitConfig>,
     translator: Arc<Mutex<Translator>>,
-    subscriptions: Arc<ResourceSubscriptions>,
-    peer_cell: Arc<OnceCell<rmcp::Peer<rmcp::RoleServer>>>,
     cancel_rx: tokio::sync::watch::Receiver<bool>,
 ) {
     tokio::spawn(async move {
-        let result = LspServer::spawn_batch(&applicable_configs).await;
+        let mut server_count = 0;
+        let mut failure_count = 0;
+        let mut pumps: JoinSet<()> = JoinSet::new();
 
-        if result.all_failed() {
-            error!(
-                "All {} configured LSP server(s) failed to initialize",
-                result.failure_count()
-            );
+        // Register each server as soon as it is up, so a slow server does not
+        // hold back the others.
+        let mut attempts = LspServer::spawn_each(&applicable_configs);
+        while let Some(result) = attempts.next().await {
             for failure in &result.failures {
                 error!("Server initialization failed: {}", failure);
             }
-            // No server will register; stop reporting "still initializing".
-            translator.lock().await.clear_expected_languages();
-            return;
+            server_count += result.server_count();
+            failure_count += result.failure_count();
+            let languages: Vec<String> = result
+                .servers
+                .keys()
+                .cloned()
+                .chain(result.failures.iter().map(|f| f.language_id.clone()))
+                .collect();
+
+            let mut t = translator.lock().await;
+            let notification_receivers = register_servers(result, &applicable_configs, &mut t);
+            // Stop reporting "still initializing" for the language, so a
+            // failed server reports NoServerForLanguage with its spawn
+            // failure instead.
+            for language_id in &languages {
+                t.finish_expected_language(language_id);
+            }
+            if !notification_receivers.is_empty() {
+                let restored = t.restore_open_documents().await;
+                if restored > 0 {
+                    info!("Reopened {restored} document(s) from the previous run");
+                }
+            }
+            drop(t);
+
+            for (lang, rx) in notification_receivers {
+                pumps.spawn(diagnostics_pump(
+                    lang,
+                    rx,
+                    Arc::clone(&translator),
+                    cancel_rx.clone(),
+                ));
+            }
         }
+        drop(attempts);
 
-        if result.partial_success() {
+        if server_count == 0 {
+            error!("All {failure_count} configured LSP server(s) failed to initialize");
+        } else if failure_count > 0 {
             warn!(
-                "Partial server initialization: {} succeeded, {} failed",
-                result.server_count(),
-                result.failure_count()
+                "Partial server initialization: {server_count} succeeded, {failure_count} failed"
             );
-            for failure in &result.failures {
-                error!("Server initialization failed: {}", failure);
-            }
         }
-
-        let server_count = result.server_count();
-        let notification_receivers = {
-            let mut t = translator.lock().await;
-            let receivers = register_servers(result, &mut t);
-            // Background initialization has completed; stop reporting "still
-            // initializing" (especially for languages whose server failed to
-            // spawn on partial success, which would otherwise return
-            // ServerInitializing forever instead of NoServerForLanguage).
-            t.clear_expected_languages();
-            receivers
-        };
         info!("Proceeding with {} LSP server(s)", server_count);
+        // Background initialization has completed.
+        translator.lock().await.clear_expected_languages();
 
-        // Start diagnostics pump tasks now that servers are registered.
-        let mut pumps: JoinSet<()> = JoinSet::new();
-        for (lang, rx) in notification_receivers {
-            pumps.spawn(diagnostics_pump(
-                lang,
-                rx,
-                Arc::clone(&translator),
-                Arc::clone(&subscriptions),
-                Arc::clone(&peer_cell),
-                cancel_rx.clone(),
-            ));
-        }
         while pumps.join_next().await.is_some() {}
     });
 }
@@ -565,6 +856,62 @@ mod tests {
         assert_eq!(roots[0], PathBuf::from("/workspace/path with spaces"));
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_background_spawn_registers_fast_server_before_slow_one() {
+        let server = |language_id: &str, script: String| {
+            let mut server_config = crate::config::LspServerConfig::rust_analyzer();
+            server_config.language_id = language_id.to_string();
+            server_config.command = "sh".to_string();
+            server_config.args = vec!["-c".to_string(), script];
+            server_config.init_timeout_seconds = Some(20);
+            ServerInitConfig {
+                server_config,
+                workspace_roots: vec![],
+                initialization_options: None,
+                notification_tx: None,
+            }
+        };
+        let response = r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":{}}}"#;
+        let fast = server(
+            "fast",
+            format!(
+                "head -c 1 >/dev/null; printf 'Content-Length: {}\\r\\n\\r\\n%s' '{response}'; cat >/dev/null",
+                response.len()
+            ),
+        );
+        let stalled = server("stalled", "sleep 30".to_string());
+
+        let translator = Arc::new(Mutex::new(Translator::new()));
+        translator
+            .lock()
+            .await
+            .set_expected_languages(["fast".to_string(), "stalled".to_string()].into());
+        let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        spawn_lsp_servers_background(vec![stalled, fast], Arc::clone(&translator), cancel_rx);
+
+        let started = std::time::Instant::now();
+        while translator
+            .lock()
+            .await
+            .handle_initialization_info("fast")
+            .is_err()
+        {
+            assert!(
+                started.elapsed() < std::time::Duration::from_secs(10),
+                "fast server not registered while the other one stalls"
+            );
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert!(matches!(
+            translator
+                .lock()
+                .await
+                .handle_initialization_info("stalled"),
+            Err(Error::ServerInitializing(_))
+        ));
+    }
+
     // Tests for graceful degradation behavior
     mod graceful_degradation_tests {
         use super::*;
@@ -677,6 +1024,24 @@ mod tests {
             assert!(display.contains("executable not found"));
         }
 
+        #[test]
+        fn test_register_servers_records_spawn_failures() {
+            let mut result = ServerInitResult::new();
+            result.add_failure(ServerSpawnFailure {
+                language_id: "go".to_string(),
+                command: "gopls".to_string(),
+                message: "executable not found in PATH".to_string(),
+            });
+
+            let mut translator = Translator::new();
+            let receivers = register_servers(result, &[], &mut translator);
+
+            assert!(receivers.is_empty());
+            let failure = translator.spawn_failure("go").unwrap();
+            assert_eq!(failure.command, "gopls");
+            assert!(translator.spawn_failure("rust").is_none());
+        }
+
         #[test]
         fn test_result_helpers_consistency() {
             let mut result = ServerInitResult::new();
@@ -700,7 +1065,10 @@ mod tests {
 
         #[tokio::test]
         async fn test_serve_degrades_when_all_servers_fail_to_spawn() {
-            use crate::config::{LspServerConfig, WorkspaceConfig};
+            use crate::config::{
+                AutoApplyLimits, CompanionFiles, DEFAULT_MAX_MESSAGE_SIZE, ExternalLocationPolicy,
+                LspServerConfig, ProtectedEditPolicy, StderrMode, TraceMode, WorkspaceConfig,
+            };
 
             // A configured server whose command cannot spawn used to make serve()
             // fail synchronously with NoServersAvailable / AllServersFailedToInit.
@@ -717,15 +1085,40 @@ mod tests {
                     position_encodings: vec!["utf-8".to_string(), "utf-16".to_string()],
                     language_extensions: vec![],
                     heuristics_max_depth: 10,
+                    allow_dependency_reads: false,
+                    external_locations: ExternalLocationPolicy::default(),
+                    protected_edits: ProtectedEditPolicy::default(),
+                    generated_patterns: Vec::new(),
+                    auto_apply_limits: AutoApplyLimits::default(),
+                    strict_positions: false,
+                    response_metadata: false,
+                    messages: std::collections::HashMap::new(),
+                    rate_limits: std::collections::HashMap::new(),
+                    tool_timeouts: std::collections::HashMap::new(),
+                    disconnect_grace_seconds: 0,
+                    client_roots: true,
                 },
+                formatting: std::collections::HashMap::new(),
                 lsp_servers: vec![LspServerConfig {
                     language_id: "rust".to_string(),
                     command: "nonexistent-command-that-will-fail-12345".to_string(),
                     args: vec![],
                     env: std::collections::HashMap::new(),
+                    env_allow: None,
+                    env_deny: Vec::new(),
                     file_patterns: vec!["**/*.rs".to_string()],
                     initialization_options: None,
                     timeout_seconds: 10,
+                    init_timeout_seconds: None,
+                    startup_grace_seconds: None,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    stderr: StderrMode::Discard,
+                    trace: TraceMode::Off,
+                    inline_completions: false,
+                    companion_files: CompanionFiles::None,
+                    visible_paths: Vec::new(),
+                    writable_paths: Vec::new(),
+                    index_export: None,
                     heuristics: None,
                 }],
             };
@@ -753,7 +1146,9 @@ mod tests {
 
         #[tokio::test]
         async fn test_serve_starts_with_empty_config() {
-            use crate::config::WorkspaceConfig;
+            use crate::config::{
+                AutoApplyLimits, ExternalLocationPolicy, ProtectedEditPolicy, WorkspaceConfig,
+            };
 
             // Server starts in protocol-only mode when no LSP servers are configured.
             // serve() blocks until the MCP transport closes, so it will error with a
@@ -764,7 +1159,20 @@ mod tests {
                     position_encodings: vec!["utf-8".to_string(), "utf-16".to_string()],
                     language_extensions: vec![],
                     heuristics_max_depth: 10,
+                    allow_dependency_reads: false,
+                    external_locations: ExternalLocationPolicy::default(),
+                    protected_edits: ProtectedEditPolicy::default(),
+                    generated_patterns: Vec::new(),
+                    auto_apply_limits: AutoApplyLimits::default(),
+                    strict_positions: false,
+                    response_metadata: false,
+                    messages: std::collections::HashMap::new(),
+                    rate_limits: std::collections::HashMap::new(),
+                    tool_timeouts: std::collections::HashMap::new(),
+                    disconnect_grace_seconds: 0,
+                    client_roots: true,
                 },
+                formatting: std::collections::HashMap::new(),
                 lsp_servers: vec![],
             };
 
@@ -810,22 +1218,13 @@ mod tests {
         #[tokio::test]
         async fn test_pump_caches_before_peer_set() {
             let translator = make_translator();
-            let subs = make_subs();
-            let peer_cell = make_peer_cell();
             let (tx, rx) = mpsc::channel(8);
             // Keep _cancel_tx alive: dropping it causes cancel_rx.changed() to return Err,
             // which makes the pump exit before processing any messages.
             let (_cancel_tx, cancel_rx) = watch::channel(false);
 
             let t = Arc::clone(&translator);
-            tokio::spawn(diagnostics_pump(
-                "rust".to_string(),
-                rx,
-                t,
-                Arc::clone(&subs),
-                Arc::clone(&peer_cell),
-                cancel_rx,
-            ));
+            tokio::spawn(diagnostics_pump("rust".to_string(), rx, t, cancel_rx));
 
             let uri: Uri = "file:///test/main.rs".parse().unwrap();
             tx.send(LspNotification::PublishDiagnostics(
@@ -865,8 +1264,6 @@ mod tests {
         #[tokio::test]
         async fn test_pump_exits_on_cancel() {
             let translator = make_translator();
-            let subs = make_subs();
-            let peer_cell = make_peer_cell();
             let (_tx, rx) = mpsc::channel::<LspNotification>(8);
             let (cancel_tx, cancel_rx) = watch::channel(false);
 
@@ -874,8 +1271,6 @@ mod tests {
                 "rust".to_string(),
                 rx,
                 translator,
-                subs,
-                peer_cell,
                 cancel_rx,
             ));
 
@@ -891,8 +1286,6 @@ mod tests {
         #[tokio::test]
         async fn test_pump_exits_when_cancel_sender_dropped() {
             let translator = make_translator();
-            let subs = make_subs();
-            let peer_cell = make_peer_cell();
             let (_tx, rx) = mpsc::channel::<LspNotification>(8);
             let (cancel_tx, cancel_rx) = watch::channel(false);
 
@@ -900,8 +1293,6 @@ mod tests {
                 "rust".to_string(),
                 rx,
                 translator,
-                subs,
-                peer_cell,
                 cancel_rx,
             ));
 
@@ -911,5 +1302,81 @@ mod tests {
                 .expect("pump did not exit within timeout")
                 .unwrap();
         }
+
+        /// Cached notifications are announced on the event bus.
+        #[tokio::test]
+        async fn test_pump_announces_cached_notifications() {
+            let translator = make_translator();
+            let mut events = translator.lock().await.events().subscribe();
+            let (tx, rx) = mpsc::channel(8);
+            let (_cancel_tx, cancel_rx) = watch::channel(false);
+            tokio::spawn(diagnostics_pump(
+                "rust".to_string(),
+                rx,
+                Arc::clone(&translator),
+                cancel_rx,
+            ));
+
+            let uri: Uri = "file:///test/main.rs".parse().unwrap();
+            tx.send(LspNotification::PublishDiagnostics(
+                PublishDiagnosticsParams {
+                    uri: uri.clone(),
+                    diagnostics: vec![],
+                    version: None,
+                },
+            ))
+            .await
+            .unwrap();
+            tx.send(LspNotification::Telemetry(serde_json::json!({"ok": true})))
+                .await
+                .unwrap();
+
+            let event = events.recv().await.unwrap();
+            assert!(matches!(
+                event,
+                BridgeEvent::DiagnosticsUpdated { uri: ref updated, .. } if *updated == uri
+            ));
+            // The diagnostics are cached by the time they are announced.
+            assert!(
+                translator
+                    .lock()
+                    .await
+                    .notification_cache()
+                    .get_diagnostics(uri.as_str())
+                    .is_some()
+            );
+            let event = events.recv().await.unwrap();
+            assert!(matches!(
+                event,
+                BridgeEvent::Notification {
+                    notification: LspNotification::Telemetry(_),
+                    ..
+                }
+            ));
+        }
+
+        /// Resource updates stop when the cancel watch sends `true`.
+        #[tokio::test]
+        async fn test_resource_updates_exit_on_cancel() {
+            let bus = bridge::EventBus::default();
+            let (cancel_tx, cancel_rx) = watch::channel(false);
+            let handle = tokio::spawn(resource_updates(
+                bus.subscribe(),
+                make_subs(),
+                make_peer_cell(),
+                cancel_rx,
+            ));
+
+            // Updates without a peer are skipped.
+            bus.publish(BridgeEvent::DiagnosticsUpdated {
+                language_id: "rust".to_string(),
+                uri: "file:///test/main.rs".parse().unwrap(),
+            });
+            cancel_tx.send(true).unwrap();
+            tokio::time::timeout(std::time::Duration::from_millis(200), handle)
+                .await
+                .expect("resource updates did not exit within timeout")
+                .unwrap();
+        }
     }
 }
@@ -9,12 +9,15 @@ use serde::de::DeserializeOwned;
 use serde_json::Value;
 use tokio::sync::{Mutex, mpsc, oneshot};
 use tokio::task::JoinHandle;
-use tokio::time::{Duration, timeout};
+use tokio::time::{Duration, Instant, timeout};
 use tracing::{debug, error, trace, warn};
 
 use crate::config::LspServerConfig;
 use crate::error::{Error, Result};
-use crate::lsp::transport::LspTransport;
+use crate::lsp::metrics;
+use crate::lsp::progress::ProgressTracker;
+use crate::lsp::sequencing::{DocumentSequencer, document_uri, is_document_sync};
+use crate::lsp::transport::{LspTransport, WireLog};
 use crate::lsp::types::{
     InboundMessage, JsonRpcError, JsonRpcRequest, JsonRpcResponse, LspNotification, RequestId,
 };
@@ -31,6 +34,10 @@ const SERVER_CANCELLED_MAX_RETRIES: u32 = 3;
 /// Initial backoff delay for server-cancelled retries (milliseconds).
 const SERVER_CANCELLED_INITIAL_DELAY_MS: u64 = 500;
 
+/// How long the message loop keeps reading a failed server's output, so
+/// stray output printed before it went away is captured for diagnostics.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
 /// Type alias for pending request tracking map.
 type PendingRequests = HashMap<RequestId, oneshot::Sender<Result<Value>>>;
 
@@ -55,6 +62,19 @@ pub struct LspClient {
     /// Command sender for outbound messages.
     command_tx: mpsc::Sender<ClientCommand>,
 
+    /// Requests awaiting a response, shared with the message loop.
+    pending_requests: Arc<Mutex<PendingRequests>>,
+
+    /// Keeps document changes from interleaving with requests in flight for
+    /// the same document.
+    sequencer: DocumentSequencer,
+
+    /// Last messages exchanged with the server.
+    wire_log: WireLog,
+
+    /// Work the server reports in progress, and its startup grace period.
+    progress: ProgressTracker,
+
     /// Background receiver task handle.
     receiver_task: Option<JoinHandle<Result<()>>>,
 }
@@ -70,6 +90,10 @@ impl Clone for LspClient {
             state: Arc::clone(&self.state),
             request_counter: Arc::clone(&self.request_counter),
             command_tx: self.command_tx.clone(),
+            pending_requests: Arc::clone(&self.pending_requests),
+            sequencer: self.sequencer.clone(),
+            wire_log: self.wire_log.clone(),
+            progress: self.progress.clone(),
             receiver_task: None,
         }
     }
@@ -108,6 +132,10 @@ impl LspClient {
             state: Arc::new(Mutex::new(super::ServerState::Uninitialized)),
             request_counter: Arc::new(AtomicI64::new(1)),
             command_tx,
+            pending_requests: Arc::new(Mutex::new(HashMap::new())),
+            sequencer: DocumentSequencer::default(),
+            wire_log: WireLog::default(),
+            progress: ProgressTracker::default(),
             receiver_task: None,
         }
     }
@@ -122,11 +150,13 @@ impl LspClient {
         let pending_requests = Arc::new(Mutex::new(HashMap::new()));
 
         let (command_tx, command_rx) = mpsc::channel(100);
+        let wire_log = transport.wire_log();
+        let progress = ProgressTracker::new(config.startup_grace());
 
         let receiver_task = tokio::spawn(Self::message_loop(
             transport,
             command_rx,
-            pending_requests,
+            Arc::clone(&pending_requests),
             None,
         ));
 
@@ -135,6 +165,10 @@ impl LspClient {
             state,
             request_counter,
             command_tx,
+            pending_requests,
+            sequencer: DocumentSequencer::default(),
+            wire_log,
+            progress,
             receiver_task: Some(receiver_task),
         }
     }
@@ -153,11 +187,13 @@ impl LspClient {
         let pending_requests = Arc::new(Mutex::new(HashMap::new()));
 
         let (command_tx, command_rx) = mpsc::channel(100);
+        let wire_log = transport.wire_log();
+        let progress = ProgressTracker::new(config.startup_grace());
 
         let receiver_task = tokio::spawn(Self::message_loop(
             transport,
             command_rx,
-            pending_requests,
+            Arc::clone(&pending_requests),
             Some(notification_tx),
         ));
 
@@ -166,10 +202,26 @@ impl LspClient {
             state,
             request_counter,
             command_tx,
+            pending_requests,
+            sequencer: DocumentSequencer::default(),
+            wire_log,
+            progress,
             receiver_task: Some(receiver_task),
         }
     }
 
+    /// Work the server reports in progress, and its startup grace period.
+    #[must_use]
+    pub const fn progress(&self) -> &ProgressTracker {
+        &self.progress
+    }
+
+    /// The last messages exchanged with the server.
+    #[must_use]
+    pub const fn wire_log(&self) -> &WireLog {
+        &self.wire_log
+    }
+
     /// Get the language ID for this client.
     #[must_use]
     pub fn language_id(&self) -> &str {
@@ -187,6 +239,10 @@ impl LspClient {
     /// -32802 (`ServerCancelled`) with `data.retriggerRequest == true`, using
     /// exponential backoff starting at 500 ms.
     ///
+    /// A request naming a `textDocument` waits for synchronization
+    /// notifications already sent for that document, and holds off new ones
+    /// until it is answered.
+    ///
     /// # Type Parameters
     ///
     /// * `P` - The type of the request parameters (must be serializable)
@@ -210,6 +266,11 @@ impl LspClient {
         R: DeserializeOwned,
     {
         let params_value = serde_json::to_value(params)?;
+        // Held until the response arrives, retries included.
+        let _document_guard = match document_uri(&params_value) {
+            Some(uri) => Some(self.sequencer.request(uri).await),
+            None => None,
+        };
         let mut delay_ms = SERVER_CANCELLED_INITIAL_DELAY_MS;
 
         for attempt in 0..=SERVER_CANCELLED_MAX_RETRIES {
@@ -241,9 +302,16 @@ impl LspClient {
                 .await
                 .map_err(|_| Error::ServerTerminated)?;
 
-            let outcome = timeout(timeout_duration, response_rx)
-                .await
-                .map_err(|_| Error::Timeout(timeout_duration.as_secs()))?
+            let sent = Instant::now();
+            let response = timeout(timeout_duration, response_rx).await;
+            metrics::record_lsp_request(&self.config.language_id, sent.elapsed());
+            let outcome = response
+                .map_err(|_| {
+                    self.progress.classify(
+                        &self.config.language_id,
+                        Error::Timeout(timeout_duration.as_secs()),
+                    )
+                })?
                 .map_err(|_| Error::ServerTerminated)?;
 
             match outcome {
@@ -270,7 +338,7 @@ impl LspClient {
                     }
                     // continue loop for next attempt
                 }
-                Err(e) => return Err(e),
+                Err(e) => return Err(self.progress.classify(&self.config.language_id, e)),
             }
         }
 
@@ -293,6 +361,9 @@ impl LspClient {
 
     /// Send notification (fire-and-forget, no response expected).
     ///
+    /// Document synchronization notifications (`didOpen`, `didChange`, ...)
+    /// wait until requests in flight for the same document are answered.
+    ///
     /// # Errors
     ///
     /// Returns an error if the server has shut down.
@@ -301,6 +372,13 @@ impl LspClient {
         P: Serialize,
     {
         let params_value = serde_json::to_value(params)?;
+        let document = document_uri(&params_value)
+            .filter(|_| is_document_sync(method))
+            .map(ToString::to_string);
+        let _document_guard = match &document {
+            Some(uri) => Some(self.sequencer.change(uri).await),
+            None => None,
+        };
 
         debug!("Sending notification: {}", method);
 
@@ -312,9 +390,34 @@ impl LspClient {
             .await
             .map_err(|_| Error::ServerTerminated)?;
 
+        if method == "textDocument/didClose"
+            && let Some(uri) = &document
+        {
+            self.sequencer.forget(uri);
+        }
         Ok(())
     }
 
+    /// Cancel every request still awaiting a response.
+    ///
+    /// Each waiting caller receives [`Error::RequestCancelled`] and the server
+    /// is sent `$/cancelRequest`. This includes requests whose callers already
+    /// gave up after a timeout. Returns the number of cancelled requests.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server has shut down.
+    pub async fn cancel_pending(&self) -> Result<usize> {
+        let pending: Vec<_> = self.pending_requests.lock().await.drain().collect();
+        let count = pending.len();
+        for (id, response_tx) in pending {
+            let _ = response_tx.send(Err(Error::RequestCancelled));
+            self.notify("$/cancelRequest", serde_json::json!({ "id": id }))
+                .await?;
+        }
+        Ok(count)
+    }
+
     /// Shutdown client gracefully.
     ///
     /// This sends a shutdown command to the background task and waits for it to complete.
@@ -357,8 +460,12 @@ impl LspClient {
             notification_tx.as_ref(),
         )
         .await;
+        // Fail the remaining waiters right away; the map outlives this task
+        // because clients share it for cancellation.
+        pending_requests.lock().await.clear();
         if let Err(ref e) = result {
             error!("Message loop exiting with error: {}", e);
+            transport.drain(OUTPUT_DRAIN_TIMEOUT).await;
         } else {
             debug!("Message loop exiting normally");
         }
@@ -508,6 +615,7 @@ impl LspClient {
             | "workspace/diagnostic/refresh"
             | "workspace/semanticTokens/refresh"
             | "workspace/inlayHint/refresh"
+            | "window/workDoneProgress/create"
             | "workspace/codeLens/refresh"
             | "window/showMessageRequest" => Ok(Value::Null),
             "workspace/configuration" => Ok(Self::workspace_configuration_result(params)),
@@ -617,6 +725,117 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_cancel_pending_fails_waiters_and_notifies_server() {
+        let (command_tx, mut command_rx) = mpsc::channel(10);
+        let client = LspClient {
+            command_tx,
+            ..LspClient::new(LspServerConfig::rust_analyzer())
+        };
+        let (response_tx, response_rx) = oneshot::channel::<Result<Value>>();
+        client
+            .pending_requests
+            .lock()
+            .await
+            .insert(RequestId::Number(7), response_tx);
+
+        assert_eq!(client.cancel_pending().await.unwrap(), 1);
+        assert!(matches!(
+            response_rx.await.unwrap(),
+            Err(Error::RequestCancelled)
+        ));
+        match command_rx.recv().await {
+            Some(ClientCommand::SendNotification { method, params }) => {
+                assert_eq!(method, "$/cancelRequest");
+                assert_eq!(params, Some(serde_json::json!({ "id": 7 })));
+            }
+            _ => panic!("expected $/cancelRequest notification"),
+        }
+        assert_eq!(client.cancel_pending().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_did_change_waits_for_request_on_same_document() {
+        let (command_tx, mut command_rx) = mpsc::channel(10);
+        let client = LspClient {
+            command_tx,
+            ..LspClient::new(LspServerConfig::rust_analyzer())
+        };
+        let document = |uri: &str| serde_json::json!({ "textDocument": { "uri": uri } });
+        let request = |uri: &'static str| {
+            let client = client.clone();
+            tokio::spawn(async move {
+                client
+                    .request::<_, Value>(
+                        "textDocument/hover",
+                        document(uri),
+                        Duration::from_secs(5),
+                    )
+                    .await
+            })
+        };
+        let next_command = async |rx: &mut mpsc::Receiver<ClientCommand>| {
+            timeout(Duration::from_millis(100), rx.recv())
+                .await
+                .ok()
+                .flatten()
+        };
+
+        let hover_lib = request("file:///ws/lib.rs");
+        let Some(ClientCommand::SendRequest { response_tx, .. }) =
+            next_command(&mut command_rx).await
+        else {
+            panic!("expected the hover request");
+        };
+
+        let change = tokio::spawn({
+            let client = client.clone();
+            async move {
+                client
+                    .notify("textDocument/didChange", document("file:///ws/lib.rs"))
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let hover_after_change = request("file:///ws/lib.rs");
+        let hover_main = request("file:///ws/main.rs");
+
+        // Only the request for the other document goes out.
+        match next_command(&mut command_rx).await {
+            Some(ClientCommand::SendRequest { request, .. }) => {
+                assert_eq!(
+                    document_uri(request.params.as_ref().unwrap()),
+                    Some("file:///ws/main.rs")
+                );
+            }
+            _ => panic!("expected the request for main.rs"),
+        }
+        assert!(next_command(&mut command_rx).await.is_none());
+
+        response_tx.send(Ok(Value::Null)).unwrap();
+        hover_lib.await.unwrap().unwrap();
+        match next_command(&mut command_rx).await {
+            Some(ClientCommand::SendNotification { method, .. }) => {
+                assert_eq!(method, "textDocument/didChange");
+            }
+            _ => panic!("expected didChange once the request was answered"),
+        }
+        change.await.unwrap().unwrap();
+        match next_command(&mut command_rx).await {
+            Some(ClientCommand::SendRequest { request, .. }) => {
+                assert_eq!(
+                    document_uri(request.params.as_ref().unwrap()),
+                    Some("file:///ws/lib.rs")
+                );
+            }
+            _ => panic!("expected the request made after didChange"),
+        }
+
+        client.cancel_pending().await.unwrap();
+        assert!(hover_after_change.await.unwrap().is_err());
+        assert!(hover_main.await.unwrap().is_err());
+    }
+
     #[tokio::test]
     async fn test_null_response_handling() {
         use crate::lsp::types::{JsonRpcResponse, RequestId};
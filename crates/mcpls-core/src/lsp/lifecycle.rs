@@ -10,23 +10,45 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::Stdio;
-use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
 
+use futures::Stream;
+use futures::stream::FuturesUnordered;
 use lsp_types::{
     ClientCapabilities, ClientInfo, GeneralClientCapabilities, InitializeParams, InitializeResult,
-    InitializedParams, PositionEncodingKind, ServerCapabilities, Uri, WorkspaceFolder,
+    InitializedParams, LogMessageParams, MessageType, PositionEncodingKind, SemanticTokenModifier,
+    SemanticTokenType, SemanticTokensLegend, SemanticTokensServerCapabilities, ServerCapabilities,
+    ServerInfo, SetTraceParams, TraceValue, WorkspaceFolder,
 };
-use tokio::process::Command;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStderr, Command};
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tokio::time::Duration;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-use crate::config::LspServerConfig;
+use crate::bridge::path_to_uri;
+use crate::config::{LspServerConfig, StderrMode, TraceMode};
 use crate::error::{Error, Result, ServerSpawnFailure};
 use crate::lsp::client::LspClient;
-use crate::lsp::transport::LspTransport;
+use crate::lsp::quirks::{self, Quirk};
+use crate::lsp::transport::{LspTransport, StrayOutput};
 use crate::lsp::types::LspNotification;
 
+/// Maximum number of stderr bytes kept for spawn and exit diagnostics.
+const STDERR_CAPTURE_LIMIT: usize = 1024;
+
+/// Maximum length of a single stderr line forwarded to the log buffer.
+const STDERR_LOG_LINE_LIMIT: usize = 4096;
+
+/// How long to wait for a server process to exit after initialization fails
+/// before treating the failure as a protocol error rather than an exit.
+const EXIT_GRACE_PERIOD: Duration = Duration::from_millis(500);
+
+/// Extra time `spawn_batch` allows on top of a server's init timeout, so the
+/// handshake diagnostics built by `spawn` win over the batch's own timeout.
+const INIT_DIAGNOSTICS_MARGIN: Duration = Duration::from_secs(2);
+
 /// State of an LSP server connection.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ServerState {
@@ -163,6 +185,12 @@ impl ServerInitResult {
     pub fn add_failure(&mut self, failure: ServerSpawnFailure) {
         self.failures.push(failure);
     }
+
+    /// Add the servers and failures of `other`.
+    pub fn merge(&mut self, other: Self) {
+        self.servers.extend(other.servers);
+        self.failures.extend(other.failures);
+    }
 }
 
 impl Default for ServerInitResult {
@@ -176,14 +204,24 @@ pub struct LspServer {
     client: LspClient,
     capabilities: ServerCapabilities,
     position_encoding: PositionEncodingKind,
+    /// `initialize` response as the server sent it, including fields
+    /// `lsp-types` does not model.
+    initialize_result: serde_json::Value,
+    /// Workarounds activated for this server.
+    quirks: Vec<Quirk>,
+    /// `serverInfo` of the `initialize` response, if the server sent it.
+    server_info: Option<ServerInfo>,
+    /// Names of the token types and modifiers in the server's semantic
+    /// tokens, if it provides them.
+    semantic_tokens_legend: Option<SemanticTokensLegend>,
     /// Receiver for push notifications from the LSP server.
     ///
     /// Extract this before registering the server to receive real-time
     /// notifications (e.g., `textDocument/publishDiagnostics`, `$/progress`).
     pub notification_rx: mpsc::Receiver<LspNotification>,
-    /// Child process handle. Kept alive for process lifetime management.
-    /// When dropped, the process is terminated via SIGKILL (`kill_on_drop`).
-    _child: tokio::process::Child,
+    /// Server process. Terminated and reaped when the server is shut down or
+    /// dropped, so no zombie processes are left behind.
+    process: ServerProcess,
 }
 
 impl std::fmt::Debug for LspServer {
@@ -192,8 +230,12 @@ impl std::fmt::Debug for LspServer {
             .field("client", &self.client)
             .field("capabilities", &self.capabilities)
             .field("position_encoding", &self.position_encoding)
+            .field("initialize_result", &self.initialize_result)
+            .field("quirks", &self.quirks)
+            .field("server_info", &self.server_info)
+            .field("semantic_tokens_legend", &self.semantic_tokens_legend)
             .field("notification_rx", &"<channel>")
-            .field("_child", &"<process>")
+            .field("process", &"<process>")
             .finish()
     }
 }
@@ -221,19 +263,32 @@ impl LspServer {
     ///
     /// Returns an error if:
     /// - Server process fails to spawn
-    /// - Initialize request fails or times out
-    /// - Server returns error during initialization
+    /// - Server exits before initializing ([`Error::ServerExited`])
+    /// - Server does not answer initialize in time
+    ///   ([`Error::InitializeTimeout`])
+    /// - Server answers initialize with an error
+    ///   ([`Error::InitializeRejected`])
     pub async fn spawn(config: ServerInitConfig) -> Result<Self> {
         info!(
             "Spawning LSP server: {} {:?}",
             config.server_config.command, config.server_config.args
         );
 
-        let mut child = Command::new(&config.server_config.command)
+        let mut command = Command::new(&config.server_config.command);
+        if config.server_config.isolates_env() {
+            command
+                .env_clear()
+                .envs(std::env::vars_os().filter(|(name, _)| {
+                    name.to_str()
+                        .is_some_and(|name| config.server_config.inherits_env(name))
+                }));
+        }
+        let mut child = command
+            .envs(&config.server_config.env)
             .args(&config.server_config.args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .stderr(Stdio::null())
+            .stderr(Stdio::piped())
             .kill_on_drop(true)
             .spawn()
             .map_err(|e| Error::ServerSpawnFailed {
@@ -241,33 +296,61 @@ impl LspServer {
                 source: e,
             })?;
 
-        let stdin = child
-            .stdin
-            .take()
-            .ok_or_else(|| Error::Transport("Failed to capture stdin".to_string()))?;
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| Error::Transport("Failed to capture stdout".to_string()))?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        // Wrap the child right away so it is reaped even if startup is
+        // abandoned part-way (e.g. the init timeout in `spawn_batch` fires).
+        let mut process = ServerProcess::new(child);
+
+        let stdin = stdin.ok_or_else(|| Error::Transport("Failed to capture stdin".to_string()))?;
+        let stdout =
+            stdout.ok_or_else(|| Error::Transport("Failed to capture stdout".to_string()))?;
 
-        let transport = LspTransport::new(stdin, stdout);
         let (notification_tx, notification_rx) = mpsc::channel(64);
+        let stderr_sink = StderrSink::open(&config.server_config.stderr, &notification_tx).await?;
+        let stderr_capture = stderr.map(|stderr| capture_stderr(stderr, stderr_sink));
+
+        let transport = LspTransport::new(stdin, stdout)
+            .with_max_message_size(config.server_config.max_message_size);
+        let stray_output = transport.stray_output();
         let client = LspClient::from_transport_with_notifications(
             config.server_config.clone(),
             transport,
             notification_tx,
         );
 
-        let (capabilities, position_encoding) = Self::initialize(&client, &config).await?;
+        let (capabilities, position_encoding, initialize_result, quirks) =
+            match Self::initialize(&client, &config).await {
+                Ok(negotiated) => negotiated,
+                Err(e) => {
+                    return Err(handshake_diagnostics(
+                        e,
+                        &mut process,
+                        stderr_capture,
+                        stray_output,
+                        &config.server_config,
+                    )
+                    .await);
+                }
+            };
 
         info!("LSP server initialized successfully");
 
+        let server_info = initialize_result
+            .get("serverInfo")
+            .and_then(|info| serde_json::from_value(info.clone()).ok());
+        let semantic_tokens_legend = semantic_tokens_legend(&capabilities);
         Ok(Self {
             client,
             capabilities,
             position_encoding,
+            initialize_result,
+            quirks,
+            server_info,
+            semantic_tokens_legend,
             notification_rx,
-            _child: child,
+            process,
         })
     }
 
@@ -278,28 +361,25 @@ impl LspServer {
     async fn initialize(
         client: &LspClient,
         config: &ServerInitConfig,
-    ) -> Result<(ServerCapabilities, PositionEncodingKind)> {
+    ) -> Result<(
+        ServerCapabilities,
+        PositionEncodingKind,
+        serde_json::Value,
+        Vec<Quirk>,
+    )> {
         debug!("Sending initialize request");
 
         let workspace_folders: Vec<WorkspaceFolder> = config
             .workspace_roots
             .iter()
             .map(|root| {
-                let path_str = root.to_str().ok_or_else(|| {
-                    let root_display = root.display();
-                    Error::InvalidUri(format!("Invalid UTF-8 in path: {root_display}"))
-                })?;
-                let uri_str = if cfg!(windows) {
-                    // Strip \\?\ extended-path prefix that canonicalize() adds on Windows.
-                    let stripped = path_str.strip_prefix(r"\\?\").unwrap_or(path_str);
-                    format!("file:///{}", stripped.replace('\\', "/"))
-                } else {
-                    format!("file://{path_str}")
-                };
-                let uri = Uri::from_str(&uri_str).map_err(|_| {
+                if root.to_str().is_none() {
                     let root_display = root.display();
-                    Error::InvalidUri(format!("Invalid workspace root: {root_display}"))
-                })?;
+                    return Err(Error::InvalidUri(format!(
+                        "Invalid UTF-8 in path: {root_display}"
+                    )));
+                }
+                let uri = path_to_uri(root);
                 Ok(WorkspaceFolder {
                     uri,
                     name: root
@@ -325,6 +405,14 @@ impl LspServer {
                     ..Default::default()
                 }),
                 text_document: Some(lsp_types::TextDocumentClientCapabilities {
+                    // `save_document` sends willSave / willSaveWaitUntil and
+                    // didSave like an editor does.
+                    synchronization: Some(lsp_types::TextDocumentSyncClientCapabilities {
+                        dynamic_registration: Some(false),
+                        will_save: Some(true),
+                        will_save_wait_until: Some(true),
+                        did_save: Some(true),
+                    }),
                     hover: Some(lsp_types::HoverClientCapabilities {
                         dynamic_registration: Some(false),
                         content_format: Some(vec![
@@ -366,10 +454,66 @@ impl LspServer {
                         }),
                         ..Default::default()
                     }),
+                    // `get_inlay_hints` resolves tooltips the server leaves
+                    // out of `textDocument/inlayHint` responses.
+                    inlay_hint: Some(lsp_types::InlayHintClientCapabilities {
+                        dynamic_registration: Some(false),
+                        resolve_support: Some(lsp_types::InlayHintResolveClientCapabilities {
+                            properties: vec!["tooltip".to_string()],
+                        }),
+                    }),
+                    completion: Some(completion_client_capabilities()),
+                    // `get_semantic_tokens` decodes full and range responses
+                    // with the legend the server sends back.
+                    semantic_tokens: Some(semantic_tokens_client_capabilities()),
+                    code_lens: Some(lsp_types::CodeLensClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    type_hierarchy: Some(lsp_types::TypeHierarchyClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    // `prepare_rename` needs servers to answer
+                    // `textDocument/prepareRename`.
+                    rename: Some(lsp_types::RenameClientCapabilities {
+                        dynamic_registration: Some(false),
+                        prepare_support: Some(true),
+                        ..Default::default()
+                    }),
+                    // `get_folding_ranges` reports whole lines, with the
+                    // kind and the text shown when folded.
+                    folding_range: Some(lsp_types::FoldingRangeClientCapabilities {
+                        line_folding_only: Some(true),
+                        folding_range_kind: Some(lsp_types::FoldingRangeKindCapability {
+                            value_set: Some(vec![
+                                lsp_types::FoldingRangeKind::Comment,
+                                lsp_types::FoldingRangeKind::Imports,
+                                lsp_types::FoldingRangeKind::Region,
+                            ]),
+                        }),
+                        folding_range: Some(lsp_types::FoldingRangeCapability {
+                            collapsed_text: Some(true),
+                        }),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                // `$/progress` tells when a server is done indexing, for
+                // `startup_grace_seconds`.
+                window: Some(lsp_types::WindowClientCapabilities {
+                    work_done_progress: Some(true),
                     ..Default::default()
                 }),
                 workspace: Some(lsp_types::WorkspaceClientCapabilities {
                     workspace_folders: Some(true),
+                    symbol: Some(lsp_types::WorkspaceSymbolClientCapabilities {
+                        dynamic_registration: Some(false),
+                        // Servers may return URI-only locations that we fill
+                        // in via `workspaceSymbol/resolve`.
+                        resolve_support: Some(lsp_types::WorkspaceSymbolResolveSupportCapability {
+                            properties: vec!["location.range".to_string()],
+                        }),
+                        ..Default::default()
+                    }),
                     ..Default::default()
                 }),
                 ..Default::default()
@@ -379,21 +523,34 @@ impl LspServer {
                 version: Some(env!("CARGO_PKG_VERSION").to_string()),
             }),
             workspace_folders: Some(workspace_folders),
+            trace: trace_value(config.server_config.trace),
             ..Default::default()
         };
+        let mut params = serde_json::to_value(params)?;
+        if config.server_config.inline_completions {
+            advertise_inline_completions(&mut params);
+        }
 
-        // Use the server's configured timeout for the initialize handshake too,
-        // not a hardcoded 30s: large solutions (e.g. a 130-project Unity .sln via
+        // Use the server's configured init timeout for the handshake, not a
+        // hardcoded 30s: large solutions (e.g. a 130-project Unity .sln via
         // OmniSharp) take minutes to respond to `initialize`.
-        let result: InitializeResult = client
+        let raw_result: serde_json::Value = client
             .request(
                 "initialize",
                 params,
-                Duration::from_secs(config.server_config.timeout_seconds),
+                Duration::from_secs(quirks::init_timeout(&config.server_config)),
             )
             .await
-            .map_err(|e| Error::LspInitFailed {
-                message: format!("Initialize request failed: {e}"),
+            .map_err(|e| match e {
+                // Classified by `handshake_diagnostics`.
+                Error::Timeout(_) | Error::LspServerError { .. } => e,
+                e => Error::LspInitFailed {
+                    message: format!("Initialize request failed: {e}"),
+                },
+            })?;
+        let result: InitializeResult =
+            serde_json::from_value(raw_result.clone()).map_err(|e| Error::LspInitFailed {
+                message: format!("Invalid initialize response: {e}"),
             })?;
 
         let position_encoding = result
@@ -414,7 +571,38 @@ impl LspServer {
                 message: format!("Initialized notification failed: {e}"),
             })?;
 
-        Ok((result.capabilities, position_encoding))
+        // Servers that ignore the `trace` of `initialize` pick it up here.
+        if let Some(value) = trace_value(config.server_config.trace) {
+            client
+                .notify("$/setTrace", SetTraceParams { value })
+                .await
+                .map_err(|e| Error::LspInitFailed {
+                    message: format!("$/setTrace notification failed: {e}"),
+                })?;
+        }
+
+        let quirks = quirks::detect(&config.server_config, &raw_result);
+        if !quirks.is_empty() {
+            let names: Vec<_> = quirks.iter().map(|quirk| quirk.name()).collect();
+            info!("Applying server quirks: {}", names.join(", "));
+        }
+        if quirks.contains(&Quirk::ConfigurationAfterInit) {
+            let settings = config
+                .initialization_options
+                .clone()
+                .unwrap_or(serde_json::Value::Null);
+            client
+                .notify(
+                    "workspace/didChangeConfiguration",
+                    lsp_types::DidChangeConfigurationParams { settings },
+                )
+                .await
+                .map_err(|e| Error::LspInitFailed {
+                    message: format!("Configuration notification failed: {e}"),
+                })?;
+        }
+
+        Ok((result.capabilities, position_encoding, raw_result, quirks))
     }
 
     /// Get server capabilities.
@@ -423,6 +611,30 @@ impl LspServer {
         &self.capabilities
     }
 
+    /// Get the server's `initialize` response as it was received.
+    #[must_use]
+    pub const fn initialize_result(&self) -> &serde_json::Value {
+        &self.initialize_result
+    }
+
+    /// Get the name and version the server reported in `initialize`.
+    #[must_use]
+    pub const fn server_info(&self) -> Option<&ServerInfo> {
+        self.server_info.as_ref()
+    }
+
+    /// Get the legend of the server's semantic tokens, if it provides them.
+    #[must_use]
+    pub const fn semantic_tokens_legend(&self) -> Option<&SemanticTokensLegend> {
+        self.semantic_tokens_legend.as_ref()
+    }
+
+    /// Get the workarounds activated for this server.
+    #[must_use]
+    pub fn quirks(&self) -> &[Quirk] {
+        &self.quirks
+    }
+
     /// Get negotiated position encoding.
     #[must_use]
     pub fn position_encoding(&self) -> PositionEncodingKind {
@@ -438,6 +650,9 @@ impl LspServer {
     /// Shutdown server gracefully.
     ///
     /// Sends shutdown request, waits for response, then sends exit notification.
+    /// The process is then given [`EXIT_GRACE_PERIOD`] to exit on its own before
+    /// it is killed, and is always reaped. If the sequence fails part-way, the
+    /// process is killed and reaped in the background instead.
     ///
     /// # Errors
     ///
@@ -445,14 +660,19 @@ impl LspServer {
     pub async fn shutdown(self) -> Result<()> {
         debug!("Shutting down LSP server");
 
-        let _: serde_json::Value = self
-            .client
+        let Self {
+            client, process, ..
+        } = self;
+
+        let _: serde_json::Value = client
             .request("shutdown", serde_json::Value::Null, Duration::from_secs(5))
             .await?;
 
-        self.client.notify("exit", serde_json::Value::Null).await?;
+        client.notify("exit", serde_json::Value::Null).await?;
+
+        client.shutdown().await?;
 
-        self.client.shutdown().await?;
+        process.wait_or_kill(EXIT_GRACE_PERIOD).await;
 
         info!("LSP server shut down successfully");
         Ok(())
@@ -467,8 +687,12 @@ impl LspServer {
     ///
     /// # Behavior
     ///
-    /// - Attempts to spawn each server sequentially
+    /// - Spawns and initializes all servers concurrently
+    /// - Bounds each server by its own init timeout
+    ///   ([`LspServerConfig::init_timeout`]), so one hung server cannot
+    ///   delay the others
     /// - Logs success (info) and failure (error) for each server
+    /// - Reports failures in the same order as `configs`
     /// - Accumulates successful servers and failures
     /// - Never panics or returns early - attempts all servers
     ///
@@ -508,36 +732,454 @@ impl LspServer {
     /// ```
     pub async fn spawn_batch(configs: &[ServerInitConfig]) -> ServerInitResult {
         let mut result = ServerInitResult::new();
+        for attempt in futures::future::join_all(configs.iter().map(Self::spawn_attempt)).await {
+            result.merge(attempt);
+        }
+        result
+    }
+
+    /// Spawn and initialize multiple LSP servers concurrently, yielding the
+    /// outcome of each as soon as it is known, with one server or one
+    /// failure per item.
+    ///
+    /// Unlike [`Self::spawn_batch`], a server that is slow to initialize
+    /// does not hold back the others.
+    pub fn spawn_each(
+        configs: &[ServerInitConfig],
+    ) -> impl Stream<Item = ServerInitResult> + Send + Unpin + '_ {
+        configs
+            .iter()
+            .map(Self::spawn_attempt)
+            .collect::<FuturesUnordered<_>>()
+    }
+
+    /// Spawn one server within its init timeout.
+    async fn spawn_attempt(config: &ServerInitConfig) -> ServerInitResult {
+        let timeout_secs = quirks::init_timeout(&config.server_config);
+        let outcome = tokio::time::timeout(
+            Duration::from_secs(timeout_secs) + INIT_DIAGNOSTICS_MARGIN,
+            Self::spawn(config.clone()),
+        )
+        .await
+        .unwrap_or(Err(Error::Timeout(timeout_secs)));
+
+        let language_id = config.server_config.language_id.clone();
+        let command = config.server_config.command.clone();
+        let mut result = ServerInitResult::new();
+        match outcome {
+            Ok(server) => {
+                info!(
+                    "Successfully spawned LSP server: {} ({})",
+                    language_id, command
+                );
+                result.add_server(language_id, server);
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to spawn LSP server: {} ({}): {}",
+                    language_id,
+                    command,
+                    e
+                );
+                result.add_failure(ServerSpawnFailure {
+                    language_id,
+                    command,
+                    message: e.to_string(),
+                });
+            }
+        }
+        result
+    }
+}
+
+/// Owned LSP server process that is always reaped.
+///
+/// `kill_on_drop` alone only sends SIGKILL; the exit status is collected by
+/// tokio on a best-effort basis, so repeatedly restarted servers can pile up
+/// as zombies. Dropping a `ServerProcess` kills the child and awaits it in a
+/// background task instead.
+struct ServerProcess {
+    child: Option<Child>,
+}
+
+impl ServerProcess {
+    const fn new(child: Child) -> Self {
+        Self { child: Some(child) }
+    }
+
+    /// Wait up to `grace` for the process to exit, then kill it. Either way
+    /// the exit status is collected before returning.
+    async fn wait_or_kill(mut self, grace: Duration) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
 
-        for config in configs {
-            let language_id = config.server_config.language_id.clone();
-            let command = config.server_config.command.clone();
-
-            match Self::spawn(config.clone()).await {
-                Ok(server) => {
-                    info!(
-                        "Successfully spawned LSP server: {} ({})",
-                        language_id, command
-                    );
-                    result.add_server(language_id, server);
+        match tokio::time::timeout(grace, child.wait()).await {
+            Ok(Ok(status)) => debug!("LSP server process exited: {}", status),
+            Ok(Err(e)) => warn!("Failed to wait for LSP server process: {}", e),
+            Err(_) => {
+                debug!("LSP server process did not exit in time, killing it");
+                // `kill` fails if the process exited between the timeout and
+                // now; the status has been collected by then, so ignore it.
+                if let Err(e) = child.kill().await {
+                    debug!("Failed to kill LSP server process: {}", e);
                 }
-                Err(e) => {
-                    tracing::error!(
-                        "Failed to spawn LSP server: {} ({}): {}",
-                        language_id,
-                        command,
-                        e
-                    );
-                    result.add_failure(ServerSpawnFailure {
-                        language_id,
-                        command,
-                        message: e.to_string(),
-                    });
+            }
+        }
+    }
+
+    /// Wait up to `grace` for the process to exit on its own.
+    ///
+    /// Returns `None` if it is still running.
+    async fn wait_for_exit(&mut self, grace: Duration) -> Option<std::process::ExitStatus> {
+        let child = self.child.as_mut()?;
+        tokio::time::timeout(grace, child.wait()).await.ok()?.ok()
+    }
+
+    /// Kill the process and collect its exit status.
+    async fn kill(&mut self) {
+        if let Some(child) = self.child.as_mut()
+            && let Err(e) = child.kill().await
+        {
+            debug!("Failed to kill LSP server process: {}", e);
+        }
+    }
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let Some(mut child) = self.child.take() else {
+            return;
+        };
+
+        // The process may already have exited; a failed kill is not an error.
+        let _ = child.start_kill();
+
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                let _ = child.wait().await;
+            });
+        }
+        // Without a runtime, dropping `child` falls back to tokio's own
+        // best-effort orphan reaping via `kill_on_drop`.
+    }
+}
+
+/// LSP trace setting for a configured [`TraceMode`]; `None` when tracing is
+/// off, so nothing is sent.
+const fn trace_value(mode: TraceMode) -> Option<TraceValue> {
+    match mode {
+        TraceMode::Off => None,
+        TraceMode::Messages => Some(TraceValue::Messages),
+        TraceMode::Verbose => Some(TraceValue::Verbose),
+    }
+}
+
+/// Completion support advertised to servers. Documentation, details, and
+/// import edits may be left out of completion items, since `get_completions`
+/// fills them in with `completionItem/resolve` when asked to.
+fn completion_client_capabilities() -> lsp_types::CompletionClientCapabilities {
+    lsp_types::CompletionClientCapabilities {
+        dynamic_registration: Some(false),
+        completion_item: Some(lsp_types::CompletionItemCapability {
+            documentation_format: Some(vec![
+                lsp_types::MarkupKind::Markdown,
+                lsp_types::MarkupKind::PlainText,
+            ]),
+            resolve_support: Some(lsp_types::CompletionItemCapabilityResolveSupport {
+                properties: vec![
+                    "documentation".to_string(),
+                    "detail".to_string(),
+                    "additionalTextEdits".to_string(),
+                ],
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Semantic tokens support advertised to servers: full and range requests
+/// in the relative format, with the standard token types and modifiers.
+fn semantic_tokens_client_capabilities() -> lsp_types::SemanticTokensClientCapabilities {
+    lsp_types::SemanticTokensClientCapabilities {
+        dynamic_registration: Some(false),
+        requests: lsp_types::SemanticTokensClientCapabilitiesRequests {
+            range: Some(true),
+            full: Some(lsp_types::SemanticTokensFullOptions::Bool(true)),
+        },
+        token_types: vec![
+            SemanticTokenType::NAMESPACE,
+            SemanticTokenType::TYPE,
+            SemanticTokenType::CLASS,
+            SemanticTokenType::ENUM,
+            SemanticTokenType::INTERFACE,
+            SemanticTokenType::STRUCT,
+            SemanticTokenType::TYPE_PARAMETER,
+            SemanticTokenType::PARAMETER,
+            SemanticTokenType::VARIABLE,
+            SemanticTokenType::PROPERTY,
+            SemanticTokenType::ENUM_MEMBER,
+            SemanticTokenType::EVENT,
+            SemanticTokenType::FUNCTION,
+            SemanticTokenType::METHOD,
+            SemanticTokenType::MACRO,
+            SemanticTokenType::KEYWORD,
+            SemanticTokenType::MODIFIER,
+            SemanticTokenType::COMMENT,
+            SemanticTokenType::STRING,
+            SemanticTokenType::NUMBER,
+            SemanticTokenType::REGEXP,
+            SemanticTokenType::OPERATOR,
+            SemanticTokenType::DECORATOR,
+        ],
+        token_modifiers: vec![
+            SemanticTokenModifier::DECLARATION,
+            SemanticTokenModifier::DEFINITION,
+            SemanticTokenModifier::READONLY,
+            SemanticTokenModifier::STATIC,
+            SemanticTokenModifier::DEPRECATED,
+            SemanticTokenModifier::ABSTRACT,
+            SemanticTokenModifier::ASYNC,
+            SemanticTokenModifier::MODIFICATION,
+            SemanticTokenModifier::DOCUMENTATION,
+            SemanticTokenModifier::DEFAULT_LIBRARY,
+        ],
+        formats: vec![lsp_types::TokenFormat::RELATIVE],
+        overlapping_token_support: Some(false),
+        multiline_token_support: Some(false),
+        server_cancel_support: Some(false),
+        augments_syntax_tokens: Some(true),
+    }
+}
+
+/// Legend of the semantic tokens a server advertised, if any.
+fn semantic_tokens_legend(capabilities: &ServerCapabilities) -> Option<SemanticTokensLegend> {
+    let options = match capabilities.semantic_tokens_provider.as_ref()? {
+        SemanticTokensServerCapabilities::SemanticTokensOptions(options) => options,
+        SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(registration) => {
+            &registration.semantic_tokens_options
+        }
+    };
+    Some(options.legend.clone())
+}
+
+/// Destination for a server's stderr output, resolved from [`StderrMode`].
+enum StderrSink {
+    Discard,
+    Log {
+        notification_tx: mpsc::Sender<LspNotification>,
+        line: Vec<u8>,
+    },
+    File(tokio::fs::File),
+    Inherit(tokio::io::Stderr),
+}
+
+impl StderrSink {
+    async fn open(
+        mode: &StderrMode,
+        notification_tx: &mpsc::Sender<LspNotification>,
+    ) -> Result<Self> {
+        Ok(match mode {
+            StderrMode::Discard => Self::Discard,
+            StderrMode::Log => Self::Log {
+                notification_tx: notification_tx.clone(),
+                line: Vec::new(),
+            },
+            StderrMode::File(path) => {
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|e| Error::FileIo {
+                        path: path.clone(),
+                        source: e,
+                    })?;
+                Self::File(file)
+            }
+            StderrMode::Inherit => Self::Inherit(tokio::io::stderr()),
+        })
+    }
+
+    async fn write(&mut self, chunk: &[u8]) {
+        match self {
+            Self::Discard => {}
+            Self::Log {
+                notification_tx,
+                line,
+            } => {
+                for &byte in chunk {
+                    if byte == b'\n' || line.len() >= STDERR_LOG_LINE_LIMIT {
+                        Self::emit_log_line(notification_tx, line);
+                    }
+                    if byte != b'\n' {
+                        line.push(byte);
+                    }
                 }
             }
+            // Write failures must not stop the pipe from being drained.
+            Self::File(file) => {
+                let _ = file.write_all(chunk).await;
+            }
+            Self::Inherit(stderr) => {
+                let _ = stderr.write_all(chunk).await;
+            }
         }
+    }
 
-        result
+    async fn finish(&mut self) {
+        match self {
+            Self::Log {
+                notification_tx,
+                line,
+            } => Self::emit_log_line(notification_tx, line),
+            Self::File(file) => {
+                let _ = file.flush().await;
+            }
+            Self::Inherit(stderr) => {
+                let _ = stderr.flush().await;
+            }
+            Self::Discard => {}
+        }
+    }
+
+    /// Forward a buffered line to the log buffer and clear it.
+    ///
+    /// Uses `try_send` so a full notification channel (e.g. before the
+    /// server is registered and its pump started) drops the line instead of
+    /// blocking the stderr pipe.
+    fn emit_log_line(notification_tx: &mpsc::Sender<LspNotification>, line: &mut Vec<u8>) {
+        let text = String::from_utf8_lossy(line).trim_end().to_string();
+        line.clear();
+        if text.is_empty() {
+            return;
+        }
+        let _ = notification_tx.try_send(LspNotification::LogMessage(LogMessageParams {
+            typ: MessageType::LOG,
+            message: text,
+        }));
+    }
+}
+
+/// Leading portion of a server's stderr, filled in by [`capture_stderr`].
+struct StderrCapture {
+    captured: Arc<Mutex<Vec<u8>>>,
+    task: JoinHandle<()>,
+}
+
+impl StderrCapture {
+    /// Wait up to [`EXIT_GRACE_PERIOD`] for the process to close its stderr,
+    /// then return what was captured.
+    ///
+    /// The wait is bounded because a child of the server (e.g. one started
+    /// by a wrapper script) may keep the pipe open after the server is gone.
+    async fn finish(self) -> String {
+        let _ = tokio::time::timeout(EXIT_GRACE_PERIOD, self.task).await;
+        let captured = self.captured.lock().unwrap_or_else(PoisonError::into_inner);
+        String::from_utf8_lossy(&captured).into_owned()
+    }
+}
+
+/// Drain a server's stderr into `sink`, keeping the first
+/// [`STDERR_CAPTURE_LIMIT`] bytes for startup diagnostics.
+///
+/// The pipe is read until EOF so a chatty server never blocks on a full pipe
+/// buffer; the task finishes when the process closes its stderr.
+fn capture_stderr(mut stderr: ChildStderr, mut sink: StderrSink) -> StderrCapture {
+    let captured = Arc::new(Mutex::new(Vec::with_capacity(STDERR_CAPTURE_LIMIT)));
+    let buffer = Arc::clone(&captured);
+    let task = tokio::spawn(async move {
+        let mut buf = [0u8; 4096];
+        while let Ok(n) = stderr.read(&mut buf).await {
+            if n == 0 {
+                break;
+            }
+            {
+                let mut captured = buffer.lock().unwrap_or_else(PoisonError::into_inner);
+                let keep = n.min(STDERR_CAPTURE_LIMIT - captured.len());
+                captured.extend_from_slice(&buf[..keep]);
+            }
+            sink.write(&buf[..n]).await;
+        }
+        sink.finish().await;
+    });
+    StderrCapture { captured, task }
+}
+
+/// Explain why the `initialize` handshake failed.
+///
+/// Distinguishes a server that answered with an error, one that exited, and
+/// one that kept running without answering; the latter two carry the
+/// captured stderr and any stdout printed before the handshake. Other
+/// errors are returned unchanged.
+async fn handshake_diagnostics(
+    error: Error,
+    process: &mut ServerProcess,
+    stderr_capture: Option<StderrCapture>,
+    stray_output: StrayOutput,
+    server_config: &LspServerConfig,
+) -> Error {
+    let command = server_config.command.clone();
+    let timed_out = match &error {
+        Error::LspServerError { code, message, .. } => {
+            return Error::InitializeRejected {
+                command,
+                code: *code,
+                message: message.clone(),
+            };
+        }
+        Error::Timeout(_) => true,
+        _ => false,
+    };
+
+    // After a timeout the process has had the whole init timeout to exit.
+    let grace = if timed_out {
+        Duration::ZERO
+    } else {
+        EXIT_GRACE_PERIOD
+    };
+    if let Some(status) = process.wait_for_exit(grace).await {
+        return Error::ServerExited {
+            command,
+            exit_code: status.code(),
+            stderr: collect_stderr(stderr_capture).await,
+            stdout: stray_output.finish(EXIT_GRACE_PERIOD).await,
+        };
+    }
+    if !timed_out {
+        return error;
+    }
+
+    // Kill the hung server so its stderr closes and the capture completes.
+    process.kill().await;
+    Error::InitializeTimeout {
+        command,
+        timeout_secs: quirks::init_timeout(server_config),
+        stderr: collect_stderr(stderr_capture).await,
+        stdout: stray_output.snapshot(),
+    }
+}
+
+/// Stderr captured from a stopped process, if it was captured at all.
+async fn collect_stderr(stderr_capture: Option<StderrCapture>) -> String {
+    match stderr_capture {
+        Some(capture) => capture.finish().await,
+        None => String::new(),
+    }
+}
+
+/// Add the proposed `textDocument.inlineCompletion` client capability
+/// (LSP 3.18), which `lsp-types` only models behind its `proposed` feature.
+fn advertise_inline_completions(params: &mut serde_json::Value) {
+    if let Some(text_document) = params
+        .pointer_mut("/capabilities/textDocument")
+        .and_then(serde_json::Value::as_object_mut)
+    {
+        text_document.insert(
+            "inlineCompletion".to_string(),
+            serde_json::json!({ "dynamicRegistration": false }),
+        );
     }
 }
 
@@ -545,6 +1187,39 @@ impl LspServer {
 #[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
+    use crate::config::{CompanionFiles, DEFAULT_MAX_MESSAGE_SIZE};
+
+    #[test]
+    fn test_advertise_inline_completions() {
+        let mut params = serde_json::json!({
+            "capabilities": { "textDocument": { "hover": {} } }
+        });
+        advertise_inline_completions(&mut params);
+        assert_eq!(
+            params["capabilities"]["textDocument"]["inlineCompletion"],
+            serde_json::json!({ "dynamicRegistration": false })
+        );
+        assert!(params["capabilities"]["textDocument"]["hover"].is_object());
+    }
+
+    #[test]
+    fn test_semantic_tokens_legend() {
+        let mut capabilities = ServerCapabilities::default();
+        assert!(semantic_tokens_legend(&capabilities).is_none());
+
+        let legend = SemanticTokensLegend {
+            token_types: vec![SemanticTokenType::FUNCTION],
+            token_modifiers: vec![SemanticTokenModifier::STATIC],
+        };
+        capabilities.semantic_tokens_provider =
+            Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+                lsp_types::SemanticTokensOptions {
+                    legend: legend.clone(),
+                    ..Default::default()
+                },
+            ));
+        assert_eq!(semantic_tokens_legend(&capabilities), Some(legend));
+    }
 
     #[test]
     fn test_server_state_ready() {
@@ -649,9 +1324,21 @@ mod tests {
                 command: "pyright-langserver".to_string(),
                 args: vec!["--stdio".to_string()],
                 env,
+                env_allow: None,
+                env_deny: Vec::new(),
                 file_patterns: vec!["**/*.py".to_string()],
                 initialization_options: Some(init_opts.clone()),
                 timeout_seconds: 10,
+                init_timeout_seconds: None,
+                startup_grace_seconds: None,
+                max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                stderr: StderrMode::Discard,
+                trace: TraceMode::Off,
+                inline_completions: false,
+                companion_files: CompanionFiles::None,
+                visible_paths: Vec::new(),
+                writable_paths: Vec::new(),
+                index_export: None,
                 heuristics: None,
             },
             workspace_roots: vec![PathBuf::from("/workspace")],
@@ -726,8 +1413,12 @@ mod tests {
             client,
             capabilities: ServerCapabilities::default(),
             position_encoding: PositionEncodingKind::UTF8,
+            initialize_result: serde_json::Value::Null,
+            quirks: Vec::new(),
+            server_info: None,
+            semantic_tokens_legend: None,
             notification_rx: mock_notification_rx,
-            _child: mock_child,
+            process: ServerProcess::new(mock_child),
         };
 
         assert_eq!(server.position_encoding(), PositionEncodingKind::UTF8);
@@ -814,8 +1505,12 @@ mod tests {
             client: client1,
             capabilities: lsp_types::ServerCapabilities::default(),
             position_encoding: PositionEncodingKind::UTF8,
+            initialize_result: serde_json::Value::Null,
+            quirks: Vec::new(),
+            server_info: None,
+            semantic_tokens_legend: None,
             notification_rx: mock_notification_rx1,
-            _child: mock_child1,
+            process: ServerProcess::new(mock_child1),
         };
 
         result.add_server("rust".to_string(), server1);
@@ -862,8 +1557,12 @@ mod tests {
             client,
             capabilities: lsp_types::ServerCapabilities::default(),
             position_encoding: PositionEncodingKind::UTF8,
+            initialize_result: serde_json::Value::Null,
+            quirks: Vec::new(),
+            server_info: None,
+            semantic_tokens_legend: None,
             notification_rx: mock_notification_rx,
-            _child: mock_child,
+            process: ServerProcess::new(mock_child),
         };
 
         result.add_server("rust".to_string(), server);
@@ -924,8 +1623,12 @@ mod tests {
                 client,
                 capabilities: lsp_types::ServerCapabilities::default(),
                 position_encoding: PositionEncodingKind::UTF8,
+                initialize_result: serde_json::Value::Null,
+                quirks: Vec::new(),
+                server_info: None,
+                semantic_tokens_legend: None,
                 notification_rx: mock_notification_rx,
-                _child: mock_child,
+                process: ServerProcess::new(mock_child),
             };
 
             result.add_server(config.language_id, server);
@@ -973,8 +1676,12 @@ mod tests {
             client: client1,
             capabilities: lsp_types::ServerCapabilities::default(),
             position_encoding: PositionEncodingKind::UTF8,
+            initialize_result: serde_json::Value::Null,
+            quirks: Vec::new(),
+            server_info: None,
+            semantic_tokens_legend: None,
             notification_rx: mock_notification_rx1,
-            _child: mock_child1,
+            process: ServerProcess::new(mock_child1),
         };
 
         result.add_server("rust".to_string(), server1);
@@ -1011,8 +1718,12 @@ mod tests {
             client: client2,
             capabilities: lsp_types::ServerCapabilities::default(),
             position_encoding: PositionEncodingKind::UTF16,
+            initialize_result: serde_json::Value::Null,
+            quirks: Vec::new(),
+            server_info: None,
+            semantic_tokens_legend: None,
             notification_rx: mock_notification_rx2,
-            _child: mock_child2,
+            process: ServerProcess::new(mock_child2),
         };
 
         result.add_server("rust".to_string(), server2);
@@ -1075,9 +1786,21 @@ mod tests {
                 command: "nonexistent-command-12345".to_string(),
                 args: vec![],
                 env: std::collections::HashMap::new(),
+                env_allow: None,
+                env_deny: Vec::new(),
                 file_patterns: vec!["**/*.rs".to_string()],
                 initialization_options: None,
                 timeout_seconds: 10,
+                init_timeout_seconds: None,
+                startup_grace_seconds: None,
+                max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                stderr: StderrMode::Discard,
+                trace: TraceMode::Off,
+                inline_completions: false,
+                companion_files: CompanionFiles::None,
+                visible_paths: Vec::new(),
+                writable_paths: Vec::new(),
+                index_export: None,
                 heuristics: None,
             },
             workspace_roots: vec![],
@@ -1108,9 +1831,21 @@ mod tests {
                     command: "nonexistent-rust-analyzer".to_string(),
                     args: vec![],
                     env: std::collections::HashMap::new(),
+                    env_allow: None,
+                    env_deny: Vec::new(),
                     file_patterns: vec!["**/*.rs".to_string()],
                     initialization_options: None,
                     timeout_seconds: 10,
+                    init_timeout_seconds: None,
+                    startup_grace_seconds: None,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    stderr: StderrMode::Discard,
+                    trace: TraceMode::Off,
+                    inline_completions: false,
+                    companion_files: CompanionFiles::None,
+                    visible_paths: Vec::new(),
+                    writable_paths: Vec::new(),
+                    index_export: None,
                     heuristics: None,
                 },
                 workspace_roots: vec![],
@@ -1123,9 +1858,21 @@ mod tests {
                     command: "nonexistent-pyright".to_string(),
                     args: vec![],
                     env: std::collections::HashMap::new(),
+                    env_allow: None,
+                    env_deny: Vec::new(),
                     file_patterns: vec!["**/*.py".to_string()],
                     initialization_options: None,
                     timeout_seconds: 10,
+                    init_timeout_seconds: None,
+                    startup_grace_seconds: None,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    stderr: StderrMode::Discard,
+                    trace: TraceMode::Off,
+                    inline_completions: false,
+                    companion_files: CompanionFiles::None,
+                    visible_paths: Vec::new(),
+                    writable_paths: Vec::new(),
+                    index_export: None,
                     heuristics: None,
                 },
                 workspace_roots: vec![],
@@ -1138,9 +1885,21 @@ mod tests {
                     command: "nonexistent-tsserver".to_string(),
                     args: vec![],
                     env: std::collections::HashMap::new(),
+                    env_allow: None,
+                    env_deny: Vec::new(),
                     file_patterns: vec!["**/*.ts".to_string()],
                     initialization_options: None,
                     timeout_seconds: 10,
+                    init_timeout_seconds: None,
+                    startup_grace_seconds: None,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    stderr: StderrMode::Discard,
+                    trace: TraceMode::Off,
+                    inline_completions: false,
+                    companion_files: CompanionFiles::None,
+                    visible_paths: Vec::new(),
+                    writable_paths: Vec::new(),
+                    index_export: None,
                     heuristics: None,
                 },
                 workspace_roots: vec![],
@@ -1176,9 +1935,21 @@ mod tests {
                     command: "cmd1-nonexistent".to_string(),
                     args: vec![],
                     env: std::collections::HashMap::new(),
+                    env_allow: None,
+                    env_deny: Vec::new(),
                     file_patterns: vec![],
                     initialization_options: None,
                     timeout_seconds: 10,
+                    init_timeout_seconds: None,
+                    startup_grace_seconds: None,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    stderr: StderrMode::Discard,
+                    trace: TraceMode::Off,
+                    inline_completions: false,
+                    companion_files: CompanionFiles::None,
+                    visible_paths: Vec::new(),
+                    writable_paths: Vec::new(),
+                    index_export: None,
                     heuristics: None,
                 },
                 workspace_roots: vec![],
@@ -1191,9 +1962,21 @@ mod tests {
                     command: "cmd2-nonexistent".to_string(),
                     args: vec![],
                     env: std::collections::HashMap::new(),
+                    env_allow: None,
+                    env_deny: Vec::new(),
                     file_patterns: vec![],
                     initialization_options: None,
                     timeout_seconds: 10,
+                    init_timeout_seconds: None,
+                    startup_grace_seconds: None,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    stderr: StderrMode::Discard,
+                    trace: TraceMode::Off,
+                    inline_completions: false,
+                    companion_files: CompanionFiles::None,
+                    visible_paths: Vec::new(),
+                    writable_paths: Vec::new(),
+                    index_export: None,
                     heuristics: None,
                 },
                 workspace_roots: vec![],
@@ -1222,9 +2005,21 @@ mod tests {
                     command: "nonexistent-test1".to_string(),
                     args: vec![],
                     env: std::collections::HashMap::new(),
+                    env_allow: None,
+                    env_deny: Vec::new(),
                     file_patterns: vec![],
                     initialization_options: None,
                     timeout_seconds: 10,
+                    init_timeout_seconds: None,
+                    startup_grace_seconds: None,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    stderr: StderrMode::Discard,
+                    trace: TraceMode::Off,
+                    inline_completions: false,
+                    companion_files: CompanionFiles::None,
+                    visible_paths: Vec::new(),
+                    writable_paths: Vec::new(),
+                    index_export: None,
                     heuristics: None,
                 },
                 workspace_roots: vec![],
@@ -1237,9 +2032,21 @@ mod tests {
                     command: "nonexistent-test2".to_string(),
                     args: vec![],
                     env: std::collections::HashMap::new(),
+                    env_allow: None,
+                    env_deny: Vec::new(),
                     file_patterns: vec![],
                     initialization_options: None,
                     timeout_seconds: 10,
+                    init_timeout_seconds: None,
+                    startup_grace_seconds: None,
+                    max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                    stderr: StderrMode::Discard,
+                    trace: TraceMode::Off,
+                    inline_completions: false,
+                    companion_files: CompanionFiles::None,
+                    visible_paths: Vec::new(),
+                    writable_paths: Vec::new(),
+                    index_export: None,
                     heuristics: None,
                 },
                 workspace_roots: vec![],
@@ -1254,4 +2061,349 @@ mod tests {
         assert_eq!(result.failures[0].language_id, "test1");
         assert_eq!(result.failures[1].language_id, "test2");
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_batch_init_timeout_runs_concurrently() {
+        let hung_server = |language_id: &str| ServerInitConfig {
+            server_config: LspServerConfig {
+                language_id: language_id.to_string(),
+                command: "sleep".to_string(),
+                args: vec!["30".to_string()],
+                env: std::collections::HashMap::new(),
+                env_allow: None,
+                env_deny: Vec::new(),
+                file_patterns: vec![],
+                initialization_options: None,
+                timeout_seconds: 10,
+                init_timeout_seconds: Some(1),
+                startup_grace_seconds: None,
+                max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+                stderr: StderrMode::Discard,
+                trace: TraceMode::Off,
+                inline_completions: false,
+                companion_files: CompanionFiles::None,
+                visible_paths: Vec::new(),
+                writable_paths: Vec::new(),
+                index_export: None,
+                heuristics: None,
+            },
+            workspace_roots: vec![],
+            initialization_options: None,
+            notification_tx: None,
+        };
+        let configs = vec![hung_server("hung1"), hung_server("hung2")];
+
+        let started = std::time::Instant::now();
+        let result = LspServer::spawn_batch(&configs).await;
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(result.failure_count(), 2);
+        assert_eq!(result.failures[0].language_id, "hung1");
+        assert_eq!(result.failures[1].language_id, "hung2");
+        assert!(result.failures[0].message.contains("timed out"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_reports_exit_code_and_stderr() {
+        let mut server_config = LspServerConfig::rust_analyzer();
+        server_config.command = "sh".to_string();
+        server_config.args = vec![
+            "-c".to_string(),
+            "echo 'server v1.0'; echo 'error: toolchain not installed' >&2; exit 3".to_string(),
+        ];
+        let config = ServerInitConfig {
+            server_config,
+            workspace_roots: vec![],
+            initialization_options: None,
+            notification_tx: None,
+        };
+
+        let err = LspServer::spawn(config).await.unwrap_err();
+
+        match err {
+            Error::ServerExited {
+                command,
+                exit_code,
+                stderr,
+                stdout,
+            } => {
+                assert_eq!(command, "sh");
+                assert_eq!(exit_code, Some(3));
+                assert!(stderr.contains("toolchain not installed"));
+                assert_eq!(stdout, "server v1.0\n");
+            }
+            other => panic!("Expected ServerExited, got {other:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_isolates_environment() {
+        let mut server_config = LspServerConfig::rust_analyzer();
+        server_config.command = "sh".to_string();
+        server_config.args = vec![
+            "-c".to_string(),
+            "echo \"[$HOME][$MCPLS_TEST_VAR]\" >&2; exit 1".to_string(),
+        ];
+        server_config.env_allow = Some(vec!["PATH".to_string()]);
+        server_config
+            .env
+            .insert("MCPLS_TEST_VAR".to_string(), "set".to_string());
+        let config = ServerInitConfig {
+            server_config,
+            workspace_roots: vec![],
+            initialization_options: None,
+            notification_tx: None,
+        };
+
+        let err = LspServer::spawn(config).await.unwrap_err();
+
+        let Error::ServerExited { stderr, .. } = err else {
+            panic!("Expected ServerExited, got {err:?}");
+        };
+        assert_eq!(stderr, "[][set]\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_reports_initialize_timeout_with_output() {
+        let mut server_config = LspServerConfig::rust_analyzer();
+        server_config.command = "sh".to_string();
+        server_config.args = vec![
+            "-c".to_string(),
+            "echo 'Listening on port 7658'; echo 'indexing' >&2; sleep 30".to_string(),
+        ];
+        server_config.init_timeout_seconds = Some(1);
+        let config = ServerInitConfig {
+            server_config,
+            workspace_roots: vec![],
+            initialization_options: None,
+            notification_tx: None,
+        };
+
+        let started = std::time::Instant::now();
+        let err = LspServer::spawn(config).await.unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(3));
+        match err {
+            Error::InitializeTimeout {
+                timeout_secs,
+                stderr,
+                stdout,
+                ..
+            } => {
+                assert_eq!(timeout_secs, 1);
+                assert_eq!(stderr, "indexing\n");
+                assert_eq!(stdout, "Listening on port 7658\n");
+            }
+            other => panic!("Expected InitializeTimeout, got {other:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_reports_initialize_error_response() {
+        let response =
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32602,"message":"rootUri is required"}}"#;
+        let mut server_config = LspServerConfig::rust_analyzer();
+        server_config.command = "sh".to_string();
+        server_config.args = vec![
+            "-c".to_string(),
+            format!(
+                "head -c 1 >/dev/null; printf 'Content-Length: {}\\r\\n\\r\\n%s' '{response}'; sleep 30",
+                response.len()
+            ),
+        ];
+        let config = ServerInitConfig {
+            server_config,
+            workspace_roots: vec![],
+            initialization_options: None,
+            notification_tx: None,
+        };
+
+        let err = LspServer::spawn(config).await.unwrap_err();
+
+        match err {
+            Error::InitializeRejected { code, message, .. } => {
+                assert_eq!(code, -32602);
+                assert_eq!(message, "rootUri is required");
+            }
+            other => panic!("Expected InitializeRejected, got {other:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_initialize_advertises_lazy_completion_resolution() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let request_file = dir.path().join("initialize.json");
+        let response = r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":{}}}"#;
+        let mut server_config = LspServerConfig::rust_analyzer();
+        server_config.command = "sh".to_string();
+        server_config.args = vec![
+            "-c".to_string(),
+            format!(
+                "IFS= read -r header; length=$(printf '%s' \"$header\" | tr -cd 0-9); \
+                 IFS= read -r blank; head -c \"$length\" > '{}'; \
+                 printf 'Content-Length: {}\\r\\n\\r\\n%s' '{response}'; cat >/dev/null",
+                request_file.display(),
+                response.len()
+            ),
+        ];
+        let config = ServerInitConfig {
+            server_config,
+            workspace_roots: vec![],
+            initialization_options: None,
+            notification_tx: None,
+        };
+
+        let _server = LspServer::spawn(config).await.unwrap();
+
+        let request: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&request_file).unwrap()).unwrap();
+        let resolve = &request["params"]["capabilities"]["textDocument"]["completion"]["completionItem"]
+            ["resolveSupport"]["properties"];
+        assert_eq!(
+            resolve,
+            &serde_json::json!(["documentation", "detail", "additionalTextEdits"])
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_truncates_captured_stderr() {
+        let mut server_config = LspServerConfig::rust_analyzer();
+        server_config.command = "sh".to_string();
+        server_config.args = vec![
+            "-c".to_string(),
+            "head -c 8192 /dev/zero | tr '\\0' x >&2; exit 1".to_string(),
+        ];
+        let config = ServerInitConfig {
+            server_config,
+            workspace_roots: vec![],
+            initialization_options: None,
+            notification_tx: None,
+        };
+
+        let err = LspServer::spawn(config).await.unwrap_err();
+
+        let Error::ServerExited { stderr, .. } = err else {
+            panic!("Expected ServerExited, got {err:?}");
+        };
+        assert_eq!(stderr.len(), STDERR_CAPTURE_LIMIT);
+    }
+
+    /// A reaped process has no `/proc` entry; a zombie still has one.
+    #[cfg(target_os = "linux")]
+    async fn assert_reaped(pid: u32) {
+        let proc_path = PathBuf::from(format!("/proc/{pid}"));
+        for _ in 0..50 {
+            if !proc_path.exists() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("process {pid} was not reaped");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_server_process_drop_reaps_child() {
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id().unwrap();
+
+        drop(ServerProcess::new(child));
+
+        assert_reaped(pid).await;
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_server_process_wait_or_kill_kills_after_grace() {
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id().unwrap();
+
+        ServerProcess::new(child)
+            .wait_or_kill(Duration::from_millis(50))
+            .await;
+
+        assert_reaped(pid).await;
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_server_process_wait_or_kill_already_exited() {
+        let child = Command::new("true").spawn().unwrap();
+        let pid = child.id().unwrap();
+
+        ServerProcess::new(child)
+            .wait_or_kill(Duration::from_secs(5))
+            .await;
+
+        assert_reaped(pid).await;
+    }
+
+    #[tokio::test]
+    async fn test_stderr_sink_log_emits_lines() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut sink = StderrSink::open(&StderrMode::Log, &tx).await.unwrap();
+
+        sink.write(b"indexing crate\r\nbuilding pro").await;
+        sink.write(b"c macros\n\npartial").await;
+        sink.finish().await;
+
+        let mut lines = Vec::new();
+        while let Ok(notification) = rx.try_recv() {
+            match notification {
+                LspNotification::LogMessage(params) => {
+                    assert_eq!(params.typ, MessageType::LOG);
+                    lines.push(params.message);
+                }
+                other => panic!("Expected LogMessage, got {other:?}"),
+            }
+        }
+        assert_eq!(lines, ["indexing crate", "building proc macros", "partial"]);
+    }
+
+    #[tokio::test]
+    async fn test_stderr_sink_log_drops_lines_when_channel_full() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let mut sink = StderrSink::open(&StderrMode::Log, &tx).await.unwrap();
+
+        sink.write(b"first\nsecond\nthird\n").await;
+
+        assert!(rx.try_recv().is_ok());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_spawn_stderr_file_mode() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let log_path = dir.path().join("server.log");
+
+        let mut server_config = LspServerConfig::rust_analyzer();
+        server_config.command = "sh".to_string();
+        server_config.args = vec![
+            "-c".to_string(),
+            "echo 'loading workspace' >&2; exit 2".to_string(),
+        ];
+        server_config.stderr = StderrMode::File(log_path.clone());
+        let config = ServerInitConfig {
+            server_config,
+            workspace_roots: vec![],
+            initialization_options: None,
+            notification_tx: None,
+        };
+
+        let err = LspServer::spawn(config).await.unwrap_err();
+
+        assert!(
+            matches!(err, Error::ServerExited { ref stderr, .. } if stderr.contains("loading workspace"))
+        );
+        let logged = std::fs::read_to_string(&log_path).unwrap();
+        assert_eq!(logged, "loading workspace\n");
+    }
 }
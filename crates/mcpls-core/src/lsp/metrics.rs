@@ -0,0 +1,138 @@
+//! Per-tool-call request metrics.
+//!
+//! A tool call runs inside [`measure`], which gives it a fresh
+//! [`RequestMetrics`] in a task-local slot. The LSP client and the bridge
+//! record what they did into it as the call proceeds, so handlers do not have
+//! to pass the metrics around. Recording outside of [`measure`] (background
+//! tasks, server startup) is a no-op.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+tokio::task_local! {
+    static METRICS: RefCell<RequestMetrics>;
+}
+
+/// What a tool call cost and which state it was answered from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RequestMetrics {
+    /// Wall-clock time of the whole tool call, in milliseconds.
+    pub elapsed_ms: u64,
+    /// Time spent waiting for language server responses, in milliseconds.
+    pub lsp_round_trip_ms: u64,
+    /// Number of requests sent to language servers, retries included.
+    pub lsp_requests: u32,
+    /// Language ID of the server that answered, or of the document used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_id: Option<String>,
+    /// Version of the document the request was made against.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_version: Option<i32>,
+    /// Whether the result was served from cached server notifications.
+    pub cache_hit: bool,
+    /// Position moved to follow the document's edits, reported separately
+    /// from the metrics.
+    #[serde(skip)]
+    pub reanchored: Option<ReanchoredPosition>,
+}
+
+/// A position of a tool call that referred to an older version of its
+/// document, and where it was moved to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReanchoredPosition {
+    /// File the position is in.
+    pub file_path: String,
+    /// Line given in the call (1-based).
+    pub line: u32,
+    /// Character given in the call (1-based).
+    pub character: u32,
+    /// Line the call was answered for (1-based).
+    pub reanchored_line: u32,
+    /// Character the call was answered for (1-based).
+    pub reanchored_character: u32,
+    /// Version of the document the given position matched.
+    pub from_version: i32,
+    /// Version of the document the call was answered for.
+    pub to_version: i32,
+}
+
+/// Run `future` with its own metrics, returning them along with its output.
+pub async fn measure<F: Future>(future: F) -> (F::Output, RequestMetrics) {
+    let started = Instant::now();
+    METRICS
+        .scope(RefCell::new(RequestMetrics::default()), async move {
+            let output = future.await;
+            let mut metrics = METRICS.with(RefCell::take);
+            metrics.elapsed_ms = millis(started.elapsed());
+            (output, metrics)
+        })
+        .await
+}
+
+fn record(update: impl FnOnce(&mut RequestMetrics)) {
+    let _ = METRICS.try_with(|metrics| update(&mut metrics.borrow_mut()));
+}
+
+/// Record a request answered by the server for `language_id`.
+pub fn record_lsp_request(language_id: &str, round_trip: Duration) {
+    record(|metrics| {
+        metrics.lsp_round_trip_ms += millis(round_trip);
+        metrics.lsp_requests += 1;
+        metrics.language_id = Some(language_id.to_string());
+    });
+}
+
+/// Record the document a request is made against.
+pub fn record_document(language_id: &str, version: i32) {
+    record(|metrics| {
+        metrics.document_version = Some(version);
+        metrics
+            .language_id
+            .get_or_insert_with(|| language_id.to_string());
+    });
+}
+
+/// Record that a position of the call was re-anchored.
+pub fn record_reanchor(reanchored: ReanchoredPosition) {
+    record(|metrics| metrics.reanchored = Some(reanchored));
+}
+
+/// Record that the result came from cached server notifications.
+pub fn record_cache_hit() {
+    record(|metrics| metrics.cache_hit = true);
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_measure_collects_recorded_metrics() {
+        // No-op outside of a measured call.
+        record_cache_hit();
+
+        let ((), metrics) = measure(async {
+            record_document("rust", 3);
+            record_lsp_request("rust", Duration::from_millis(12));
+            record_lsp_request("rust", Duration::from_millis(5));
+        })
+        .await;
+        assert_eq!(metrics.lsp_round_trip_ms, 17);
+        assert_eq!(metrics.lsp_requests, 2);
+        assert_eq!(metrics.language_id.as_deref(), Some("rust"));
+        assert_eq!(metrics.document_version, Some(3));
+        assert!(!metrics.cache_hit);
+
+        let ((), metrics) = measure(async { record_cache_hit() }).await;
+        assert!(metrics.cache_hit);
+        assert_eq!(metrics.lsp_requests, 0);
+    }
+}
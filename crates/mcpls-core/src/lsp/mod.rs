@@ -5,12 +5,19 @@
 
 mod client;
 mod lifecycle;
+pub(crate) mod metrics;
+mod progress;
+mod quirks;
+mod sequencing;
+pub(crate) mod timeout;
 mod transport;
 pub(crate) mod types;
 
 pub use client::LspClient;
 pub use lifecycle::{LspServer, ServerInitConfig, ServerInitResult, ServerState};
-pub use transport::LspTransport;
+pub use progress::ProgressTracker;
+pub use quirks::Quirk;
+pub use transport::{LspTransport, StrayOutput, WireDirection, WireLog, WireMessage};
 pub use types::{
     InboundMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, LspNotification,
     RequestId,
@@ -0,0 +1,252 @@
+//! Work a server reports in progress, and its startup grace period.
+//!
+//! Servers that index the workspace on startup time out or answer
+//! `ContentModified` until they are done. With `startup_grace_seconds` set,
+//! such errors are reported as [`Error::ServerIndexing`], telling the agent
+//! when to retry, during the grace period after the server starts and for
+//! as long as the server reports work in progress through `$/progress`.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde_json::Value;
+use tokio::time::{Duration, Instant};
+
+use crate::error::Error;
+
+/// JSON-RPC error code of a request the server gave up on because the
+/// document changed, or, for many servers, because it is still loading.
+pub const CONTENT_MODIFIED_CODE: i32 = -32801;
+
+/// Longest retry delay suggested for a server that is still indexing.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// Work begun with `$/progress` and not yet ended.
+#[derive(Debug, Clone)]
+struct ActiveWork {
+    title: String,
+    message: Option<String>,
+    percentage: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    /// Work in progress, by token.
+    work: BTreeMap<String, ActiveWork>,
+    /// End of the grace period, or `None` when none is configured.
+    grace_until: Option<Instant>,
+}
+
+/// Progress of a server's work. Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressTracker {
+    state: Arc<Mutex<ProgressState>>,
+}
+
+impl ProgressTracker {
+    /// Create a tracker whose grace period, if any, starts now.
+    #[must_use]
+    pub fn new(grace: Option<Duration>) -> Self {
+        let tracker = Self::default();
+        tracker.lock().grace_until = grace.map(|grace| Instant::now() + grace);
+        tracker
+    }
+
+    /// Record a `$/progress` notification: `begin` starts tracking the work
+    /// named by `token`, `report` updates it, and `end` stops tracking it.
+    /// Partial results and unknown kinds are ignored.
+    pub fn update(&self, token: &Value, value: &Value) {
+        let key = token.to_string();
+        let message = value
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let percentage = value.get("percentage").and_then(Value::as_u64);
+        let mut state = self.lock();
+        match value.get("kind").and_then(Value::as_str) {
+            Some("begin") => {
+                let title = value
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .unwrap_or("Working")
+                    .to_string();
+                state.work.insert(
+                    key,
+                    ActiveWork {
+                        title,
+                        message,
+                        percentage,
+                    },
+                );
+            }
+            Some("report") => {
+                if let Some(work) = state.work.get_mut(&key) {
+                    if message.is_some() {
+                        work.message = message;
+                    }
+                    if percentage.is_some() {
+                        work.percentage = percentage;
+                    }
+                }
+            }
+            Some("end") => {
+                state.work.remove(&key);
+            }
+            _ => {}
+        }
+    }
+
+    /// Summary of the work in progress, such as `Indexing: crate foo (42%)`,
+    /// or `None` when the server reports none.
+    #[must_use]
+    pub fn summary(&self) -> Option<String> {
+        let state = self.lock();
+        let work: Vec<String> = state
+            .work
+            .values()
+            .map(|work| {
+                let mut summary = work.title.clone();
+                if let Some(message) = &work.message {
+                    summary = format!("{summary}: {message}");
+                }
+                if let Some(percentage) = work.percentage {
+                    summary = format!("{summary} ({percentage}%)");
+                }
+                summary
+            })
+            .collect();
+        drop(state);
+        (!work.is_empty()).then(|| work.join("; "))
+    }
+
+    /// Report `error`, returned by the server for `language_id`, as
+    /// [`Error::ServerIndexing`] if it is a timeout or `ContentModified`
+    /// error while the server is still indexing. Other errors, and all
+    /// errors of servers without a grace period, are returned unchanged.
+    #[must_use]
+    pub fn classify(&self, language_id: &str, error: Error) -> Error {
+        let indexing_error = match &error {
+            Error::Timeout(_) => true,
+            Error::LspServerError { code, .. } => *code == CONTENT_MODIFIED_CODE,
+            _ => false,
+        };
+        if !indexing_error {
+            return error;
+        }
+        let Some(retry_after) = self.retry_after(Instant::now()) else {
+            return error;
+        };
+        Error::ServerIndexing {
+            language_id: language_id.to_string(),
+            retry_after_secs: (retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0))
+                .max(1),
+            progress: self.summary(),
+        }
+    }
+
+    /// How long to wait before retrying at `now`: the rest of the grace
+    /// period, or while work is in progress after it, up to
+    /// [`MAX_RETRY_AFTER`]. `None` when the server is not indexing.
+    fn retry_after(&self, now: Instant) -> Option<Duration> {
+        let state = self.lock();
+        let grace_until = state.grace_until?;
+        if now < grace_until {
+            Some((grace_until - now).min(MAX_RETRY_AFTER))
+        } else {
+            (!state.work.is_empty()).then_some(MAX_RETRY_AFTER)
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProgressState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_progress_summary() {
+        let tracker = ProgressTracker::default();
+        assert!(tracker.summary().is_none());
+
+        let token = json!("rustAnalyzer/Indexing");
+        tracker.update(&token, &json!({"kind": "begin", "title": "Indexing"}));
+        tracker.update(
+            &token,
+            &json!({"kind": "report", "message": "3/7 (core)", "percentage": 42}),
+        );
+        tracker.update(&json!(7), &json!({"kind": "begin", "title": "Fetching"}));
+        assert_eq!(
+            tracker.summary().unwrap(),
+            "Indexing: 3/7 (core) (42%); Fetching"
+        );
+
+        tracker.update(&token, &json!({"kind": "end"}));
+        tracker.update(&json!(7), &json!({"kind": "end"}));
+        assert!(tracker.summary().is_none());
+    }
+
+    #[test]
+    fn test_classify_during_grace_period() {
+        let tracker = ProgressTracker::new(Some(Duration::from_secs(60)));
+        match tracker.classify("rust", Error::Timeout(30)) {
+            Error::ServerIndexing {
+                language_id,
+                retry_after_secs,
+                progress,
+            } => {
+                assert_eq!(language_id, "rust");
+                assert_eq!(retry_after_secs, MAX_RETRY_AFTER.as_secs());
+                assert!(progress.is_none());
+            }
+            other => panic!("expected ServerIndexing, got {other:?}"),
+        }
+        let content_modified = Error::LspServerError {
+            code: CONTENT_MODIFIED_CODE,
+            message: "content modified".to_string(),
+            data: None,
+        };
+        assert!(matches!(
+            tracker.classify("rust", content_modified),
+            Error::ServerIndexing { .. }
+        ));
+        assert!(matches!(
+            tracker.classify("rust", Error::ServerTerminated),
+            Error::ServerTerminated
+        ));
+    }
+
+    #[test]
+    fn test_classify_after_grace_period() {
+        let tracker = ProgressTracker::new(Some(Duration::ZERO));
+        assert!(matches!(
+            tracker.classify("rust", Error::Timeout(30)),
+            Error::Timeout(30)
+        ));
+
+        // Work still in progress keeps the server indexing.
+        tracker.update(
+            &json!(1),
+            &json!({"kind": "begin", "title": "Loading", "percentage": 10}),
+        );
+        match tracker.classify("rust", Error::Timeout(30)) {
+            Error::ServerIndexing { progress, .. } => {
+                assert_eq!(progress.as_deref(), Some("Loading (10%)"));
+            }
+            other => panic!("expected ServerIndexing, got {other:?}"),
+        }
+
+        // Without a grace period, errors are never converted.
+        let tracker = ProgressTracker::new(None);
+        tracker.update(&json!(1), &json!({"kind": "begin", "title": "Loading"}));
+        assert!(matches!(
+            tracker.classify("rust", Error::Timeout(30)),
+            Error::Timeout(30)
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+//! Known deviations of specific language servers and their workarounds.
+//!
+//! Servers are recognized by the `serverInfo.name` of their `initialize`
+//! response. Workarounds that must be in place before that response arrives
+//! (such as the handshake timeout) recognize the server by its command
+//! instead. The quirks activated for a server are recorded on it, so tools
+//! can report them.
+
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::config::LspServerConfig;
+
+/// `initialize` timeout used for slow-starting servers when none is
+/// configured.
+const SLOW_STARTUP_INIT_TIMEOUT_SECS: u64 = 120;
+
+/// A known deviation of a language server that mcpls works around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quirk {
+    /// The server only picks up its settings once it receives
+    /// `workspace/didChangeConfiguration`, so one is sent after `initialized`.
+    ConfigurationAfterInit,
+    /// The server publishes diagnostics but does not implement
+    /// `textDocument/diagnostic`; diagnostics are served from the
+    /// notification cache instead.
+    PushDiagnosticsOnly,
+    /// The server takes minutes to answer `initialize` on real projects; the
+    /// handshake gets a longer default timeout.
+    SlowStartup,
+}
+
+impl Quirk {
+    /// Stable name of the quirk, as reported by tools.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ConfigurationAfterInit => "configuration_after_init",
+            Self::PushDiagnosticsOnly => "push_diagnostics_only",
+            Self::SlowStartup => "slow_startup",
+        }
+    }
+
+    /// Whether the quirk applies to a server that answered `initialize`
+    /// with `initialize_result`.
+    fn applies(self, initialize_result: &Value) -> bool {
+        match self {
+            // Newer servers implement pull diagnostics.
+            Self::PushDiagnosticsOnly => initialize_result
+                .pointer("/capabilities/diagnosticProvider")
+                .is_none_or(Value::is_null),
+            Self::ConfigurationAfterInit | Self::SlowStartup => true,
+        }
+    }
+}
+
+/// A server with known quirks.
+struct KnownServer {
+    /// `serverInfo.name` the server reports.
+    server_name: &'static str,
+    /// Executable name, for workarounds needed before `initialize` returns.
+    command: &'static str,
+    quirks: &'static [Quirk],
+}
+
+const KNOWN_SERVERS: &[KnownServer] = &[
+    KnownServer {
+        server_name: "gopls",
+        command: "gopls",
+        quirks: &[Quirk::ConfigurationAfterInit],
+    },
+    KnownServer {
+        server_name: "typescript-language-server",
+        command: "typescript-language-server",
+        quirks: &[Quirk::PushDiagnosticsOnly],
+    },
+    KnownServer {
+        server_name: "Eclipse JDT Language Server",
+        command: "jdtls",
+        quirks: &[Quirk::SlowStartup],
+    },
+];
+
+/// Quirks to work around for the server that sent `initialize_result`.
+#[must_use]
+pub fn detect(config: &LspServerConfig, initialize_result: &Value) -> Vec<Quirk> {
+    let server_name = initialize_result
+        .pointer("/serverInfo/name")
+        .and_then(Value::as_str);
+    KNOWN_SERVERS
+        .iter()
+        .filter(|known| server_name == Some(known.server_name) || is_known_command(config, known))
+        .flat_map(|known| known.quirks.iter().copied())
+        .filter(|quirk| quirk.applies(initialize_result))
+        .collect()
+}
+
+/// Timeout for the `initialize` request of `config`'s server, in seconds.
+///
+/// A configured `init_timeout_seconds` always wins; otherwise slow-starting
+/// servers get at least [`SLOW_STARTUP_INIT_TIMEOUT_SECS`].
+#[must_use]
+pub fn init_timeout(config: &LspServerConfig) -> u64 {
+    let timeout = config.init_timeout();
+    let slow_startup = KNOWN_SERVERS
+        .iter()
+        .any(|known| known.quirks.contains(&Quirk::SlowStartup) && is_known_command(config, known));
+    if config.init_timeout_seconds.is_none() && slow_startup {
+        timeout.max(SLOW_STARTUP_INIT_TIMEOUT_SECS)
+    } else {
+        timeout
+    }
+}
+
+fn is_known_command(config: &LspServerConfig, known: &KnownServer) -> bool {
+    Path::new(&config.command)
+        .file_stem()
+        .is_some_and(|stem| stem == known.command)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn config(command: &str) -> LspServerConfig {
+        LspServerConfig {
+            command: command.to_string(),
+            ..LspServerConfig::rust_analyzer()
+        }
+    }
+
+    #[test]
+    fn test_detect_by_server_name() {
+        let gopls = serde_json::json!({
+            "capabilities": {},
+            "serverInfo": { "name": "gopls", "version": "v0.16.2" }
+        });
+        assert_eq!(
+            detect(&config("/opt/go/bin/go-lsp"), &gopls),
+            vec![Quirk::ConfigurationAfterInit]
+        );
+
+        let rust_analyzer = serde_json::json!({
+            "capabilities": {},
+            "serverInfo": { "name": "rust-analyzer" }
+        });
+        assert!(detect(&config("rust-analyzer"), &rust_analyzer).is_empty());
+    }
+
+    #[test]
+    fn test_push_diagnostics_only_needs_missing_provider() {
+        let old = serde_json::json!({
+            "capabilities": {},
+            "serverInfo": { "name": "typescript-language-server" }
+        });
+        assert_eq!(
+            detect(&config("typescript-language-server"), &old),
+            vec![Quirk::PushDiagnosticsOnly]
+        );
+
+        let new = serde_json::json!({
+            "capabilities": { "diagnosticProvider": { "interFileDependencies": true, "workspaceDiagnostics": false } },
+            "serverInfo": { "name": "typescript-language-server" }
+        });
+        assert!(detect(&config("typescript-language-server"), &new).is_empty());
+    }
+
+    #[test]
+    fn test_slow_startup_init_timeout() {
+        let jdtls = config("/usr/local/bin/jdtls");
+        assert_eq!(init_timeout(&jdtls), SLOW_STARTUP_INIT_TIMEOUT_SECS);
+        // jdtls does not always send serverInfo.
+        assert_eq!(
+            detect(&jdtls, &serde_json::json!({ "capabilities": {} })),
+            vec![Quirk::SlowStartup]
+        );
+
+        let configured = LspServerConfig {
+            init_timeout_seconds: Some(20),
+            ..jdtls
+        };
+        assert_eq!(init_timeout(&configured), 20);
+        assert_eq!(
+            init_timeout(&config("rust-analyzer")),
+            config("rust-analyzer").init_timeout()
+        );
+    }
+}
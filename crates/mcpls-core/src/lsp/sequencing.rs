@@ -0,0 +1,125 @@
+//! Per-document ordering of the messages sent to a language server.
+//!
+//! A server answers a request against the document version it had when the
+//! request arrived. If a `didChange` for the same document slips in while the
+//! request is in flight, the answer describes stale content, or the server
+//! gives up with `ContentModified`. Requests that name a document therefore
+//! hold a shared lock on it until their response arrives, and document
+//! synchronization notifications take it exclusively: a change waits for the
+//! requests already in flight, and requests made after it wait for the
+//! change to be sent. Requests for different documents never wait on each
+//! other.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde_json::Value;
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+
+/// Notifications that change what the server holds for a document.
+const DOCUMENT_SYNC_NOTIFICATIONS: &[&str] = &[
+    "textDocument/didOpen",
+    "textDocument/didChange",
+    "textDocument/didClose",
+    "textDocument/didSave",
+    "textDocument/willSave",
+];
+
+/// Per-document locks shared by all clones of a client.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentSequencer {
+    locks: Arc<Mutex<HashMap<String, Arc<RwLock<()>>>>>,
+}
+
+impl DocumentSequencer {
+    fn lock_for(&self, uri: &str) -> Arc<RwLock<()>> {
+        let mut locks = self.locks.lock().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(locks.entry(uri.to_string()).or_default())
+    }
+
+    /// Wait until no synchronization notification for `uri` is pending, and
+    /// hold off new ones until the guard is dropped.
+    pub async fn request(&self, uri: &str) -> OwnedRwLockReadGuard<()> {
+        self.lock_for(uri).read_owned().await
+    }
+
+    /// Wait until every request in flight for `uri` has been answered, and
+    /// hold off new ones until the guard is dropped.
+    pub async fn change(&self, uri: &str) -> OwnedRwLockWriteGuard<()> {
+        self.lock_for(uri).write_owned().await
+    }
+
+    /// Drop the lock of a closed document. Holders of the lock keep it alive
+    /// until they are done.
+    pub fn forget(&self, uri: &str) {
+        self.locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(uri);
+    }
+
+    /// Number of documents with a lock, for tests.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.locks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+/// URI of the document a request or notification is about, taken from its
+/// `textDocument` parameter.
+pub fn document_uri(params: &Value) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+/// Whether a notification changes what the server holds for a document.
+pub fn is_document_sync(method: &str) -> bool {
+    DOCUMENT_SYNC_NOTIFICATIONS.contains(&method)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_document_uri() {
+        let params = serde_json::json!({
+            "textDocument": { "uri": "file:///ws/lib.rs" },
+            "position": { "line": 0, "character": 0 }
+        });
+        assert_eq!(document_uri(&params), Some("file:///ws/lib.rs"));
+        assert_eq!(document_uri(&serde_json::json!({ "query": "User" })), None);
+        assert!(is_document_sync("textDocument/didChange"));
+        assert!(!is_document_sync("$/cancelRequest"));
+    }
+
+    #[tokio::test]
+    async fn test_change_waits_for_requests_on_the_same_document() {
+        let sequencer = DocumentSequencer::default();
+        let request = sequencer.request("file:///ws/lib.rs").await;
+        // Requests for other documents, and more requests for this one, are
+        // not held up.
+        let other = sequencer.change("file:///ws/main.rs").await;
+        drop(sequencer.request("file:///ws/lib.rs").await);
+
+        let waited = tokio::time::timeout(
+            Duration::from_millis(50),
+            sequencer.change("file:///ws/lib.rs"),
+        )
+        .await
+        .is_err();
+        assert!(waited, "change must wait for the request");
+
+        drop(request);
+        drop(sequencer.change("file:///ws/lib.rs").await);
+        drop(other);
+        assert_eq!(sequencer.len(), 2);
+        sequencer.forget("file:///ws/lib.rs");
+        assert_eq!(sequencer.len(), 1);
+    }
+}
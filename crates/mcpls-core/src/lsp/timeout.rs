@@ -0,0 +1,39 @@
+//! Request timeout of the tool call in progress.
+//!
+//! A tool call runs inside [`scope`] with the timeout configured for the tool
+//! or passed with the call. The requests that answer the call wait for
+//! [`request_timeout`], which is that timeout inside a tool call and the
+//! request's own default outside one (background tasks, the embedding API).
+//! Auxiliary requests, such as resolving items of a response, keep their own
+//! shorter limits.
+
+use std::future::Future;
+use std::time::Duration;
+
+tokio::task_local! {
+    static TOOL_TIMEOUT: Duration;
+}
+
+/// Run `future` with `timeout` as its request timeout.
+pub async fn scope<F: Future>(timeout: Duration, future: F) -> F::Output {
+    TOOL_TIMEOUT.scope(timeout, future).await
+}
+
+/// Timeout of a request that answers the current tool call, or `default`
+/// outside of [`scope`].
+pub fn request_timeout(default: Duration) -> Duration {
+    TOOL_TIMEOUT.try_with(|timeout| *timeout).unwrap_or(default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_timeout_follows_scope() {
+        let default = Duration::from_secs(30);
+        assert_eq!(request_timeout(default), default);
+        let scoped = scope(Duration::from_secs(90), async { request_timeout(default) }).await;
+        assert_eq!(scoped, Duration::from_secs(90));
+    }
+}
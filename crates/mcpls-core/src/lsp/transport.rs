@@ -1,6 +1,7 @@
 //! LSP transport layer for stdio communication.
 //!
-//! This module implements the LSP header-content message format over stdin/stdout.
+//! This module implements the LSP header-content message format over a
+//! server's stdin/stdout, or any other pair of byte streams such as a socket.
 //! Messages follow the format:
 //! ```text
 //! Content-Length: 123\r\n
@@ -8,44 +9,217 @@
 //! {"jsonrpc":"2.0",...}
 //! ```
 
-use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 
+use serde::Serialize;
 use serde_json::Value;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{ChildStdin, ChildStdout};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::sync::watch;
 use tracing::{debug, trace, warn};
 
+use crate::config::DEFAULT_MAX_MESSAGE_SIZE;
 use crate::error::{Error, Result};
 use crate::lsp::types::{InboundMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
 
-/// Maximum allowed Content-Length (10 MB)
-const MAX_CONTENT_LENGTH: usize = 10 * 1024 * 1024;
+/// Header name announcing the content size, lowercased with its colon.
+const CONTENT_LENGTH_HEADER: &str = "content-length:";
+
+/// Header name announcing the content type, lowercased with its colon.
+const CONTENT_TYPE_HEADER: &str = "content-type:";
+
+/// Charsets accepted in the `Content-Type` header. LSP only allows UTF-8;
+/// `utf8` is accepted for backwards compatibility, as the specification
+/// recommends.
+const SUPPORTED_CHARSETS: &[&str] = &["utf-8", "utf8"];
+
+/// Maximum number of stray output bytes kept for startup diagnostics.
+const STRAY_OUTPUT_LIMIT: usize = 1024;
+
+/// Number of messages kept in a server's wire log.
+const WIRE_LOG_CAPACITY: usize = 50;
+
+/// Maximum number of bytes kept of each message in the wire log.
+const WIRE_MESSAGE_LIMIT: usize = 2048;
+
+/// Direction of a message in the wire log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WireDirection {
+    /// Sent to the server.
+    Sent,
+    /// Received from the server.
+    Received,
+}
+
+/// A message exchanged with a server, shortened to [`WIRE_MESSAGE_LIMIT`]
+/// bytes.
+#[derive(Debug, Clone, Serialize)]
+pub struct WireMessage {
+    /// Whether the message was sent or received.
+    pub direction: WireDirection,
+    /// JSON content of the message.
+    pub content: String,
+}
+
+/// The last [`WIRE_LOG_CAPACITY`] messages exchanged with a server, for bug
+/// reports.
+///
+/// Clones share the same buffer, so a handle taken before the transport is
+/// moved into a client still sees later messages.
+#[derive(Debug, Clone, Default)]
+pub struct WireLog {
+    messages: Arc<Mutex<VecDeque<WireMessage>>>,
+}
+
+impl WireLog {
+    /// Copy of the logged messages, oldest first.
+    #[must_use]
+    pub fn snapshot(&self) -> Vec<WireMessage> {
+        self.messages
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Log a message, dropping the oldest one when the log is full.
+    fn record(&self, direction: WireDirection, content: &str) {
+        let mut end = content.len().min(WIRE_MESSAGE_LIMIT);
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        let mut kept = content[..end].to_string();
+        if end < content.len() {
+            kept.push('…');
+        }
+        let mut messages = self.messages.lock().unwrap_or_else(PoisonError::into_inner);
+        if messages.len() == WIRE_LOG_CAPACITY {
+            messages.pop_front();
+        }
+        messages.push_back(WireMessage {
+            direction,
+            content: kept,
+        });
+    }
+}
+
+/// Leading portion of the output a server printed to stdout outside of
+/// framed messages, such as a startup banner or a usage message.
+///
+/// Clones share the same buffer, so a handle taken before the transport is
+/// moved into a client still sees later output.
+#[derive(Debug, Clone)]
+pub struct StrayOutput {
+    captured: Arc<Mutex<String>>,
+    closed: watch::Receiver<bool>,
+}
+
+impl StrayOutput {
+    /// Copy of the stray output captured so far.
+    #[must_use]
+    pub fn snapshot(&self) -> String {
+        self.captured
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Wait up to `grace` for the server to close its stdout, then return
+    /// the stray output it printed.
+    pub async fn finish(mut self, grace: Duration) -> String {
+        let _ = tokio::time::timeout(grace, self.closed.wait_for(|closed| *closed)).await;
+        self.snapshot()
+    }
+
+    /// Record a skipped line, up to [`STRAY_OUTPUT_LIMIT`] bytes in total.
+    fn record(&self, line: &str) {
+        let mut captured = self.captured.lock().unwrap_or_else(PoisonError::into_inner);
+        for c in line.chars().chain(std::iter::once('\n')) {
+            if captured.len() + c.len_utf8() > STRAY_OUTPUT_LIMIT {
+                return;
+            }
+            captured.push(c);
+        }
+    }
+}
+
+/// Stream messages are sent to: a server's stdin.
+type Input = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// Stream messages are received from: a server's stdout.
+type Output = Box<dyn AsyncRead + Send + Unpin>;
 
 /// LSP transport layer handling header-content format.
 ///
 /// This transport handles the LSP protocol's header-content message format,
 /// parsing Content-Length headers and reading exact message content.
-#[derive(Debug)]
 pub struct LspTransport {
-    stdin: ChildStdin,
-    stdout: BufReader<ChildStdout>,
+    stdin: Input,
+    stdout: MessageReader<BufReader<Output>>,
+}
+
+impl std::fmt::Debug for LspTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LspTransport")
+            .field("max_content_length", &self.stdout.max_content_length)
+            .finish_non_exhaustive()
+    }
 }
 
 impl LspTransport {
-    /// Create transport from child process stdio.
+    /// Create transport from a server's stdio.
+    ///
+    /// Any byte streams will do, such as in-memory pipes standing in for a
+    /// server in tests.
     ///
     /// # Arguments
     ///
-    /// * `stdin` - The child process's stdin handle for sending messages
-    /// * `stdout` - The child process's stdout handle for receiving messages
+    /// * `stdin` - The server's stdin, for sending messages
+    /// * `stdout` - The server's stdout, for receiving messages
     #[must_use]
-    pub fn new(stdin: ChildStdin, stdout: ChildStdout) -> Self {
+    pub fn new(
+        stdin: impl AsyncWrite + Send + Unpin + 'static,
+        stdout: impl AsyncRead + Send + Unpin + 'static,
+    ) -> Self {
         Self {
-            stdin,
-            stdout: BufReader::new(stdout),
+            stdin: Box::new(stdin),
+            stdout: MessageReader::new(BufReader::new(Box::new(stdout))),
         }
     }
 
+    /// Create transport from a single stream carrying both directions, such
+    /// as a TCP or Unix socket connected to a server.
+    #[must_use]
+    pub fn from_stream(stream: impl AsyncRead + AsyncWrite + Send + 'static) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self::new(writer, reader)
+    }
+
+    /// Set the maximum size in bytes of a received message; larger messages
+    /// are skipped. Defaults to [`DEFAULT_MAX_MESSAGE_SIZE`].
+    #[must_use]
+    pub const fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.stdout.max_content_length = max_message_size;
+        self
+    }
+
+    /// Handle to the output the server printed outside of framed messages.
+    #[must_use]
+    pub fn stray_output(&self) -> StrayOutput {
+        self.stdout.stray_output.clone()
+    }
+
+    /// Handle to the last messages exchanged with the server.
+    #[must_use]
+    pub fn wire_log(&self) -> WireLog {
+        self.stdout.wire_log.clone()
+    }
+
     /// Send message to LSP server.
     ///
     /// Formats the message with proper Content-Length header and sends it
@@ -62,6 +236,7 @@ impl LspTransport {
         let header = format!("Content-Length: {}\r\n\r\n", content.len());
 
         trace!("Sending LSP message: {}", content);
+        self.stdout.wire_log.record(WireDirection::Sent, &content);
 
         self.stdin.write_all(header.as_bytes()).await?;
         self.stdin.write_all(content.as_bytes()).await?;
@@ -73,103 +248,221 @@ impl LspTransport {
     /// Receive next message from LSP server.
     ///
     /// Reads headers, extracts Content-Length, reads exact message content,
-    /// and parses it as either a response or notification.
+    /// and parses it as a request, response, or notification. Stray output
+    /// before a header (such as a startup banner), frames that cannot be
+    /// read, messages declaring a charset other than UTF-8, and malformed
+    /// messages are logged and skipped; the elements of a JSON-RPC batch are
+    /// returned one by one.
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - Reading headers fails
-    /// - Content-Length header is missing or invalid
-    /// - Reading message content fails
-    /// - JSON parsing fails
-    /// - Message format is invalid
+    /// Returns an error if reading from the server fails or the server
+    /// closed its stdout.
     pub async fn receive(&mut self) -> Result<InboundMessage> {
+        self.stdout.receive().await
+    }
+
+    /// Read and discard the server's remaining output for up to `limit`,
+    /// so stray output it printed before exiting is still captured.
+    pub async fn drain(&mut self, limit: Duration) {
+        let _ = tokio::time::timeout(limit, async {
+            while self.stdout.receive().await.is_ok() {}
+        })
+        .await;
+    }
+}
+
+/// Reads framed messages from a server's output.
+#[derive(Debug)]
+struct MessageReader<R> {
+    reader: R,
+    /// Largest content accepted, in bytes.
+    max_content_length: usize,
+    /// Messages of a batch not yet returned.
+    queued: VecDeque<InboundMessage>,
+    /// Output skipped before a header.
+    stray_output: StrayOutput,
+    /// Last messages read.
+    wire_log: WireLog,
+    /// Set once the stream has ended.
+    closed: watch::Sender<bool>,
+}
+
+impl<R: AsyncBufRead + Unpin> MessageReader<R> {
+    fn new(reader: R) -> Self {
+        let (closed, closed_rx) = watch::channel(false);
+        Self {
+            reader,
+            max_content_length: DEFAULT_MAX_MESSAGE_SIZE,
+            queued: VecDeque::new(),
+            stray_output: StrayOutput {
+                captured: Arc::default(),
+                closed: closed_rx,
+            },
+            wire_log: WireLog::default(),
+            closed,
+        }
+    }
+
+    async fn receive(&mut self) -> Result<InboundMessage> {
         loop {
-            let headers = self.read_headers().await?;
-
-            let content_length = headers
-                .get("content-length")
-                .ok_or_else(|| {
-                    Error::LspProtocolError("Missing Content-Length header".to_string())
-                })?
-                .parse::<usize>()
-                .map_err(|e| Error::LspProtocolError(format!("Invalid Content-Length: {e}")))?;
-
-            if content_length > MAX_CONTENT_LENGTH {
-                return Err(Error::LspProtocolError(format!(
-                    "Content-Length {content_length} exceeds maximum allowed size of {MAX_CONTENT_LENGTH} bytes"
-                )));
+            if let Some(message) = self.queued.pop_front() {
+                return Ok(message);
+            }
+
+            let (content_length, charset) = self.read_headers().await?;
+            if let Some(charset) = charset.filter(|charset| {
+                !SUPPORTED_CHARSETS
+                    .iter()
+                    .any(|supported| charset.eq_ignore_ascii_case(supported))
+            }) {
+                warn!(
+                    "Skipping message with unsupported charset '{charset}' in Content-Type header; only utf-8 is supported"
+                );
+                self.skip_content(content_length).await?;
+                continue;
+            }
+            if content_length > self.max_content_length {
+                warn!(
+                    "Skipping message with Content-Length {content_length} exceeding maximum allowed size of {} bytes (raise max_message_size to accept it)",
+                    self.max_content_length
+                );
+                self.skip_content(content_length).await?;
+                continue;
             }
 
-            let content = self.read_content(content_length).await?;
+            let Some(content) = self.read_content(content_length).await? else {
+                continue;
+            };
 
             trace!("Received LSP message: {}", content);
+            self.wire_log.record(WireDirection::Received, &content);
 
-            let value: Value = serde_json::from_str(&content)?;
+            match serde_json::from_str::<Value>(&content) {
+                Ok(value) => self.queue(value),
+                Err(e) => warn!("Skipping LSP message that is not valid JSON: {e}"),
+            }
+        }
+    }
 
+    /// Queue the messages carried by one frame: a single message or the
+    /// elements of a batch.
+    fn queue(&mut self, value: Value) {
+        let values = match value {
+            Value::Array(batch) => batch,
+            value => vec![value],
+        };
+        for value in values {
             // Some servers (notably OmniSharp) occasionally emit a bare `null`
-            // (or other non-object) JSON-RPC message. Skip it and read the next
-            // framed message instead of killing the whole message loop.
+            // (or other non-object) JSON-RPC message, in bursts during
+            // startup; log at debug to avoid flooding the logs for what is a
+            // recoverable, expected condition.
             if !value.is_object() {
-                // Some servers (notably OmniSharp) emit a burst of these during
-                // startup; log at debug to avoid flooding the logs for what is a
-                // recoverable, expected condition.
                 debug!("Skipping non-object LSP message: {}", value);
                 continue;
             }
-
-            return parse_inbound_message(value);
+            match parse_inbound_message(value) {
+                Ok(message) => self.queued.push_back(message),
+                Err(e) => warn!("Skipping invalid LSP message: {e}"),
+            }
         }
     }
 
-    /// Read headers until blank line.
+    /// Read a header block and return its Content-Length and the charset
+    /// its Content-Type declares, if any.
     ///
     /// Headers are in the format "Key: Value\r\n" and are terminated by
-    /// a blank line ("\r\n").
-    async fn read_headers(&mut self) -> Result<HashMap<String, String>> {
-        let mut headers = HashMap::new();
+    /// a blank line ("\r\n"). Until a valid `Content-Length` header is seen,
+    /// other lines are treated as stray output and skipped, so banners
+    /// printed to stdout do not break the connection. A banner without a
+    /// trailing newline may run into the header line; the header is still
+    /// found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream ends.
+    async fn read_headers(&mut self) -> Result<(usize, Option<String>)> {
+        let mut content_length = None;
+        let mut charset: Option<String> = None;
         let mut line = String::new();
 
         loop {
             line.clear();
-            let bytes_read = self.stdout.read_line(&mut line).await?;
+            let bytes_read = self.reader.read_line(&mut line).await?;
 
             // EOF - stream closed (read_line returns 0 bytes on EOF)
-            if bytes_read == 0 || line.is_empty() {
-                trace!(
-                    "EOF detected in read_headers: bytes_read={}, line_len={}",
-                    bytes_read,
-                    line.len()
-                );
+            if bytes_read == 0 {
+                trace!("EOF detected in read_headers");
+                self.closed.send_replace(true);
                 return Err(Error::ServerTerminated);
             }
 
-            if line == "\r\n" || line == "\n" {
-                break;
+            let trimmed = line.trim_end_matches(['\r', '\n']);
+            if trimmed.is_empty() {
+                let Some(length) = content_length else {
+                    continue;
+                };
+                return Ok((length, charset));
             }
 
-            if let Some((key, value)) = line.trim_end().split_once(':') {
-                headers.insert(key.trim().to_lowercase(), value.trim().to_string());
-            } else {
-                warn!("Malformed header: {}", line.trim());
+            if let Some(value) = header_value(trimmed, CONTENT_TYPE_HEADER) {
+                charset = content_type_charset(value);
+            } else if let Some(value) = header_value(trimmed, CONTENT_LENGTH_HEADER) {
+                content_length = value.parse::<usize>().ok();
+                if content_length.is_none() {
+                    warn!("Skipping invalid Content-Length header: {trimmed}");
+                }
+            } else if content_length.is_none() {
+                debug!("Skipping stray server output: {trimmed}");
+                self.stray_output.record(trimmed);
+            } else if trimmed.split_once(':').is_none() {
+                warn!("Malformed header: {trimmed}");
             }
         }
-
-        Ok(headers)
     }
 
     /// Read exact number of content bytes.
     ///
-    /// Reads exactly `length` bytes from stdout and converts to UTF-8 string.
-    async fn read_content(&mut self, length: usize) -> Result<String> {
+    /// Returns `None`, after logging, if the content is not valid UTF-8.
+    async fn read_content(&mut self, length: usize) -> Result<Option<String>> {
         let mut buffer = vec![0u8; length];
-        self.stdout.read_exact(&mut buffer).await?;
+        self.reader.read_exact(&mut buffer).await?;
 
-        String::from_utf8(buffer)
-            .map_err(|e| Error::LspProtocolError(format!("Invalid UTF-8 in content: {e}")))
+        match String::from_utf8(buffer) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) => {
+                warn!("Skipping LSP message with invalid UTF-8: {e}");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Discard `length` content bytes without buffering them.
+    async fn skip_content(&mut self, length: usize) -> Result<()> {
+        let mut content = (&mut self.reader).take(length as u64);
+        tokio::io::copy(&mut content, &mut tokio::io::sink()).await?;
+        Ok(())
     }
 }
 
+/// Value of the header `name` (lowercased, with its colon) in `line`, which
+/// may be preceded by stray output.
+fn header_value<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    // ASCII lowercasing keeps byte offsets valid for `line`.
+    let start = line.to_ascii_lowercase().find(name)?;
+    Some(line[start + name.len()..].trim())
+}
+
+/// The `charset` parameter of a `Content-Type` header value, if present.
+fn content_type_charset(value: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, charset) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| charset.trim().trim_matches('"').to_string())
+    })
+}
+
 fn parse_inbound_message(value: Value) -> Result<InboundMessage> {
     if value.get("method").is_some() {
         if value.get("id").is_some() {
@@ -201,9 +494,203 @@ fn parse_inbound_message(value: Value) -> Result<InboundMessage> {
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::*;
     use crate::lsp::types::RequestId;
 
+    fn frame(content: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{content}", content.len())
+    }
+
+    async fn receive_all(input: &str) -> Vec<InboundMessage> {
+        let mut reader = MessageReader::new(input.as_bytes());
+        let mut messages = Vec::new();
+        loop {
+            match reader.receive().await {
+                Ok(message) => messages.push(message),
+                Err(Error::ServerTerminated) => return messages,
+                Err(e) => panic!("unexpected error: {e}"),
+            }
+        }
+    }
+
+    fn notification_method(message: &InboundMessage) -> &str {
+        match message {
+            InboundMessage::Notification(n) => &n.method,
+            other => panic!("expected notification, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transport_over_bidirectional_stream() {
+        let (client_end, server_end) = tokio::io::duplex(1024);
+        let mut client = LspTransport::from_stream(client_end);
+        let mut server = LspTransport::from_stream(server_end);
+
+        let request = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" });
+        client.send(&request).await.unwrap();
+        let InboundMessage::Request(received) = server.receive().await.unwrap() else {
+            panic!("expected request");
+        };
+        assert_eq!(received.method, "ping");
+
+        let response = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "result": null });
+        server.send(&response).await.unwrap();
+        assert!(matches!(
+            client.receive().await.unwrap(),
+            InboundMessage::Response(_)
+        ));
+
+        drop(server);
+        assert!(matches!(
+            client.receive().await,
+            Err(Error::ServerTerminated)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_receive_skips_stray_output_before_headers() {
+        let note = r#"{"jsonrpc":"2.0","method":"a"}"#;
+        let input = format!(
+            "Starting server v1.2\n\nINFO: ready\r\n{}banner{}",
+            frame(note),
+            frame(&note.replace("\"a\"", "\"b\""))
+        );
+
+        let messages = receive_all(&input).await;
+        let methods: Vec<_> = messages.iter().map(notification_method).collect();
+        assert_eq!(methods, ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_stray_output_is_captured_up_to_limit() {
+        let note = r#"{"jsonrpc":"2.0","method":"a"}"#;
+        let input = format!("Usage: server --stdio\n{}", frame(note));
+        let mut reader = MessageReader::new(input.as_bytes());
+        let stray_output = reader.stray_output.clone();
+
+        reader.receive().await.unwrap();
+        assert_eq!(stray_output.snapshot(), "Usage: server --stdio\n");
+
+        let flood = "x".repeat(STRAY_OUTPUT_LIMIT * 2);
+        let input = format!("{flood}\n{}", frame(note));
+        let mut reader = MessageReader::new(input.as_bytes());
+        reader.receive().await.unwrap();
+        assert_eq!(reader.stray_output.snapshot().len(), STRAY_OUTPUT_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn test_wire_log_keeps_recent_messages() {
+        let input: String = (0..=WIRE_LOG_CAPACITY)
+            .map(|i| frame(&format!(r#"{{"jsonrpc":"2.0","method":"m{i}"}}"#)))
+            .collect();
+        let mut reader = MessageReader::new(input.as_bytes());
+        for _ in 0..=WIRE_LOG_CAPACITY {
+            reader.receive().await.unwrap();
+        }
+        let messages = reader.wire_log.snapshot();
+        assert_eq!(messages.len(), WIRE_LOG_CAPACITY);
+        assert_eq!(messages[0].direction, WireDirection::Received);
+        assert!(messages[0].content.contains("\"m1\""));
+
+        let log = WireLog::default();
+        log.record(WireDirection::Sent, &"é".repeat(WIRE_MESSAGE_LIMIT));
+        let content = &log.snapshot()[0].content;
+        assert!(content.ends_with('…'));
+        assert!(content.len() <= WIRE_MESSAGE_LIMIT + '…'.len_utf8());
+    }
+
+    #[tokio::test]
+    async fn test_receive_resyncs_after_invalid_frames() {
+        let note = r#"{"jsonrpc":"2.0","method":"ok"}"#;
+        let input = format!(
+            "Content-Length: lots\r\n\r\n{}{}{}",
+            frame("{not json"),
+            frame(r#"{"jsonrpc":"2.0","id":1}"#),
+            frame(note)
+        );
+
+        let messages = receive_all(&input).await;
+        let methods: Vec<_> = messages.iter().map(notification_method).collect();
+        assert_eq!(methods, ["ok"]);
+    }
+
+    #[tokio::test]
+    async fn test_receive_unpacks_batches() {
+        let batch =
+            r#"[{"jsonrpc":"2.0","id":1,"result":null},null,{"jsonrpc":"2.0","method":"n"}]"#;
+
+        let messages = receive_all(&frame(batch)).await;
+        assert_eq!(messages.len(), 2);
+        assert!(
+            matches!(&messages[0], InboundMessage::Response(r) if r.id == RequestId::Number(1))
+        );
+        assert_eq!(notification_method(&messages[1]), "n");
+    }
+
+    #[test]
+    fn test_header_value_after_stray_output() {
+        assert_eq!(
+            header_value("Content-Length: 12", CONTENT_LENGTH_HEADER),
+            Some("12")
+        );
+        assert_eq!(
+            header_value("bannercontent-length:7 ", CONTENT_LENGTH_HEADER),
+            Some("7")
+        );
+        assert_eq!(
+            header_value("Content-Type: json", CONTENT_LENGTH_HEADER),
+            None
+        );
+    }
+
+    #[test]
+    fn test_content_type_charset() {
+        assert_eq!(
+            content_type_charset("application/vscode-jsonrpc; charset=utf-8"),
+            Some("utf-8".to_string())
+        );
+        assert_eq!(
+            content_type_charset("application/json; Charset=\"UTF8\""),
+            Some("UTF8".to_string())
+        );
+        assert_eq!(content_type_charset("application/json"), None);
+    }
+
+    #[tokio::test]
+    async fn test_receive_checks_content_type_charset() {
+        let note = r#"{"jsonrpc":"2.0","method":"a"}"#;
+        let utf8 = format!(
+            "Content-Type: application/vscode-jsonrpc; charset=utf8\r\n{}",
+            frame(note)
+        );
+        assert_eq!(receive_all(&utf8).await.len(), 1);
+
+        // A frame in an unsupported charset is skipped; the next one still
+        // arrives.
+        let latin1 = format!(
+            "Content-Length: {}\r\nContent-Type: application/json; charset=iso-8859-1\r\n\r\n{note}{}",
+            note.len(),
+            frame(&note.replace("\"a\"", "\"b\""))
+        );
+        let messages = receive_all(&latin1).await;
+        let methods: Vec<_> = messages.iter().map(notification_method).collect();
+        assert_eq!(methods, ["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_receive_skips_messages_over_max_size() {
+        let note = r#"{"jsonrpc":"2.0","method":"a"}"#;
+        let large = format!(r#"{{"jsonrpc":"2.0","method":"{}"}}"#, "x".repeat(64));
+        let input = format!("{}{}", frame(&large), frame(note));
+
+        let mut reader = MessageReader::new(input.as_bytes());
+        reader.max_content_length = 32;
+        let message = reader.receive().await.unwrap();
+        assert_eq!(notification_method(&message), "a");
+    }
+
     #[test]
     fn test_header_parsing() {
         let headers_text = "Content-Length: 123\r\nContent-Type: application/json\r\n";
@@ -254,8 +741,9 @@ mod tests {
     }
 
     #[test]
-    fn test_max_content_length_constant() {
-        assert_eq!(MAX_CONTENT_LENGTH, 10 * 1024 * 1024);
+    fn test_default_max_content_length() {
+        let reader = MessageReader::new(&b""[..]);
+        assert_eq!(reader.max_content_length, 10 * 1024 * 1024);
     }
 
     #[test]
@@ -382,4 +870,167 @@ mod tests {
             assert_eq!(value_trimmed, "456");
         }
     }
+
+    /// Property tests over malformed and arbitrary server output.
+    mod properties {
+        use proptest::prelude::*;
+
+        use super::*;
+        use crate::lsp::LspNotification;
+
+        /// Arbitrary JSON without floats, which do not survive a text round
+        /// trip bit for bit.
+        fn json_value() -> impl Strategy<Value = Value> {
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::from),
+                any::<i64>().prop_map(Value::from),
+                ".{0,12}".prop_map(Value::from),
+            ];
+            leaf.prop_recursive(3, 24, 4, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+                    prop::collection::btree_map(".{0,8}", inner, 0..4)
+                        .prop_map(|map| Value::Object(map.into_iter().collect())),
+                ]
+            })
+        }
+
+        /// Lines a server might print outside of frames.
+        fn stray_line() -> impl Strategy<Value = String> {
+            "[ -~]{0,40}".prop_filter("no headers", |line| {
+                let line = line.to_ascii_lowercase();
+                !line.contains(CONTENT_LENGTH_HEADER) && !line.contains(CONTENT_TYPE_HEADER)
+            })
+        }
+
+        fn run<F: std::future::Future>(future: F) -> F::Output {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(future)
+        }
+
+        proptest! {
+            #[test]
+            fn sent_notifications_are_received(
+                notes in prop::collection::vec((".{1,16}", json_value()), 0..8),
+            ) {
+                let (server_stdin, client_stdout) = tokio::io::duplex(1024);
+                let mut client = LspTransport::new(server_stdin, tokio::io::empty());
+                let mut server = LspTransport::new(tokio::io::sink(), client_stdout);
+
+                let received = run(async {
+                    let send = async {
+                        for (method, params) in &notes {
+                            let note = serde_json::json!({
+                                "jsonrpc": "2.0",
+                                "method": method,
+                                "params": params,
+                            });
+                            client.send(&note).await.unwrap();
+                        }
+                        drop(client);
+                    };
+                    let receive = async {
+                        let mut received = Vec::new();
+                        while let Ok(message) = server.receive().await {
+                            received.push(message);
+                        }
+                        received
+                    };
+                    tokio::join!(send, receive).1
+                });
+
+                prop_assert_eq!(received.len(), notes.len());
+                for (message, (method, params)) in received.iter().zip(&notes) {
+                    let InboundMessage::Notification(note) = message else {
+                        return Err(TestCaseError::fail(format!("not a notification: {message:?}")));
+                    };
+                    prop_assert_eq!(&note.method, method);
+                    // A `null` params field reads as no params.
+                    prop_assert_eq!(note.params.as_ref(), Some(params).filter(|p| !p.is_null()));
+                }
+            }
+
+            #[test]
+            fn stray_output_between_frames_is_skipped(
+                frames in prop::collection::vec((prop::collection::vec(stray_line(), 0..3), "[a-z/]{1,12}"), 0..6),
+            ) {
+                let mut input = String::new();
+                for (stray, method) in &frames {
+                    for line in stray {
+                        input.push_str(line);
+                        input.push('\n');
+                    }
+                    input.push_str(&frame(&serde_json::json!({ "jsonrpc": "2.0", "method": method }).to_string()));
+                }
+
+                let messages = run(receive_all(&input));
+                let methods: Vec<_> = messages.iter().map(notification_method).collect();
+                let expected: Vec<_> = frames.iter().map(|(_, method)| method.as_str()).collect();
+                prop_assert_eq!(methods, expected);
+            }
+
+            #[test]
+            fn arbitrary_output_ends_without_panicking(
+                declared in prop::option::of(0usize..96),
+                bytes in prop::collection::vec(any::<u8>(), 0..256),
+            ) {
+                let mut input = declared
+                    .map(|length| format!("Content-Length: {length}\r\n\r\n").into_bytes())
+                    .unwrap_or_default();
+                input.extend_from_slice(&bytes);
+
+                // Every message consumes input, so the reader must give up
+                // within one call per byte.
+                let ended = run(async {
+                    let mut reader = MessageReader::new(input.as_slice());
+                    for _ in 0..=input.len() {
+                        if reader.receive().await.is_err() {
+                            return true;
+                        }
+                    }
+                    false
+                });
+                prop_assert!(ended);
+            }
+
+            #[test]
+            fn header_values_are_found_after_stray_output(
+                prefix in stray_line(),
+                length in any::<usize>(),
+                charset in "[a-zA-Z0-9-]{1,12}",
+            ) {
+                let line = format!("{prefix}Content-Length: {length}");
+                let expected = length.to_string();
+                prop_assert_eq!(header_value(&line, CONTENT_LENGTH_HEADER), Some(expected.as_str()));
+                let value = format!("application/vscode-jsonrpc; charset=\"{charset}\"");
+                prop_assert_eq!(content_type_charset(&value), Some(charset));
+            }
+
+            #[test]
+            fn header_helpers_accept_any_line(line in "\\PC*") {
+                let _ = header_value(&line, CONTENT_LENGTH_HEADER);
+                let _ = content_type_charset(&line);
+            }
+
+            #[test]
+            fn notifications_parse_from_any_params(
+                method in prop_oneof![
+                    Just("textDocument/publishDiagnostics".to_string()),
+                    Just("window/logMessage".to_string()),
+                    Just("window/showMessage".to_string()),
+                    Just("$/logTrace".to_string()),
+                    Just("$/progress".to_string()),
+                    Just("telemetry/event".to_string()),
+                    ".{0,24}",
+                ],
+                params in prop::option::of(json_value()),
+            ) {
+                let _ = LspNotification::parse(&method, params);
+            }
+        }
+    }
 }
@@ -85,11 +85,11 @@ pub enum InboundMessage {
 /// Typed LSP notification variants.
 ///
 /// Uses types from `lsp_types` crate for LSP-standard notifications.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum LspNotification {
     /// textDocument/publishDiagnostics
     PublishDiagnostics(PublishDiagnosticsParams),
-    /// window/logMessage
+    /// window/logMessage, and $/logTrace recorded as a log message
     LogMessage(LogMessageParams),
     /// window/showMessage
     ShowMessage(ShowMessageParams),
@@ -100,6 +100,8 @@ pub enum LspNotification {
         /// Progress value.
         value: serde_json::Value,
     },
+    /// telemetry/event
+    Telemetry(serde_json::Value),
     /// Unknown or unhandled notification
     Other {
         /// Method name.
@@ -161,6 +163,26 @@ impl LspNotification {
                     params: None,
                 }
             }
+            // Traces go to the log buffer, with the verbose details on the
+            // lines after the message.
+            "$/logTrace" => {
+                if let Some(p) = params
+                    && let Ok(trace) = serde_json::from_value::<lsp_types::LogTraceParams>(p)
+                {
+                    let message = match trace.verbose {
+                        Some(verbose) => format!("{}\n{verbose}", trace.message),
+                        None => trace.message,
+                    };
+                    return Self::LogMessage(LogMessageParams {
+                        typ: lsp_types::MessageType::LOG,
+                        message,
+                    });
+                }
+                Self::Other {
+                    method: Cow::Owned(method.to_string()),
+                    params: None,
+                }
+            }
             "window/showMessage" => {
                 if let Some(p) = params
                     && let Ok(parsed) = serde_json::from_value(p)
@@ -183,6 +205,8 @@ impl LspNotification {
                     params,
                 }
             }
+            // The payload is server-defined; keep it as is.
+            "telemetry/event" => Self::Telemetry(params.unwrap_or(Value::Null)),
             _ => Self::Other {
                 method: Cow::Owned(method.to_string()),
                 params,
@@ -380,6 +404,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_telemetry_event() {
+        let params = json!({"type": "perf", "durationMs": 1520});
+
+        match super::LspNotification::parse("telemetry/event", Some(params.clone())) {
+            super::LspNotification::Telemetry(data) => assert_eq!(data, params),
+            _ => panic!("Expected Telemetry variant"),
+        }
+        match super::LspNotification::parse("telemetry/event", None) {
+            super::LspNotification::Telemetry(data) => assert!(data.is_null()),
+            _ => panic!("Expected Telemetry variant"),
+        }
+    }
+
+    #[test]
+    fn test_parse_log_trace() {
+        let params = json!({
+            "message": "Sending request 'textDocument/hover - (12)'.",
+            "verbose": "Params: {}"
+        });
+
+        match super::LspNotification::parse("$/logTrace", Some(params)) {
+            super::LspNotification::LogMessage(log) => {
+                assert_eq!(log.typ, lsp_types::MessageType::LOG);
+                assert_eq!(
+                    log.message,
+                    "Sending request 'textDocument/hover - (12)'.\nParams: {}"
+                );
+            }
+            _ => panic!("Expected LogMessage variant"),
+        }
+    }
+
     #[test]
     fn test_notification_with_no_params() {
         let notification = super::LspNotification::parse("some/notification", None);
@@ -0,0 +1,241 @@
+//! Alternatives to suggest when a server lacks the capability a tool needs.
+//!
+//! A server without a capability answers the request with `MethodNotFound`.
+//! Retrying the same tool cannot succeed, so the error returned to the agent
+//! names the missing capability and the tools that approximate it instead.
+
+use serde::Serialize;
+
+/// JSON-RPC error code of a request for a method the server does not
+/// implement.
+pub const METHOD_NOT_FOUND: i32 = -32601;
+
+/// A tool to try instead of one the server cannot answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Fallback {
+    /// Name of the tool to call instead.
+    pub tool: &'static str,
+    /// How to use the tool to get a comparable answer.
+    pub hint: &'static str,
+}
+
+/// Fallbacks for the tools backed by one LSP capability.
+struct CapabilityFallbacks {
+    /// LSP method the capability provides.
+    method: &'static str,
+    /// Tools that depend on the capability.
+    tools: &'static [&'static str],
+    fallbacks: &'static [Fallback],
+}
+
+const CAPABILITY_FALLBACKS: &[CapabilityFallbacks] = &[
+    CapabilityFallbacks {
+        method: "textDocument/implementation",
+        tools: &["go_to_implementation"],
+        fallbacks: &[Fallback {
+            tool: "get_references",
+            hint: "set include_declaration to false and look for impl blocks or subclasses among the results",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/typeDefinition",
+        tools: &["go_to_type_definition"],
+        fallbacks: &[
+            Fallback {
+                tool: "get_hover",
+                hint: "read the type from the hover text",
+            },
+            Fallback {
+                tool: "workspace_symbol_search",
+                hint: "search for the type name from the hover text",
+            },
+        ],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/declaration",
+        tools: &["get_declaration"],
+        fallbacks: &[Fallback {
+            tool: "get_definition",
+            hint: "most servers that do not separate the two answer definition requests with the declaration",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/definition",
+        tools: &["get_definition"],
+        fallbacks: &[Fallback {
+            tool: "workspace_symbol_search",
+            hint: "search for the symbol name",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/references",
+        tools: &["get_references"],
+        fallbacks: &[Fallback {
+            tool: "prepare_call_hierarchy",
+            hint: "then get_incoming_calls, for references to functions",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/prepareCallHierarchy",
+        tools: &[
+            "prepare_call_hierarchy",
+            "get_incoming_calls",
+            "get_outgoing_calls",
+        ],
+        fallbacks: &[Fallback {
+            tool: "get_references",
+            hint: "set include_declaration to false; callers are the references inside other functions",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/prepareTypeHierarchy",
+        tools: &["prepare_type_hierarchy", "get_supertypes", "get_subtypes"],
+        fallbacks: &[Fallback {
+            tool: "go_to_implementation",
+            hint: "on a trait or interface, lists the types implementing it",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/prepareRename",
+        tools: &["prepare_rename"],
+        fallbacks: &[Fallback {
+            tool: "get_definition",
+            hint: "a symbol whose definition is in the workspace can usually be renamed; rename_symbol reports an error otherwise",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/rangeFormatting",
+        tools: &["format_range"],
+        fallbacks: &[Fallback {
+            tool: "format_document",
+            hint: "formats the whole file; keep the edits inside the range",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/diagnostic",
+        tools: &["get_diagnostics", "get_diagnostics_batch"],
+        fallbacks: &[Fallback {
+            tool: "get_cached_diagnostics",
+            hint: "returns the diagnostics the server published for the file",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/documentSymbol",
+        tools: &["get_document_symbols", "get_enclosing_scope"],
+        fallbacks: &[Fallback {
+            tool: "workspace_symbol_search",
+            hint: "search by name; results include the file of each symbol",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "workspace/symbol",
+        tools: &["workspace_symbol_search", "scan_workspace"],
+        fallbacks: &[
+            Fallback {
+                tool: "search_workspace_text",
+                hint: "search for the symbol name as text",
+            },
+            Fallback {
+                tool: "get_document_symbols",
+                hint: "list the symbols of the files you expect the symbol in",
+            },
+        ],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/signatureHelp",
+        tools: &["get_signature_help"],
+        fallbacks: &[Fallback {
+            tool: "get_hover",
+            hint: "hover over the function name to read its signature",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/inlayHint",
+        tools: &["get_inlay_hints"],
+        fallbacks: &[Fallback {
+            tool: "get_hover",
+            hint: "hover over each binding to read its type",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/semanticTokens",
+        tools: &["get_semantic_tokens"],
+        fallbacks: &[
+            Fallback {
+                tool: "get_document_symbols",
+                hint: "read the kinds of the declarations in the file",
+            },
+            Fallback {
+                tool: "get_hover",
+                hint: "hover over an identifier to see what it is",
+            },
+        ],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/foldingRange",
+        tools: &["get_folding_ranges"],
+        fallbacks: &[Fallback {
+            tool: "get_document_symbols",
+            hint: "use the ranges of the symbols as the blocks of the file",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/codeLens",
+        tools: &["get_code_lenses"],
+        fallbacks: &[
+            Fallback {
+                tool: "get_references",
+                hint: "count the references of a symbol directly",
+            },
+            Fallback {
+                tool: "get_document_symbols",
+                hint: "look for test functions and main among the symbols",
+            },
+        ],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/selectionRange",
+        tools: &["get_selection_ranges", "get_enclosing_scope"],
+        fallbacks: &[Fallback {
+            tool: "find_matching_bracket",
+            hint: "find the innermost bracket pair around the position, which needs no server",
+        }],
+    },
+    CapabilityFallbacks {
+        method: "textDocument/rename",
+        tools: &["rename_symbol"],
+        fallbacks: &[Fallback {
+            tool: "get_references",
+            hint: "set include_declaration to true and edit each location",
+        }],
+    },
+];
+
+/// The LSP method `tool` needs, and the tools to try when the server does
+/// not implement it.
+#[must_use]
+pub fn for_tool(tool: &str) -> Option<(&'static str, &'static [Fallback])> {
+    CAPABILITY_FALLBACKS
+        .iter()
+        .find(|entry| entry.tools.contains(&tool))
+        .map(|entry| (entry.method, entry.fallbacks))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_tool() {
+        let (method, fallbacks) = for_tool("go_to_implementation").unwrap();
+        assert_eq!(method, "textDocument/implementation");
+        assert_eq!(fallbacks[0].tool, "get_references");
+
+        assert_eq!(
+            for_tool("get_incoming_calls").unwrap().0,
+            "textDocument/prepareCallHierarchy"
+        );
+        assert!(for_tool("get_server_logs").is_none());
+    }
+}
@@ -4,39 +4,246 @@
 //! The actual tool implementations use the `#[tool]` macro from rmcp
 //! and are defined in the `server` module.
 
-use std::sync::Arc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::Instant;
 
-use tokio::sync::Mutex;
+use serde::Serialize;
+use tokio::sync::{Mutex, MutexGuard, Notify};
 
-use crate::bridge::{ResourceSubscriptions, Translator};
+use super::rate_limit::RateLimiter;
+use crate::bridge::{ResourceSubscriptions, SessionId, Translator, next_session_id};
+use crate::error::Error;
+
+/// Number of tool calls a session remembers for `get_recent_calls`.
+const MAX_RECENT_CALLS: usize = 100;
+
+/// Maximum length of a recorded parameter summary, in characters.
+const MAX_PARAMS_SUMMARY_CHARS: usize = 200;
+
+/// A tool call made in a session.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentCall {
+    /// Name of the tool.
+    pub tool: String,
+    /// Arguments as JSON, shortened to [`MAX_PARAMS_SUMMARY_CHARS`].
+    pub params: String,
+    /// `ok` or `error`.
+    pub status: &'static str,
+    /// Error message of a failed call.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Wall-clock time of the call, in milliseconds.
+    pub duration_ms: u64,
+}
+
+impl RecentCall {
+    /// Record a call of `tool` with `arguments` that took `duration_ms`.
+    #[must_use]
+    pub fn new(
+        tool: String,
+        arguments: Option<&serde_json::Map<String, serde_json::Value>>,
+        error: Option<String>,
+        duration_ms: u64,
+    ) -> Self {
+        let params = arguments
+            .and_then(|arguments| serde_json::to_string(arguments).ok())
+            .unwrap_or_else(|| "{}".to_string());
+        let params = if params.chars().count() > MAX_PARAMS_SUMMARY_CHARS {
+            let mut short: String = params.chars().take(MAX_PARAMS_SUMMARY_CHARS).collect();
+            short.push('…');
+            short
+        } else {
+            params
+        };
+        Self {
+            tool,
+            params,
+            status: if error.is_some() { "error" } else { "ok" },
+            error,
+            duration_ms,
+        }
+    }
+}
+
+/// Recent tool calls of a session, newest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentCallsResult {
+    /// The calls, at most the requested number.
+    pub calls: Vec<RecentCall>,
+}
+
+/// Count of the MCP client sessions open on a transport that serves several,
+/// so the bridge can wind down once the last one closes.
+#[derive(Debug, Default)]
+pub struct OpenSessions {
+    /// Sessions currently open.
+    open: AtomicUsize,
+    /// Sessions opened so far, telling whether one came and went while the
+    /// bridge was idle.
+    opened: AtomicU64,
+    /// Notified when the last open session closes.
+    all_closed: Notify,
+}
+
+impl OpenSessions {
+    /// Number of sessions currently open.
+    #[must_use]
+    pub fn open(&self) -> usize {
+        self.open.load(Ordering::SeqCst)
+    }
+
+    /// Number of sessions opened so far.
+    #[must_use]
+    pub fn opened(&self) -> u64 {
+        self.opened.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the last open session closes.
+    pub async fn all_closed(&self) {
+        self.all_closed.notified().await;
+    }
+
+    fn session_opened(&self) {
+        self.opened.fetch_add(1, Ordering::SeqCst);
+        self.open.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn session_closed(&self) {
+        if self.open.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.all_closed.notify_one();
+        }
+    }
+}
 
 /// Shared context for all tool handlers.
 ///
 /// Holds the translator and subscription state. The MCP peer handle is not
 /// stored here because resource-update notifications are sent by the pump
 /// tasks in `lib.rs`, which own their own `Arc<OnceCell<Peer<RoleServer>>>`.
+///
+/// Each context belongs to one MCP client session. When it is dropped, the
+/// session's document overlays are discarded.
 pub struct HandlerContext {
     /// Translator for converting MCP calls to LSP requests.
     pub translator: Arc<Mutex<Translator>>,
     /// Set of resource URIs the MCP client has subscribed to.
     pub subscriptions: Arc<ResourceSubscriptions>,
+    /// MCP client session the context belongs to.
+    pub session: SessionId,
+    /// The session's most recent tool calls, oldest first.
+    recent_calls: std::sync::Mutex<VecDeque<RecentCall>>,
+    /// Times of the session's recent tool calls, for `workspace.rate_limits`.
+    rate_limiter: std::sync::Mutex<RateLimiter>,
+    /// Sessions open on the transport, shared by all contexts.
+    sessions: Arc<OpenSessions>,
+    /// Whether the context counts in [`Self::sessions`]. The context
+    /// sessions are created from is not a session of its own.
+    counted: bool,
 }
 
 impl HandlerContext {
-    /// Create a new handler context.
+    /// Create a new handler context for a new session.
     #[must_use]
-    pub const fn new(
+    pub fn new(
         translator: Arc<Mutex<Translator>>,
         subscriptions: Arc<ResourceSubscriptions>,
     ) -> Self {
         Self {
             translator,
             subscriptions,
+            session: next_session_id(),
+            recent_calls: std::sync::Mutex::new(VecDeque::new()),
+            rate_limiter: std::sync::Mutex::new(RateLimiter::default()),
+            sessions: Arc::new(OpenSessions::default()),
+            counted: false,
+        }
+    }
+
+    /// Create a context for another session sharing the same translator,
+    /// counted in [`Self::sessions`] until it is dropped.
+    #[must_use]
+    pub fn new_session(&self) -> Self {
+        let mut context = Self::new(
+            Arc::clone(&self.translator),
+            Arc::clone(&self.subscriptions),
+        );
+        self.sessions.session_opened();
+        context.sessions = Arc::clone(&self.sessions);
+        context.counted = true;
+        context
+    }
+
+    /// Sessions open on the transport.
+    #[must_use]
+    pub fn sessions(&self) -> Arc<OpenSessions> {
+        Arc::clone(&self.sessions)
+    }
+
+    /// Lock the translator for a tool call of this session, switching the
+    /// documents held by the servers to the session's view first.
+    pub async fn translator(&self) -> MutexGuard<'_, Translator> {
+        let mut translator = self.translator.lock().await;
+        translator.enter_session(self.session).await;
+        translator
+    }
+
+    /// Remember a finished tool call, forgetting the oldest beyond
+    /// [`MAX_RECENT_CALLS`].
+    pub fn record_call(&self, call: RecentCall) {
+        let mut calls = self
+            .recent_calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if calls.len() == MAX_RECENT_CALLS {
+            calls.pop_front();
+        }
+        calls.push_back(call);
+    }
+
+    /// Count a call of `tool` against the session's rate `limits`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RateLimited`] if the session used up the limit of the
+    /// tool's class in the last minute.
+    pub fn check_rate_limit(&self, tool: &str, limits: &HashMap<String, u32>) -> Result<(), Error> {
+        self.rate_limiter
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .check(tool, limits, Instant::now())
+    }
+
+    /// The session's last `limit` tool calls, newest first.
+    pub fn recent_calls(&self, limit: usize) -> RecentCallsResult {
+        let calls = self
+            .recent_calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        RecentCallsResult {
+            calls: calls.iter().rev().take(limit).cloned().collect(),
+        }
+    }
+}
+
+impl Drop for HandlerContext {
+    fn drop(&mut self) {
+        if self.counted {
+            self.sessions.session_closed();
+        }
+        let translator = Arc::clone(&self.translator);
+        let session = self.session;
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                translator.lock().await.end_session(session);
+            });
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
     use crate::bridge::Translator;
@@ -48,4 +255,77 @@ mod tests {
         let context = HandlerContext::new(translator, subscriptions);
         assert!(Arc::strong_count(&context.translator) == 1);
     }
+
+    #[test]
+    fn test_new_session_shares_translator() {
+        let translator = Arc::new(Mutex::new(Translator::new()));
+        let subscriptions = Arc::new(ResourceSubscriptions::new());
+        let context = HandlerContext::new(translator, subscriptions);
+        let other = context.new_session();
+        assert!(Arc::ptr_eq(&context.translator, &other.translator));
+        assert_ne!(context.session, other.session);
+    }
+
+    #[tokio::test]
+    async fn test_open_sessions() {
+        let translator = Arc::new(Mutex::new(Translator::new()));
+        let subscriptions = Arc::new(ResourceSubscriptions::new());
+        let context = HandlerContext::new(translator, subscriptions);
+        let sessions = context.sessions();
+        assert_eq!(sessions.open(), 0);
+
+        let first = context.new_session();
+        let second = context.new_session();
+        assert_eq!(sessions.open(), 2);
+        assert_eq!(sessions.opened(), 2);
+
+        drop(first);
+        drop(second);
+        assert_eq!(sessions.open(), 0);
+        assert_eq!(sessions.opened(), 2);
+        tokio::time::timeout(std::time::Duration::from_secs(1), sessions.all_closed())
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_recent_calls_ring_buffer() {
+        let translator = Arc::new(Mutex::new(Translator::new()));
+        let subscriptions = Arc::new(ResourceSubscriptions::new());
+        let context = HandlerContext::new(translator, subscriptions);
+
+        let mut arguments = serde_json::Map::new();
+        arguments.insert("query".to_string(), "x".repeat(500).into());
+        context.record_call(RecentCall::new(
+            "workspace_symbol_search".to_string(),
+            Some(&arguments),
+            None,
+            12,
+        ));
+        for n in 0..MAX_RECENT_CALLS {
+            context.record_call(RecentCall::new(
+                format!("tool_{n}"),
+                None,
+                Some("failed".to_string()),
+                1,
+            ));
+        }
+
+        let recent = context.recent_calls(2).calls;
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].tool, format!("tool_{}", MAX_RECENT_CALLS - 1));
+        assert_eq!(recent[0].status, "error");
+        assert_eq!(recent[0].params, "{}");
+        // The first call has been forgotten.
+        let all = context.recent_calls(usize::MAX).calls;
+        assert_eq!(all.len(), MAX_RECENT_CALLS);
+        assert!(
+            all.iter()
+                .all(|call| call.tool != "workspace_symbol_search")
+        );
+
+        let call = RecentCall::new("t".to_string(), Some(&arguments), None, 0);
+        assert_eq!(call.status, "ok");
+        assert_eq!(call.params.chars().count(), MAX_PARAMS_SUMMARY_CHARS + 1);
+    }
 }
@@ -3,10 +3,13 @@
 //! This module defines the MCP tools that expose LSP capabilities
 //! to AI agents.
 
+mod fallbacks;
 mod handlers;
+mod rate_limit;
 mod server;
 mod tools;
 
+pub use handlers::{OpenSessions, RecentCall};
 pub use server::McplsServer;
 pub use tools::{
     CallHierarchyCallsParams, CallHierarchyPrepareParams, CompletionsParams, DefinitionParams,
@@ -0,0 +1,177 @@
+//! Per-session rate limits on tool calls.
+//!
+//! An agent stuck in a loop can call `get_references` or `get_diagnostics`
+//! hundreds of times a minute and keep its language server busy for everyone
+//! else. `workspace.rate_limits` caps the calls per minute of each class of
+//! tools in a session; calls over the cap are rejected before they reach the
+//! server, telling the agent when the next one will be accepted.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+
+/// Window the limits are counted over.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Class of `tool`, one of [`crate::config::TOOL_CLASSES`]. Tools not
+/// listed, such as the status and log tools, are `server` tools.
+pub fn tool_class(tool: &str) -> &'static str {
+    match tool {
+        "get_hover"
+        | "get_definition"
+        | "get_declaration"
+        | "get_references"
+        | "go_to_implementation"
+        | "go_to_type_definition"
+        | "prepare_call_hierarchy"
+        | "get_incoming_calls"
+        | "get_outgoing_calls"
+        | "prepare_type_hierarchy"
+        | "get_supertypes"
+        | "get_subtypes"
+        | "get_signature_help"
+        | "get_completions"
+        | "get_inline_completions"
+        | "get_document_symbols"
+        | "get_enclosing_scope"
+        | "find_matching_bracket"
+        | "get_inlay_hints"
+        | "get_semantic_tokens"
+        | "get_folding_ranges"
+        | "get_selection_ranges"
+        | "get_code_lenses"
+        | "prepare_rename"
+        | "get_code_actions" => "navigation",
+        "get_diagnostics"
+        | "get_diagnostics_batch"
+        | "get_cached_diagnostics"
+        | "rust_run_flycheck"
+        | "get_document_drift" => "diagnostics",
+        "workspace_symbol_search"
+        | "search_workspace_text"
+        | "scan_workspace"
+        | "export_symbols"
+        | "prefetch_files" => "search",
+        "rename_symbol"
+        | "rename_symbols_batch"
+        | "rust_ssr"
+        | "format_document"
+        | "format_range"
+        | "confirm_apply"
+        | "undo_last_edit"
+        | "update_document"
+        | "save_document"
+        | "notify_manifest_changes" => "edit",
+        _ => "server",
+    }
+}
+
+/// Times of a session's recent tool calls, by tool class.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    calls: HashMap<&'static str, VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    /// Count a call of `tool` made at `now` against `limits`, the calls per
+    /// minute allowed for each tool class.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::RateLimited`] if the call's class already had its
+    /// limit of calls in the last minute. Rejected calls do not count.
+    pub fn check(
+        &mut self,
+        tool: &str,
+        limits: &HashMap<String, u32>,
+        now: Instant,
+    ) -> Result<(), Error> {
+        let class = tool_class(tool);
+        let Some(&limit) = limits.get(class).filter(|limit| **limit > 0) else {
+            return Ok(());
+        };
+        let calls = self.calls.entry(class).or_default();
+        while calls
+            .front()
+            .is_some_and(|call| now.duration_since(*call) >= WINDOW)
+        {
+            calls.pop_front();
+        }
+        if let Some(oldest) = calls.front()
+            && calls.len() >= limit as usize
+        {
+            let retry_after = WINDOW.saturating_sub(now.duration_since(*oldest));
+            return Err(Error::RateLimited {
+                class: class.to_string(),
+                limit,
+                retry_after_ms: u64::try_from(retry_after.as_millis())
+                    .unwrap_or(u64::MAX)
+                    .max(1),
+            });
+        }
+        calls.push_back(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::config::TOOL_CLASSES;
+
+    #[test]
+    fn test_tool_classes_are_configurable() {
+        for tool in [
+            "get_references",
+            "get_diagnostics",
+            "scan_workspace",
+            "rust_ssr",
+            "reset_bridge",
+        ] {
+            assert!(TOOL_CLASSES.contains(&tool_class(tool)));
+        }
+        assert_eq!(tool_class("get_references"), "navigation");
+        assert_eq!(tool_class("get_server_logs"), "server");
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limits = HashMap::from([("navigation".to_string(), 2), ("edit".to_string(), 0)]);
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+
+        assert!(limiter.check("get_hover", &limits, start).is_ok());
+        assert!(
+            limiter
+                .check("get_references", &limits, start + Duration::from_secs(10))
+                .is_ok()
+        );
+        let err = limiter
+            .check("get_definition", &limits, start + Duration::from_secs(20))
+            .unwrap_err();
+        match err {
+            Error::RateLimited {
+                class,
+                limit,
+                retry_after_ms,
+            } => {
+                assert_eq!(class, "navigation");
+                assert_eq!(limit, 2);
+                assert_eq!(retry_after_ms, 40_000);
+            }
+            other => panic!("expected RateLimited, got {other:?}"),
+        }
+
+        // Other classes, unlimited ones, and a limit of 0 are not affected.
+        for _ in 0..10 {
+            assert!(limiter.check("get_diagnostics", &limits, start).is_ok());
+            assert!(limiter.check("rename_symbol", &limits, start).is_ok());
+        }
+
+        // The first call leaves the window after a minute.
+        assert!(limiter.check("get_hover", &limits, start + WINDOW).is_ok());
+        assert!(limiter.check("get_hover", &limits, start + WINDOW).is_err());
+    }
+}
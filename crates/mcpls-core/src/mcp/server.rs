@@ -3,27 +3,267 @@
 //! This module provides the MCP server that exposes LSP capabilities
 //! as MCP tools using the rmcp SDK.
 
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use rmcp::handler::server::tool::ToolCallContext;
 use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::{
-    Implementation, ListResourcesResult, RawResource, ReadResourceRequestParams,
-    ReadResourceResult, ResourceContents, ServerCapabilities, ServerInfo, SubscribeRequestParams,
-    UnsubscribeRequestParams,
+    Implementation, ListResourcesResult, Meta, RawResource, ReadResourceRequestParams,
+    ReadResourceResult, ResourceContents, Root, ServerCapabilities, ServerInfo,
+    SubscribeRequestParams, Tool, UnsubscribeRequestParams,
 };
 use rmcp::{ErrorData as McpError, RoleServer, ServerHandler, tool, tool_handler, tool_router};
 use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
 
-use super::handlers::HandlerContext;
+use super::fallbacks;
+use super::handlers::{HandlerContext, OpenSessions, RecentCall};
 use super::tools::{
     CachedDiagnosticsParams, CallHierarchyCallsParams, CallHierarchyPrepareParams,
-    CodeActionsParams, CompletionsParams, DefinitionParams, DiagnosticsParams,
-    DocumentSymbolsParams, FormatDocumentParams, GoToImplementationParams,
-    GoToTypeDefinitionParams, HoverParams, InlayHintsParams, ReferencesParams, RenameParams,
-    ServerLogsParams, ServerMessagesParams, SignatureHelpParams, WorkspaceSymbolParams,
+    CodeActionsParams, CodeLensesParams, CompletionsParams, ConfirmApplyParams, DeclarationParams,
+    DefinitionParams, DiagnosticsBatchParams, DiagnosticsParams, DocumentDriftParams,
+    DocumentSymbolsParams, EnclosingScopeParams, ExportSymbolsParams, FoldingRangesParams,
+    FormatDocumentParams, FormatRangeParams, GoToImplementationParams, GoToTypeDefinitionParams,
+    HoverParams, InitializationInfoParams, InlayHintsParams, InlineCompletionsParams,
+    LanguageInfoParams, MatchingBracketParams, NotifyManifestChangesParams, PrefetchFilesParams,
+    PrepareRenameParams, RecentCallsParams, ReferencesParams, RenameBatchParams, RenameParams,
+    ResetBridgeParams, RunFlycheckParams, RustSsrParams, SaveDocumentParams, ScanWorkspaceParams,
+    SearchWorkspaceTextParams, SelectionRangesParams, SemanticTokensParams, ServerLogsParams,
+    ServerMessagesParams, ServerTelemetryParams, SignatureHelpParams, TypeHierarchyParams,
+    TypeHierarchyPrepareParams, UpdateDocumentParams, WorkspaceSymbolParams,
 };
 use crate::bridge::resources::{make_uri, parse_uri};
-use crate::bridge::{ResourceSubscriptions, Translator};
+use crate::bridge::{
+    CallHierarchyTarget, ResourceSubscriptions, Translator, TypeHierarchyTarget, uri_to_path,
+    write_scip_index,
+};
+use crate::config::{MAX_TOOL_TIMEOUT_SECS, SERVER_REQUEST_TOOLS};
+use crate::error::Error;
+use crate::lsp::metrics::{self, ReanchoredPosition, RequestMetrics};
+use crate::lsp::timeout;
+use crate::snapshot::{Snapshot, write_snapshot};
+
+/// Convert a translator error into an MCP tool error.
+///
+/// Errors a client can recover from carry structured `data` so callers need
+/// not parse the message.
+fn tool_error(e: &Error) -> McpError {
+    let data = match e {
+        Error::EditConflict { paths } => Some(serde_json::json!({
+            "kind": "edit_conflict",
+            "retryable": true,
+            "paths": paths,
+        })),
+        Error::UnsavedOverlay { paths } => Some(serde_json::json!({
+            "kind": "unsaved_overlay",
+            "retryable": false,
+            "paths": paths,
+        })),
+        Error::RateLimited {
+            class,
+            limit,
+            retry_after_ms,
+        } => Some(serde_json::json!({
+            "kind": "rate_limited",
+            "retryable": true,
+            "tool_class": class,
+            "limit": limit,
+            "retry_after_ms": retry_after_ms,
+        })),
+        Error::ServerIndexing {
+            language_id,
+            retry_after_secs,
+            progress,
+        } => Some(serde_json::json!({
+            "kind": "server_indexing",
+            "retryable": true,
+            "language_id": language_id,
+            "retry_after_ms": retry_after_secs.saturating_mul(1000),
+            "progress": progress,
+        })),
+        Error::LspServerError { code, .. } if *code == fallbacks::METHOD_NOT_FOUND => {
+            Some(serde_json::json!({
+                "kind": "unsupported",
+                "retryable": false,
+            }))
+        }
+        _ => None,
+    };
+    McpError::internal_error(e.to_string(), data)
+}
+
+/// Point an agent whose server lacks the capability `tool` needs at the
+/// tools that can stand in for it.
+fn add_fallbacks(error: &mut McpError, tool: &str) {
+    let Some(data) = error.data.as_mut() else {
+        return;
+    };
+    if data["kind"] != "unsupported" {
+        return;
+    }
+    let Some((method, fallbacks)) = fallbacks::for_tool(tool) else {
+        return;
+    };
+    let suggestions = fallbacks
+        .iter()
+        .map(|fallback| format!("{} ({})", fallback.tool, fallback.hint))
+        .collect::<Vec<_>>()
+        .join(" or ");
+    error.message = format!("{}; {method} unsupported, try {suggestions}", error.message).into();
+    data["capability"] = serde_json::json!(method);
+    data["alternatives"] = serde_json::json!(fallbacks);
+}
+
+/// The function a call hierarchy tool is asked about: the passed item,
+/// handle, or position, in that order of preference.
+fn call_hierarchy_target(params: CallHierarchyCallsParams) -> Result<CallHierarchyTarget, Error> {
+    match params {
+        CallHierarchyCallsParams {
+            item: Some(item), ..
+        } => Ok(CallHierarchyTarget::Item(item)),
+        CallHierarchyCallsParams {
+            handle: Some(handle),
+            ..
+        } => Ok(CallHierarchyTarget::Handle(handle)),
+        CallHierarchyCallsParams {
+            file_path: Some(file_path),
+            line: Some(line),
+            character: Some(character),
+            ..
+        } => Ok(CallHierarchyTarget::Position {
+            file_path,
+            line,
+            character,
+        }),
+        _ => Err(Error::InvalidToolParams(
+            "Pass item (from prepare_call_hierarchy), handle, or file_path, line, and character"
+                .to_string(),
+        )),
+    }
+}
+
+/// Document the request timeout of `tool`, `seconds`, in its description,
+/// and add the `timeout_seconds` argument that overrides it. Tools that send
+/// no language server requests (see [`SERVER_REQUEST_TOOLS`]) are returned
+/// unchanged.
+fn document_timeout(mut tool: Tool, seconds: u64) -> Tool {
+    if !SERVER_REQUEST_TOOLS.contains(&tool.name.as_ref()) {
+        return tool;
+    }
+    let description = tool.description.as_deref().unwrap_or_default();
+    tool.description = Some(
+        format!(
+            "{description} Language server requests time out after {seconds} s; pass timeout_seconds to change that."
+        )
+        .into(),
+    );
+    let mut schema = (*tool.input_schema).clone();
+    if let Some(properties) = schema
+        .entry("properties")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+    {
+        properties.insert(
+            "timeout_seconds".to_string(),
+            serde_json::json!({
+                "type": "integer",
+                "minimum": 1,
+                "maximum": MAX_TOOL_TIMEOUT_SECS,
+                "description": format!(
+                    "Seconds each language server request may take (default {seconds}, at most {MAX_TOOL_TIMEOUT_SECS})."
+                ),
+            }),
+        );
+    }
+    tool.input_schema = Arc::new(schema);
+    tool
+}
+
+/// Take the `timeout_seconds` argument out of a tool call's arguments.
+fn take_timeout(arguments: Option<&mut rmcp::model::JsonObject>) -> Result<Option<u64>, Error> {
+    let Some(value) = arguments.and_then(|arguments| arguments.remove("timeout_seconds")) else {
+        return Ok(None);
+    };
+    match value.as_u64() {
+        Some(seconds) if (1..=MAX_TOOL_TIMEOUT_SECS).contains(&seconds) => Ok(Some(seconds)),
+        _ => Err(Error::InvalidToolParams(format!(
+            "timeout_seconds must be an integer between 1 and {MAX_TOOL_TIMEOUT_SECS}, got {value}"
+        ))),
+    }
+}
+
+/// The type a type hierarchy tool is asked about: the passed item, handle,
+/// or position, in that order of preference.
+fn type_hierarchy_target(params: TypeHierarchyParams) -> Result<TypeHierarchyTarget, Error> {
+    match params {
+        TypeHierarchyParams {
+            item: Some(item), ..
+        } => Ok(TypeHierarchyTarget::Item(item)),
+        TypeHierarchyParams {
+            handle: Some(handle),
+            ..
+        } => Ok(TypeHierarchyTarget::Handle(handle)),
+        TypeHierarchyParams {
+            file_path: Some(file_path),
+            line: Some(line),
+            character: Some(character),
+            ..
+        } => Ok(TypeHierarchyTarget::Position {
+            file_path,
+            line,
+            character,
+        }),
+        _ => Err(Error::InvalidToolParams(
+            "Pass item (from prepare_type_hierarchy), handle, or file_path, line, and character"
+                .to_string(),
+        )),
+    }
+}
+
+/// Check a tool's `output_format` parameter against the formats it offers
+/// besides the default, `json`, and return the requested one.
+fn parse_output_format(
+    output_format: Option<&str>,
+    formats: &[&'static str],
+) -> Result<&'static str, McpError> {
+    let Some(requested) = output_format.map(str::to_lowercase) else {
+        return Ok("json");
+    };
+    std::iter::once("json")
+        .chain(formats.iter().copied())
+        .find(|format| *format == requested)
+        .ok_or_else(|| {
+            tool_error(&Error::InvalidToolParams(format!(
+                "Invalid output_format: '{requested}'. Valid values: json, {}",
+                formats.join(", ")
+            )))
+        })
+}
+
+/// Add `metrics` to a tool result's `_meta` under `mcpls/metrics`.
+fn attach_metrics(result: &mut rmcp::model::CallToolResult, metrics: &RequestMetrics) {
+    if let Ok(value) = serde_json::to_value(metrics) {
+        result
+            .meta
+            .get_or_insert_with(Meta::new)
+            .0
+            .insert("mcpls/metrics".to_string(), value);
+    }
+}
+
+/// Add the position a tool call was re-anchored at to the result's `_meta`
+/// under `mcpls/reanchored`. Unlike the metrics, it is always reported: the
+/// answer is for a different position than the one asked about.
+fn attach_reanchored(result: &mut rmcp::model::CallToolResult, reanchored: &ReanchoredPosition) {
+    if let Ok(value) = serde_json::to_value(reanchored) {
+        result
+            .meta
+            .get_or_insert_with(Meta::new)
+            .0
+            .insert("mcpls/reanchored".to_string(), value);
+    }
+}
 
 /// MCP server that exposes LSP capabilities as tools.
 #[derive(Clone)]
@@ -43,9 +283,24 @@ impl McplsServer {
         Self { context }
     }
 
+    /// Create a server for another MCP client session sharing this server's
+    /// translator. The sessions' document overlays are kept apart.
+    #[must_use]
+    pub fn new_session(&self) -> Self {
+        Self {
+            context: Arc::new(self.context.new_session()),
+        }
+    }
+
+    /// Sessions created with [`Self::new_session`] that are still open.
+    #[must_use]
+    pub fn sessions(&self) -> Arc<OpenSessions> {
+        self.context.sessions()
+    }
+
     /// Get hover information at a position in a file.
     #[tool(
-        description = "Type and documentation info at position. Returns signatures, docs, and inferred types for symbols."
+        description = "Type and documentation info at position, or for the symbol named by symbol_name. Returns signatures, docs, and inferred types for symbols. Use sections=[\"signature\"] or max_length to skip long doc comments."
     )]
     async fn get_hover(
         &self,
@@ -53,23 +308,41 @@ impl McplsServer {
             file_path,
             line,
             character,
+            symbol_name,
+            occurrence_index,
+            sections,
+            max_length,
         }): Parameters<HoverParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator.handle_hover(file_path, line, character).await
+            let mut translator = self.context.translator().await;
+            let result = match translator
+                .resolve_position(
+                    &file_path,
+                    line,
+                    character,
+                    symbol_name.as_deref(),
+                    occurrence_index,
+                )
+                .await
+            {
+                Ok((line, character)) => translator.handle_hover(file_path, line, character).await,
+                Err(e) => Err(e),
+            };
+            drop(translator);
+            result.map(|hover| hover.shaped(&sections, max_length))
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
     /// Get the definition location of a symbol.
     #[tool(
-        description = "Definition location of symbol at position. Returns file path, line, and character where declared."
+        description = "Definition location of symbol at position, or of the symbol named by symbol_name. Returns file path, line, and character where declared. On a trait or interface this is the declaration; use go_to_implementation for its implementations."
     )]
     async fn get_definition(
         &self,
@@ -77,25 +350,43 @@ impl McplsServer {
             file_path,
             line,
             character,
+            symbol_name,
+            occurrence_index,
+            include_context,
+            limit,
         }): Parameters<DefinitionParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator
-                .handle_definition(file_path, line, character)
+            let mut translator = self.context.translator().await;
+            match translator
+                .resolve_position(
+                    &file_path,
+                    line,
+                    character,
+                    symbol_name.as_deref(),
+                    occurrence_index,
+                )
                 .await
+            {
+                Ok((line, character)) => {
+                    translator
+                        .handle_definition(file_path, line, character, include_context, limit)
+                        .await
+                }
+                Err(e) => Err(e),
+            }
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
     /// Find all references to a symbol.
     #[tool(
-        description = "All references to symbol at position. Returns locations across workspace where symbol is used."
+        description = "All references to symbol at position, or to the symbol named by symbol_name. Returns locations across workspace where symbol is used. output_format=dot returns a Graphviz graph of the referencing files instead of JSON."
     )]
     async fn get_references(
         &self,
@@ -103,20 +394,51 @@ impl McplsServer {
             file_path,
             line,
             character,
+            symbol_name,
+            occurrence_index,
             include_declaration,
+            limit,
+            scope,
+            output_format,
         }): Parameters<ReferencesParams>,
     ) -> Result<String, McpError> {
+        let dot = parse_output_format(output_format.as_deref(), &["dot"])? == "dot";
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator
-                .handle_references(file_path, line, character, include_declaration)
+            let mut translator = self.context.translator().await;
+            let result = match translator
+                .resolve_position(
+                    &file_path,
+                    line,
+                    character,
+                    symbol_name.as_deref(),
+                    occurrence_index,
+                )
                 .await
+            {
+                Ok((line, character)) => translator
+                    .handle_references(
+                        file_path.clone(),
+                        line,
+                        character,
+                        include_declaration,
+                        limit,
+                        &scope,
+                    )
+                    .await
+                    .map(|value| (value, line, character)),
+                Err(e) => Err(e),
+            };
+            drop(translator);
+            result
         };
 
         match result {
-            Ok(value) => serde_json::to_string(&value)
+            Ok((value, line, character)) if dot => {
+                Ok(value.to_dot(Path::new(&file_path), line, character))
+            }
+            Ok((value, ..)) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
@@ -129,406 +451,441 @@ impl McplsServer {
         Parameters(DiagnosticsParams { file_path }): Parameters<DiagnosticsParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
+            let mut translator = self.context.translator().await;
             translator.handle_diagnostics(file_path).await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Rename a symbol across the workspace.
+    /// Get diagnostics for several files at once.
     #[tool(
-        description = "Rename symbol across workspace. Returns text edits for all files where symbol is used."
+        description = "Diagnostics for a list of files and/or a glob. Opens the files and queries them concurrently; returns per-file results. Stops at deadline_ms with the files checked so far and completed=false."
     )]
-    async fn rename_symbol(
+    async fn get_diagnostics_batch(
         &self,
-        Parameters(RenameParams {
+        Parameters(DiagnosticsBatchParams {
+            paths,
+            glob,
+            max_files,
+            deadline_ms,
+        }): Parameters<DiagnosticsBatchParams>,
+    ) -> Result<String, McpError> {
+        let deadline = Instant::now() + Duration::from_millis(deadline_ms);
+        // Only opening the files needs the translator; the servers are
+        // queried after the lock is released.
+        let batch = {
+            let mut translator = self.context.translator().await;
+            translator
+                .prepare_diagnostics_batch(paths, glob.as_deref(), max_files)
+                .await
+        };
+
+        match batch {
+            Ok(batch) => serde_json::to_string(&batch.collect(deadline).await)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Open files ahead of positional queries.
+    #[tool(
+        description = "Open a list of files on their language servers concurrently, so later queries on them do not wait for the open. Returns per-file status."
+    )]
+    async fn prefetch_files(
+        &self,
+        Parameters(PrefetchFilesParams { paths }): Parameters<PrefetchFilesParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_prefetch_files(paths).await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Check whether a symbol can be renamed.
+    #[tool(
+        description = "Check whether the symbol at a position can be renamed before calling rename_symbol. Returns renameable, the range a rename replaces, and the current name as placeholder, or renameable=false with the server's reason."
+    )]
+    async fn prepare_rename(
+        &self,
+        Parameters(PrepareRenameParams {
             file_path,
             line,
             character,
-            new_name,
-        }): Parameters<RenameParams>,
+        }): Parameters<PrepareRenameParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
+            let mut translator = self.context.translator().await;
             translator
-                .handle_rename(file_path, line, character, new_name)
+                .handle_prepare_rename(file_path, line, character)
                 .await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get code completion suggestions.
+    /// Rename a symbol across the workspace.
     #[tool(
-        description = "Completion suggestions at position. Returns methods, functions, variables, types, and snippets."
+        description = "Rename symbol across workspace. Returns text edits for all files where symbol is used; with apply=true also writes them to disk."
     )]
-    async fn get_completions(
+    async fn rename_symbol(
         &self,
-        Parameters(CompletionsParams {
+        Parameters(RenameParams {
             file_path,
             line,
             character,
-            trigger,
-        }): Parameters<CompletionsParams>,
+            new_name,
+            apply,
+        }): Parameters<RenameParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
+            let mut translator = self.context.translator().await;
             translator
-                .handle_completions(file_path, line, character, trigger)
+                .handle_rename(file_path, line, character, new_name, apply)
                 .await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get all symbols in a document.
+    /// Rename several symbols as one edit.
     #[tool(
-        description = "Symbols in a file. Returns hierarchical outline with functions, classes, structs, and locations."
+        description = "Rename several symbols at once, e.g. for a systematic API rename. Positions refer to the current code; the renames are merged into one edit, rejected if they overlap, and with apply=true written to disk all or none."
     )]
-    async fn get_document_symbols(
+    async fn rename_symbols_batch(
         &self,
-        Parameters(DocumentSymbolsParams { file_path }): Parameters<DocumentSymbolsParams>,
+        Parameters(RenameBatchParams { renames, apply }): Parameters<RenameBatchParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator.handle_document_symbols(file_path).await
+            let mut translator = self.context.translator().await;
+            translator.handle_rename_batch(renames, apply).await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Format a document according to language server rules.
+    /// Undo the most recent edit mcpls applied to disk.
     #[tool(
-        description = "Format document with language-specific rules. Returns text edits for indentation, spacing, and style."
+        description = "Undo the most recent edit mcpls applied to disk (e.g. rename_symbol with apply=true). Restores previous file contents; refuses if a file changed since."
     )]
-    async fn format_document(
-        &self,
-        Parameters(FormatDocumentParams {
-            file_path,
-            tab_size,
-            insert_spaces,
-        }): Parameters<FormatDocumentParams>,
-    ) -> Result<String, McpError> {
+    async fn undo_last_edit(&self) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator
-                .handle_format_document(file_path, tab_size, insert_spaces)
-                .await
+            let mut translator = self.context.translator().await;
+            translator.handle_undo_last_edit().await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Search for symbols across the workspace.
+    /// Apply an edit held back for exceeding the auto-apply limits.
     #[tool(
-        description = "Search workspace symbols by name. Supports partial matching and fuzzy search."
+        description = "Apply an edit that rename_symbol, rename_symbols_batch, or rust_ssr only previewed because it touched more files or made more edits than the configured limits. Pass the returned confirmation_token; each token works once."
     )]
-    async fn workspace_symbol_search(
+    async fn confirm_apply(
         &self,
-        Parameters(WorkspaceSymbolParams {
-            query,
-            kind_filter,
-            limit,
-        }): Parameters<WorkspaceSymbolParams>,
+        Parameters(ConfirmApplyParams { token }): Parameters<ConfirmApplyParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator
-                .handle_workspace_symbol(query, kind_filter, limit)
-                .await
+            let mut translator = self.context.translator().await;
+            translator.handle_confirm_apply(&token).await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get code actions for a range.
+    /// Set or clear the unsaved content of a document for this session.
     #[tool(
-        description = "Code actions for range. Returns quick fixes, refactorings, and source actions with edits."
+        description = "Push unsaved content for a file so later tools analyze it instead of the file on disk. Private to this MCP session; omit content to go back to the file on disk."
     )]
-    async fn get_code_actions(
+    async fn update_document(
         &self,
-        Parameters(CodeActionsParams {
-            file_path,
-            start_line,
-            start_character,
-            end_line,
-            end_character,
-            kind_filter,
-        }): Parameters<CodeActionsParams>,
+        Parameters(UpdateDocumentParams { file_path, content }): Parameters<UpdateDocumentParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator
-                .handle_code_actions(
-                    file_path,
-                    start_line,
-                    start_character,
-                    end_line,
-                    end_character,
-                    kind_filter,
-                )
-                .await
+            let mut translator = self.context.translator().await;
+            translator.handle_update_document(file_path, content).await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Prepare call hierarchy at a position.
+    /// Save a document, applying the server's will-save edits.
     #[tool(
-        description = "Prepare call hierarchy at position. Returns callable items for incoming/outgoing call analysis."
+        description = "Save a file like an editor: writes this session's unsaved content (or the file as is), first applying edits the server asks for on save, such as whitespace cleanup or import sorting. Undo with undo_last_edit."
     )]
-    async fn prepare_call_hierarchy(
+    async fn save_document(
         &self,
-        Parameters(CallHierarchyPrepareParams {
-            file_path,
-            line,
-            character,
-        }): Parameters<CallHierarchyPrepareParams>,
+        Parameters(SaveDocumentParams { file_path }): Parameters<SaveDocumentParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator
-                .handle_call_hierarchy_prepare(file_path, line, character)
-                .await
+            let mut translator = self.context.translator().await;
+            translator.handle_save_document(file_path).await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get incoming calls (callers).
+    /// Compare the content tracked for a document with the file on disk.
     #[tool(
-        description = "Functions calling the specified item. Takes call hierarchy item, returns all callers."
+        description = "Check whether the content mcpls tracks for an open file, which is what its language server analyzes, differs from the file on disk, with a unified diff if so. Use when results look stale."
     )]
-    async fn get_incoming_calls(
+    async fn get_document_drift(
         &self,
-        Parameters(CallHierarchyCallsParams { item }): Parameters<CallHierarchyCallsParams>,
+        Parameters(DocumentDriftParams { file_path }): Parameters<DocumentDriftParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator.handle_incoming_calls(item).await
+            let translator = self.context.translator().await;
+            translator.handle_document_drift(file_path).await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get outgoing calls (callees).
+    /// Report build manifests edited outside of mcpls.
     #[tool(
-        description = "Functions called by the specified item. Takes call hierarchy item, returns all callees."
+        description = "Report edited build manifests (Cargo.toml, go.mod, package.json) so language servers reload the project model, e.g. after adding a dependency. Edits mcpls writes itself are reported automatically."
     )]
-    async fn get_outgoing_calls(
+    async fn notify_manifest_changes(
         &self,
-        Parameters(CallHierarchyCallsParams { item }): Parameters<CallHierarchyCallsParams>,
+        Parameters(NotifyManifestChangesParams { paths }): Parameters<NotifyManifestChangesParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator.handle_outgoing_calls(item).await
+            let translator = self.context.translator().await;
+            translator.handle_notify_manifest_changes(paths).await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get cached diagnostics for a file.
+    /// Get code completion suggestions.
     #[tool(
-        description = "Cached diagnostics from server notifications. Faster than get_diagnostics, no new analysis."
+        description = "Completion suggestions at position. Returns methods, functions, variables, types, and snippets, with documentation and auto-import edits for the top items unless resolve=false."
     )]
-    async fn get_cached_diagnostics(
+    async fn get_completions(
         &self,
-        Parameters(CachedDiagnosticsParams { file_path }): Parameters<CachedDiagnosticsParams>,
+        Parameters(CompletionsParams {
+            file_path,
+            line,
+            character,
+            trigger,
+            resolve,
+        }): Parameters<CompletionsParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator.handle_cached_diagnostics(&file_path)
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_completions(file_path, line, character, trigger, resolve)
+                .await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get recent LSP server log messages.
+    /// Get inline completions.
     #[tool(
-        description = "Recent server log messages. Filter by level (error, warning, info, debug) for debugging."
+        description = "Experimental. Inline (ghost text) completions at position: multi-line suggestions of the code that likely follows, complementing get_completions. Requires a server configured with inline_completions = true that supports LSP 3.18 textDocument/inlineCompletion."
     )]
-    async fn get_server_logs(
+    async fn get_inline_completions(
         &self,
-        Parameters(ServerLogsParams { limit, min_level }): Parameters<ServerLogsParams>,
+        Parameters(InlineCompletionsParams {
+            file_path,
+            line,
+            character,
+        }): Parameters<InlineCompletionsParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator.handle_server_logs(limit, min_level)
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_inline_completions(file_path, line, character)
+                .await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get recent LSP server messages.
+    /// Get all symbols in a document.
     #[tool(
-        description = "Recent server messages (showMessage notifications). User-facing prompts and status updates."
+        description = "Symbols in a file. Returns hierarchical outline with functions, classes, structs, and locations. output_format=tree returns a compact indented text outline instead of JSON."
     )]
-    async fn get_server_messages(
+    async fn get_document_symbols(
         &self,
-        Parameters(ServerMessagesParams { limit }): Parameters<ServerMessagesParams>,
+        Parameters(DocumentSymbolsParams {
+            file_path,
+            output_format,
+        }): Parameters<DocumentSymbolsParams>,
     ) -> Result<String, McpError> {
+        let tree = parse_output_format(output_format.as_deref(), &["tree"])? == "tree";
         let result = {
-            let mut translator = self.context.translator.lock().await;
-            translator.handle_server_messages(limit)
+            let mut translator = self.context.translator().await;
+            translator.handle_document_symbols(file_path).await
         };
 
         match result {
+            Ok(value) if tree => Ok(value.to_tree()),
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get signature help at a position.
+    /// Get the region enclosing a position.
     #[tool(
-        description = "Signature help at position. Returns parameter info, active signature/parameter, and documentation while typing a call."
+        description = "Exact range and text of the expression, statement, function, or type enclosing a position. Use to quote or rewrite precisely the right region."
     )]
-    async fn get_signature_help(
+    async fn get_enclosing_scope(
         &self,
-        Parameters(SignatureHelpParams {
+        Parameters(EnclosingScopeParams {
             file_path,
             line,
             character,
-        }): Parameters<SignatureHelpParams>,
+            level,
+        }): Parameters<EnclosingScopeParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
+            let mut translator = self.context.translator().await;
             translator
-                .handle_signature_help(file_path, line, character)
+                .handle_enclosing_scope(file_path, line, character, &level)
                 .await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Go to implementation locations.
+    /// Find the bracket matching a position.
     #[tool(
-        description = "Implementation locations of trait method or interface member at position."
+        description = "Matching bracket: for a position on (, [, or {, or on its closing bracket, the positions of both; elsewhere the innermost pair around it. Use to find block boundaries before editing. No language server needed."
     )]
-    async fn go_to_implementation(
+    async fn find_matching_bracket(
         &self,
-        Parameters(GoToImplementationParams {
+        Parameters(MatchingBracketParams {
             file_path,
             line,
             character,
-        }): Parameters<GoToImplementationParams>,
+        }): Parameters<MatchingBracketParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
+            let translator = self.context.translator().await;
             translator
-                .handle_implementation(file_path, line, character)
+                .handle_find_matching_bracket(file_path, line, character)
                 .await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Go to type definition location.
+    /// Format a document according to language server rules.
     #[tool(
-        description = "Type definition location of expression at position. Distinct from go-to-definition for variable bindings."
+        description = "Format document with language-specific rules. Returns text edits for indentation, spacing, and style."
     )]
-    async fn go_to_type_definition(
+    async fn format_document(
         &self,
-        Parameters(GoToTypeDefinitionParams {
+        Parameters(FormatDocumentParams {
             file_path,
-            line,
-            character,
-        }): Parameters<GoToTypeDefinitionParams>,
+            tab_size,
+            insert_spaces,
+        }): Parameters<FormatDocumentParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
+            let mut translator = self.context.translator().await;
             translator
-                .handle_type_definition(file_path, line, character)
+                .handle_format_document(file_path, tab_size, insert_spaces)
                 .await
         };
 
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
 
-    /// Get inlay hints for a range.
+    /// Format a range of a document according to language server rules.
     #[tool(
-        description = "Inlay hints in range. Returns inferred type/parameter annotations the editor would render inline."
+        description = "Format only the text between start and end with language-specific rules, e.g. a block just edited. Returns text edits like format_document, without touching the rest of the file."
     )]
-    async fn get_inlay_hints(
+    async fn format_range(
         &self,
-        Parameters(InlayHintsParams {
+        Parameters(FormatRangeParams {
             file_path,
             start_line,
             start_character,
             end_line,
             end_character,
-        }): Parameters<InlayHintsParams>,
+            tab_size,
+            insert_spaces,
+        }): Parameters<FormatRangeParams>,
     ) -> Result<String, McpError> {
         let result = {
-            let mut translator = self.context.translator.lock().await;
+            let mut translator = self.context.translator().await;
             translator
-                .handle_inlay_hints(
+                .handle_format_range(
                     file_path,
-                    start_line,
-                    start_character,
-                    end_line,
-                    end_character,
+                    (start_line, start_character),
+                    (end_line, end_character),
+                    tab_size,
+                    insert_spaces,
                 )
                 .await
         };
@@ -536,34 +893,925 @@ impl McplsServer {
         match result {
             Ok(value) => serde_json::to_string(&value)
                 .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
-            Err(e) => Err(McpError::internal_error(e.to_string(), None)),
+            Err(e) => Err(tool_error(&e)),
         }
     }
-}
 
-#[tool_handler]
-impl ServerHandler for McplsServer {
-    async fn list_resources(
+    /// Search for symbols across the workspace.
+    #[tool(
+        description = "Search workspace symbols by name. Supports partial matching and fuzzy search."
+    )]
+    async fn workspace_symbol_search(
         &self,
-        _request: Option<rmcp::model::PaginatedRequestParams>,
-        _context: rmcp::service::RequestContext<RoleServer>,
-    ) -> Result<ListResourcesResult, McpError> {
-        // TODO(critic-S5): paginate when max_documents == 0 (unlimited mode can produce
-        // very large single-page responses that may exceed transport buffers).
-        let resources: Vec<_> = {
-            let translator = self.context.translator.lock().await;
+        Parameters(WorkspaceSymbolParams {
+            query,
+            kind_filter,
+            limit,
+        }): Parameters<WorkspaceSymbolParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
             translator
-                .document_tracker()
-                .open_paths()
-                .filter_map(|path| {
-                    let uri = make_uri(path)
-                        .inspect_err(|e| {
-                            tracing::warn!(
-                                "Skipping path in list_resources (make_uri failed): {}: {e}",
-                                path.display()
-                            );
-                        })
-                        .ok()?;
+                .handle_workspace_symbol(query, kind_filter, limit)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Count workspace files per language.
+    #[tool(
+        description = "Per-language inventory of workspace files (gitignore-aware): file counts, total sizes, and whether a language server is running for each. Gives a structural overview before navigating."
+    )]
+    async fn scan_workspace(
+        &self,
+        Parameters(ScanWorkspaceParams { globs, max_files }): Parameters<ScanWorkspaceParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let translator = self.context.translator().await;
+            translator.handle_scan_workspace(&globs, max_files)
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Export the symbols of every workspace file.
+    #[tool(
+        description = "Symbol map of the whole workspace: the document symbols of every file a language server handles (gitignore-aware), for offline analysis. output_format=scip writes a SCIP index to .mcpls/index/symbols.scip instead and returns its path and counts."
+    )]
+    async fn export_symbols(
+        &self,
+        Parameters(ExportSymbolsParams {
+            globs,
+            max_files,
+            output_format,
+        }): Parameters<ExportSymbolsParams>,
+    ) -> Result<String, McpError> {
+        let scip = parse_output_format(output_format.as_deref(), &["scip"])? == "scip";
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_export_symbols(&globs, max_files).await
+        };
+
+        let map = result.map_err(|e| tool_error(&e))?;
+        let serialized = if scip {
+            let written = write_scip_index(&map).await.map_err(|e| tool_error(&e))?;
+            serde_json::to_string(&written)
+        } else {
+            serde_json::to_string(&map)
+        };
+        serialized.map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None))
+    }
+
+    /// Search text across the workspace.
+    #[tool(
+        description = "Search text across workspace files (gitignore-aware). Uses a server search extension (rust-analyzer structural search) when it covers the files, else a substring scan. Returns matches with line text."
+    )]
+    async fn search_workspace_text(
+        &self,
+        Parameters(SearchWorkspaceTextParams {
+            query,
+            globs,
+            engine,
+            case_sensitive,
+            max_results,
+        }): Parameters<SearchWorkspaceTextParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_search_workspace_text(&query, &globs, &engine, case_sensitive, max_results)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Structural search and replace with rust-analyzer.
+    #[tool(
+        description = "Rust structural search and replace (rust-analyzer SSR): rewrite every match of a pattern like foo($a, $b) to a replacement like bar($b, $a), resolving paths. Returns the edits; with dry_run=false also writes them to disk."
+    )]
+    async fn rust_ssr(
+        &self,
+        Parameters(RustSsrParams {
+            pattern,
+            replacement,
+            dry_run,
+            file_path,
+        }): Parameters<RustSsrParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_rust_ssr(&pattern, &replacement, file_path, dry_run)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get code actions for a range.
+    #[tool(
+        description = "Code actions for range. Returns quick fixes, refactorings, and source actions with edits."
+    )]
+    async fn get_code_actions(
+        &self,
+        Parameters(CodeActionsParams {
+            file_path,
+            start_line,
+            start_character,
+            end_line,
+            end_character,
+            kind_filter,
+        }): Parameters<CodeActionsParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_code_actions(
+                    file_path,
+                    start_line,
+                    start_character,
+                    end_line,
+                    end_character,
+                    kind_filter,
+                )
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Prepare call hierarchy at a position.
+    #[tool(
+        description = "Prepare call hierarchy at position. Returns callable items for incoming/outgoing call analysis."
+    )]
+    async fn prepare_call_hierarchy(
+        &self,
+        Parameters(CallHierarchyPrepareParams {
+            file_path,
+            line,
+            character,
+        }): Parameters<CallHierarchyPrepareParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_call_hierarchy_prepare(file_path, line, character)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get incoming calls (callers).
+    #[tool(
+        description = "Functions calling the specified function. Takes a call hierarchy item from prepare_call_hierarchy, its handle (or a workspace_symbol_search handle), or file_path, line, and character of the function name; returns all callers. output_format=dot returns a Graphviz graph instead of JSON."
+    )]
+    async fn get_incoming_calls(
+        &self,
+        Parameters(params): Parameters<CallHierarchyCallsParams>,
+    ) -> Result<String, McpError> {
+        let dot = parse_output_format(params.output_format.as_deref(), &["dot"])? == "dot";
+        let target = call_hierarchy_target(params).map_err(|e| tool_error(&e))?;
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_incoming_calls(target).await
+        };
+
+        match result {
+            Ok(value) if dot => Ok(value.to_dot()),
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get outgoing calls (callees).
+    #[tool(
+        description = "Functions called by the specified function. Takes a call hierarchy item from prepare_call_hierarchy, its handle (or a workspace_symbol_search handle), or file_path, line, and character of the function name; returns all callees. output_format=dot returns a Graphviz graph instead of JSON."
+    )]
+    async fn get_outgoing_calls(
+        &self,
+        Parameters(params): Parameters<CallHierarchyCallsParams>,
+    ) -> Result<String, McpError> {
+        let dot = parse_output_format(params.output_format.as_deref(), &["dot"])? == "dot";
+        let target = call_hierarchy_target(params).map_err(|e| tool_error(&e))?;
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_outgoing_calls(target).await
+        };
+
+        match result {
+            Ok(value) if dot => Ok(value.to_dot()),
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Prepare type hierarchy at a position.
+    #[tool(
+        description = "Prepare type hierarchy at position. Returns type items (classes, interfaces, traits) for supertype/subtype analysis."
+    )]
+    async fn prepare_type_hierarchy(
+        &self,
+        Parameters(TypeHierarchyPrepareParams {
+            file_path,
+            line,
+            character,
+        }): Parameters<TypeHierarchyPrepareParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_type_hierarchy_prepare(file_path, line, character)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get supertypes.
+    #[tool(
+        description = "Direct supertypes of the specified type: extended classes, implemented interfaces or traits. Takes a type hierarchy item from prepare_type_hierarchy, its handle (or a workspace_symbol_search handle), or file_path, line, and character of the type name. output_format=dot returns a Graphviz graph instead of JSON."
+    )]
+    async fn get_supertypes(
+        &self,
+        Parameters(params): Parameters<TypeHierarchyParams>,
+    ) -> Result<String, McpError> {
+        let dot = parse_output_format(params.output_format.as_deref(), &["dot"])? == "dot";
+        let target = type_hierarchy_target(params).map_err(|e| tool_error(&e))?;
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_supertypes(target).await
+        };
+
+        match result {
+            Ok(value) if dot => Ok(value.to_dot()),
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get subtypes.
+    #[tool(
+        description = "Direct subtypes of the specified type: extending classes, implementing types. Takes a type hierarchy item from prepare_type_hierarchy, its handle (or a workspace_symbol_search handle), or file_path, line, and character of the type name. output_format=dot returns a Graphviz graph instead of JSON."
+    )]
+    async fn get_subtypes(
+        &self,
+        Parameters(params): Parameters<TypeHierarchyParams>,
+    ) -> Result<String, McpError> {
+        let dot = parse_output_format(params.output_format.as_deref(), &["dot"])? == "dot";
+        let target = type_hierarchy_target(params).map_err(|e| tool_error(&e))?;
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_subtypes(target).await
+        };
+
+        match result {
+            Ok(value) if dot => Ok(value.to_dot()),
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get cached diagnostics for a file.
+    #[tool(
+        description = "Cached diagnostics from server notifications. Faster than get_diagnostics, no new analysis."
+    )]
+    async fn get_cached_diagnostics(
+        &self,
+        Parameters(CachedDiagnosticsParams { file_path }): Parameters<CachedDiagnosticsParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_cached_diagnostics(&file_path)
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Run rust-analyzer's flycheck.
+    #[tool(
+        description = "Run cargo check via rust-analyzer (flycheck) for borrowck and other compiler errors rust-analyzer does not report itself. Results arrive asynchronously; read them with get_cached_diagnostics."
+    )]
+    async fn rust_run_flycheck(
+        &self,
+        Parameters(RunFlycheckParams { file_path }): Parameters<RunFlycheckParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_rust_run_flycheck(file_path).await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Reset the bridge state.
+    #[tool(
+        description = "Recovery tool: cancel in-flight LSP requests and clear cached diagnostics (scope=requests), also close all tracked documents (scope=documents, default), or also restart the language servers (scope=servers)."
+    )]
+    async fn reset_bridge(
+        &self,
+        Parameters(ResetBridgeParams { scope }): Parameters<ResetBridgeParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_reset_bridge(&scope).await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get a language server's initialize response.
+    #[tool(
+        description = "Initialize response of a running language server: serverInfo (name, version), capabilities, and experimental fields. Confirms which server binary answered."
+    )]
+    async fn get_initialization_info(
+        &self,
+        Parameters(InitializationInfoParams { language_id }): Parameters<InitializationInfoParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let translator = self.context.translator().await;
+            translator.handle_initialization_info(&language_id)
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Summarize what a language server supports.
+    #[tool(
+        description = "What a running language server supports: negotiated position encoding, document sync kind, the LSP requests it implements, and mcpls workarounds applied for it. Use to adapt to a server before relying on a feature."
+    )]
+    async fn get_language_info(
+        &self,
+        Parameters(LanguageInfoParams { language_id }): Parameters<LanguageInfoParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let translator = self.context.translator().await;
+            translator.handle_language_info(&language_id)
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// List the languages files are routed to.
+    #[tool(
+        description = "Languages files are routed to: the effective extension table (built-in, language_extensions overrides, server file_patterns) grouped by language, with each language's server state. Use to debug why a file is treated as plaintext or has no server."
+    )]
+    async fn list_supported_languages(&self) -> Result<String, McpError> {
+        let result = {
+            let translator = self.context.translator().await;
+            translator.handle_supported_languages()
+        };
+
+        serde_json::to_string(&result)
+            .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None))
+    }
+
+    /// List this session's recent tool calls.
+    #[tool(
+        description = "Recent tool calls of this MCP session, newest first: tool name, shortened arguments, ok/error status, and duration. Use to review what was asked so far or why a call failed."
+    )]
+    async fn get_recent_calls(
+        &self,
+        Parameters(RecentCallsParams { limit }): Parameters<RecentCallsParams>,
+    ) -> Result<String, McpError> {
+        serde_json::to_string(&self.context.recent_calls(limit))
+            .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None))
+    }
+
+    /// Write a diagnostics snapshot for a bug report.
+    #[tool(
+        description = "Write a diagnostics snapshot for a bug report to .mcpls/snapshots/ under the workspace root: configuration with server env values redacted, server versions, capabilities, and state, recent server logs and messages, this session's recent tool calls, and the last LSP messages exchanged with each server. Returns the file path. The LSP messages include document content; review before sharing."
+    )]
+    async fn create_snapshot(&self) -> Result<String, McpError> {
+        let recent_calls = self.context.recent_calls(usize::MAX).calls;
+        let snapshot = {
+            let mut translator = self.context.translator().await;
+            Snapshot::collect(&mut translator, recent_calls)
+        };
+
+        let result = write_snapshot(&snapshot, None)
+            .await
+            .map_err(|e| tool_error(&e))?;
+        serde_json::to_string(&result)
+            .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None))
+    }
+
+    #[tool(
+        description = "Recent server log messages. Filter by level (error, warning, info, debug) for debugging."
+    )]
+    async fn get_server_logs(
+        &self,
+        Parameters(ServerLogsParams { limit, min_level }): Parameters<ServerLogsParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_server_logs(limit, min_level)
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get recent LSP server messages.
+    #[tool(
+        description = "Recent server messages (showMessage notifications). User-facing prompts and status updates."
+    )]
+    async fn get_server_messages(
+        &self,
+        Parameters(ServerMessagesParams { limit }): Parameters<ServerMessagesParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_server_messages(limit)
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get recent LSP telemetry events.
+    #[tool(
+        description = "Recent telemetry/event notifications. Server-defined progress and performance data, e.g. from jdtls or tsserver."
+    )]
+    async fn get_server_telemetry(
+        &self,
+        Parameters(ServerTelemetryParams { limit, language_id }): Parameters<ServerTelemetryParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_server_telemetry(limit, language_id.as_deref())
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get signature help at a position.
+    #[tool(
+        description = "Signature help at a call site. Returns each overload's label, parameter names and types, documentation, and the index of the active signature and parameter, for writing the arguments of a call."
+    )]
+    async fn get_signature_help(
+        &self,
+        Parameters(SignatureHelpParams {
+            file_path,
+            line,
+            character,
+        }): Parameters<SignatureHelpParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_signature_help(file_path, line, character)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Go to implementation locations.
+    #[tool(
+        description = "Implementation locations of trait, interface, or member at position: every concrete impl site across the workspace, in the same shape as get_definition."
+    )]
+    async fn go_to_implementation(
+        &self,
+        Parameters(GoToImplementationParams {
+            file_path,
+            line,
+            character,
+            include_context,
+            limit,
+        }): Parameters<GoToImplementationParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_implementation(file_path, line, character, include_context, limit)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Go to type definition location.
+    #[tool(
+        description = "Type definition location of expression at position: from a variable, parameter, or field to the struct, class, or interface it is an instance of. Distinct from go-to-definition for variable bindings."
+    )]
+    async fn go_to_type_definition(
+        &self,
+        Parameters(GoToTypeDefinitionParams {
+            file_path,
+            line,
+            character,
+        }): Parameters<GoToTypeDefinitionParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_type_definition(file_path, line, character)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Go to declaration location.
+    #[tool(
+        description = "Declaration location of symbol at position. Distinct from get_definition where a language separates the two, e.g. the header declaration of a C/C++ function rather than its implementation."
+    )]
+    async fn get_declaration(
+        &self,
+        Parameters(DeclarationParams {
+            file_path,
+            line,
+            character,
+        }): Parameters<DeclarationParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_declaration(file_path, line, character)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get inlay hints for a range.
+    #[tool(
+        description = "Inlay hints for a line range. Returns the inferred types and parameter names an editor would render inline, with 1-based positions; useful context when reading code without an IDE."
+    )]
+    async fn get_inlay_hints(
+        &self,
+        Parameters(InlayHintsParams {
+            file_path,
+            start_line,
+            start_character,
+            end_line,
+            end_character,
+        }): Parameters<InlayHintsParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_inlay_hints(
+                    file_path,
+                    start_line,
+                    start_character,
+                    end_line,
+                    end_character,
+                )
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get the semantic tokens of a file or line range.
+    #[tool(
+        description = "Semantic tokens of a file or line range: each token's 1-based line, character, and length with its type (function, variable, type, macro, ...) and modifiers (declaration, readonly, mutable, ...) as the server classifies them. Use to tell what identifiers are without hovering each one."
+    )]
+    async fn get_semantic_tokens(
+        &self,
+        Parameters(SemanticTokensParams {
+            file_path,
+            start_line,
+            end_line,
+            max_tokens,
+        }): Parameters<SemanticTokensParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_semantic_tokens(file_path, start_line, end_line, max_tokens)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get the foldable blocks of a file.
+    #[tool(
+        description = "Foldable blocks of a file (imports, functions, types, comments, #region markers) as 1-based line ranges with their kind. Use to see the structure of a large file before deciding which lines to read."
+    )]
+    async fn get_folding_ranges(
+        &self,
+        Parameters(FoldingRangesParams { file_path }): Parameters<FoldingRangesParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator.handle_folding_ranges(file_path).await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get the nested syntactic ranges around a position.
+    #[tool(
+        description = "Nested syntactic ranges around a position, innermost first (token, expression, statement, block, function, module), as expand-selection walks them. Each has its 1-based range, line count, and first line. Use to find the exact extent of the enclosing expression or function."
+    )]
+    async fn get_selection_ranges(
+        &self,
+        Parameters(SelectionRangesParams {
+            file_path,
+            line,
+            character,
+        }): Parameters<SelectionRangesParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_selection_ranges(file_path, line, character)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+
+    /// Get the code lenses of a file.
+    #[tool(
+        description = "Code lenses of a file: the commands a server shows above lines, such as \"3 references\", \"5 implementations\", \"Run test\", or \"Run main\", with their 1-based ranges. Lenses are resolved so titles are complete. Use to find tests and entry points or how widely a symbol is used."
+    )]
+    async fn get_code_lenses(
+        &self,
+        Parameters(CodeLensesParams {
+            file_path,
+            include_arguments,
+        }): Parameters<CodeLensesParams>,
+    ) -> Result<String, McpError> {
+        let result = {
+            let mut translator = self.context.translator().await;
+            translator
+                .handle_code_lenses(file_path, include_arguments)
+                .await
+        };
+
+        match result {
+            Ok(value) => serde_json::to_string(&value)
+                .map_err(|e| McpError::internal_error(format!("Serialization error: {e}"), None)),
+            Err(e) => Err(tool_error(&e)),
+        }
+    }
+}
+
+impl McplsServer {
+    /// Names of the tools the server offers.
+    #[must_use]
+    pub fn tool_names() -> Vec<String> {
+        Self::tool_router()
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect()
+    }
+
+    /// Ask the client for its roots and make them workspace roots, when the
+    /// client has roots and `workspace.client_roots` is on.
+    async fn sync_client_roots(&self, peer: &rmcp::Peer<RoleServer>) {
+        let has_roots = peer
+            .peer_info()
+            .is_some_and(|info| info.capabilities.roots.is_some());
+        if !has_roots || !self.context.translator().await.client_roots_enabled() {
+            return;
+        }
+        // Roots are deprecated in the latest protocol revision, but clients
+        // in use still report them.
+        #[allow(deprecated)]
+        let roots = match peer.list_roots().await {
+            Ok(result) => result.roots,
+            Err(e) => {
+                tracing::warn!("Failed to list client roots: {e}");
+                return;
+            }
+        };
+        let (added, removed) = self
+            .context
+            .translator()
+            .await
+            .set_client_roots(root_paths(&roots))
+            .await;
+        if !added.is_empty() || !removed.is_empty() {
+            tracing::info!("Client roots changed: added {added:?}, removed {removed:?}");
+        }
+    }
+}
+
+/// Local directories named by client roots. Roots that are not `file://`
+/// URIs or do not exist are skipped.
+fn root_paths(roots: &[Root]) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for root in roots {
+        let path = root
+            .uri
+            .parse::<lsp_types::Uri>()
+            .ok()
+            .and_then(|uri| uri_to_path(&uri))
+            .and_then(|path| path.canonicalize().ok())
+            .filter(|path| path.is_dir());
+        match path {
+            Some(path) if !paths.contains(&path) => paths.push(path),
+            Some(_) => {}
+            None => tracing::warn!("Ignoring client root {}", root.uri),
+        }
+    }
+    paths
+}
+
+#[tool_handler]
+impl ServerHandler for McplsServer {
+    async fn call_tool(
+        &self,
+        request: rmcp::model::CallToolRequestParams,
+        context: rmcp::service::RequestContext<RoleServer>,
+    ) -> Result<rmcp::model::CallToolResult, McpError> {
+        let mut request = request;
+        let tool = request.name.to_string();
+        let arguments = request.arguments.clone();
+        let requested_timeout = take_timeout(request.arguments.as_mut());
+        let (response_metadata, checked) = {
+            let translator = self.context.translator.lock().await;
+            (
+                translator.response_metadata(),
+                requested_timeout.and_then(|timeout| {
+                    self.context
+                        .check_rate_limit(&tool, translator.rate_limits())?;
+                    Ok(timeout.unwrap_or_else(|| translator.tool_timeout(&tool)))
+                }),
+            )
+        };
+        let (result, metrics) = match checked {
+            Ok(seconds) => {
+                let call = ToolCallContext::new(self, request, context);
+                metrics::measure(timeout::scope(
+                    Duration::from_secs(seconds),
+                    Self::tool_router().call(call),
+                ))
+                .await
+            }
+            Err(e) => (Err(tool_error(&e)), RequestMetrics::default()),
+        };
+        let result = result.map_err(|mut e| {
+            add_fallbacks(&mut e, &tool);
+            e
+        });
+
+        let error = match &result {
+            Ok(result) if result.is_error == Some(true) => {
+                Some("tool reported an error".to_string())
+            }
+            Ok(_) => None,
+            Err(e) => Some(e.message.to_string()),
+        };
+        self.context.record_call(RecentCall::new(
+            tool,
+            arguments.as_ref(),
+            error,
+            metrics.elapsed_ms,
+        ));
+
+        let mut result = result?;
+        if response_metadata {
+            attach_metrics(&mut result, &metrics);
+        }
+        if let Some(reanchored) = &metrics.reanchored {
+            attach_reanchored(&mut result, reanchored);
+        }
+        Ok(result)
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<rmcp::model::PaginatedRequestParams>,
+        _context: rmcp::service::RequestContext<RoleServer>,
+    ) -> Result<rmcp::model::ListToolsResult, McpError> {
+        let translator = self.context.translator().await;
+        let tools = Self::tool_router()
+            .list_all()
+            .into_iter()
+            .map(|tool| {
+                let seconds = translator.tool_timeout(&tool.name);
+                document_timeout(tool, seconds)
+            })
+            .collect();
+        drop(translator);
+        Ok(rmcp::model::ListToolsResult::with_all_items(tools))
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<rmcp::model::PaginatedRequestParams>,
+        _context: rmcp::service::RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        // TODO(critic-S5): paginate when max_documents == 0 (unlimited mode can produce
+        // very large single-page responses that may exceed transport buffers).
+        let resources: Vec<_> = {
+            let translator = self.context.translator().await;
+            translator
+                .document_tracker()
+                .open_paths()
+                .filter_map(|path| {
+                    let uri = make_uri(path)
+                        .inspect_err(|e| {
+                            tracing::warn!(
+                                "Skipping path in list_resources (make_uri failed): {}: {e}",
+                                path.display()
+                            );
+                        })
+                        .ok()?;
                     let name = path
                         .file_name()
                         .and_then(|n| n.to_str())
@@ -590,7 +1838,7 @@ impl ServerHandler for McplsServer {
 
         // Enforce workspace-root containment — mirrors the guard in every LSP tool.
         {
-            let translator = self.context.translator.lock().await;
+            let translator = self.context.translator().await;
             translator
                 .validate_path(&path)
                 .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
@@ -602,7 +1850,7 @@ impl ServerHandler for McplsServer {
         // in the response shape. Currently both return `{"diagnostics":null}` which is
         // ambiguous for clients that need to know whether analysis has run yet.
         let diagnostics = {
-            let translator = self.context.translator.lock().await;
+            let translator = self.context.translator().await;
             translator
                 .notification_cache()
                 .get_diagnostics(lsp_uri.as_str())
@@ -628,7 +1876,7 @@ impl ServerHandler for McplsServer {
 
         // Enforce workspace-root containment (same invariant as every LSP tool).
         {
-            let translator = self.context.translator.lock().await;
+            let translator = self.context.translator().await;
             translator
                 .validate_path(&path)
                 .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
@@ -647,131 +1895,534 @@ impl ServerHandler for McplsServer {
         Ok(())
     }
 
-    async fn unsubscribe(
-        &self,
-        request: UnsubscribeRequestParams,
-        _context: rmcp::service::RequestContext<RoleServer>,
-    ) -> Result<(), McpError> {
-        // Parse the URI for consistency with subscribe validation.
-        parse_uri(&request.uri).map_err(|e| McpError::invalid_params(e.to_string(), None))?;
-        self.context.subscriptions.unsubscribe(&request.uri).await;
-        Ok(())
-    }
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParams,
+        _context: rmcp::service::RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        // Parse the URI for consistency with subscribe validation.
+        parse_uri(&request.uri).map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+        self.context.subscriptions.unsubscribe(&request.uri).await;
+        Ok(())
+    }
+
+    async fn on_initialized(&self, context: rmcp::service::NotificationContext<RoleServer>) {
+        self.sync_client_roots(&context.peer).await;
+    }
+
+    async fn on_roots_list_changed(&self, context: rmcp::service::NotificationContext<RoleServer>) {
+        self.sync_client_roots(&context.peer).await;
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        let mut implementation = Implementation::new("mcpls", env!("CARGO_PKG_VERSION"));
+        implementation.title = Some("MCPLS - MCP to LSP Bridge".to_string());
+        implementation.description = Some(env!("CARGO_PKG_DESCRIPTION").to_string());
+        implementation.website_url = Some("https://github.com/bug-ops/mcpls".to_string());
+
+        let capabilities = ServerCapabilities::builder()
+            .enable_tools()
+            .enable_resources()
+            .enable_resources_subscribe()
+            .build();
+        let mut server_info = ServerInfo::new(capabilities);
+        server_info.server_info = implementation;
+        server_info.instructions = Some(
+            concat!(
+                "Universal MCP to LSP bridge. Exposes Language Server Protocol ",
+                "capabilities as MCP tools for semantic code intelligence. ",
+                "Supports hover, definition, references, diagnostics, rename, ",
+                "completions, symbols, and formatting."
+            )
+            .to_string(),
+        );
+
+        server_info
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn create_test_server() -> McplsServer {
+        let translator = Arc::new(Mutex::new(Translator::new()));
+        let subscriptions = Arc::new(ResourceSubscriptions::new());
+        McplsServer::new(translator, subscriptions)
+    }
+
+    #[test]
+    fn test_tool_error_marks_edit_conflict_retryable() {
+        let err = tool_error(&Error::EditConflict {
+            paths: vec![std::path::PathBuf::from("/ws/src/lib.rs")],
+        });
+        let data = err.data.unwrap();
+        assert_eq!(data["retryable"], true);
+        assert_eq!(data["paths"][0], "/ws/src/lib.rs");
+
+        assert!(tool_error(&Error::NoServerConfigured).data.is_none());
+
+        let err = tool_error(&Error::ServerIndexing {
+            language_id: "rust".to_string(),
+            retry_after_secs: 8,
+            progress: Some("Indexing (42%)".to_string()),
+        });
+        assert!(err.message.contains("retry in 8 seconds"));
+        let data = err.data.unwrap();
+        assert_eq!(data["kind"], "server_indexing");
+        assert_eq!(data["retry_after_ms"], 8000);
+    }
+
+    #[test]
+    fn test_tool_error_reports_rate_limit() {
+        let err = tool_error(&Error::RateLimited {
+            class: "navigation".to_string(),
+            limit: 60,
+            retry_after_ms: 1500,
+        });
+        assert!(err.message.contains("retry after 1500 ms"));
+        let data = err.data.unwrap();
+        assert_eq!(data["kind"], "rate_limited");
+        assert_eq!(data["retryable"], true);
+        assert_eq!(data["tool_class"], "navigation");
+        assert_eq!(data["retry_after_ms"], 1500);
+    }
+
+    #[test]
+    fn test_unsupported_capability_suggests_alternatives() {
+        let unsupported = || {
+            tool_error(&Error::LspServerError {
+                code: fallbacks::METHOD_NOT_FOUND,
+                message: "Unhandled method textDocument/implementation".to_string(),
+                data: None,
+            })
+        };
+
+        let mut err = unsupported();
+        add_fallbacks(&mut err, "go_to_implementation");
+        let data = err.data.unwrap();
+        assert_eq!(data["kind"], "unsupported");
+        assert_eq!(data["retryable"], false);
+        assert_eq!(data["capability"], "textDocument/implementation");
+        assert_eq!(data["alternatives"][0]["tool"], "get_references");
+        assert!(err.message.contains("try get_references"));
+
+        // Tools without a mapping keep the plain error.
+        let mut err = unsupported();
+        add_fallbacks(&mut err, "get_server_logs");
+        assert!(err.data.unwrap().get("alternatives").is_none());
+    }
+
+    #[test]
+    fn test_fallbacks_name_existing_tools() {
+        let tools = McplsServer::tool_names();
+        for tool in &tools {
+            if let Some((_, alternatives)) = fallbacks::for_tool(tool) {
+                for alternative in alternatives {
+                    assert!(tools.iter().any(|name| name == alternative.tool));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_document_timeout() {
+        let tool = |name: &str| McplsServer::tool_router().get(name).cloned().unwrap();
+        let hover = document_timeout(tool("get_hover"), 45);
+        assert!(
+            hover
+                .description
+                .unwrap()
+                .ends_with("time out after 45 s; pass timeout_seconds to change that.")
+        );
+        let timeout = &hover.input_schema["properties"]["timeout_seconds"];
+        assert_eq!(timeout["maximum"], MAX_TOOL_TIMEOUT_SECS);
+        assert!(
+            timeout["description"]
+                .as_str()
+                .unwrap()
+                .contains("default 45")
+        );
+
+        // Tools that send no language server requests are left alone.
+        for name in ["get_server_logs", "find_matching_bracket", "confirm_apply"] {
+            let other = tool(name);
+            assert_eq!(
+                document_timeout(other.clone(), 45).description,
+                other.description
+            );
+        }
+        for name in SERVER_REQUEST_TOOLS {
+            assert!(McplsServer::tool_router().get(name).is_some(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_take_timeout() {
+        let mut arguments = serde_json::json!({ "file_path": "/ws/a.rs", "timeout_seconds": 90 })
+            .as_object()
+            .cloned()
+            .unwrap();
+        assert_eq!(take_timeout(Some(&mut arguments)).unwrap(), Some(90));
+        assert!(!arguments.contains_key("timeout_seconds"));
+        assert_eq!(take_timeout(Some(&mut arguments)).unwrap(), None);
+        assert_eq!(take_timeout(None).unwrap(), None);
+
+        arguments.insert("timeout_seconds".to_string(), serde_json::json!(0));
+        let err = take_timeout(Some(&mut arguments)).unwrap_err();
+        assert!(err.to_string().contains("between 1 and 600"));
+    }
+
+    #[test]
+    fn test_root_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let canonical = dir.path().canonicalize().unwrap();
+        let uri = crate::bridge::path_to_uri(&canonical).to_string();
+        let roots = [
+            Root::new(uri.clone()),
+            Root::new(uri),
+            Root::new("https://example.com/repo"),
+            Root::new("file:///does/not/exist"),
+        ];
+        assert_eq!(root_paths(&roots), [canonical]);
+    }
+
+    #[tokio::test]
+    async fn test_server_info() {
+        let server = create_test_server();
+        let info = server.get_info();
+
+        assert!(info.capabilities.tools.is_some());
+        assert_eq!(info.server_info.name, "mcpls");
+        assert!(info.instructions.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_hover_tool_with_params() {
+        let server = create_test_server();
+        let params = Parameters(HoverParams {
+            file_path: "/nonexistent/file.rs".to_string(),
+            line: Some(1),
+            character: Some(1),
+            symbol_name: None,
+            occurrence_index: 0,
+            sections: vec![],
+            max_length: None,
+        });
+
+        // This should return an error (no LSP server configured)
+        let result = server.get_hover(params).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_definition_tool_with_params() {
+        let server = create_test_server();
+        let params = Parameters(DefinitionParams {
+            file_path: "/test/file.rs".to_string(),
+            line: Some(10),
+            character: Some(5),
+            symbol_name: None,
+            occurrence_index: 0,
+            include_context: false,
+            limit: None,
+        });
+
+        let result = server.get_definition(params).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_references_tool_with_params() {
+        let server = create_test_server();
+        let params = Parameters(ReferencesParams {
+            file_path: "/test/file.rs".to_string(),
+            line: Some(10),
+            character: Some(5),
+            symbol_name: None,
+            occurrence_index: 0,
+            include_declaration: false,
+            limit: None,
+            scope: "workspace".to_string(),
+            output_format: None,
+        });
+
+        let result = server.get_references(params).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_references_tool_rejects_unknown_scope() {
+        let server = create_test_server();
+        let params = Parameters(ReferencesParams {
+            file_path: "/test/file.rs".to_string(),
+            line: Some(10),
+            character: Some(5),
+            symbol_name: None,
+            occurrence_index: 0,
+            include_declaration: false,
+            limit: None,
+            scope: "directory:".to_string(),
+            output_format: None,
+        });
+
+        let err = server.get_references(params).await.unwrap_err();
+        assert!(err.message.contains("Invalid scope"));
+    }
+
+    #[tokio::test]
+    async fn test_position_tools_require_anchor() {
+        let server = create_test_server();
+        let params: HoverParams = serde_json::from_value(serde_json::json!({
+            "file_path": "/test/file.rs",
+            "line": 10,
+        }))
+        .unwrap();
+        let err = server.get_hover(Parameters(params)).await.unwrap_err();
+        assert!(err.message.contains("symbol_name"));
+
+        let params: DefinitionParams = serde_json::from_value(serde_json::json!({
+            "file_path": "/test/file.rs",
+            "symbol_name": "parse",
+        }))
+        .unwrap();
+        assert_eq!(params.occurrence_index, 0);
+        assert!(server.get_definition(Parameters(params)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_tool_with_params() {
+        let server = create_test_server();
+        let params = Parameters(DiagnosticsParams {
+            file_path: "/test/file.rs".to_string(),
+        });
+
+        let result = server.get_diagnostics(params).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reset_bridge_tool() {
+        let server = create_test_server();
+        let json = server
+            .reset_bridge(Parameters(ResetBridgeParams {
+                scope: "servers".to_string(),
+            }))
+            .await
+            .unwrap();
+        let result: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(result["cancelled_requests"], 0);
+        assert_eq!(result["restarted_servers"], serde_json::json!([]));
+
+        let invalid = Parameters(ResetBridgeParams {
+            scope: "everything".to_string(),
+        });
+        assert!(server.reset_bridge(invalid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_document_tool_without_server() {
+        let server = create_test_server();
+        let result = server
+            .update_document(Parameters(UpdateDocumentParams {
+                file_path: "/tmp/test.rs".to_string(),
+                content: Some("fn main() {}".to_string()),
+            }))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_initialization_info_tool_without_server() {
+        let server = create_test_server();
+        let result = server
+            .get_initialization_info(Parameters(InitializationInfoParams {
+                language_id: "rust".to_string(),
+            }))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_recent_calls_tool() {
+        let server = create_test_server();
+        server
+            .context
+            .record_call(RecentCall::new("get_hover".to_string(), None, None, 3));
+        let result = server
+            .get_recent_calls(Parameters(RecentCallsParams { limit: 10 }))
+            .await
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["calls"][0]["tool"], "get_hover");
+        assert_eq!(value["calls"][0]["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_create_snapshot_tool() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let server = create_test_server();
+        server
+            .context
+            .translator()
+            .await
+            .set_workspace_roots(vec![dir.path().to_path_buf()]);
+        server
+            .context
+            .record_call(RecentCall::new("get_hover".to_string(), None, None, 3));
 
-    fn get_info(&self) -> ServerInfo {
-        let mut implementation = Implementation::new("mcpls", env!("CARGO_PKG_VERSION"));
-        implementation.title = Some("MCPLS - MCP to LSP Bridge".to_string());
-        implementation.description = Some(env!("CARGO_PKG_DESCRIPTION").to_string());
-        implementation.website_url = Some("https://github.com/bug-ops/mcpls".to_string());
+        let result = server.create_snapshot().await.unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["recent_calls"], 1);
+        let written = std::fs::read_to_string(value["output_path"].as_str().unwrap()).unwrap();
+        let snapshot: serde_json::Value = serde_json::from_str(&written).unwrap();
+        assert_eq!(snapshot["recent_calls"][0]["tool"], "get_hover");
+    }
 
-        let capabilities = ServerCapabilities::builder()
-            .enable_tools()
-            .enable_resources()
-            .enable_resources_subscribe()
-            .build();
-        let mut server_info = ServerInfo::new(capabilities);
-        server_info.server_info = implementation;
-        server_info.instructions = Some(
-            concat!(
-                "Universal MCP to LSP bridge. Exposes Language Server Protocol ",
-                "capabilities as MCP tools for semantic code intelligence. ",
-                "Supports hover, definition, references, diagnostics, rename, ",
-                "completions, symbols, and formatting."
-            )
-            .to_string(),
-        );
+    #[test]
+    fn test_attach_metrics() {
+        let mut result = rmcp::model::CallToolResult::success(vec![]);
+        let metrics = RequestMetrics {
+            lsp_requests: 1,
+            language_id: Some("rust".to_string()),
+            ..RequestMetrics::default()
+        };
+        attach_metrics(&mut result, &metrics);
 
-        server_info
+        let meta = result.meta.unwrap();
+        assert_eq!(meta.0["mcpls/metrics"]["lsp_requests"], 1);
+        assert_eq!(meta.0["mcpls/metrics"]["language_id"], "rust");
+        assert_eq!(meta.0["mcpls/metrics"]["cache_hit"], false);
     }
-}
 
-#[cfg(test)]
-#[allow(clippy::unwrap_used)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_get_language_info_tool_without_server() {
+        let server = create_test_server();
+        let result = server
+            .get_language_info(Parameters(LanguageInfoParams {
+                language_id: "rust".to_string(),
+            }))
+            .await;
+        assert!(result.is_err());
+    }
 
-    fn create_test_server() -> McplsServer {
-        let translator = Arc::new(Mutex::new(Translator::new()));
-        let subscriptions = Arc::new(ResourceSubscriptions::new());
-        McplsServer::new(translator, subscriptions)
+    #[tokio::test]
+    async fn test_list_supported_languages_tool() {
+        let server = create_test_server();
+        let json_str = server.list_supported_languages().await.unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(parsed["fallback_language"], "plaintext");
+        assert!(parsed["languages"].as_array().unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn test_server_info() {
+    async fn test_rust_run_flycheck_tool_without_server() {
         let server = create_test_server();
-        let info = server.get_info();
+        let params = Parameters(RunFlycheckParams { file_path: None });
 
-        assert!(info.capabilities.tools.is_some());
-        assert_eq!(info.server_info.name, "mcpls");
-        assert!(info.instructions.is_some());
+        let result = server.rust_run_flycheck(params).await;
+        assert!(result.is_err());
     }
 
     #[tokio::test]
-    async fn test_hover_tool_with_params() {
+    async fn test_diagnostics_batch_tool_reports_per_file_errors() {
         let server = create_test_server();
-        let params = Parameters(HoverParams {
-            file_path: "/nonexistent/file.rs".to_string(),
-            line: 1,
-            character: 1,
+        let params = Parameters(DiagnosticsBatchParams {
+            paths: vec!["/test/a.rs".to_string(), "/test/b.rs".to_string()],
+            glob: None,
+            max_files: 1,
+            deadline_ms: 60_000,
         });
 
-        // This should return an error (no LSP server configured)
-        let result = server.get_hover(params).await;
-        assert!(result.is_err());
+        let json = server.get_diagnostics_batch(params).await.unwrap();
+        let result: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(result["truncated"], true);
+        assert_eq!(result["completed"], true);
+        assert_eq!(result["files"].as_array().unwrap().len(), 1);
+        assert_eq!(result["files"][0]["file_path"], "/test/a.rs");
+        assert!(result["files"][0]["error"].is_string());
+
+        let empty = Parameters(DiagnosticsBatchParams {
+            paths: vec![],
+            glob: None,
+            max_files: 50,
+            deadline_ms: 60_000,
+        });
+        assert!(server.get_diagnostics_batch(empty).await.is_err());
     }
 
     #[tokio::test]
-    async fn test_definition_tool_with_params() {
+    async fn test_prepare_rename_tool_with_params() {
         let server = create_test_server();
-        let params = Parameters(DefinitionParams {
+        let params = Parameters(PrepareRenameParams {
             file_path: "/test/file.rs".to_string(),
             line: 10,
             character: 5,
         });
 
-        let result = server.get_definition(params).await;
+        let result = server.prepare_rename(params).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
-    async fn test_references_tool_with_params() {
+    async fn test_rename_tool_with_params() {
         let server = create_test_server();
-        let params = Parameters(ReferencesParams {
+        let params = Parameters(RenameParams {
             file_path: "/test/file.rs".to_string(),
             line: 10,
             character: 5,
-            include_declaration: false,
+            new_name: "new_name".to_string(),
+            apply: false,
         });
 
-        let result = server.get_references(params).await;
+        let result = server.rename_symbol(params).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
-    async fn test_diagnostics_tool_with_params() {
+    async fn test_rename_symbols_batch_tool_rejects_empty_batch() {
         let server = create_test_server();
-        let params = Parameters(DiagnosticsParams {
-            file_path: "/test/file.rs".to_string(),
+        let params = Parameters(RenameBatchParams {
+            renames: Vec::new(),
+            apply: false,
         });
 
-        let result = server.get_diagnostics(params).await;
+        let result = server.rename_symbols_batch(params).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
-    async fn test_rename_tool_with_params() {
+    async fn test_rust_ssr_rejects_non_rust_context() {
         let server = create_test_server();
-        let params = Parameters(RenameParams {
-            file_path: "/test/file.rs".to_string(),
-            line: 10,
-            character: 5,
-            new_name: "new_name".to_string(),
+        let params = Parameters(RustSsrParams {
+            pattern: "foo($a)".to_string(),
+            replacement: "bar($a)".to_string(),
+            dry_run: true,
+            file_path: Some("/test/file.py".to_string()),
         });
 
-        let result = server.rename_symbol(params).await;
+        let result = server.rust_ssr(params).await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_undo_last_edit_without_applied_edit() {
+        let server = create_test_server();
+        let err = server.undo_last_edit().await.unwrap_err();
+        assert!(err.message.contains("no applied edit to undo"));
+    }
+
+    #[tokio::test]
+    async fn test_confirm_apply_unknown_token() {
+        let server = create_test_server();
+        let params = Parameters(ConfirmApplyParams {
+            token: "confirm-1".to_string(),
+        });
+        let err = server.confirm_apply(params).await.unwrap_err();
+        assert!(err.message.contains("confirm-1"));
+    }
+
     #[tokio::test]
     async fn test_completions_tool_with_params() {
         let server = create_test_server();
@@ -780,36 +2431,204 @@ mod tests {
             line: 10,
             character: 5,
             trigger: None,
+            resolve: true,
         });
 
         let result = server.get_completions(params).await;
         assert!(result.is_err());
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_completions_tool_resolves_lazy_items_by_default() {
+        // Leaves documentation for `completionItem/resolve` once told the
+        // client can resolve it.
+        const SERVER: &str = r#"
+lazy=0
+while IFS= read -r header; do
+  length=$(printf '%s' "$header" | tr -cd 0-9)
+  IFS= read -r blank
+  body=$(head -c "$length")
+  id=$(printf '%s' "$body" | sed -n 's/.*"id":\([0-9]*\).*/\1/p')
+  case "$body" in
+    *'"method":"initialize"'*)
+      case "$body" in *resolveSupport*) lazy=1 ;; esac
+      result='{"capabilities":{"completionProvider":{"resolveProvider":true}}}' ;;
+    *'"method":"textDocument/completion"'*)
+      if [ "$lazy" = 1 ]; then
+        result='[{"label":"len"}]'
+      else
+        result='[{"label":"len","documentation":"Returns the length."}]'
+      fi ;;
+    *'"method":"completionItem/resolve"'*)
+      result='{"label":"len","documentation":"Returns the length."}' ;;
+    *)
+      [ -n "$id" ] || continue
+      result=null ;;
+  esac
+  response="{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":$result}"
+  printf 'Content-Length: %s\r\n\r\n%s' "$(printf '%s' "$response" | wc -c | tr -d ' ')" "$response"
+done
+"#;
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let file = root.join("lib.rs");
+        std::fs::write(&file, "fn f() {}\n").unwrap();
+        let mut server_config = crate::config::LspServerConfig::rust_analyzer();
+        server_config.command = "sh".to_string();
+        server_config.args = vec!["-c".to_string(), SERVER.to_string()];
+        let lsp_server = crate::lsp::LspServer::spawn(crate::lsp::ServerInitConfig {
+            server_config,
+            workspace_roots: vec![root.clone()],
+            initialization_options: None,
+            notification_tx: None,
+        })
+        .await
+        .unwrap();
+
+        let mut translator =
+            Translator::new().with_extensions(std::collections::HashMap::from([(
+                "rs".to_string(),
+                "rust".to_string(),
+            )]));
+        translator.set_workspace_roots(vec![root]);
+        translator.register_client("rust".to_string(), lsp_server.client().clone());
+        translator.register_server("rust".to_string(), lsp_server);
+        let server = McplsServer::new(
+            Arc::new(Mutex::new(translator)),
+            Arc::new(ResourceSubscriptions::new()),
+        );
+
+        let params: CompletionsParams = serde_json::from_value(serde_json::json!({
+            "file_path": file.display().to_string(),
+            "line": 1,
+            "character": 1,
+        }))
+        .unwrap();
+        let result = server.get_completions(Parameters(params)).await.unwrap();
+        let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(result["items"][0]["documentation"], "Returns the length.");
+    }
+
+    #[tokio::test]
+    async fn test_save_document_tool_with_params() {
+        let server = create_test_server();
+        let params = Parameters(SaveDocumentParams {
+            file_path: "/test/file.rs".to_string(),
+        });
+
+        let result = server.save_document(params).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_document_drift_tool_outside_workspace() {
+        let server = create_test_server();
+        let result = server
+            .get_document_drift(Parameters(DocumentDriftParams {
+                file_path: "/test/file.rs".to_string(),
+            }))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_inline_completions_tool_with_params() {
+        let server = create_test_server();
+        let params = Parameters(InlineCompletionsParams {
+            file_path: "/test/file.rs".to_string(),
+            line: 10,
+            character: 5,
+        });
+
+        let result = server.get_inline_completions(params).await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_document_symbols_tool_with_params() {
         let server = create_test_server();
         let params = Parameters(DocumentSymbolsParams {
             file_path: "/test/file.rs".to_string(),
+            output_format: None,
         });
 
         let result = server.get_document_symbols(params).await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_document_symbols_tool_rejects_unknown_output_format() {
+        let server = create_test_server();
+        let params = Parameters(DocumentSymbolsParams {
+            file_path: "/test/file.rs".to_string(),
+            output_format: Some("yaml".to_string()),
+        });
+
+        let err = server.get_document_symbols(params).await.unwrap_err();
+        assert!(err.message.contains("output_format"));
+    }
+
+    #[tokio::test]
+    async fn test_enclosing_scope_tool_rejects_unknown_level() {
+        let server = create_test_server();
+        let params = Parameters(EnclosingScopeParams {
+            file_path: "/test/file.rs".to_string(),
+            line: 1,
+            character: 1,
+            level: "block".to_string(),
+        });
+
+        let err = server.get_enclosing_scope(params).await.unwrap_err();
+        assert!(err.message.contains("Invalid level"));
+    }
+
+    #[tokio::test]
+    async fn test_find_matching_bracket_tool_rejects_zero_position() {
+        let server = create_test_server();
+        let params = Parameters(MatchingBracketParams {
+            file_path: "/test/file.rs".to_string(),
+            line: 0,
+            character: 1,
+        });
+
+        let err = server.find_matching_bracket(params).await.unwrap_err();
+        assert!(err.message.contains(">= 1"));
+    }
+
     #[tokio::test]
     async fn test_format_document_tool_with_params() {
         let server = create_test_server();
         let params = Parameters(FormatDocumentParams {
             file_path: "/test/file.rs".to_string(),
-            tab_size: 4,
-            insert_spaces: true,
+            tab_size: Some(4),
+            insert_spaces: None,
         });
 
         let result = server.format_document(params).await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_format_range_tool_rejects_reversed_range() {
+        let server = create_test_server();
+        let params = |end_line: u32| {
+            Parameters(FormatRangeParams {
+                file_path: "/test/file.rs".to_string(),
+                start_line: 10,
+                start_character: 1,
+                end_line,
+                end_character: 1,
+                tab_size: None,
+                insert_spaces: Some(true),
+            })
+        };
+
+        let err = server.format_range(params(4)).await.unwrap_err();
+        assert!(err.message.contains("before or equal to end"));
+        assert!(server.format_range(params(20)).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_workspace_symbol_search_tool_with_params() {
         let server = create_test_server();
@@ -822,6 +2641,37 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_scan_workspace_tool_without_roots() {
+        let server = create_test_server();
+        let result = server
+            .scan_workspace(Parameters(ScanWorkspaceParams {
+                globs: vec![],
+                max_files: 10,
+            }))
+            .await
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(value["total_files"], 0);
+        assert_eq!(value["languages"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_export_symbols_tool() {
+        let server = create_test_server();
+        let params = |output_format: &str| {
+            Parameters(ExportSymbolsParams {
+                globs: vec![],
+                max_files: 10,
+                output_format: Some(output_format.to_string()),
+            })
+        };
+        let err = server.export_symbols(params("lsif")).await.unwrap_err();
+        assert!(err.message.contains("Invalid output_format"));
+        let err = server.export_symbols(params("scip")).await.unwrap_err();
+        assert!(err.message.contains("no workspace roots"));
+    }
+
     #[tokio::test]
     async fn test_code_actions_tool_with_params() {
         let server = create_test_server();
@@ -852,20 +2702,14 @@ mod tests {
     #[tokio::test]
     async fn test_incoming_calls_tool_with_params() {
         let server = create_test_server();
-        let item = serde_json::json!({
-            "name": "test_function",
-            "kind": 12,
-            "uri": "file:///test/file.rs",
-            "range": {
-                "start": {"line": 0, "character": 0},
-                "end": {"line": 0, "character": 10}
-            },
-            "selectionRange": {
-                "start": {"line": 0, "character": 0},
-                "end": {"line": 0, "character": 10}
-            }
+        let params = Parameters(CallHierarchyCallsParams {
+            item: None,
+            handle: None,
+            file_path: Some("/test/file.rs".to_string()),
+            line: Some(1),
+            character: Some(4),
+            output_format: None,
         });
-        let params = Parameters(CallHierarchyCallsParams { item });
         let result = server.get_incoming_calls(params).await;
         assert!(result.is_err());
     }
@@ -873,24 +2717,75 @@ mod tests {
     #[tokio::test]
     async fn test_outgoing_calls_tool_with_params() {
         let server = create_test_server();
-        let item = serde_json::json!({
-            "name": "test_function",
-            "kind": 12,
-            "uri": "file:///test/file.rs",
-            "range": {
-                "start": {"line": 0, "character": 0},
-                "end": {"line": 0, "character": 10}
-            },
-            "selectionRange": {
-                "start": {"line": 0, "character": 0},
-                "end": {"line": 0, "character": 10}
-            }
+        let params = Parameters(CallHierarchyCallsParams {
+            item: None,
+            handle: None,
+            file_path: Some("/test/file.rs".to_string()),
+            line: Some(1),
+            character: Some(4),
+            output_format: None,
         });
-        let params = Parameters(CallHierarchyCallsParams { item });
         let result = server.get_outgoing_calls(params).await;
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_call_hierarchy_tools_reject_unknown_output_format() {
+        let server = create_test_server();
+        let params = Parameters(CallHierarchyCallsParams {
+            item: None,
+            handle: None,
+            file_path: Some("/test/file.rs".to_string()),
+            line: Some(1),
+            character: Some(4),
+            output_format: Some("svg".to_string()),
+        });
+        let err = server.get_outgoing_calls(params).await.unwrap_err();
+        assert!(err.message.contains("Valid values: json, dot"));
+    }
+
+    #[tokio::test]
+    async fn test_call_hierarchy_tools_need_item_or_position() {
+        let server = create_test_server();
+        let params = Parameters(CallHierarchyCallsParams {
+            item: None,
+            handle: None,
+            file_path: Some("/test/file.rs".to_string()),
+            line: Some(1),
+            character: None,
+            output_format: None,
+        });
+        let err = server.get_incoming_calls(params).await.unwrap_err();
+        assert!(err.message.contains("Pass item"));
+    }
+
+    #[tokio::test]
+    async fn test_type_hierarchy_tools_with_params() {
+        let server = create_test_server();
+        let result = server
+            .prepare_type_hierarchy(Parameters(TypeHierarchyPrepareParams {
+                file_path: "/test/file.rs".to_string(),
+                line: 10,
+                character: 5,
+            }))
+            .await;
+        assert!(result.is_err());
+
+        let params = |character: Option<u32>| {
+            Parameters(TypeHierarchyParams {
+                item: None,
+                handle: None,
+                file_path: Some("/test/file.rs".to_string()),
+                line: Some(1),
+                character,
+                output_format: None,
+            })
+        };
+        assert!(server.get_supertypes(params(Some(8))).await.is_err());
+        let err = server.get_subtypes(params(None)).await.unwrap_err();
+        assert!(err.message.contains("prepare_type_hierarchy"));
+    }
+
     #[tokio::test]
     async fn test_cached_diagnostics_tool_with_params() {
         use std::fs;
@@ -1037,6 +2932,55 @@ mod tests {
         assert!(parsed.get("messages").is_some());
     }
 
+    #[tokio::test]
+    async fn test_notify_manifest_changes_tool_rejects_missing_file() {
+        let server = create_test_server();
+        let params = Parameters(NotifyManifestChangesParams {
+            paths: vec!["/nonexistent/Cargo.toml".to_string()],
+        });
+
+        let result = server.notify_manifest_changes(params).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_files_tool_reports_per_file_errors() {
+        let server = create_test_server();
+        let params = Parameters(PrefetchFilesParams {
+            paths: vec![
+                "/nonexistent/a.rs".to_string(),
+                "/nonexistent/a.rs".to_string(),
+            ],
+        });
+
+        let json_str = server.prefetch_files(params).await.unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        let files = parsed["files"].as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0]["status"], "failed");
+        assert!(files[0]["error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_server_telemetry_tool() {
+        let server = create_test_server();
+        server
+            .context
+            .translator()
+            .await
+            .notification_cache_mut()
+            .store_telemetry("java", serde_json::json!({ "name": "build" }));
+
+        let params = Parameters(ServerTelemetryParams {
+            limit: 20,
+            language_id: Some("java".to_string()),
+        });
+        let json_str = server.get_server_telemetry(params).await.unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        assert_eq!(parsed["events"][0]["language_id"], "java");
+        assert_eq!(parsed["events"][0]["data"]["name"], "build");
+    }
+
     #[tokio::test]
     async fn test_server_messages_tool_with_custom_limit() {
         let server = create_test_server();
@@ -1094,6 +3038,8 @@ mod tests {
             file_path: "/test/file.rs".to_string(),
             line: 10,
             character: 5,
+            include_context: false,
+            limit: None,
         });
 
         let result = server.go_to_implementation(params).await;
@@ -1113,21 +3059,98 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_get_declaration_tool_with_params() {
+        let server = create_test_server();
+        let params = Parameters(DeclarationParams {
+            file_path: "/test/file.h".to_string(),
+            line: 10,
+            character: 5,
+        });
+
+        let result = server.get_declaration(params).await;
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn test_get_inlay_hints_tool_with_params() {
         let server = create_test_server();
         let params = Parameters(InlayHintsParams {
             file_path: "/test/file.rs".to_string(),
             start_line: 1,
-            start_character: 1,
+            start_character: Some(1),
             end_line: 10,
-            end_character: 1,
+            end_character: Some(1),
         });
 
         let result = server.get_inlay_hints(params).await;
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_inlay_hints_params_default_to_whole_lines() {
+        let params: InlayHintsParams = serde_json::from_value(serde_json::json!({
+            "file_path": "/test/file.rs",
+            "start_line": 3,
+            "end_line": 7,
+        }))
+        .unwrap();
+        assert_eq!(params.start_character, None);
+        assert_eq!(params.end_character, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_semantic_tokens_tool() {
+        let server = create_test_server();
+        let params: SemanticTokensParams = serde_json::from_value(serde_json::json!({
+            "file_path": "/test/file.rs",
+            "start_line": 3,
+        }))
+        .unwrap();
+        assert_eq!(params.end_line, None);
+        assert_eq!(params.max_tokens, 1000);
+
+        let result = server.get_semantic_tokens(Parameters(params)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_folding_ranges_tool() {
+        let server = create_test_server();
+        let params = FoldingRangesParams {
+            file_path: "/test/file.rs".to_string(),
+        };
+
+        let result = server.get_folding_ranges(Parameters(params)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_selection_ranges_tool() {
+        let server = create_test_server();
+        let params = SelectionRangesParams {
+            file_path: "/test/file.rs".to_string(),
+            line: 10,
+            character: 5,
+        };
+
+        let result = server.get_selection_ranges(Parameters(params)).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_code_lenses_tool() {
+        let server = create_test_server();
+        let params: CodeLensesParams = serde_json::from_value(serde_json::json!({
+            "file_path": "/test/file.rs",
+        }))
+        .unwrap();
+        assert!(!params.include_arguments);
+
+        let result = server.get_code_lenses(Parameters(params)).await;
+        assert!(result.is_err());
+    }
+
     // ------------------------------------------------------------------
     // Resource handler tests (logic-level, avoiding rmcp::service::RequestContext
     // which requires a live Peer with private fields)
@@ -3,70 +3,227 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::bridge::{CallHierarchyItemResult, RenameTarget, TypeHierarchyItemResult};
+
 /// Parameters for the `get_hover` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for getting hover information at a position in a file.")]
 pub struct HoverParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
-    /// Line number (1-based).
-    #[schemars(description = "Line number (1-based).")]
-    pub line: u32,
-    /// Character/column number (1-based).
-    #[schemars(description = "Character/column number (1-based).")]
-    pub character: u32,
+    /// Line number (1-based); required unless `symbol_name` is given.
+    #[schemars(description = "Line number (1-based). Required unless symbol_name is given.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Character/column number (1-based); required unless `symbol_name` is
+    /// given.
+    #[schemars(
+        description = "Character/column number (1-based). Required unless symbol_name is given."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<u32>,
+    /// Name of the symbol to anchor at instead of `line` and `character`.
+    #[schemars(
+        description = "Name of the symbol to query instead of giving line and character. The bridge finds its declaration in the file (or its text when the file does not declare it), so the query stays valid after edits move the symbol. Takes precedence over line and character."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol_name: Option<String>,
+    /// Which occurrence of `symbol_name` to anchor at (0-based).
+    #[schemars(
+        description = "Which occurrence of symbol_name to use when the file has several, in document order (0-based, default: 0)."
+    )]
+    #[serde(default)]
+    pub occurrence_index: u32,
+    /// Sections of the hover text to return; all when empty.
+    #[schemars(
+        description = "Sections of the hover text to return, e.g. [\"signature\"] or [\"signature\", \"description\"]. Other sections are named after their markdown heading (\"examples\", \"panics\"). All when empty."
+    )]
+    #[serde(default)]
+    pub sections: Vec<String>,
+    /// Maximum length of the returned contents in characters.
+    #[schemars(
+        description = "Maximum length of the returned contents in characters. Trailing sections are dropped whole before a section is cut."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
 }
 
 /// Parameters for the `get_definition` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for getting the definition location of a symbol.")]
 pub struct DefinitionParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
-    /// Line number (1-based).
-    #[schemars(description = "Line number (1-based).")]
-    pub line: u32,
-    /// Character/column number (1-based).
-    #[schemars(description = "Character/column number (1-based).")]
-    pub character: u32,
+    /// Line number (1-based); required unless `symbol_name` is given.
+    #[schemars(description = "Line number (1-based). Required unless symbol_name is given.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Character/column number (1-based); required unless `symbol_name` is
+    /// given.
+    #[schemars(
+        description = "Character/column number (1-based). Required unless symbol_name is given."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<u32>,
+    /// Name of the symbol to anchor at instead of `line` and `character`.
+    #[schemars(
+        description = "Name of the symbol to query instead of giving line and character. The bridge finds its declaration in the file (or its text when the file does not declare it), so the query stays valid after edits move the symbol. Takes precedence over line and character."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol_name: Option<String>,
+    /// Which occurrence of `symbol_name` to anchor at (0-based).
+    #[schemars(
+        description = "Which occurrence of symbol_name to use when the file has several, in document order (0-based, default: 0)."
+    )]
+    #[serde(default)]
+    pub occurrence_index: u32,
+    /// Whether to attach the enclosing symbol to each location.
+    #[schemars(
+        description = "Attach the enclosing symbol (name, kind, parent) to each location. Costs one documentSymbol request per target file."
+    )]
+    #[serde(default)]
+    pub include_context: bool,
+    /// Maximum number of locations to return (default: all).
+    #[schemars(
+        description = "Maximum number of locations to return (default: all). Results are ranked: same file first, then workspace files, then dependencies."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
 }
 
 /// Parameters for the `get_references` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for finding all references to a symbol.")]
 pub struct ReferencesParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
-    /// Line number (1-based).
-    #[schemars(description = "Line number (1-based).")]
-    pub line: u32,
-    /// Character/column number (1-based).
-    #[schemars(description = "Character/column number (1-based).")]
-    pub character: u32,
+    /// Line number (1-based); required unless `symbol_name` is given.
+    #[schemars(description = "Line number (1-based). Required unless symbol_name is given.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Character/column number (1-based); required unless `symbol_name` is
+    /// given.
+    #[schemars(
+        description = "Character/column number (1-based). Required unless symbol_name is given."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<u32>,
+    /// Name of the symbol to anchor at instead of `line` and `character`.
+    #[schemars(
+        description = "Name of the symbol to query instead of giving line and character. The bridge finds its declaration in the file (or its text when the file does not declare it), so the query stays valid after edits move the symbol. Takes precedence over line and character."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol_name: Option<String>,
+    /// Which occurrence of `symbol_name` to anchor at (0-based).
+    #[schemars(
+        description = "Which occurrence of symbol_name to use when the file has several, in document order (0-based, default: 0)."
+    )]
+    #[serde(default)]
+    pub occurrence_index: u32,
     /// Whether to include the declaration in the results.
     #[schemars(description = "Whether to include the declaration in the results.")]
     #[serde(default)]
     pub include_declaration: bool,
+    /// Maximum number of locations to return (default: all).
+    #[schemars(
+        description = "Maximum number of locations to return (default: all). Results are ranked: same file first, then workspace files, then dependencies."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Which references to return: workspace (default), file, or directory:<path>.
+    #[schemars(
+        description = "Which references to return: workspace (default), file (only the queried file), or directory:<path> (only files under the directory; relative paths are resolved against the workspace roots). Applied before limit."
+    )]
+    #[serde(default = "default_reference_scope")]
+    pub scope: String,
+    /// Output format: json (default) or dot.
+    #[schemars(
+        description = "Output format: json (default) or dot, a Graphviz graph with one edge per referencing file, labelled with the lines of its references."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
+}
+
+fn default_reference_scope() -> String {
+    "workspace".to_string()
 }
 
 /// Parameters for the `get_diagnostics` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for getting diagnostics (errors, warnings) for a file.")]
 pub struct DiagnosticsParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+}
+
+/// Parameters for the `get_diagnostics_batch` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting diagnostics for several files at once.")]
+pub struct DiagnosticsBatchParams {
+    /// Absolute paths to the files.
+    #[schemars(description = "Absolute paths to the files.")]
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Gitignore-style glob relative to the workspace roots (e.g. "src/**/*.rs").
+    #[schemars(
+        description = "Gitignore-style glob relative to the workspace roots (e.g. \"src/**/*.rs\")."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    /// Maximum number of files to check (default: 50).
+    #[schemars(description = "Maximum number of files to check (default: 50).")]
+    #[serde(default = "default_batch_max_files")]
+    pub max_files: usize,
+    /// Time budget for the whole batch in milliseconds (default: 60000).
+    #[schemars(
+        description = "Time budget for the whole batch in milliseconds (default: 60000). Files not checked by then are left out and completed is false."
+    )]
+    #[serde(default = "default_batch_deadline_ms")]
+    pub deadline_ms: u64,
+}
+
+const fn default_batch_max_files() -> usize {
+    50
+}
+
+const fn default_batch_deadline_ms() -> u64 {
+    60_000
+}
+
+/// Parameters for the `prefetch_files` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for opening files ahead of positional queries.")]
+pub struct PrefetchFilesParams {
+    /// Absolute paths to the files.
+    #[schemars(description = "Absolute paths to the files.")]
+    pub paths: Vec<String>,
+}
+
+/// Parameters for the `prepare_rename` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for checking whether a symbol can be renamed.")]
+pub struct PrepareRenameParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
+    /// Line number (1-based).
+    #[schemars(description = "Line number (1-based).")]
+    pub line: u32,
+    /// Character/column number (1-based).
+    #[schemars(description = "Character/column number (1-based).")]
+    pub character: u32,
 }
 
 /// Parameters for the `rename_symbol` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for renaming a symbol across the workspace.")]
 pub struct RenameParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
     /// Line number (1-based).
     #[schemars(description = "Line number (1-based).")]
@@ -77,14 +234,92 @@ pub struct RenameParams {
     /// New name for the symbol.
     #[schemars(description = "New name for the symbol.")]
     pub new_name: String,
+    /// Whether to write the edits to disk.
+    #[schemars(
+        description = "Write the edits to disk (default: false). Refused with a retryable error if a target file changed since the edit was computed. Edits above the configured size limits are only previewed, with a confirmation_token for confirm_apply."
+    )]
+    #[serde(default)]
+    pub apply: bool,
+}
+
+/// Parameters for the `rename_symbols_batch` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for renaming several symbols as one edit.")]
+pub struct RenameBatchParams {
+    /// Renames to perform, each at a position in the current code.
+    #[schemars(
+        description = "Renames to perform. Every position refers to the current code, before any of the renames."
+    )]
+    pub renames: Vec<RenameTarget>,
+    /// Whether to write the edits to disk.
+    #[schemars(
+        description = "Write the merged edits to disk (default: false), all or none. Refused with a retryable error if a target file changed since the edits were computed. Edits above the configured size limits are only previewed, with a confirmation_token for confirm_apply."
+    )]
+    #[serde(default)]
+    pub apply: bool,
+}
+
+/// Parameters for the `confirm_apply` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for confirming a previewed edit.")]
+pub struct ConfirmApplyParams {
+    /// Token from the previewed result.
+    #[schemars(
+        description = "confirmation_token returned by an edit-applying tool whose edit was too large to apply without confirmation."
+    )]
+    pub token: String,
+}
+
+/// Parameters for the `update_document` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for setting the unsaved content of a document.")]
+pub struct UpdateDocumentParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Unsaved content; omit to drop it and use the file on disk again.
+    #[schemars(
+        description = "Full unsaved content of the document. Omit to drop it and use the file on disk again."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// Parameters for the `save_document` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for saving a document.")]
+pub struct SaveDocumentParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+}
+
+/// Parameters for the `get_document_drift` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for comparing a tracked document with disk.")]
+pub struct DocumentDriftParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+}
+
+/// Parameters for the `notify_manifest_changes` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for reporting edited build manifests.")]
+pub struct NotifyManifestChangesParams {
+    /// Absolute paths to the edited files.
+    #[schemars(
+        description = "Absolute paths to the edited files. Only build manifests (Cargo.toml, go.mod, package.json) are reported to the servers."
+    )]
+    pub paths: Vec<String>,
 }
 
 /// Parameters for the `get_completions` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for getting code completion suggestions.")]
 pub struct CompletionsParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
     /// Line number (1-based).
     #[schemars(description = "Line number (1-based).")]
@@ -95,40 +330,102 @@ pub struct CompletionsParams {
     /// Optional trigger character (e.g., '.', ':', '->').
     #[schemars(description = "Optional trigger character (e.g., '.', ':', '->').")]
     pub trigger: Option<String>,
+    /// Whether to resolve the items for full details.
+    #[schemars(
+        description = "Ask the server for the full details of the top items, including documentation and auto-import edits (default: true). Set to false for a faster list of labels from servers that send minimal items."
+    )]
+    #[serde(default = "default_resolve_completions")]
+    pub resolve: bool,
+}
+
+const fn default_resolve_completions() -> bool {
+    true
+}
+
+/// Parameters for the `get_inline_completions` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting inline (ghost text) completions.")]
+pub struct InlineCompletionsParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Line number (1-based).
+    #[schemars(description = "Line number (1-based).")]
+    pub line: u32,
+    /// Character/column number (1-based).
+    #[schemars(description = "Character/column number (1-based).")]
+    pub character: u32,
 }
 
 /// Parameters for the `get_document_symbols` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for getting all symbols in a document.")]
 pub struct DocumentSymbolsParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
+    /// Output format: json (default) or tree.
+    #[schemars(
+        description = "Output format: json (default) or tree, an indented text outline with kinds and line numbers that costs far fewer tokens."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
 }
 
 /// Parameters for the `format_document` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for formatting a document.")]
 pub struct FormatDocumentParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
-    /// Tab size for formatting (default: 4).
-    #[schemars(description = "Tab size for formatting (default: 4).")]
-    #[serde(default = "default_tab_size")]
-    pub tab_size: u32,
-    /// Whether to use spaces instead of tabs (default: true).
-    #[schemars(description = "Whether to use spaces instead of tabs (default: true).")]
-    #[serde(default = "default_insert_spaces")]
-    pub insert_spaces: bool,
-}
-
-const fn default_tab_size() -> u32 {
-    4
+    /// Tab size for formatting (default: the configured default, or 4).
+    #[schemars(
+        description = "Tab size for formatting (default: the configured default for the language, or 4)."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab_size: Option<u32>,
+    /// Whether to use spaces instead of tabs (default: the configured
+    /// default, or true).
+    #[schemars(
+        description = "Whether to use spaces instead of tabs (default: the configured default for the language, or true)."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insert_spaces: Option<bool>,
 }
 
-const fn default_insert_spaces() -> bool {
-    true
+/// Parameters for the `format_range` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for formatting a range of a document.")]
+pub struct FormatRangeParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Start line (1-based).
+    #[schemars(description = "Start line (1-based).")]
+    pub start_line: u32,
+    /// Start character (1-based).
+    #[schemars(description = "Start character (1-based).")]
+    pub start_character: u32,
+    /// End line (1-based).
+    #[schemars(description = "End line (1-based).")]
+    pub end_line: u32,
+    /// End character (1-based).
+    #[schemars(description = "End character (1-based).")]
+    pub end_character: u32,
+    /// Tab size for formatting (default: the configured default, or 4).
+    #[schemars(
+        description = "Tab size for formatting (default: the configured default for the language, or 4)."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab_size: Option<u32>,
+    /// Whether to use spaces instead of tabs (default: the configured
+    /// default, or true).
+    #[schemars(
+        description = "Whether to use spaces instead of tabs (default: the configured default for the language, or true)."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insert_spaces: Option<bool>,
 }
 
 /// Parameters for the `workspace_symbol_search` tool.
@@ -152,14 +449,168 @@ const fn default_max_results() -> u32 {
     100
 }
 
+/// Parameters for the `scan_workspace` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for building a per-language inventory of workspace files.")]
+pub struct ScanWorkspaceParams {
+    /// Gitignore-style globs relative to the workspace roots; all files when empty.
+    #[schemars(
+        description = "Gitignore-style globs relative to the workspace roots (e.g. \"src/**\"); all files when empty."
+    )]
+    #[serde(default)]
+    pub globs: Vec<String>,
+    /// Maximum number of files to scan (default: 10000).
+    #[schemars(description = "Maximum number of files to scan (default: 10000).")]
+    #[serde(default = "default_scan_max_files")]
+    pub max_files: usize,
+}
+
+const fn default_scan_max_files() -> usize {
+    10_000
+}
+
+/// Parameters for the `export_symbols` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for exporting the symbols of every workspace file.")]
+pub struct ExportSymbolsParams {
+    /// Gitignore-style globs relative to the workspace roots; all files when empty.
+    #[schemars(
+        description = "Gitignore-style globs relative to the workspace roots (e.g. \"src/**\"); all files when empty."
+    )]
+    #[serde(default)]
+    pub globs: Vec<String>,
+    /// Maximum number of files to export (default: 1000).
+    #[schemars(description = "Maximum number of files to export (default: 1000).")]
+    #[serde(default = "default_export_max_files")]
+    pub max_files: usize,
+    /// Output format: json (default) or scip.
+    #[schemars(
+        description = "Output format: json (default), returning the symbol map, or scip, writing a SCIP index to .mcpls/index/symbols.scip under the workspace root and returning its path and counts."
+    )]
+    #[serde(default)]
+    pub output_format: Option<String>,
+}
+
+const fn default_export_max_files() -> usize {
+    1000
+}
+
+/// Parameters for the `search_workspace_text` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for searching text across the workspace.")]
+pub struct SearchWorkspaceTextParams {
+    /// Text to search for.
+    #[schemars(description = "Text to search for.")]
+    pub query: String,
+    /// Gitignore-style globs relative to the workspace roots; all files when empty.
+    #[schemars(
+        description = "Gitignore-style globs relative to the workspace roots (e.g. \"src/**/*.rs\"); all files when empty."
+    )]
+    #[serde(default)]
+    pub globs: Vec<String>,
+    /// Search engine: "auto", "lsp", or "text" (default: "auto").
+    #[schemars(
+        description = "Search engine: \"auto\" (server search extension when available, else text), \"lsp\", or \"text\" (default: \"auto\")."
+    )]
+    #[serde(default = "default_search_engine")]
+    pub engine: String,
+    /// Whether the text search is case-sensitive (default: true).
+    #[schemars(
+        description = "Whether the search is case-sensitive (default: true). Case-insensitive searches use the text engine."
+    )]
+    #[serde(default = "default_case_sensitive")]
+    pub case_sensitive: bool,
+    /// Maximum number of matches to return (default: 100).
+    #[schemars(description = "Maximum number of matches to return (default: 100).")]
+    #[serde(default = "default_search_max_results")]
+    pub max_results: usize,
+}
+
+fn default_search_engine() -> String {
+    "auto".to_string()
+}
+
+const fn default_case_sensitive() -> bool {
+    true
+}
+
+const fn default_search_max_results() -> usize {
+    100
+}
+
+/// Parameters for the `rust_ssr` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for a rust-analyzer structural search and replace.")]
+pub struct RustSsrParams {
+    /// Pattern to match, with `$name` placeholders (e.g. `foo($a, $b)`).
+    #[schemars(
+        description = "Pattern to match, with $name placeholders (e.g. \"foo($a, $b)\"). Paths are resolved, so it also matches uses through imports."
+    )]
+    pub pattern: String,
+    /// Replacement, using the placeholders of the pattern (e.g. `bar($b, $a)`).
+    #[schemars(
+        description = "Replacement, using the placeholders of the pattern (e.g. \"bar($b, $a)\")."
+    )]
+    pub replacement: String,
+    /// Whether to only return the edits without writing them (default: true).
+    #[schemars(
+        description = "Only return the edits without writing them to disk (default: true). With false, the edits are applied and can be reverted with undo_last_edit; edits above the configured size limits are only previewed, with a confirmation_token for confirm_apply."
+    )]
+    #[serde(default = "default_dry_run")]
+    pub dry_run: bool,
+    /// Rust file paths in the rule are resolved in; any Rust file when omitted.
+    #[schemars(
+        description = "Absolute path of the Rust file whose scope resolves paths in the rule; any Rust file in the workspace when omitted."
+    )]
+    #[serde(default)]
+    pub file_path: Option<String>,
+}
+
+const fn default_dry_run() -> bool {
+    true
+}
+
+/// Parameters for the `get_enclosing_scope` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting the region enclosing a position.")]
+pub struct EnclosingScopeParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Line number (1-based).
+    #[schemars(description = "Line number (1-based).")]
+    pub line: u32,
+    /// Character/column number (1-based).
+    #[schemars(description = "Character/column number (1-based).")]
+    pub character: u32,
+    /// Region to return: expression, statement, function, or type.
+    #[schemars(description = "Region to return: expression, statement, function, or type.")]
+    pub level: String,
+}
+
+/// Parameters for the `find_matching_bracket` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for finding the bracket matching a position.")]
+pub struct MatchingBracketParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Line number (1-based).
+    #[schemars(description = "Line number (1-based).")]
+    pub line: u32,
+    /// Character/column number (1-based).
+    #[schemars(description = "Character/column number (1-based).")]
+    pub character: u32,
+}
+
 /// Parameters for the `get_code_actions` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(
     description = "Parameters for getting available code actions (quick fixes, refactorings) for a range."
 )]
 pub struct CodeActionsParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
     /// Start line (1-based).
     #[schemars(description = "Start line (1-based).")]
@@ -183,8 +634,8 @@ pub struct CodeActionsParams {
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for preparing call hierarchy at a position.")]
 pub struct CallHierarchyPrepareParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
     /// Line number (1-based).
     #[schemars(description = "Line number (1-based).")]
@@ -201,8 +652,92 @@ pub struct CallHierarchyPrepareParams {
 )]
 pub struct CallHierarchyCallsParams {
     /// The call hierarchy item to get calls for (from prepare response).
-    #[schemars(description = "The call hierarchy item to get calls for (from prepare response).")]
-    pub item: serde_json::Value,
+    #[schemars(
+        description = "A call hierarchy item from prepare_call_hierarchy, passed back unchanged. Alternatively pass its handle, or file_path, line, and character."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<CallHierarchyItemResult>,
+    /// Handle of a call hierarchy item or workspace symbol.
+    #[schemars(
+        description = "The handle returned with a call hierarchy item or workspace symbol, instead of item."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+    /// Absolute path to the file, when no item is passed.
+    #[schemars(description = "Absolute path to the file of the function, when no item is passed.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    /// Line number (1-based), when no item is passed.
+    #[schemars(
+        description = "Line number (1-based) of the function name, when no item is passed."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Character/column number (1-based), when no item is passed.
+    #[schemars(
+        description = "Character/column number (1-based) of the function name, when no item is passed."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<u32>,
+    /// Output format: json (default) or dot.
+    #[schemars(
+        description = "Output format: json (default) or dot, a Graphviz graph with one edge per call, labelled with the lines of the call sites."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
+}
+
+/// Parameters for the `prepare_type_hierarchy` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for preparing type hierarchy at a position.")]
+pub struct TypeHierarchyPrepareParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Line number (1-based).
+    #[schemars(description = "Line number (1-based).")]
+    pub line: u32,
+    /// Character/column number (1-based).
+    #[schemars(description = "Character/column number (1-based).")]
+    pub character: u32,
+}
+
+/// Parameters for the `get_supertypes` and `get_subtypes` tools.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting supertypes or subtypes of a type hierarchy item.")]
+pub struct TypeHierarchyParams {
+    /// The type hierarchy item to get related types for (from prepare response).
+    #[schemars(
+        description = "A type hierarchy item from prepare_type_hierarchy, passed back unchanged. Alternatively pass its handle, or file_path, line, and character."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<TypeHierarchyItemResult>,
+    /// Handle of a type hierarchy item or workspace symbol.
+    #[schemars(
+        description = "The handle returned with a type hierarchy item or workspace symbol, instead of item."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+    /// Absolute path to the file, when no item is passed.
+    #[schemars(description = "Absolute path to the file of the type, when no item is passed.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    /// Line number (1-based), when no item is passed.
+    #[schemars(description = "Line number (1-based) of the type name, when no item is passed.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    /// Character/column number (1-based), when no item is passed.
+    #[schemars(
+        description = "Character/column number (1-based) of the type name, when no item is passed."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character: Option<u32>,
+    /// Output format: json (default) or dot.
+    #[schemars(
+        description = "Output format: json (default) or dot, a Graphviz graph with one edge per subtype-supertype relation."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
 }
 
 /// Parameters for the `get_cached_diagnostics` tool.
@@ -211,11 +746,71 @@ pub struct CallHierarchyCallsParams {
     description = "Parameters for getting cached diagnostics from LSP server notifications."
 )]
 pub struct CachedDiagnosticsParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
 }
 
+/// Parameters for the `rust_run_flycheck` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for running rust-analyzer's cargo check (flycheck).")]
+pub struct RunFlycheckParams {
+    /// Absolute path to a Rust file whose workspace to check (default: all workspaces).
+    #[schemars(
+        description = "Absolute path to a Rust file whose workspace to check (default: all workspaces)."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+}
+
+/// Parameters for the `reset_bridge` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for resetting the MCP-LSP bridge state.")]
+pub struct ResetBridgeParams {
+    /// What to reset: requests, documents (default), or servers. Each scope includes the previous ones.
+    #[schemars(
+        description = "What to reset: requests, documents (default), or servers. Each scope includes the previous ones."
+    )]
+    #[serde(default = "default_reset_scope")]
+    pub scope: String,
+}
+
+fn default_reset_scope() -> String {
+    "documents".to_string()
+}
+
+/// Parameters for the `get_initialization_info` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for reading a language server's initialize response.")]
+pub struct InitializationInfoParams {
+    /// Language ID of the server (e.g. "rust", "python").
+    #[schemars(description = "Language ID of the server (e.g. \"rust\", \"python\").")]
+    pub language_id: String,
+}
+
+/// Parameters for the `get_language_info` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for summarizing what a language server supports.")]
+pub struct LanguageInfoParams {
+    /// Language ID of the server (e.g. "rust", "python").
+    #[schemars(description = "Language ID of the server (e.g. \"rust\", \"python\").")]
+    pub language_id: String,
+}
+
+/// Parameters for the `get_recent_calls` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for listing recent tool calls of this session.")]
+pub struct RecentCallsParams {
+    /// Maximum number of calls to return (default: 20).
+    #[schemars(description = "Maximum number of calls to return (default: 20).")]
+    #[serde(default = "default_recent_calls_limit")]
+    pub limit: usize,
+}
+
+const fn default_recent_calls_limit() -> usize {
+    20
+}
+
 /// Parameters for the `get_server_logs` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for getting recent LSP server log messages.")]
@@ -250,12 +845,30 @@ const fn default_message_limit() -> usize {
     20
 }
 
+/// Parameters for the `get_server_telemetry` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting recent LSP telemetry/event notifications.")]
+pub struct ServerTelemetryParams {
+    /// Maximum number of events to return, most recent kept (default: 20).
+    #[schemars(
+        description = "Maximum number of events to return, most recent kept (default: 20)."
+    )]
+    #[serde(default = "default_message_limit")]
+    pub limit: usize,
+    /// Only return events from the server for this language ID.
+    #[schemars(
+        description = "Only return events from the server for this language ID (e.g. 'java'). Omit for all servers."
+    )]
+    #[serde(default)]
+    pub language_id: Option<String>,
+}
+
 /// Parameters for the `get_signature_help` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for getting signature help at a position in a file.")]
 pub struct SignatureHelpParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
     /// Line number (1-based).
     #[schemars(description = "Line number (1-based).")]
@@ -269,8 +882,8 @@ pub struct SignatureHelpParams {
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for navigating to implementations of a symbol.")]
 pub struct GoToImplementationParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
     /// Line number (1-based).
     #[schemars(description = "Line number (1-based).")]
@@ -278,14 +891,26 @@ pub struct GoToImplementationParams {
     /// Character/column number (1-based).
     #[schemars(description = "Character/column number (1-based).")]
     pub character: u32,
+    /// Whether to attach the enclosing symbol to each location.
+    #[schemars(
+        description = "Attach the enclosing symbol (name, kind, parent) to each location. Costs one documentSymbol request per target file."
+    )]
+    #[serde(default)]
+    pub include_context: bool,
+    /// Maximum number of locations to return (default: all).
+    #[schemars(
+        description = "Maximum number of locations to return (default: all). Results are ranked: same file first, then workspace files, then dependencies."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
 }
 
 /// Parameters for the `go_to_type_definition` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for navigating to the type definition of an expression.")]
 pub struct GoToTypeDefinitionParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
     /// Line number (1-based).
     #[schemars(description = "Line number (1-based).")]
@@ -295,23 +920,106 @@ pub struct GoToTypeDefinitionParams {
     pub character: u32,
 }
 
+/// Parameters for the `get_declaration` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for navigating to the declaration of a symbol.")]
+pub struct DeclarationParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Line number (1-based).
+    #[schemars(description = "Line number (1-based).")]
+    pub line: u32,
+    /// Character/column number (1-based).
+    #[schemars(description = "Character/column number (1-based).")]
+    pub character: u32,
+}
+
+/// Parameters for the `get_semantic_tokens` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting the semantic tokens of a file or line range.")]
+pub struct SemanticTokensParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// First line (1-based), default the first line of the file.
+    #[schemars(description = "First line (1-based). Default: the first line of the file.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<u32>,
+    /// Last line (1-based, inclusive), default the last line of the file.
+    #[schemars(
+        description = "Last line (1-based, inclusive). Default: the last line of the file."
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<u32>,
+    /// Maximum number of tokens to return (default: 1000).
+    #[schemars(description = "Maximum number of tokens to return (default: 1000).")]
+    #[serde(default = "default_semantic_tokens_limit")]
+    pub max_tokens: usize,
+}
+
+const fn default_semantic_tokens_limit() -> usize {
+    1000
+}
+
+/// Parameters for the `get_code_lenses` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting the code lenses of a file.")]
+pub struct CodeLensesParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Whether to include the arguments of each lens's command.
+    #[schemars(
+        description = "Include the arguments of each lens's command, such as the test runnable rust-analyzer passes to runSingle. Off by default: they can be large."
+    )]
+    #[serde(default)]
+    pub include_arguments: bool,
+}
+
+/// Parameters for the `get_selection_ranges` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting the nested ranges around a position.")]
+pub struct SelectionRangesParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+    /// Line number (1-based).
+    #[schemars(description = "Line number (1-based).")]
+    pub line: u32,
+    /// Character/column number (1-based).
+    #[schemars(description = "Character/column number (1-based).")]
+    pub character: u32,
+}
+
+/// Parameters for the `get_folding_ranges` tool.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(description = "Parameters for getting the foldable blocks of a file.")]
+pub struct FoldingRangesParams {
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
+    pub file_path: String,
+}
+
 /// Parameters for the `get_inlay_hints` tool.
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[schemars(description = "Parameters for getting inlay hints in a range.")]
 pub struct InlayHintsParams {
-    /// Absolute path to the file.
-    #[schemars(description = "Absolute path to the file.")]
+    /// Absolute path to the file, or a path relative to a workspace root.
+    #[schemars(description = "Absolute path to the file, or a path relative to a workspace root.")]
     pub file_path: String,
     /// Start line (1-based).
     #[schemars(description = "Start line (1-based).")]
     pub start_line: u32,
-    /// Start character (1-based).
-    #[schemars(description = "Start character (1-based).")]
-    pub start_character: u32,
-    /// End line (1-based).
-    #[schemars(description = "End line (1-based).")]
+    /// Start character (1-based), default the start of the line.
+    #[schemars(description = "Start character (1-based). Default: the start of start_line.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_character: Option<u32>,
+    /// End line (1-based), inclusive.
+    #[schemars(description = "End line (1-based), inclusive.")]
     pub end_line: u32,
-    /// End character (1-based).
-    #[schemars(description = "End character (1-based).")]
-    pub end_character: u32,
+    /// End character (1-based), default the end of the line.
+    #[schemars(description = "End character (1-based). Default: the end of end_line.")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_character: Option<u32>,
 }
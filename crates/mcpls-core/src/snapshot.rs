@@ -0,0 +1,274 @@
+//! Diagnostics snapshots for bug reports.
+//!
+//! `mcpls snapshot` and the `create_snapshot` tool bundle what is needed to
+//! reproduce a problem with a language server into a single JSON file: the
+//! configuration with secrets redacted, the version, capabilities, and state
+//! of each server, its recent log messages, the session's recent tool calls,
+//! and the last messages exchanged with each server.
+//!
+//! The wire log holds excerpts of the requests and responses, including
+//! document content sent to the servers. Review a snapshot before attaching
+//! it to a public issue.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::bridge::{LogEntry, ServerMessage, ServerSnapshot, Translator};
+use crate::config::ServerConfig;
+use crate::embed::Bridge;
+use crate::error::{Error, Result};
+use crate::mcp::RecentCall;
+
+/// Directory, under the first workspace root, snapshots are written to.
+pub const SNAPSHOT_DIR: &str = ".mcpls/snapshots";
+
+/// Number of server log entries and messages a snapshot keeps.
+const SNAPSHOT_LOG_LIMIT: usize = 200;
+
+/// Everything a bug report about the bridge needs, as written by
+/// [`write_snapshot`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    /// Version of mcpls that took the snapshot.
+    pub mcpls_version: &'static str,
+    /// When the snapshot was taken, in seconds since the Unix epoch.
+    pub created_at: u64,
+    /// Workspace roots of the bridge.
+    pub workspace_roots: Vec<PathBuf>,
+    /// Configuration, with server environment values redacted.
+    pub config: serde_json::Value,
+    /// Why the servers could not be started, when none were.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub startup_error: Option<String>,
+    /// State of each configured server.
+    pub servers: Vec<ServerSnapshot>,
+    /// Recent log messages of the servers.
+    pub logs: Vec<LogEntry>,
+    /// Recent messages the servers showed to the user.
+    pub messages: Vec<ServerMessage>,
+    /// Recent tool calls of the session, newest first.
+    pub recent_calls: Vec<RecentCall>,
+}
+
+impl Snapshot {
+    /// Take a snapshot of `translator`, with the session's `recent_calls`.
+    #[must_use]
+    pub fn collect(translator: &mut Translator, recent_calls: Vec<RecentCall>) -> Self {
+        let logs = translator
+            .handle_server_logs(SNAPSHOT_LOG_LIMIT, None)
+            .map(|result| result.logs)
+            .unwrap_or_default();
+        let messages = translator
+            .handle_server_messages(SNAPSHOT_LOG_LIMIT)
+            .map(|result| result.messages)
+            .unwrap_or_default();
+        Self {
+            workspace_roots: translator.workspace_roots().to_vec(),
+            config: translator.sanitized_config().clone(),
+            servers: translator.server_snapshots(),
+            logs,
+            messages,
+            recent_calls,
+            ..Self::empty()
+        }
+    }
+
+    /// A snapshot of a bridge whose servers could not be started.
+    fn failed(config: &ServerConfig, workspace_roots: Vec<PathBuf>, error: &Error) -> Self {
+        Self {
+            workspace_roots,
+            config: config.sanitized(),
+            startup_error: Some(match error {
+                Error::AllServersFailedToInit { failures, .. } => {
+                    let failures: Vec<String> = failures.iter().map(ToString::to_string).collect();
+                    format!("{error}: {}", failures.join("; "))
+                }
+                _ => error.to_string(),
+            }),
+            ..Self::empty()
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            mcpls_version: env!("CARGO_PKG_VERSION"),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            workspace_roots: Vec::new(),
+            config: serde_json::Value::Null,
+            startup_error: None,
+            servers: Vec::new(),
+            logs: Vec::new(),
+            messages: Vec::new(),
+            recent_calls: Vec::new(),
+        }
+    }
+
+    /// Default path of the snapshot: `snapshot-<created_at>.json` in
+    /// [`SNAPSHOT_DIR`] under the first workspace root.
+    fn default_path(&self) -> PathBuf {
+        self.workspace_roots
+            .first()
+            .map_or_else(|| PathBuf::from("."), Clone::clone)
+            .join(SNAPSHOT_DIR)
+            .join(format!("snapshot-{}.json", self.created_at))
+    }
+}
+
+/// Result of writing a snapshot.
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotResult {
+    /// Path of the written snapshot.
+    pub output_path: String,
+    /// Number of servers described.
+    pub servers: usize,
+    /// Number of log entries included.
+    pub log_entries: usize,
+    /// Number of tool calls included.
+    pub recent_calls: usize,
+}
+
+/// Write `snapshot` as JSON to `output`, or to its default path in
+/// [`SNAPSHOT_DIR`] when it is `None`.
+///
+/// # Errors
+///
+/// Returns [`Error::FileIo`] if the snapshot cannot be written.
+pub async fn write_snapshot(
+    snapshot: &Snapshot,
+    output: Option<PathBuf>,
+) -> Result<SnapshotResult> {
+    let file = output.unwrap_or_else(|| snapshot.default_path());
+    if let Some(dir) = file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        create_dir(dir).await?;
+        // Keep snapshots out of version control without touching the
+        // project's own ignore files.
+        if dir.ends_with(SNAPSHOT_DIR)
+            && let Some(state_dir) = dir.parent()
+        {
+            let gitignore = state_dir.join(".gitignore");
+            if !gitignore.exists() {
+                write(&gitignore, b"*\n").await?;
+            }
+        }
+    }
+    write(&file, &serde_json::to_vec_pretty(snapshot)?).await?;
+    Ok(SnapshotResult {
+        output_path: file.display().to_string(),
+        servers: snapshot.servers.len(),
+        log_entries: snapshot.logs.len(),
+        recent_calls: snapshot.recent_calls.len(),
+    })
+}
+
+/// Start the servers for `root`, or the configured workspace roots when it
+/// is `None`, take a snapshot once they are up, and write it to `output`.
+///
+/// A snapshot is written even when no server starts, recording why.
+///
+/// # Errors
+///
+/// Returns an error if `root` cannot be resolved, the configuration is
+/// invalid, or the snapshot cannot be written.
+pub async fn snapshot(
+    config: ServerConfig,
+    root: Option<PathBuf>,
+    output: Option<PathBuf>,
+) -> Result<SnapshotResult> {
+    let snapshot = match Bridge::start(&config, root.clone()).await {
+        Ok(bridge) => {
+            let snapshot = Snapshot::collect(&mut *bridge.translator().await, Vec::new());
+            bridge.shutdown().await;
+            snapshot
+        }
+        Err(
+            e @ (Error::NoServerConfigured
+            | Error::AllServersFailedToInit { .. }
+            | Error::Config(_)
+            | Error::InvalidConfig(_)),
+        ) => {
+            warn!("Servers did not start: {e}");
+            let roots = root.map_or_else(
+                || crate::resolve_workspace_roots(&config.workspace.roots),
+                |root| vec![root],
+            );
+            Snapshot::failed(&config, roots, &e)
+        }
+        Err(e) => return Err(e),
+    };
+    let result = write_snapshot(&snapshot, output).await?;
+    info!("Wrote snapshot to {}", result.output_path);
+    Ok(result)
+}
+
+async fn create_dir(dir: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|source| Error::FileIo {
+            path: dir.to_path_buf(),
+            source,
+        })
+}
+
+async fn write(path: &Path, content: &[u8]) -> Result<()> {
+    tokio::fs::write(path, content)
+        .await
+        .map_err(|source| Error::FileIo {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_snapshot_to_default_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let mut translator = Translator::new();
+        translator.set_workspace_roots(vec![root.clone()]);
+        translator.set_sanitized_config(serde_json::json!({"lsp_servers": []}));
+
+        let snapshot = Snapshot::collect(&mut translator, Vec::new());
+        let result = write_snapshot(&snapshot, None).await.unwrap();
+
+        let path = PathBuf::from(&result.output_path);
+        assert!(path.starts_with(root.join(SNAPSHOT_DIR)));
+        assert!(root.join(".mcpls/.gitignore").exists());
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(written["mcpls_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(written["config"]["lsp_servers"], serde_json::json!([]));
+        assert!(written.get("startup_error").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_records_startup_error() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let output = dir.path().join("report.json");
+        let config: ServerConfig = toml::from_str(
+            r#"
+            [[lsp_servers]]
+            language_id = "rust"
+            command = "mcpls-test-missing-server"
+            env = { TOKEN = "secret" }
+            "#,
+        )
+        .unwrap();
+
+        let result = snapshot(config, Some(dir.path().to_path_buf()), Some(output.clone()))
+            .await
+            .unwrap();
+        assert_eq!(result.servers, 0);
+        let written = std::fs::read_to_string(output).unwrap();
+        assert!(written.contains("startup_error"));
+        assert!(!written.contains("secret"));
+    }
+}
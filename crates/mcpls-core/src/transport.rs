@@ -101,14 +101,53 @@ pub(crate) async fn run_stdio(
         .map_err(|e| crate::Error::McpServer(format!("MCP server error: {e}")))
 }
 
+/// What the HTTP transport winds down once its last session closes.
+///
+/// In-flight LSP requests are cancelled at once. Tracked documents stay open
+/// for `grace` so a client that reconnects finds the servers warm, then are
+/// closed unless a session opened in the meantime. The servers keep running.
+#[cfg(feature = "transport-http")]
+pub(crate) struct IdleShutdown {
+    pub translator: std::sync::Arc<tokio::sync::Mutex<crate::bridge::Translator>>,
+    pub canceller: crate::bridge::RequestCanceller,
+    pub grace: std::time::Duration,
+}
+
+#[cfg(feature = "transport-http")]
+impl IdleShutdown {
+    async fn run(self, sessions: std::sync::Arc<crate::mcp::OpenSessions>) {
+        loop {
+            sessions.all_closed().await;
+            if sessions.open() > 0 {
+                continue;
+            }
+            let opened = sessions.opened();
+            match self.canceller.cancel_all().await {
+                0 => {}
+                count => tracing::info!("Last session closed: cancelled {count} LSP request(s)"),
+            }
+            tokio::time::sleep(self.grace).await;
+            if sessions.open() > 0 || sessions.opened() != opened {
+                continue;
+            }
+            let closed = self.translator.lock().await.close_all_documents().await;
+            if closed > 0 {
+                tracing::info!("No session reconnected: closed {closed} document(s)");
+            }
+        }
+    }
+}
+
 /// Run the MCP server over Streamable HTTP (MCP spec 2025-11-25).
 ///
 /// Binds `cfg.bind`, mounts the MCP service at `cfg.path` (and `/`), and
-/// serves until `Ctrl-C` or `SIGTERM` is received.
+/// serves until `Ctrl-C` or `SIGTERM` is received. Whenever the last session
+/// closes, `idle` winds the bridge down.
 ///
-/// Each HTTP session receives its own `McplsServer` clone. The shared
+/// Each HTTP session receives its own `McplsServer` session. The shared
 /// `Arc<Mutex<Translator>>` inside is the same across all sessions, so LSP
-/// state is still global per process.
+/// state is still global per process; only the document overlays pushed
+/// with `update_document` are kept per session.
 ///
 /// # Note
 ///
@@ -120,6 +159,7 @@ pub(crate) async fn run_stdio(
 pub(crate) async fn run_http(
     mcp_server: crate::mcp::McplsServer,
     cfg: HttpConfig,
+    idle: IdleShutdown,
 ) -> Result<(), crate::Error> {
     use std::sync::Arc;
 
@@ -138,7 +178,7 @@ pub(crate) async fn run_http(
     http_cfg.cancellation_token = cancel.clone();
 
     let service = StreamableHttpService::new(
-        move || Ok::<_, std::io::Error>(mcp_for_factory.clone()),
+        move || Ok::<_, std::io::Error>(mcp_for_factory.new_session()),
         session_manager,
         http_cfg,
     );
@@ -161,7 +201,8 @@ pub(crate) async fn run_http(
         );
     }
 
-    axum::serve(listener, app)
+    let idle_task = tokio::spawn(idle.run(mcp_server.sessions()));
+    let result = axum::serve(listener, app)
         .with_graceful_shutdown(async move {
             // On Unix, containers (Docker/systemd) send SIGTERM; handle both
             // SIGTERM and SIGINT (Ctrl-C) so shutdown is clean in all environments.
@@ -192,7 +233,9 @@ pub(crate) async fn run_http(
             cancel.cancel();
         })
         .await
-        .map_err(|e| crate::Error::McpServer(format!("http serve: {e}")))
+        .map_err(|e| crate::Error::McpServer(format!("http serve: {e}")));
+    idle_task.abort();
+    result
 }
 
 #[cfg(test)]
@@ -209,7 +252,17 @@ mod tests {
     mod http_tests {
         use std::net::SocketAddr;
 
-        use super::super::{HttpConfig, Transport};
+        use super::super::{HttpConfig, IdleShutdown, Transport};
+
+        fn idle_shutdown(
+            translator: &std::sync::Arc<tokio::sync::Mutex<crate::bridge::Translator>>,
+        ) -> IdleShutdown {
+            IdleShutdown {
+                translator: std::sync::Arc::clone(translator),
+                canceller: translator.try_lock().unwrap().request_canceller(),
+                grace: std::time::Duration::ZERO,
+            }
+        }
 
         #[test]
         fn test_http_config_fields() {
@@ -255,6 +308,7 @@ mod tests {
 
             let translator = Arc::new(Mutex::new(Translator::new()));
             let subs = Arc::new(ResourceSubscriptions::new());
+            let idle = idle_shutdown(&translator);
             let server = McplsServer::new(translator, subs);
 
             // Bind port 0 so the OS assigns a free port.
@@ -267,7 +321,7 @@ mod tests {
                 path: "/mcp".to_string(),
             };
 
-            let server_task = tokio::spawn(super::super::run_http(server, cfg));
+            let server_task = tokio::spawn(super::super::run_http(server, cfg, idle));
             tokio::time::sleep(std::time::Duration::from_millis(50)).await;
 
             // A successful TCP connect proves the listener is up.
@@ -296,6 +350,7 @@ mod tests {
 
             let translator = Arc::new(Mutex::new(Translator::new()));
             let subs = Arc::new(ResourceSubscriptions::new());
+            let idle = idle_shutdown(&translator);
             let server = McplsServer::new(translator, subs);
 
             let cfg = HttpConfig {
@@ -303,7 +358,7 @@ mod tests {
                 path: "/mcp".to_string(),
             };
 
-            let result = super::super::run_http(server, cfg).await;
+            let result = super::super::run_http(server, cfg, idle).await;
             assert!(
                 result.is_err(),
                 "run_http should fail when port is occupied"
@@ -311,5 +366,64 @@ mod tests {
 
             drop(occupied);
         }
+
+        /// Verifies in-flight LSP requests are cancelled once the last
+        /// session closes.
+        #[cfg(unix)]
+        #[tokio::test]
+        async fn test_idle_shutdown_cancels_requests() {
+            use std::sync::Arc;
+            use std::time::Duration;
+
+            use tokio::sync::Mutex;
+
+            use crate::bridge::{ResourceSubscriptions, Translator};
+            use crate::lsp::{LspClient, LspTransport};
+            use crate::mcp::McplsServer;
+
+            // `sleep` never answers, so the request stays in flight.
+            let mut child = tokio::process::Command::new("sleep")
+                .arg("60")
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()
+                .unwrap();
+            let transport =
+                LspTransport::new(child.stdin.take().unwrap(), child.stdout.take().unwrap());
+            let client = LspClient::from_transport(
+                crate::config::LspServerConfig::rust_analyzer(),
+                transport,
+            );
+            let translator = Arc::new(Mutex::new(Translator::new()));
+            translator
+                .lock()
+                .await
+                .register_client("rust".to_string(), client.clone());
+            let idle = idle_shutdown(&translator);
+            let server = McplsServer::new(translator, Arc::new(ResourceSubscriptions::new()));
+            let idle_task = tokio::spawn(idle.run(server.sessions()));
+
+            let session = server.new_session();
+            let request = tokio::spawn(async move {
+                client
+                    .request::<_, serde_json::Value>(
+                        "workspace/symbol",
+                        serde_json::json!({ "query": "" }),
+                        Duration::from_secs(60),
+                    )
+                    .await
+            });
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            assert!(!request.is_finished());
+
+            drop(session);
+            let result = tokio::time::timeout(Duration::from_secs(5), request)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(matches!(result, Err(crate::Error::RequestCancelled)));
+            idle_task.abort();
+        }
     }
 }
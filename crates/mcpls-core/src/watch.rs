@@ -0,0 +1,232 @@
+//! Headless diagnostics watcher.
+//!
+//! `mcpls watch` runs the bridge without an MCP host: it spawns the language
+//! servers for a workspace, polls the workspace for files that changed on
+//! disk, syncs them to their servers as an editor would on save, and prints a
+//! JSON line to stdout whenever the diagnostics of a file change. Files are
+//! found with the same gitignore-aware walk the workspace tools use.
+
+use std::collections::HashMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use ignore::WalkBuilder;
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+
+use crate::bridge::{BridgeEvent, DiagnosticsResult, Translator, is_manifest, uri_to_path};
+use crate::config::ServerConfig;
+use crate::embed::Bridge;
+use crate::error::{Error, Result};
+
+/// One line of `mcpls watch` output: the diagnostics of a file after they
+/// changed.
+#[derive(Debug, Serialize)]
+struct DiagnosticsEvent<'a> {
+    file_path: String,
+    #[serde(flatten)]
+    result: &'a DiagnosticsResult,
+}
+
+/// Watch `root`, or the configured workspace roots when it is `None`, and
+/// stream diagnostics changes to stdout as JSON lines until interrupted.
+///
+/// The workspace is scanned every `interval`. Files created or modified
+/// since the previous scan are synced to their server, deleted ones are
+/// closed, and edited build manifests are reported to every server. A file's
+/// diagnostics are printed each time they differ from the last ones printed
+/// for it, including when they clear.
+///
+/// # Errors
+///
+/// Returns an error if `root` cannot be resolved, the configuration is
+/// invalid, no server applies to the workspace, or every server fails to
+/// start.
+pub async fn watch(config: ServerConfig, root: Option<PathBuf>, interval: Duration) -> Result<()> {
+    let bridge = Bridge::start(&config, root).await?;
+    let mut events = bridge.subscribe();
+    let workspace_roots = bridge.translator().await.workspace_roots().to_vec();
+
+    let mut files = scan(workspace_roots.clone()).await;
+    info!(
+        "Watching {} file(s) under {} root(s)",
+        files.len(),
+        workspace_roots.len()
+    );
+    let mut printed: HashMap<String, String> = HashMap::new();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = ticker.tick() => {
+                let current = scan(workspace_roots.clone()).await;
+                let changed = changed_files(&files, &current);
+                files = current;
+                sync_changes(&mut *bridge.translator().await, &changed).await;
+            }
+            event = events.recv() => {
+                let uri = match event {
+                    Ok(BridgeEvent::DiagnosticsUpdated { uri, .. }) => uri,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(count)) => {
+                        warn!("Missed {count} bridge event(s)");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let Some(path) = uri_to_path(&uri) else { continue };
+                // The bridge cached the diagnostics before publishing them.
+                let Ok(result) = bridge
+                    .translator()
+                    .await
+                    .handle_cached_diagnostics(&path.to_string_lossy())
+                else {
+                    continue;
+                };
+                print_if_changed(&mut printed, &path, &result)?;
+            }
+        }
+    }
+    bridge.shutdown().await;
+    info!("Stopped watching");
+    Ok(())
+}
+
+/// Sync `changed` files to their servers, reporting edited build manifests
+/// first so the servers reload their project model before rechecking.
+async fn sync_changes(translator: &mut Translator, changed: &[PathBuf]) {
+    let manifests: Vec<String> = changed
+        .iter()
+        .filter(|path| is_manifest(path))
+        .map(|path| path.display().to_string())
+        .collect();
+    if !manifests.is_empty()
+        && let Err(e) = translator.handle_notify_manifest_changes(manifests).await
+    {
+        warn!("Failed to report manifest changes: {e}");
+    }
+    for path in changed {
+        match translator.sync_from_disk(path).await {
+            Ok(()) => debug!("Synced {}", path.display()),
+            Err(
+                e @ (Error::NoServerForLanguage { .. }
+                | Error::ServerInitializing(_)
+                | Error::PathOutsideWorkspace(_)),
+            ) => debug!("Not syncing {}: {e}", path.display()),
+            Err(e) => warn!("Failed to sync {}: {e}", path.display()),
+        }
+    }
+}
+
+/// Print the diagnostics of `path` as a JSON line unless they are the ones
+/// last printed for it. Files without printed diagnostics count as clean.
+fn print_if_changed(
+    printed: &mut HashMap<String, String>,
+    path: &Path,
+    result: &DiagnosticsResult,
+) -> Result<()> {
+    let file_path = path.display().to_string();
+    let diagnostics = serde_json::to_string(&result.diagnostics)?;
+    let previous = printed.get(&file_path).map_or("[]", String::as_str);
+    if previous == diagnostics {
+        return Ok(());
+    }
+    let line = serde_json::to_string(&DiagnosticsEvent {
+        file_path: file_path.clone(),
+        result,
+    })?;
+    printed.insert(file_path, diagnostics);
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{line}")?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Modification times of the files under `roots`, skipping hidden and
+/// gitignored ones.
+async fn scan(roots: Vec<PathBuf>) -> HashMap<PathBuf, SystemTime> {
+    tokio::task::spawn_blocking(move || {
+        let mut files = HashMap::new();
+        for root in &roots {
+            for entry in WalkBuilder::new(root).require_git(false).build().flatten() {
+                if !entry.file_type().is_some_and(|t| t.is_file()) {
+                    continue;
+                }
+                if let Ok(modified) = entry.metadata().and_then(|m| Ok(m.modified()?)) {
+                    files.insert(entry.into_path(), modified);
+                }
+            }
+        }
+        files
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Files created, modified, or deleted between two scans, sorted by path.
+fn changed_files(
+    before: &HashMap<PathBuf, SystemTime>,
+    after: &HashMap<PathBuf, SystemTime>,
+) -> Vec<PathBuf> {
+    let mut changed: Vec<PathBuf> = after
+        .iter()
+        .filter(|(path, modified)| before.get(*path) != Some(*modified))
+        .map(|(path, _)| path.clone())
+        .chain(
+            before
+                .keys()
+                .filter(|path| !after.contains_key(*path))
+                .cloned(),
+        )
+        .collect();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_files() {
+        let old = SystemTime::UNIX_EPOCH;
+        let new = old + Duration::from_secs(1);
+        let before = HashMap::from([
+            (PathBuf::from("/ws/kept.rs"), old),
+            (PathBuf::from("/ws/edited.rs"), old),
+            (PathBuf::from("/ws/deleted.rs"), old),
+        ]);
+        let after = HashMap::from([
+            (PathBuf::from("/ws/kept.rs"), old),
+            (PathBuf::from("/ws/edited.rs"), new),
+            (PathBuf::from("/ws/created.rs"), new),
+        ]);
+        assert_eq!(
+            changed_files(&before, &after),
+            [
+                PathBuf::from("/ws/created.rs"),
+                PathBuf::from("/ws/deleted.rs"),
+                PathBuf::from("/ws/edited.rs"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scan_skips_gitignored_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(root.join("lib.rs"), "fn f() {}\n").unwrap();
+        std::fs::write(root.join("target/out.rs"), "fn g() {}\n").unwrap();
+
+        let files = scan(vec![root.clone()]).await;
+        assert!(files.contains_key(&root.join("lib.rs")));
+        assert!(!files.contains_key(&root.join("target/out.rs")));
+    }
+}
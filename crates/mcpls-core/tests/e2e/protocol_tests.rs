@@ -64,7 +64,7 @@ fn test_e2e_list_tools() -> Result<()> {
         .as_array()
         .unwrap_or_else(|| panic!("tools should be an array"));
 
-    assert_eq!(tools.len(), 20, "Should have exactly 20 tools");
+    assert_eq!(tools.len(), 54, "Should have exactly 54 tools");
 
     let tool_names: Vec<&str> = tools.iter().filter_map(|t| t["name"].as_str()).collect();
 
@@ -73,22 +73,56 @@ fn test_e2e_list_tools() -> Result<()> {
         "get_definition",
         "get_references",
         "get_diagnostics",
+        "get_diagnostics_batch",
+        "prefetch_files",
+        "search_workspace_text",
+        "rust_run_flycheck",
+        "reset_bridge",
         "rename_symbol",
+        "rename_symbols_batch",
+        "rust_ssr",
+        "confirm_apply",
+        "undo_last_edit",
+        "update_document",
+        "save_document",
+        "get_document_drift",
+        "notify_manifest_changes",
         "get_completions",
+        "get_inline_completions",
         "get_document_symbols",
         "format_document",
+        "format_range",
+        "prepare_rename",
         "workspace_symbol_search",
+        "scan_workspace",
+        "export_symbols",
         "get_code_actions",
         "prepare_call_hierarchy",
         "get_incoming_calls",
         "get_outgoing_calls",
+        "prepare_type_hierarchy",
+        "get_supertypes",
+        "get_subtypes",
         "get_cached_diagnostics",
         "get_server_logs",
         "get_server_messages",
+        "get_server_telemetry",
+        "get_recent_calls",
+        "create_snapshot",
+        "get_initialization_info",
+        "get_language_info",
+        "list_supported_languages",
         "get_signature_help",
         "go_to_implementation",
         "go_to_type_definition",
+        "get_declaration",
         "get_inlay_hints",
+        "get_semantic_tokens",
+        "get_folding_ranges",
+        "get_selection_ranges",
+        "get_code_lenses",
+        "get_enclosing_scope",
+        "find_matching_bracket",
     ] {
         assert!(tool_names.contains(expected), "Should have {expected} tool");
     }
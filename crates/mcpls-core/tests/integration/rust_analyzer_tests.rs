@@ -15,7 +15,9 @@ use std::sync::{Arc, Once};
 use std::time::{Duration, Instant};
 
 use mcpls_core::bridge::Translator;
-use mcpls_core::config::LspServerConfig;
+use mcpls_core::config::{
+    CompanionFiles, DEFAULT_MAX_MESSAGE_SIZE, LspServerConfig, StderrMode, TraceMode,
+};
 use mcpls_core::lsp::{LspServer, ServerInitConfig};
 use tokio::sync::Mutex;
 use tokio::time::timeout;
@@ -52,9 +54,21 @@ async fn setup_rust_analyzer() -> Arc<Mutex<Translator>> {
         command: "rust-analyzer".to_string(),
         args: vec![],
         env: std::collections::HashMap::new(),
+        env_allow: None,
+        env_deny: Vec::new(),
         file_patterns: vec!["**/*.rs".to_string()],
         initialization_options: None,
         timeout_seconds: 30,
+        init_timeout_seconds: None,
+        startup_grace_seconds: None,
+        max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+        stderr: StderrMode::Discard,
+        trace: TraceMode::Off,
+        inline_completions: false,
+        companion_files: CompanionFiles::None,
+        visible_paths: Vec::new(),
+        writable_paths: Vec::new(),
+        index_export: None,
         heuristics: None,
     };
 
@@ -243,6 +257,8 @@ async fn test_definition_user_struct() {
             types_file.to_string_lossy().to_string(),
             9,
             20, // Position on "User"
+            true,
+            None,
         ),
     )
     .await;
@@ -288,6 +304,8 @@ async fn test_definition_across_files() {
             functions_file.to_string_lossy().to_string(),
             3,
             24, // Position on "Repository"
+            false,
+            None,
         ),
     )
     .await;
@@ -330,6 +348,8 @@ async fn test_references_create_repo_function() {
             7,
             12,   // Position on "create_repo"
             true, // Include declaration
+            None,
+            "workspace",
         ),
     )
     .await;
@@ -374,6 +394,8 @@ async fn test_references_user_struct() {
             18,
             15, // Position on "User"
             true,
+            None,
+            "workspace",
         ),
     )
     .await;
@@ -607,6 +629,7 @@ async fn test_completions_basic() {
             23,
             10, // Position after "repo."
             None,
+            false,
         ),
     )
     .await;
@@ -644,8 +667,8 @@ async fn test_format_document() {
         Duration::from_secs(10),
         translator.lock().await.handle_format_document(
             lib_file.to_string_lossy().to_string(),
-            4,    // tab_size
-            true, // insert_spaces
+            Some(4),    // tab_size
+            Some(true), // insert_spaces
         ),
     )
     .await;